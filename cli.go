@@ -0,0 +1,89 @@
+package main
+
+import (
+	"context"
+	"errors"
+	"flag"
+	"fmt"
+	"io"
+	"strings"
+
+	"dns_resolver/resolver"
+
+	"github.com/miekg/dns"
+)
+
+// Коды завершения подкоманды resolve
+const (
+	exitOK       = 0
+	exitFailure  = 1 // SERVFAIL, блокировка или ошибка конфигурации
+	exitUsage    = 2
+	exitNXDOMAIN = 3
+)
+
+// runResolve выполняет подкоманду "resolve <имя> [тип]": разрешает одно имя
+// через библиотечный Resolver, печатает записи и возвращает код завершения
+func runResolve(args []string, stdout, stderr io.Writer) int {
+	fs := flag.NewFlagSet("resolve", flag.ContinueOnError)
+	fs.SetOutput(stderr)
+	configPath := fs.String("config", "", "Файл конфигурации в формате JSON")
+	forward := fs.String("forward", "", "Список вышестоящих резолверов через запятую (режим пересылки)")
+	fs.Usage = func() {
+		fmt.Fprintln(stderr, "Использование: dns_resolver resolve [-config файл] [-forward адреса] <имя> [тип]")
+		fs.PrintDefaults()
+	}
+	if err := fs.Parse(args); err != nil {
+		return exitUsage
+	}
+	if fs.NArg() < 1 || fs.NArg() > 2 {
+		fs.Usage()
+		return exitUsage
+	}
+
+	name := fs.Arg(0)
+	qtype := dns.TypeA
+	if fs.NArg() == 2 {
+		t, ok := dns.StringToType[strings.ToUpper(fs.Arg(1))]
+		if !ok {
+			fmt.Fprintf(stderr, "Неизвестный тип записи: %s\n", fs.Arg(1))
+			return exitUsage
+		}
+		qtype = t
+	}
+
+	cfg := resolver.DefaultConfig()
+	if *configPath != "" {
+		loaded, err := resolver.LoadConfig(*configPath)
+		if err != nil {
+			fmt.Fprintf(stderr, "Ошибка загрузки конфигурации: %v\n", err)
+			return exitFailure
+		}
+		cfg = loaded
+	}
+	if *forward != "" {
+		cfg.Forwarders = strings.Split(*forward, ",")
+	}
+
+	r, err := resolver.New(cfg)
+	if err != nil {
+		fmt.Fprintf(stderr, "Некорректная конфигурация: %v\n", err)
+		return exitFailure
+	}
+	records, err := r.Resolve(context.Background(), name, qtype)
+	switch {
+	case errors.Is(err, resolver.ErrNXDOMAIN):
+		fmt.Fprintf(stderr, "%s: NXDOMAIN\n", dns.Fqdn(name))
+		return exitNXDOMAIN
+	case err != nil:
+		fmt.Fprintf(stderr, "%s: %v\n", dns.Fqdn(name), err)
+		return exitFailure
+	}
+
+	if len(records) == 0 {
+		fmt.Fprintf(stderr, "%s: нет записей типа %s\n", dns.Fqdn(name), dns.TypeToString[qtype])
+	}
+	for _, rr := range records {
+		fmt.Fprintln(stdout, rr.String())
+	}
+	return exitOK
+}
@@ -5,8 +5,6 @@ go 1.25.0
 require github.com/miekg/dns v1.1.68
 
 require (
-	github.com/bogdanovich/dns_resolver v0.0.0-20241017020855-457246373ab9 // indirect
-	github.com/domainr/dnsr v0.0.0-20250821082029-260b2a7895ed // indirect
 	golang.org/x/mod v0.27.0 // indirect
 	golang.org/x/net v0.43.0 // indirect
 	golang.org/x/sync v0.16.0 // indirect
@@ -0,0 +1,105 @@
+package resolver
+
+import (
+	"fmt"
+	"log/slog"
+	"net"
+	"sort"
+	"strings"
+)
+
+const (
+	aclActionRefuse = "refuse"
+	aclActionDrop   = "drop"
+)
+
+type aclRule struct {
+	network *net.IPNet
+	allow   bool
+}
+
+// ACL ограничивает круг клиентов, которым разрешено обращаться к резолверу.
+// Из подходящих правил действует самое специфичное; при равной длине
+// префикса запрет сильнее разрешения. Если список разрешений пуст, клиенты,
+// не попавшие под запрет, допускаются.
+type ACL struct {
+	rules    []aclRule // отсортированы от длинного префикса к короткому
+	hasAllow bool
+}
+
+// NewACL разбирает списки разрешенных и запрещенных сетей в нотации CIDR.
+// Одиночный адрес трактуется как сеть из одного адреса.
+func NewACL(allow, deny []string) (*ACL, error) {
+	acl := &ACL{hasAllow: len(allow) > 0}
+	for _, list := range []struct {
+		cidrs []string
+		allow bool
+	}{{allow, true}, {deny, false}} {
+		for _, cidr := range list.cidrs {
+			network, err := parseCIDR(cidr)
+			if err != nil {
+				return nil, err
+			}
+			acl.rules = append(acl.rules, aclRule{network: network, allow: list.allow})
+		}
+	}
+
+	sort.SliceStable(acl.rules, func(i, j int) bool {
+		oi, _ := acl.rules[i].network.Mask.Size()
+		oj, _ := acl.rules[j].network.Mask.Size()
+		if oi != oj {
+			return oi > oj
+		}
+		return !acl.rules[i].allow && acl.rules[j].allow
+	})
+	return acl, nil
+}
+
+// Allowed сообщает, разрешено ли клиенту с адресом ip отправлять запросы
+func (a *ACL) Allowed(ip net.IP) bool {
+	if a == nil || len(a.rules) == 0 {
+		return true
+	}
+	if ip == nil {
+		return !a.hasAllow
+	}
+	if ip4 := ip.To4(); ip4 != nil {
+		ip = ip4
+	}
+	for _, rule := range a.rules {
+		if rule.network.Contains(ip) {
+			return rule.allow
+		}
+	}
+	return !a.hasAllow
+}
+
+// newConfigACL строит ACL из конфигурации; ошибки разбора отсеиваются при
+// проверке конфигурации, поэтому здесь некорректная ACL запрещает всех
+func newConfigACL(cfg Config) *ACL {
+	acl, err := NewACL(cfg.ACLAllow, cfg.ACLDeny)
+	if err != nil {
+		slog.Error("Некорректная ACL, все клиенты запрещены", "error", err)
+		return &ACL{hasAllow: true}
+	}
+	return acl
+}
+
+func parseCIDR(cidr string) (*net.IPNet, error) {
+	cidr = strings.TrimSpace(cidr)
+	if !strings.Contains(cidr, "/") {
+		ip := net.ParseIP(cidr)
+		if ip == nil {
+			return nil, fmt.Errorf("некорректный адрес в ACL: %s", cidr)
+		}
+		if ip4 := ip.To4(); ip4 != nil {
+			return &net.IPNet{IP: ip4, Mask: net.CIDRMask(32, 32)}, nil
+		}
+		return &net.IPNet{IP: ip, Mask: net.CIDRMask(128, 128)}, nil
+	}
+	_, network, err := net.ParseCIDR(cidr)
+	if err != nil {
+		return nil, fmt.Errorf("некорректная сеть в ACL: %s", cidr)
+	}
+	return network, nil
+}
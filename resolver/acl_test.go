@@ -0,0 +1,76 @@
+package resolver
+
+import (
+	"net"
+	"testing"
+
+	"github.com/miekg/dns"
+)
+
+func TestACL(t *testing.T) {
+	acl, err := NewACL(
+		[]string{"10.0.0.0/8", "192.0.2.1", "2001:db8::/32"},
+		[]string{"10.1.0.0/16", "10.1.2.3"},
+	)
+	if err != nil {
+		t.Fatalf("Ошибка разбора ACL: %v", err)
+	}
+
+	tests := map[string]bool{
+		"10.2.3.4":     true,  // разрешенная сеть
+		"192.0.2.1":    true,  // разрешенный адрес
+		"192.0.2.2":    false, // не попадает ни под одно правило
+		"10.1.9.9":     false, // запрещенная подсеть внутри разрешенной
+		"10.1.2.3":     false,
+		"2001:db8::53": true,
+		"2001:db9::53": false,
+	}
+	for addr, want := range tests {
+		if got := acl.Allowed(net.ParseIP(addr)); got != want {
+			t.Errorf("Allowed(%s) = %v, ожидалось %v", addr, got, want)
+		}
+	}
+}
+
+func TestACLOverlapMostSpecificWins(t *testing.T) {
+	acl, err := NewACL([]string{"10.1.2.0/24"}, []string{"10.0.0.0/8"})
+	if err != nil {
+		t.Fatalf("Ошибка разбора ACL: %v", err)
+	}
+	if !acl.Allowed(net.ParseIP("10.1.2.3")) {
+		t.Errorf("Более специфичное разрешение должно перекрывать запрет сети")
+	}
+	if acl.Allowed(net.ParseIP("10.1.3.3")) {
+		t.Errorf("Адрес вне разрешенной подсети должен быть запрещен")
+	}
+
+	denyOnly, _ := NewACL(nil, []string{"203.0.113.0/24"})
+	if !denyOnly.Allowed(net.ParseIP("198.51.100.1")) || denyOnly.Allowed(net.ParseIP("203.0.113.7")) {
+		t.Errorf("При пустом списке разрешений должны запрещаться только сети из списка запретов")
+	}
+
+	if _, err := NewACL([]string{"10.0.0.0/33"}, nil); err == nil {
+		t.Errorf("Ожидалась ошибка для некорректной сети")
+	}
+}
+
+func TestACLRefusesOrDropsClient(t *testing.T) {
+	s := NewDNSServer()
+	s.acl, _ = NewACL([]string{"127.0.0.0/8"}, nil)
+
+	req := new(dns.Msg)
+	req.SetQuestion("example.com.", dns.TypeA)
+
+	w := &testResponseWriter{remote: &net.UDPAddr{IP: net.ParseIP("198.51.100.1"), Port: 53000}}
+	s.handleRequest(w, req)
+	if w.msg == nil || w.msg.Rcode != dns.RcodeRefused {
+		t.Errorf("Ожидался REFUSED для запрещенного клиента, получено %v", w.msg)
+	}
+
+	s.cfg.ACLAction = aclActionDrop
+	w = &testResponseWriter{remote: &net.UDPAddr{IP: net.ParseIP("198.51.100.1"), Port: 53000}}
+	s.handleRequest(w, req)
+	if w.msg != nil {
+		t.Errorf("В режиме drop запрещенному клиенту не должен отправляться ответ, получено %v", w.msg)
+	}
+}
@@ -0,0 +1,109 @@
+package resolver
+
+import (
+	"encoding/json"
+	"log/slog"
+	"net"
+	"net/http"
+	"strings"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// adminAllowed разрешает служебные запросы только с loopback-адресов и
+// только клиентам, допущенным ACL
+func (s *DNSServer) adminAllowed(r *http.Request) bool {
+	host, _, err := net.SplitHostPort(r.RemoteAddr)
+	if err != nil {
+		host = r.RemoteAddr
+	}
+	ip := net.ParseIP(host)
+	return ip != nil && ip.IsLoopback() && s.acl.Allowed(ip)
+}
+
+// cacheHandler отдает содержимое кэша ответов в JSON с оставшимся временем
+// жизни каждой записи
+func (s *DNSServer) cacheHandler(w http.ResponseWriter, r *http.Request) {
+	if !s.adminAllowed(r) {
+		http.Error(w, "forbidden", http.StatusForbidden)
+		return
+	}
+	if r.Method != http.MethodGet {
+		w.Header().Set("Allow", http.MethodGet)
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+
+	w.Header().Set("Content-Type", "application/json")
+	if err := json.NewEncoder(w).Encode(s.cache.Dump(time.Now())); err != nil {
+		slog.Warn("Ошибка вывода содержимого кэша", "error", err)
+	}
+}
+
+// FlushCache очищает кэш ответов и кэш сбоев разрешения без перезапуска
+// сервера и возвращает количество удаленных ответов
+func (s *DNSServer) FlushCache() int {
+	removed := s.cache.Flush()
+	s.servfailCache.Range(func(key, _ interface{}) bool {
+		s.servfailCache.Delete(key)
+		return true
+	})
+	slog.Info("Кэш очищен", "removed", removed)
+	return removed
+}
+
+// FlushName удаляет из кэша ответы для имени name и типа qtype; при qtype 0
+// удаляются ответы всех типов
+func (s *DNSServer) FlushName(name string, qtype uint16) int {
+	removed := s.cache.RemoveName(name, qtype)
+	match := nameKeyMatcher(name, qtype)
+	s.servfailCache.Range(func(key, _ interface{}) bool {
+		if match(key.(string)) {
+			s.servfailCache.Delete(key)
+		}
+		return true
+	})
+	slog.Info("Имя удалено из кэша", "name", name, "qtype", dns.TypeToString[qtype], "removed", removed)
+	return removed
+}
+
+// flushHandler очищает кэш по POST-запросу: целиком или, если задан
+// параметр name, только для этого имени (и типа type, если он задан)
+func (s *DNSServer) flushHandler(w http.ResponseWriter, r *http.Request) {
+	if !s.adminAllowed(r) {
+		http.Error(w, "forbidden", http.StatusForbidden)
+		return
+	}
+	if r.Method != http.MethodPost {
+		w.Header().Set("Allow", http.MethodPost)
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+
+	name := r.URL.Query().Get("name")
+	typeStr := r.URL.Query().Get("type")
+	var removed int
+	switch {
+	case name != "":
+		var qtype uint16
+		if typeStr != "" {
+			var ok bool
+			if qtype, ok = dns.StringToType[strings.ToUpper(typeStr)]; !ok {
+				http.Error(w, "unknown type", http.StatusBadRequest)
+				return
+			}
+		}
+		removed = s.FlushName(name, qtype)
+	case typeStr != "":
+		http.Error(w, "type requires name", http.StatusBadRequest)
+		return
+	default:
+		removed = s.FlushCache()
+	}
+
+	w.Header().Set("Content-Type", "application/json")
+	if err := json.NewEncoder(w).Encode(map[string]int{"removed": removed}); err != nil {
+		slog.Warn("Ошибка вывода результата очистки кэша", "error", err)
+	}
+}
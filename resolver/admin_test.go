@@ -0,0 +1,171 @@
+package resolver
+
+import (
+	"encoding/json"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+func TestCacheDumpEndpoint(t *testing.T) {
+	s := NewDNSServer()
+	s.cache.Set(cacheKey("example.com.", dns.TypeA), &CacheEntry{
+		Name:      "example.com.",
+		Qtype:     dns.TypeA,
+		Records:   []dns.RR{mustRR(t, "example.com. 300 IN A 192.0.2.1")},
+		ExpiresAt: time.Now().Add(300 * time.Second),
+	})
+	s.cache.Set(cacheKey("example.org.", dns.TypeAAAA), &CacheEntry{
+		Name:      "example.org.",
+		Qtype:     dns.TypeAAAA,
+		Records:   []dns.RR{mustRR(t, "example.org. 60 IN AAAA 2001:db8::1")},
+		ExpiresAt: time.Now().Add(60 * time.Second),
+	})
+
+	srv := httptest.NewServer(http.HandlerFunc(s.cacheHandler))
+	defer srv.Close()
+
+	resp, err := http.Get(srv.URL + "/cache")
+	if err != nil {
+		t.Fatalf("Ошибка запроса содержимого кэша: %v", err)
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode != http.StatusOK {
+		t.Fatalf("Ожидался код 200, получен %d", resp.StatusCode)
+	}
+
+	var entries []CacheDumpEntry
+	if err := json.NewDecoder(resp.Body).Decode(&entries); err != nil {
+		t.Fatalf("Ошибка разбора JSON: %v", err)
+	}
+	if len(entries) != 2 {
+		t.Fatalf("Ожидалось 2 записи, получено %d", len(entries))
+	}
+
+	want := map[string]struct {
+		qtype string
+		ttl   int64
+	}{
+		"example.com.": {"A", 300},
+		"example.org.": {"AAAA", 60},
+	}
+	for _, entry := range entries {
+		w, ok := want[entry.Name]
+		if !ok {
+			t.Errorf("Неожиданная запись %s", entry.Name)
+			continue
+		}
+		if entry.Type != w.qtype {
+			t.Errorf("%s: тип %s, ожидался %s", entry.Name, entry.Type, w.qtype)
+		}
+		if entry.TTLRemaining > w.ttl || entry.TTLRemaining < w.ttl-5 {
+			t.Errorf("%s: оставшийся TTL %d, ожидался около %d", entry.Name, entry.TTLRemaining, w.ttl)
+		}
+		if len(entry.Records) != 1 {
+			t.Errorf("%s: ожидалась 1 запись, получено %d", entry.Name, len(entry.Records))
+		}
+	}
+}
+
+func TestCacheDumpRejectsRemoteClients(t *testing.T) {
+	s := NewDNSServer()
+
+	req := httptest.NewRequest(http.MethodGet, "/cache", nil)
+	req.RemoteAddr = "192.0.2.10:40000"
+	rec := httptest.NewRecorder()
+	s.cacheHandler(rec, req)
+	if rec.Code != http.StatusForbidden {
+		t.Errorf("Запрос с внешнего адреса должен отклоняться, получен код %d", rec.Code)
+	}
+
+	// Loopback-клиент, запрещенный ACL, тоже не допускается
+	acl, err := NewACL(nil, []string{"127.0.0.0/8"})
+	if err != nil {
+		t.Fatalf("Ошибка создания ACL: %v", err)
+	}
+	s.acl = acl
+	req = httptest.NewRequest(http.MethodGet, "/cache", nil)
+	req.RemoteAddr = "127.0.0.1:40000"
+	rec = httptest.NewRecorder()
+	s.cacheHandler(rec, req)
+	if rec.Code != http.StatusForbidden {
+		t.Errorf("Запрещенный ACL клиент должен отклоняться, получен код %d", rec.Code)
+	}
+}
+
+func flushRequest(s *DNSServer, query string) *httptest.ResponseRecorder {
+	req := httptest.NewRequest(http.MethodPost, "/cache/flush"+query, nil)
+	req.RemoteAddr = "127.0.0.1:40000"
+	rec := httptest.NewRecorder()
+	s.flushHandler(rec, req)
+	return rec
+}
+
+func TestCacheFlushEndpoint(t *testing.T) {
+	s := NewDNSServer()
+	for _, name := range []string{"example.com.", "example.org."} {
+		s.cache.Set(cacheKey(name, dns.TypeA), newTestEntry(t, name, time.Minute))
+	}
+	s.servfailCache.Store(cacheKey("broken.example.", dns.TypeA), time.Now().Add(time.Minute))
+
+	if rec := flushRequest(s, "?name=example.com&type=a"); rec.Code != http.StatusOK {
+		t.Fatalf("Ожидался код 200, получен %d", rec.Code)
+	}
+	if _, ok := s.cache.Get(cacheKey("example.com.", dns.TypeA)); ok {
+		t.Error("Запись example.com. не удалена")
+	}
+	if _, ok := s.cache.Get(cacheKey("example.org.", dns.TypeA)); !ok {
+		t.Error("Запись example.org. не должна удаляться при очистке одного имени")
+	}
+
+	if rec := flushRequest(s, "?type=A"); rec.Code != http.StatusBadRequest {
+		t.Errorf("Тип без имени должен отклоняться, получен код %d", rec.Code)
+	}
+
+	rec := flushRequest(s, "")
+	if rec.Code != http.StatusOK {
+		t.Fatalf("Ожидался код 200, получен %d", rec.Code)
+	}
+	var result map[string]int
+	if err := json.NewDecoder(rec.Body).Decode(&result); err != nil || result["removed"] != 1 {
+		t.Errorf("Ожидалось {\"removed\": 1}, получено %v (ошибка %v)", result, err)
+	}
+	if s.cache.Len() != 0 {
+		t.Errorf("После полной очистки кэш должен быть пуст, записей: %d", s.cache.Len())
+	}
+	if _, ok := s.servfailCache.Load(cacheKey("broken.example.", dns.TypeA)); ok {
+		t.Error("Полная очистка должна сбрасывать кэш сбоев")
+	}
+
+	get := httptest.NewRequest(http.MethodGet, "/cache/flush", nil)
+	get.RemoteAddr = "127.0.0.1:40000"
+	getRec := httptest.NewRecorder()
+	s.flushHandler(getRec, get)
+	if getRec.Code != http.StatusMethodNotAllowed {
+		t.Errorf("GET не должен очищать кэш, получен код %d", getRec.Code)
+	}
+}
+
+func TestCacheFlushDuringQueries(t *testing.T) {
+	s := NewDNSServer()
+	key := cacheKey("example.com.", dns.TypeA)
+	entry := newTestEntry(t, "example.com.", time.Minute)
+
+	done := make(chan struct{})
+	go func() {
+		defer close(done)
+		for i := 0; i < 1000; i++ {
+			s.cache.Set(key, entry)
+			s.cache.Get(key)
+			s.cache.Dump(time.Now())
+		}
+	}()
+	for i := 0; i < 100; i++ {
+		s.FlushCache()
+		s.FlushName("example.com.", 0)
+	}
+	<-done
+}
@@ -0,0 +1,174 @@
+package resolver
+
+import (
+	"context"
+	"fmt"
+	"runtime"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// newBenchServer создает сервер без ограничения частоты запросов, чтобы
+// бенчмарк измерял обработку, а не отказы
+func newBenchServer() *DNSServer {
+	s := NewDNSServer()
+	s.rateLimiter = NewRateLimiter(0, 0)
+	s.transport = &cannedTransport{}
+	s.forwarders = []string{"192.0.2.53:53"}
+	return s
+}
+
+// BenchmarkHandleRequestCached измеряет обработку запроса, ответ на который
+// уже есть в кэше
+func BenchmarkHandleRequestCached(b *testing.B) {
+	s := newBenchServer()
+	rr, _ := dns.NewRR("example.com. 300 IN A 192.0.2.1")
+	s.cache.Set(cacheKey("example.com.", dns.TypeA), &CacheEntry{
+		Name:      "example.com.",
+		Qtype:     dns.TypeA,
+		Records:   []dns.RR{rr},
+		ExpiresAt: time.Now().Add(time.Hour),
+	})
+
+	req := new(dns.Msg)
+	req.SetQuestion("example.com.", dns.TypeA)
+	req.SetEdns0(1232, false)
+
+	b.ReportAllocs()
+	b.ResetTimer()
+	for i := 0; i < b.N; i++ {
+		s.handleRequest(&testResponseWriter{}, req)
+	}
+}
+
+// BenchmarkHandleRequestParallel измеряет пропускную способность кэша при
+// одновременных запросах
+func BenchmarkHandleRequestParallel(b *testing.B) {
+	s := newBenchServer()
+	const names = 64
+	for i := 0; i < names; i++ {
+		name := fmt.Sprintf("host%d.example.", i)
+		rr, _ := dns.NewRR(name + " 300 IN A 192.0.2.1")
+		s.cache.Set(cacheKey(name, dns.TypeA), &CacheEntry{
+			Name:      name,
+			Qtype:     dns.TypeA,
+			Records:   []dns.RR{rr},
+			ExpiresAt: time.Now().Add(time.Hour),
+		})
+	}
+
+	b.ReportAllocs()
+	b.ResetTimer()
+	b.RunParallel(func(pb *testing.PB) {
+		i := 0
+		for pb.Next() {
+			req := new(dns.Msg)
+			req.SetQuestion(fmt.Sprintf("host%d.example.", i%names), dns.TypeA)
+			s.handleRequest(&testResponseWriter{}, req)
+			i++
+		}
+	})
+}
+
+// BenchmarkHandleRequestForward измеряет полный путь разрешения с промахом
+// кэша; вышестоящий сервер заменен транспортом без сети
+func BenchmarkHandleRequestForward(b *testing.B) {
+	s := newBenchServer()
+
+	b.ReportAllocs()
+	b.ResetTimer()
+	for i := 0; i < b.N; i++ {
+		req := new(dns.Msg)
+		req.SetQuestion(fmt.Sprintf("host%d.example.", i), dns.TypeA)
+		s.handleRequest(&testResponseWriter{}, req)
+	}
+}
+
+// BenchmarkMessagePackUnpack измеряет разбор запроса и упаковку ответа
+func BenchmarkMessagePackUnpack(b *testing.B) {
+	req := new(dns.Msg)
+	req.SetQuestion("www.example.com.", dns.TypeA)
+	req.SetEdns0(1232, true)
+	query, err := req.Pack()
+	if err != nil {
+		b.Fatalf("Не удалось упаковать запрос: %v", err)
+	}
+
+	reply := new(dns.Msg)
+	reply.SetReply(req)
+	reply.Compress = true
+	for _, s := range []string{
+		"www.example.com. 300 IN CNAME web.example.com.",
+		"web.example.com. 300 IN A 192.0.2.1",
+		"web.example.com. 300 IN A 192.0.2.2",
+		"example.com. 3600 IN NS ns1.example.com.",
+	} {
+		rr, _ := dns.NewRR(s)
+		reply.Answer = append(reply.Answer, rr)
+	}
+
+	b.ReportAllocs()
+	b.ResetTimer()
+	for i := 0; i < b.N; i++ {
+		msg := new(dns.Msg)
+		if err := msg.Unpack(query); err != nil {
+			b.Fatal(err)
+		}
+		if _, err := reply.Pack(); err != nil {
+			b.Fatal(err)
+		}
+	}
+}
+
+// BenchmarkUDPListeners измеряет пропускную способность приема по UDP с
+// одним сокетом и с сокетом SO_REUSEPORT на каждый процессор
+func BenchmarkUDPListeners(b *testing.B) {
+	for _, n := range []int{1, runtime.GOMAXPROCS(0)} {
+		b.Run(fmt.Sprintf("listeners=%d", n), func(b *testing.B) {
+			addr := freeUDPAddr(b, "udp4", "127.0.0.1:0")
+			s := newBenchServer()
+			s.cacheFile = ""
+			s.metricsAddr = ""
+			s.cfg.HealthListen = ""
+			s.cfg.UDPListeners = n
+			rr, _ := dns.NewRR("example.com. 300 IN A 192.0.2.1")
+			s.cache.Set(cacheKey("example.com.", dns.TypeA), &CacheEntry{
+				Name:      "example.com.",
+				Qtype:     dns.TypeA,
+				Records:   []dns.RR{rr},
+				ExpiresAt: time.Now().Add(time.Hour),
+			})
+			go s.Start(addr)
+			waitServing(b, s)
+			defer s.Shutdown(context.Background())
+
+			b.SetParallelism(8)
+			b.ResetTimer()
+			b.RunParallel(func(pb *testing.PB) {
+				// Отдельный сокет на горутину: ядро распределяет клиентов
+				// по сокетам сервера по адресу и порту источника
+				conn, err := dns.Dial("udp", addr)
+				if err != nil {
+					b.Errorf("Не удалось открыть сокет: %v", err)
+					return
+				}
+				defer conn.Close()
+				req := new(dns.Msg)
+				req.SetQuestion("example.com.", dns.TypeA)
+				for pb.Next() {
+					conn.SetDeadline(time.Now().Add(time.Second))
+					if err := conn.WriteMsg(req); err != nil {
+						b.Errorf("Ошибка отправки: %v", err)
+						return
+					}
+					if _, err := conn.ReadMsg(); err != nil {
+						b.Errorf("Ошибка приема: %v", err)
+						return
+					}
+				}
+			})
+		})
+	}
+}
@@ -0,0 +1,142 @@
+package resolver
+
+import (
+	"bufio"
+	"fmt"
+	"io"
+	"log/slog"
+	"net"
+	"os"
+	"strings"
+
+	"github.com/miekg/dns"
+)
+
+const blockedTTL = 300
+
+// ignoredHostsNames - служебные имена из hosts-файлов, которые не блокируются
+var ignoredHostsNames = map[string]bool{
+	"localhost.":             true,
+	"localhost.localdomain.": true,
+	"local.":                 true,
+	"broadcasthost.":         true,
+	"ip6-localhost.":         true,
+	"ip6-loopback.":          true,
+}
+
+// Blocklist - список блокируемых доменов (реклама, трекеры, вредоносные сайты)
+type Blocklist struct {
+	domains           map[string]struct{}
+	includeSubdomains bool // блокировать также все поддомены из списка
+	sinkhole          bool // отвечать 0.0.0.0 / :: вместо NXDOMAIN
+}
+
+// LoadBlocklist загружает список из файла в формате hosts или по одному домену в строке
+func LoadBlocklist(path string, includeSubdomains, sinkhole bool) (*Blocklist, error) {
+	f, err := os.Open(path)
+	if err != nil {
+		return nil, err
+	}
+	defer f.Close()
+
+	return parseBlocklist(f, includeSubdomains, sinkhole)
+}
+
+func parseBlocklist(r io.Reader, includeSubdomains, sinkhole bool) (*Blocklist, error) {
+	b := &Blocklist{
+		domains:           make(map[string]struct{}),
+		includeSubdomains: includeSubdomains,
+		sinkhole:          sinkhole,
+	}
+
+	scanner := bufio.NewScanner(r)
+	for scanner.Scan() {
+		line := scanner.Text()
+		if idx := strings.Index(line, "#"); idx >= 0 {
+			line = line[:idx]
+		}
+		fields := strings.Fields(line)
+		if len(fields) == 0 {
+			continue
+		}
+
+		// Формат hosts: первым полем идет адрес, за ним имена
+		names := fields
+		if len(fields) > 1 && net.ParseIP(fields[0]) != nil {
+			names = fields[1:]
+		}
+		for _, name := range names {
+			name = strings.ToLower(dns.Fqdn(name))
+			if ignoredHostsNames[name] || net.ParseIP(strings.TrimSuffix(name, ".")) != nil {
+				continue
+			}
+			if _, ok := dns.IsDomainName(name); !ok {
+				return nil, fmt.Errorf("некорректное имя в списке блокировки: %s", name)
+			}
+			b.domains[name] = struct{}{}
+		}
+	}
+	if err := scanner.Err(); err != nil {
+		return nil, err
+	}
+	return b, nil
+}
+
+// Blocked сообщает, должно ли имя быть заблокировано
+func (b *Blocklist) Blocked(name string) bool {
+	if b == nil || len(b.domains) == 0 {
+		return false
+	}
+
+	name = strings.ToLower(dns.Fqdn(name))
+	for {
+		if _, ok := b.domains[name]; ok {
+			return true
+		}
+		if !b.includeSubdomains {
+			return false
+		}
+		idx := strings.Index(name, ".")
+		if idx < 0 || idx == len(name)-1 {
+			return false
+		}
+		name = name[idx+1:]
+	}
+}
+
+// Len возвращает количество доменов в списке
+func (b *Blocklist) Len() int {
+	if b == nil {
+		return 0
+	}
+	return len(b.domains)
+}
+
+// sendBlockedResponse отвечает на запрос заблокированного имени
+func (s *DNSServer) sendBlockedResponse(w dns.ResponseWriter, req *dns.Msg) {
+	question := req.Question[0]
+	slog.Info("Домен заблокирован списком фильтрации", "qname", question.Name)
+
+	reply := new(dns.Msg)
+	reply.SetReply(req)
+	reply.RecursionAvailable = true
+
+	hdr := dns.RR_Header{Name: question.Name, Class: dns.ClassINET, Ttl: blockedTTL}
+	switch {
+	case s.blocklist.sinkhole && question.Qtype == dns.TypeA:
+		hdr.Rrtype = dns.TypeA
+		reply.Answer = append(reply.Answer, &dns.A{Hdr: hdr, A: net.IPv4zero})
+	case s.blocklist.sinkhole && question.Qtype == dns.TypeAAAA:
+		hdr.Rrtype = dns.TypeAAAA
+		reply.Answer = append(reply.Answer, &dns.AAAA{Hdr: hdr, AAAA: net.IPv6unspecified})
+	case s.blocklist.sinkhole:
+		// Для остальных типов при sinkhole отвечаем пустым NOERROR
+	default:
+		reply.Rcode = dns.RcodeNameError
+	}
+	s.addEDE(reply, req, dns.ExtendedErrorCodeBlocked, "")
+
+	if err := w.WriteMsg(reply); err != nil {
+		slog.Error("Ошибка записи ответа", "error", err)
+	}
+}
@@ -0,0 +1,73 @@
+package resolver
+
+import (
+	"strings"
+	"testing"
+
+	"github.com/miekg/dns"
+)
+
+const sampleBlocklist = `# Пример списка блокировки
+127.0.0.1 localhost
+0.0.0.0 ads.example.com tracker.example.net
+malware.example.org
+`
+
+func TestBlocklistMatching(t *testing.T) {
+	list, err := parseBlocklist(strings.NewReader(sampleBlocklist), true, false)
+	if err != nil {
+		t.Fatalf("Ошибка разбора списка: %v", err)
+	}
+
+	for name, want := range map[string]bool{
+		"ads.example.com.":        true,  // точное совпадение
+		"ADS.Example.com":         true,  // регистр и точка в конце не важны
+		"cdn.ads.example.com.":    true,  // поддомен
+		"malware.example.org.":    true,  // формат "домен в строке"
+		"example.com.":            false, // родительский домен не блокируется
+		"notads.example.com.":     false,
+		"www.example.org.":        false,
+		"localhost.":              false,
+		"tracker.example.net.com": false,
+	} {
+		if got := list.Blocked(name); got != want {
+			t.Errorf("Blocked(%s) = %v, ожидалось %v", name, got, want)
+		}
+	}
+}
+
+func TestBlocklistExactOnly(t *testing.T) {
+	list, err := parseBlocklist(strings.NewReader(sampleBlocklist), false, false)
+	if err != nil {
+		t.Fatalf("Ошибка разбора списка: %v", err)
+	}
+	if !list.Blocked("ads.example.com.") {
+		t.Errorf("Точное совпадение должно блокироваться")
+	}
+	if list.Blocked("cdn.ads.example.com.") {
+		t.Errorf("Поддомен не должен блокироваться без includeSubdomains")
+	}
+}
+
+func TestHandleRequestBlockedDomain(t *testing.T) {
+	s := NewDNSServer()
+	s.blocklist, _ = parseBlocklist(strings.NewReader(sampleBlocklist), true, false)
+
+	req := new(dns.Msg)
+	req.SetQuestion("ads.example.com.", dns.TypeA)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+	if w.msg == nil || w.msg.Rcode != dns.RcodeNameError {
+		t.Errorf("Ожидался NXDOMAIN для заблокированного домена, получено %v", w.msg)
+	}
+
+	s.blocklist.sinkhole = true
+	w = &testResponseWriter{}
+	s.handleRequest(w, req)
+	if w.msg == nil || len(w.msg.Answer) != 1 {
+		t.Fatalf("Ожидалась sinkhole-запись, получено %v", w.msg)
+	}
+	if a, ok := w.msg.Answer[0].(*dns.A); !ok || !a.A.Equal([]byte{0, 0, 0, 0}) {
+		t.Errorf("Ожидался ответ 0.0.0.0, получено %v", w.msg.Answer[0])
+	}
+}
@@ -0,0 +1,45 @@
+package resolver
+
+import (
+	"context"
+	"errors"
+	"sync/atomic"
+)
+
+// errQueryBudget возвращается, когда разрешение исчерпало лимит исходящих запросов
+var errQueryBudget = errors.New("исчерпан лимит исходящих запросов")
+
+// queryBudget - число исходящих запросов, оставшееся у одного разрешения.
+// Защищает от петель делегирования и зон с чрезмерной вложенностью.
+type queryBudget struct {
+	remaining int64
+}
+
+type budgetKey struct{}
+
+// withQueryBudget ограничивает разрешение в ctx n исходящими запросами;
+// n <= 0 снимает ограничение
+func withQueryBudget(ctx context.Context, n int) context.Context {
+	if n <= 0 {
+		return ctx
+	}
+	return context.WithValue(ctx, budgetKey{}, &queryBudget{remaining: int64(n)})
+}
+
+// spendQuery списывает один запрос из бюджета ctx
+func spendQuery(ctx context.Context) error {
+	b, ok := ctx.Value(budgetKey{}).(*queryBudget)
+	if !ok {
+		return nil
+	}
+	if atomic.AddInt64(&b.remaining, -1) < 0 {
+		return errQueryBudget
+	}
+	return nil
+}
+
+// budgetExhausted сообщает, была ли отклонена попытка запроса сверх бюджета ctx
+func budgetExhausted(ctx context.Context) bool {
+	b, ok := ctx.Value(budgetKey{}).(*queryBudget)
+	return ok && atomic.LoadInt64(&b.remaining) < 0
+}
@@ -0,0 +1,677 @@
+package resolver
+
+import (
+	"container/list"
+	"encoding/json"
+	"fmt"
+	"log/slog"
+	"net/netip"
+	"os"
+	"strings"
+	"sync"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// CacheEntry содержит закэшированный ответ на вопрос
+type CacheEntry struct {
+	Name       string
+	Qtype      uint16
+	Records    []dns.RR
+	Authority  []dns.RR
+	Additional []dns.RR
+	ExpiresAt  time.Time
+
+	// TTL - срок хранения, с которым запись попала в кэш; по нему
+	// выбирается момент упреждающего обновления
+	TTL time.Duration
+
+	// Subnet - подсеть клиентов (ECS), для которой подобран ответ; нулевое
+	// значение - ответ общий для всех клиентов
+	Subnet netip.Prefix
+
+	// Rcode - код ответа; у отрицательной записи (NXDOMAIN или NODATA) нет
+	// записей ответа, а SOA хранится в Authority
+	Rcode int
+}
+
+// Cache - кэш ответов с ограничением по количеству записей и вытеснением
+// давно не использовавшихся записей (LRU)
+type Cache struct {
+	mu          sync.Mutex
+	maxEntries  int
+	staleWindow time.Duration // Сколько истёкшие записи хранятся для serve-stale (RFC 8767)
+	entries     map[string]*list.Element
+	lru         *list.List // от недавно использованных к давно неиспользованным
+
+	// l2 - необязательный второй уровень на диске: вытесненные из памяти
+	// записи переносятся туда и возвращаются в память при обращении
+	l2 *DiskCache
+}
+
+type cacheItem struct {
+	key   string
+	entry *CacheEntry
+}
+
+// persistedEntry - сериализуемое представление записи кэша
+type persistedEntry struct {
+	Key        string    `json:"key"`
+	Name       string    `json:"name"`
+	Qtype      uint16    `json:"qtype"`
+	Records    []string  `json:"records"`
+	Authority  []string  `json:"authority,omitempty"`
+	Additional []string  `json:"additional,omitempty"`
+	ExpiresAt  time.Time `json:"expires_at"`
+	TTL        Duration  `json:"ttl"`
+	Subnet     string    `json:"subnet,omitempty"`
+	Rcode      int       `json:"rcode,omitempty"`
+}
+
+// NewCache создает кэш, хранящий не более maxEntries записей
+func NewCache(maxEntries int) *Cache {
+	return NewStaleCache(maxEntries, 0)
+}
+
+// NewStaleCache создает кэш, который хранит истёкшие записи ещё staleWindow,
+// чтобы отдавать их, когда вышестоящие серверы недоступны
+func NewStaleCache(maxEntries int, staleWindow time.Duration) *Cache {
+	return &Cache{
+		maxEntries:  maxEntries,
+		staleWindow: staleWindow,
+		entries:     make(map[string]*list.Element),
+		lru:         list.New(),
+	}
+}
+
+// SetSecondary подключает к кэшу второй уровень на диске
+func (c *Cache) SetSecondary(l2 *DiskCache) {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	c.l2 = l2
+}
+
+// Get возвращает копию записей, если они есть в кэше и не истекли
+func (c *Cache) Get(key string) ([]dns.RR, bool) {
+	if records, ok := c.get(key); ok || !c.promote(key) {
+		return records, ok
+	}
+	return c.get(key)
+}
+
+func (c *Cache) get(key string) ([]dns.RR, bool) {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+
+	elem, ok := c.entries[key]
+	if !ok {
+		return nil, false
+	}
+
+	item := elem.Value.(*cacheItem)
+	now := time.Now()
+	if now.After(item.entry.ExpiresAt) {
+		if c.evictable(item.entry, now) {
+			c.removeElement(elem)
+		}
+		return nil, false
+	}
+
+	c.lru.MoveToFront(elem)
+	return copyRecords(item.entry.Records), true
+}
+
+// GetEntry возвращает копию записи кэша вместе с секциями authority и additional
+func (c *Cache) GetEntry(key string) (*CacheEntry, bool) {
+	if entry, ok := c.getEntry(key); ok || !c.promote(key) {
+		return entry, ok
+	}
+	return c.getEntry(key)
+}
+
+func (c *Cache) getEntry(key string) (*CacheEntry, bool) {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+
+	elem, ok := c.entries[key]
+	if !ok {
+		return nil, false
+	}
+
+	item := elem.Value.(*cacheItem)
+	now := time.Now()
+	if now.After(item.entry.ExpiresAt) {
+		if c.evictable(item.entry, now) {
+			c.removeElement(elem)
+		}
+		return nil, false
+	}
+
+	c.lru.MoveToFront(elem)
+	entry := *item.entry
+	entry.Records = copyRecords(entry.Records)
+	entry.Authority = copyRecords(entry.Authority)
+	entry.Additional = copyRecords(entry.Additional)
+	return &entry, true
+}
+
+// GetStale возвращает записи, истёкшие не более staleWindow назад
+func (c *Cache) GetStale(key string) ([]dns.RR, bool) {
+	if records, ok := c.getStale(key); ok || !c.promote(key) {
+		return records, ok
+	}
+	return c.getStale(key)
+}
+
+func (c *Cache) getStale(key string) ([]dns.RR, bool) {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+
+	elem, ok := c.entries[key]
+	if !ok {
+		return nil, false
+	}
+
+	item := elem.Value.(*cacheItem)
+	if c.evictable(item.entry, time.Now()) {
+		c.removeElement(elem)
+		return nil, false
+	}
+	// Устаревший отрицательный ответ не заменяет SERVFAIL: в нем нет
+	// записей, которые можно было бы отдать
+	if item.entry.negative() {
+		return nil, false
+	}
+	return copyRecords(item.entry.Records), true
+}
+
+// Set добавляет запись в кэш, вытесняя самые старые записи при превышении
+// лимита. При подключенном втором уровне вытесненные записи переносятся на диск.
+func (c *Cache) Set(key string, entry *CacheEntry) {
+	l2, evicted := c.set(key, entry)
+	if l2 == nil {
+		return
+	}
+	now := time.Now()
+	for _, item := range evicted {
+		if c.evictable(item.entry, now) {
+			continue
+		}
+		if err := l2.Store(item.key, item.entry); err != nil {
+			slog.Warn("Не удалось перенести запись кэша на диск", "key", item.key, "error", err)
+		}
+	}
+}
+
+// set добавляет запись в память и возвращает вытесненные записи. Запись на
+// диск выполняется вызывающим без удержания блокировки.
+func (c *Cache) set(key string, entry *CacheEntry) (*DiskCache, []cacheItem) {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+
+	if elem, ok := c.entries[key]; ok {
+		elem.Value.(*cacheItem).entry = entry
+		c.lru.MoveToFront(elem)
+		return nil, nil
+	}
+
+	c.entries[key] = c.lru.PushFront(&cacheItem{key: key, entry: entry})
+
+	var evicted []cacheItem
+	for c.maxEntries > 0 && c.lru.Len() > c.maxEntries {
+		back := c.lru.Back()
+		evicted = append(evicted, *back.Value.(*cacheItem))
+		c.removeElement(back)
+	}
+	return c.l2, evicted
+}
+
+// promote переносит запись со второго уровня в память. Возвращает false,
+// если второго уровня нет или на нем нет пригодной записи.
+func (c *Cache) promote(key string) bool {
+	c.mu.Lock()
+	l2 := c.l2
+	c.mu.Unlock()
+	if l2 == nil {
+		return false
+	}
+
+	entry, ok := l2.Take(key)
+	if !ok || c.evictable(entry, time.Now()) {
+		return false
+	}
+	c.Set(key, entry)
+	return true
+}
+
+// Len возвращает количество записей в памяти (без второго уровня)
+func (c *Cache) Len() int {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	return c.lru.Len()
+}
+
+// Flush удаляет все записи кэша, включая второй уровень, и возвращает их количество
+func (c *Cache) Flush() int {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+
+	removed := c.lru.Len()
+	c.entries = make(map[string]*list.Element)
+	c.lru.Init()
+	if c.l2 != nil {
+		removed += c.l2.RemoveIf(func(string, time.Time) bool { return true })
+	}
+	return removed
+}
+
+// RemoveName удаляет записи для имени name и типа qtype; при qtype 0
+// удаляются записи всех типов. Возвращает количество удаленных записей.
+func (c *Cache) RemoveName(name string, qtype uint16) int {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+
+	match := nameKeyMatcher(name, qtype)
+	removed := 0
+	for key, elem := range c.entries {
+		if match(key) {
+			c.removeElement(elem)
+			removed++
+		}
+	}
+	if c.l2 != nil {
+		removed += c.l2.RemoveIf(func(key string, _ time.Time) bool {
+			return match(key)
+		})
+	}
+	return removed
+}
+
+// nameKeyMatcher возвращает проверку, относится ли ключ кэша к имени name и
+// типу qtype (при qtype 0 - к любому типу), включая ответы для отдельных
+// подсетей ECS
+func nameKeyMatcher(name string, qtype uint16) func(key string) bool {
+	if qtype != 0 {
+		exact := cacheKey(name, qtype)
+		return func(key string) bool {
+			return key == exact || strings.HasPrefix(key, exact+"/")
+		}
+	}
+	prefix := strings.TrimSuffix(cacheKey(name, 0), "0")
+	return func(key string) bool {
+		return strings.HasPrefix(key, prefix)
+	}
+}
+
+// RemoveExpired удаляет записи, истёкшие более staleWindow назад, в том
+// числе со второго уровня, и возвращает их количество
+func (c *Cache) RemoveExpired(now time.Time) int {
+	c.mu.Lock()
+	removed := 0
+	for elem := c.lru.Front(); elem != nil; {
+		next := elem.Next()
+		if c.evictable(elem.Value.(*cacheItem).entry, now) {
+			c.removeElement(elem)
+			removed++
+		}
+		elem = next
+	}
+	l2 := c.l2
+	c.mu.Unlock()
+
+	// Обход каталога может быть долгим, поэтому выполняется без блокировки
+	if l2 != nil {
+		removed += l2.RemoveIf(func(_ string, expiresAt time.Time) bool {
+			return now.After(expiresAt.Add(c.staleWindow))
+		})
+	}
+	return removed
+}
+
+// Expiring возвращает ключи и записи, которые ещё не истекли, но истекут
+// раньше, чем через lead(entry)
+func (c *Cache) Expiring(now time.Time, lead func(entry *CacheEntry) time.Duration) []cacheItem {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+
+	var items []cacheItem
+	for elem := c.lru.Front(); elem != nil; elem = elem.Next() {
+		item := elem.Value.(*cacheItem)
+		// Отрицательные ответы не обновляются упреждающе: поток запросов
+		// к несуществующим именам не должен удваивать нагрузку
+		if item.entry.negative() {
+			continue
+		}
+		if !now.After(item.entry.ExpiresAt) && item.entry.ExpiresAt.Before(now.Add(lead(item.entry))) {
+			items = append(items, *item)
+		}
+	}
+	return items
+}
+
+// CacheDumpEntry - запись кэша в отладочном выводе
+type CacheDumpEntry struct {
+	Key          string   `json:"key"`
+	Name         string   `json:"name"`
+	Type         string   `json:"type"`
+	Records      []string `json:"records"`
+	TTLRemaining int64    `json:"ttl_remaining"` // секунды; отрицательное значение - запись устарела
+}
+
+// Dump возвращает содержимое кэша от недавно использованных записей к давно
+// неиспользованным, не меняя порядок LRU
+func (c *Cache) Dump(now time.Time) []CacheDumpEntry {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+
+	entries := make([]CacheDumpEntry, 0, c.lru.Len())
+	for elem := c.lru.Front(); elem != nil; elem = elem.Next() {
+		item := elem.Value.(*cacheItem)
+		entries = append(entries, CacheDumpEntry{
+			Key:          item.key,
+			Name:         item.entry.Name,
+			Type:         dns.TypeToString[item.entry.Qtype],
+			Records:      recordStrings(item.entry.Records),
+			TTLRemaining: int64(item.entry.ExpiresAt.Sub(now) / time.Second),
+		})
+	}
+	return entries
+}
+
+// Save сохраняет неистёкшие записи кэша в файл и возвращает их количество
+func (c *Cache) Save(path string) (int, error) {
+	c.mu.Lock()
+	now := time.Now()
+	entries := make([]persistedEntry, 0, c.lru.Len())
+	// Запись от давно использованных к недавним сохраняет порядок LRU при загрузке
+	for elem := c.lru.Back(); elem != nil; elem = elem.Prev() {
+		item := elem.Value.(*cacheItem)
+		if now.After(item.entry.ExpiresAt) {
+			continue
+		}
+		entries = append(entries, newPersistedEntry(item.key, item.entry))
+	}
+	c.mu.Unlock()
+
+	data, err := json.Marshal(entries)
+	if err != nil {
+		return 0, err
+	}
+
+	tmpPath := path + ".tmp"
+	if err := os.WriteFile(tmpPath, data, 0o644); err != nil {
+		return 0, err
+	}
+	if err := os.Rename(tmpPath, path); err != nil {
+		return 0, err
+	}
+	return len(entries), nil
+}
+
+// Load загружает записи кэша из файла, пропуская уже истёкшие
+func (c *Cache) Load(path string) (int, error) {
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return 0, err
+	}
+
+	var entries []persistedEntry
+	if err := json.Unmarshal(data, &entries); err != nil {
+		return 0, err
+	}
+
+	now := time.Now()
+	loaded := 0
+	for _, pe := range entries {
+		if now.After(pe.ExpiresAt) {
+			continue
+		}
+
+		entry, err := pe.cacheEntry()
+		if err != nil {
+			continue
+		}
+		// Ключ строится заново: файл мог быть записан с именем без точки
+		// в конце или в другом регистре, и такая запись не нашлась бы
+		key := pe.Key
+		if entry.Name != "" {
+			key = subnetKey(cacheKey(entry.Name, entry.Qtype), entry.Subnet)
+		}
+		c.Set(key, entry)
+		loaded++
+	}
+	return loaded, nil
+}
+
+// newPersistedEntry переводит запись кэша в сериализуемый вид
+func newPersistedEntry(key string, entry *CacheEntry) persistedEntry {
+	pe := persistedEntry{
+		Key:        key,
+		Name:       entry.Name,
+		Qtype:      entry.Qtype,
+		Records:    recordStrings(entry.Records),
+		Authority:  recordStrings(entry.Authority),
+		Additional: recordStrings(entry.Additional),
+		ExpiresAt:  entry.ExpiresAt,
+		TTL:        Duration{entry.TTL},
+		Rcode:      entry.Rcode,
+	}
+	if entry.Subnet.IsValid() {
+		pe.Subnet = entry.Subnet.String()
+	}
+	return pe
+}
+
+// cacheEntry восстанавливает запись кэша из сериализованного вида
+func (pe persistedEntry) cacheEntry() (*CacheEntry, error) {
+	records, err := parseRecords(pe.Records)
+	if err != nil {
+		return nil, err
+	}
+	authority, err := parseRecords(pe.Authority)
+	if err != nil {
+		return nil, err
+	}
+	if len(records) == 0 && len(authority) == 0 {
+		return nil, fmt.Errorf("запись %s без ответов", pe.Key)
+	}
+	additional, err := parseRecords(pe.Additional)
+	if err != nil {
+		return nil, err
+	}
+	var subnet netip.Prefix
+	if pe.Subnet != "" {
+		if subnet, err = netip.ParsePrefix(pe.Subnet); err != nil {
+			return nil, err
+		}
+	}
+	return &CacheEntry{
+		Name:       pe.Name,
+		Qtype:      pe.Qtype,
+		Records:    records,
+		Authority:  authority,
+		Additional: additional,
+		ExpiresAt:  pe.ExpiresAt,
+		TTL:        pe.TTL.Duration,
+		Subnet:     subnet,
+		Rcode:      pe.Rcode,
+	}, nil
+}
+
+// negative сообщает, что запись хранит отрицательный ответ (RFC 2308)
+func (e *CacheEntry) negative() bool {
+	return len(e.Records) == 0
+}
+
+// evictable сообщает, что запись истекла и вышла за окно serve-stale
+func (c *Cache) evictable(entry *CacheEntry, now time.Time) bool {
+	return now.After(entry.ExpiresAt.Add(c.staleWindow))
+}
+
+func (c *Cache) removeElement(elem *list.Element) {
+	c.lru.Remove(elem)
+	delete(c.entries, elem.Value.(*cacheItem).key)
+}
+
+// recordStrings переводит записи в текстовое представление для сохранения
+func recordStrings(records []dns.RR) []string {
+	if len(records) == 0 {
+		return nil
+	}
+	strs := make([]string, 0, len(records))
+	for _, rr := range records {
+		strs = append(strs, rr.String())
+	}
+	return strs
+}
+
+// parseRecords разбирает записи из текстового представления
+func parseRecords(strs []string) ([]dns.RR, error) {
+	records := make([]dns.RR, 0, len(strs))
+	for _, str := range strs {
+		rr, err := dns.NewRR(str)
+		if err != nil {
+			return nil, err
+		}
+		if rr == nil {
+			return nil, fmt.Errorf("пустая запись")
+		}
+		records = append(records, rr)
+	}
+	return records, nil
+}
+
+// cacheKey формирует ключ кэша из имени и типа запроса. Имя приводится к
+// нижнему регистру и полной форме с точкой в конце, так что "example.com"
+// и "Example.COM." дают один ключ.
+func cacheKey(name string, qtype uint16) string {
+	return fmt.Sprintf("%s:%d", strings.ToLower(dns.CanonicalName(name)), qtype)
+}
+
+// copyRecords делает глубокую копию записей, чтобы изменения ответа не затрагивали кэш
+func copyRecords(records []dns.RR) []dns.RR {
+	copied := make([]dns.RR, 0, len(records))
+	for _, rr := range records {
+		copied = append(copied, dns.Copy(rr))
+	}
+	return copied
+}
+
+// remainingTTL уменьшает TTL записей до времени, оставшегося до истечения
+// записи кэша, чтобы клиенты не хранили ответ дольше, чем он действителен
+func remainingTTL(records []dns.RR, expiresAt, now time.Time) {
+	var remaining uint32
+	if d := expiresAt.Sub(now); d > 0 {
+		remaining = uint32(d / time.Second)
+	}
+	for _, rr := range records {
+		if rr.Header().Ttl > remaining {
+			rr.Header().Ttl = remaining
+		}
+	}
+}
+
+// clampTTL переводит TTL в длительность хранения, ограниченную снизу и сверху;
+// нулевая граница означает отсутствие ограничения
+func clampTTL(ttl uint32, minTTL, maxTTL time.Duration) time.Duration {
+	d := time.Duration(ttl) * time.Second
+	if minTTL > 0 && d < minTTL {
+		d = minTTL
+	}
+	if maxTTL > 0 && d > maxTTL {
+		d = maxTTL
+	}
+	return d
+}
+
+// minTTL возвращает минимальный TTL среди записей
+func minTTL(records []dns.RR) uint32 {
+	var ttl uint32
+	for i, rr := range records {
+		if i == 0 || rr.Header().Ttl < ttl {
+			ttl = rr.Header().Ttl
+		}
+	}
+	return ttl
+}
+
+// negativeTTL возвращает TTL отрицательного ответа (NXDOMAIN или NODATA) по
+// SOA из секции authority: меньшее из TTL самой SOA и поля MINIMUM
+// (RFC 2308, раздел 5). Без SOA ответ не кэшируется, и возвращается 0.
+func negativeTTL(rcode int, answers, authority []dns.RR) uint32 {
+	if len(answers) > 0 || (rcode != dns.RcodeSuccess && rcode != dns.RcodeNameError) {
+		return 0
+	}
+	for _, rr := range authority {
+		if soa, ok := rr.(*dns.SOA); ok {
+			return min(soa.Hdr.Ttl, soa.Minttl)
+		}
+	}
+	return 0
+}
+
+// cacheTTL возвращает срок хранения в кэше ответа на name с TTL ttl.
+// Границы ближайшей зоны из zone_ttl заменяют глобальные, даже если выходят
+// за их пределы.
+func (s *DNSServer) cacheTTL(name string, ttl uint32) time.Duration {
+	lo, hi := s.cfg.MinCacheTTL.Duration, s.cfg.MaxCacheTTL.Duration
+	if rule, ok := s.zoneTTLRule(name); ok {
+		if rule.Min.Duration > 0 {
+			lo = rule.Min.Duration
+			if hi > 0 && hi < lo {
+				hi = lo
+			}
+		}
+		if rule.Max.Duration > 0 {
+			hi = rule.Max.Duration
+			if lo > hi {
+				lo = hi
+			}
+		}
+	}
+	return clampTTL(ttl, lo, hi)
+}
+
+// zoneTTLRule возвращает правило zone_ttl ближайшей зоны, содержащей name
+func (s *DNSServer) zoneTTLRule(name string) (ZoneTTL, bool) {
+	if len(s.zoneTTL) == 0 {
+		return ZoneTTL{}, false
+	}
+	name = strings.ToLower(dns.Fqdn(name))
+	for off, end := 0, false; !end; off, end = dns.NextLabel(name, off) {
+		if rule, ok := s.zoneTTL[name[off:]]; ok {
+			return rule, true
+		}
+	}
+	rule, ok := s.zoneTTL["."]
+	return rule, ok
+}
+
+// normalizeZoneTTL приводит имена зон к нижнему регистру с точкой в конце
+func normalizeZoneTTL(rules map[string]ZoneTTL) map[string]ZoneTTL {
+	if len(rules) == 0 {
+		return nil
+	}
+	normalized := make(map[string]ZoneTTL, len(rules))
+	for zone, rule := range rules {
+		normalized[strings.ToLower(dns.Fqdn(zone))] = rule
+	}
+	return normalized
+}
+
+// validZoneTTL проверяет правила zone_ttl
+func validZoneTTL(rules map[string]ZoneTTL) error {
+	for zone, rule := range rules {
+		if _, ok := dns.IsDomainName(zone); !ok {
+			return fmt.Errorf("zone_ttl: некорректное имя зоны %q", zone)
+		}
+		if rule.Min.Duration < 0 || rule.Max.Duration < 0 {
+			return fmt.Errorf("zone_ttl: границы для зоны %s не могут быть отрицательными", zone)
+		}
+		if rule.Max.Duration > 0 && rule.Min.Duration > rule.Max.Duration {
+			return fmt.Errorf("zone_ttl: min для зоны %s превышает max", zone)
+		}
+	}
+	return nil
+}
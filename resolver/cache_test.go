@@ -0,0 +1,667 @@
+package resolver
+
+import (
+	"encoding/json"
+	"fmt"
+	"net"
+	"os"
+	"path/filepath"
+	"sync/atomic"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// newTestEntry создает запись кэша с одной A-записью
+func newTestEntry(t *testing.T, name string, ttl time.Duration) *CacheEntry {
+	t.Helper()
+	rr, err := dns.NewRR(fmt.Sprintf("%s 300 IN A 192.0.2.1", name))
+	if err != nil {
+		t.Fatalf("Не удалось создать RR: %v", err)
+	}
+	return &CacheEntry{
+		Name:      name,
+		Qtype:     dns.TypeA,
+		Records:   []dns.RR{rr},
+		ExpiresAt: time.Now().Add(ttl),
+	}
+}
+
+func TestCacheEvictsLeastRecentlyUsed(t *testing.T) {
+	cache := NewCache(3)
+	for i := 0; i < 3; i++ {
+		name := fmt.Sprintf("host%d.example.", i)
+		cache.Set(name, newTestEntry(t, name, time.Minute))
+	}
+
+	// Обращение к host0 делает его недавно использованным
+	if _, ok := cache.Get("host0.example."); !ok {
+		t.Fatalf("Ожидалось попадание в кэш для host0")
+	}
+
+	cache.Set("host3.example.", newTestEntry(t, "host3.example.", time.Minute))
+	cache.Set("host4.example.", newTestEntry(t, "host4.example.", time.Minute))
+
+	if cache.Len() != 3 {
+		t.Errorf("Ожидалось 3 записи в кэше, получено %d", cache.Len())
+	}
+	for _, evicted := range []string{"host1.example.", "host2.example."} {
+		if _, ok := cache.Get(evicted); ok {
+			t.Errorf("Запись %s должна была быть вытеснена", evicted)
+		}
+	}
+	for _, kept := range []string{"host0.example.", "host3.example.", "host4.example."} {
+		if _, ok := cache.Get(kept); !ok {
+			t.Errorf("Запись %s должна остаться в кэше", kept)
+		}
+	}
+}
+
+func TestCacheExpiredEntries(t *testing.T) {
+	cache := NewCache(10)
+	cache.Set("old.example.", newTestEntry(t, "old.example.", -time.Second))
+	cache.Set("fresh.example.", newTestEntry(t, "fresh.example.", time.Minute))
+
+	if removed := cache.RemoveExpired(time.Now()); removed != 1 {
+		t.Errorf("Ожидалось удаление 1 записи, удалено %d", removed)
+	}
+	if _, ok := cache.Get("old.example."); ok {
+		t.Errorf("Истёкшая запись не должна возвращаться")
+	}
+}
+
+func TestCacheGetReturnsCopy(t *testing.T) {
+	cache := NewCache(10)
+	cache.Set("copy.example.", newTestEntry(t, "copy.example.", time.Minute))
+
+	records, _ := cache.Get("copy.example.")
+	records[0].Header().Ttl = 1
+
+	records, _ = cache.Get("copy.example.")
+	if records[0].Header().Ttl != 300 {
+		t.Errorf("Изменение ответа не должно затрагивать кэш, TTL=%d", records[0].Header().Ttl)
+	}
+}
+
+func TestCacheSaveLoadRoundTrip(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "cache.json")
+
+	cache := NewCache(10)
+	cache.Set("keep.example.:1", newTestEntry(t, "keep.example.", time.Minute))
+	cache.Set("expired.example.:1", newTestEntry(t, "expired.example.", -time.Second))
+
+	saved, err := cache.Save(path)
+	if err != nil {
+		t.Fatalf("Ошибка сохранения кэша: %v", err)
+	}
+	if saved != 1 {
+		t.Errorf("Ожидалось сохранение 1 записи, сохранено %d", saved)
+	}
+
+	restored := NewCache(10)
+	loaded, err := restored.Load(path)
+	if err != nil {
+		t.Fatalf("Ошибка загрузки кэша: %v", err)
+	}
+	if loaded != 1 {
+		t.Errorf("Ожидалась загрузка 1 записи, загружено %d", loaded)
+	}
+
+	records, ok := restored.Get("keep.example.:1")
+	if !ok {
+		t.Fatalf("Запись keep.example. не восстановлена")
+	}
+	if a, ok := records[0].(*dns.A); !ok || a.A.String() != "192.0.2.1" {
+		t.Errorf("Неверная восстановленная запись: %v", records[0])
+	}
+	if _, ok := restored.Get("expired.example.:1"); ok {
+		t.Errorf("Истёкшая запись не должна восстанавливаться")
+	}
+}
+
+func TestClampTTL(t *testing.T) {
+	tests := []struct {
+		ttl      uint32
+		min, max time.Duration
+		want     time.Duration
+	}{
+		{ttl: 300, max: 24 * time.Hour, want: 300 * time.Second},
+		{ttl: 10 * 365 * 86400, max: 24 * time.Hour, want: 24 * time.Hour},
+		{ttl: 5, min: time.Minute, max: time.Hour, want: time.Minute},
+		{ttl: 1 << 31, want: (1 << 31) * time.Second},
+	}
+	for _, tt := range tests {
+		if got := clampTTL(tt.ttl, tt.min, tt.max); got != tt.want {
+			t.Errorf("clampTTL(%d, %v, %v) = %v, ожидалось %v", tt.ttl, tt.min, tt.max, got, tt.want)
+		}
+	}
+}
+
+func TestHugeTTLClampedAndZeroTTLNotCached(t *testing.T) {
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		ttl := 315360000 // десять лет
+		if r.Question[0].Name == "zero.example." {
+			ttl = 0
+		}
+		rr, _ := dns.NewRR(fmt.Sprintf("%s %d IN A 192.0.2.1", r.Question[0].Name, ttl))
+		reply.Answer = append(reply.Answer, rr)
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+
+	hugeKey := cacheKey("huge.example.", dns.TypeA)
+	s.lookup(hugeKey, "huge.example.", "A", dns.TypeA)
+	if _, ok := s.cache.Get(hugeKey); !ok {
+		t.Fatalf("Запись с огромным TTL должна быть закэширована")
+	}
+	s.cache.RemoveExpired(time.Now().Add(s.cfg.MaxCacheTTL.Duration + time.Second))
+	if _, ok := s.cache.Get(hugeKey); ok {
+		t.Errorf("Запись с огромным TTL должна истечь через max_cache_ttl")
+	}
+
+	zeroKey := cacheKey("zero.example.", dns.TypeA)
+	s.lookup(zeroKey, "zero.example.", "A", dns.TypeA)
+	if _, ok := s.cache.Get(zeroKey); ok {
+		t.Errorf("Запись с нулевым TTL не должна кэшироваться")
+	}
+}
+
+func TestCacheKeepsStaleEntriesWithinWindow(t *testing.T) {
+	cache := NewStaleCache(10, time.Hour)
+	cache.Set("stale.example.", newTestEntry(t, "stale.example.", -time.Minute))
+	cache.Set("gone.example.", newTestEntry(t, "gone.example.", -2*time.Hour))
+
+	if _, ok := cache.Get("stale.example."); ok {
+		t.Errorf("Истёкшая запись не должна возвращаться как свежая")
+	}
+	if _, ok := cache.GetStale("stale.example."); !ok {
+		t.Errorf("Запись в окне serve-stale должна быть доступна")
+	}
+	if removed := cache.RemoveExpired(time.Now()); removed != 1 {
+		t.Errorf("Ожидалось удаление 1 записи за пределами окна, удалено %d", removed)
+	}
+	if _, ok := cache.GetStale("gone.example."); ok {
+		t.Errorf("Запись за пределами окна serve-stale не должна возвращаться")
+	}
+}
+
+func TestServeStaleWhenUpstreamFails(t *testing.T) {
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetRcode(r, dns.RcodeServerFailure)
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+	key := cacheKey("stale.example.", dns.TypeA)
+	s.cache.Set(key, newTestEntry(t, "stale.example.", -time.Minute))
+
+	result := s.lookup(key, "stale.example.", "A", dns.TypeA)
+	if result.rcode != dns.RcodeSuccess {
+		t.Fatalf("Ожидался NOERROR с устаревшим ответом, получено %s", dns.RcodeToString[result.rcode])
+	}
+	if len(result.answers) != 1 || result.answers[0].Header().Ttl != staleAnswerTTL {
+		t.Errorf("Ожидался устаревший ответ с TTL %d, получено %v", staleAnswerTTL, result.answers)
+	}
+
+	if result := s.lookup(cacheKey("fresh.example.", dns.TypeA), "fresh.example.", "A", dns.TypeA); result.rcode != dns.RcodeServerFailure {
+		t.Errorf("Без устаревшей записи ожидался SERVFAIL, получено %s", dns.RcodeToString[result.rcode])
+	}
+}
+
+func TestCacheSaveLoadPreservesEdgeCaseNames(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "cache.json")
+	names := []string{
+		"xn--d1acufc.xn--p1ai.", // интернационализированное имя (домен.рф)
+		`dot\.inside.example.`,  // точка внутри метки
+		"_sip._tcp.example.",    // служебные метки
+		"UPPER.Case.Example.",   // регистр должен сохраняться в записи
+	}
+
+	cache := NewCache(10)
+	for _, name := range names {
+		cache.Set(cacheKey(name, dns.TypeA), newTestEntry(t, name, time.Minute))
+	}
+	if _, err := cache.Save(path); err != nil {
+		t.Fatalf("Ошибка сохранения кэша: %v", err)
+	}
+
+	restored := NewCache(10)
+	if _, err := restored.Load(path); err != nil {
+		t.Fatalf("Ошибка загрузки кэша: %v", err)
+	}
+
+	for _, name := range names {
+		key := cacheKey(name, dns.TypeA)
+		elem, ok := restored.entries[key]
+		if !ok {
+			t.Errorf("Запись %s не восстановлена", name)
+			continue
+		}
+		entry := elem.Value.(*cacheItem).entry
+		if entry.Name != name || entry.Qtype != dns.TypeA {
+			t.Errorf("Ожидалось %s/A, восстановлено %s/%d", name, entry.Name, entry.Qtype)
+		}
+		if owner := entry.Records[0].Header().Name; owner != name {
+			t.Errorf("Имя владельца записи изменилось: %s -> %s", name, owner)
+		}
+	}
+}
+
+func TestCacheFlush(t *testing.T) {
+	cache := NewCache(0)
+	for i := 0; i < 3; i++ {
+		name := fmt.Sprintf("host%d.example.", i)
+		cache.Set(cacheKey(name, dns.TypeA), newTestEntry(t, name, time.Minute))
+	}
+
+	if removed := cache.Flush(); removed != 3 {
+		t.Errorf("Ожидалось удаление 3 записей, удалено %d", removed)
+	}
+	if cache.Len() != 0 {
+		t.Errorf("После очистки кэш должен быть пуст, записей: %d", cache.Len())
+	}
+
+	// Кэш остается пригодным для работы после очистки
+	cache.Set(cacheKey("host0.example.", dns.TypeA), newTestEntry(t, "host0.example.", time.Minute))
+	if _, ok := cache.Get(cacheKey("host0.example.", dns.TypeA)); !ok {
+		t.Error("Запись, добавленная после очистки, не найдена")
+	}
+}
+
+func TestCacheRemoveName(t *testing.T) {
+	cache := NewCache(0)
+	set := func(name string, qtype uint16) {
+		entry := newTestEntry(t, name, time.Minute)
+		entry.Qtype = qtype
+		cache.Set(cacheKey(name, qtype), entry)
+	}
+	set("example.com.", dns.TypeA)
+	set("example.com.", dns.TypeAAAA)
+	set("example.com.", dns.TypeMX)
+	set("www.example.com.", dns.TypeA)
+
+	if removed := cache.RemoveName("Example.COM", dns.TypeA); removed != 1 {
+		t.Errorf("Ожидалось удаление 1 записи, удалено %d", removed)
+	}
+	if _, ok := cache.Get(cacheKey("example.com.", dns.TypeA)); ok {
+		t.Error("Запись A для example.com. не удалена")
+	}
+	if _, ok := cache.Get(cacheKey("example.com.", dns.TypeAAAA)); !ok {
+		t.Error("Запись AAAA для example.com. не должна удаляться")
+	}
+
+	if removed := cache.RemoveName("example.com.", 0); removed != 2 {
+		t.Errorf("Ожидалось удаление 2 записей, удалено %d", removed)
+	}
+	if _, ok := cache.Get(cacheKey("www.example.com.", dns.TypeA)); !ok {
+		t.Error("Поддомен не должен удаляться вместе с именем")
+	}
+	if cache.Len() != 1 {
+		t.Errorf("Ожидалась 1 оставшаяся запись, осталось %d", cache.Len())
+	}
+}
+
+func TestCacheKeyIgnoresCase(t *testing.T) {
+	cache := NewCache(0)
+	cache.Set(cacheKey("Example.COM.", dns.TypeA), newTestEntry(t, "Example.COM.", time.Minute))
+
+	for _, name := range []string{"example.com.", "EXAMPLE.COM", "eXaMpLe.CoM."} {
+		if _, ok := cache.Get(cacheKey(name, dns.TypeA)); !ok {
+			t.Errorf("Запрос %s должен попадать в ту же запись кэша", name)
+		}
+	}
+	if cache.Len() != 1 {
+		t.Errorf("Ожидалась одна запись, получено %d", cache.Len())
+	}
+}
+
+func TestMixedCaseQueryHitsCache(t *testing.T) {
+	s := NewDNSServer()
+	s.forwarders = []string{startOutcomeUpstream(t)}
+
+	req := new(dns.Msg)
+	req.SetQuestion("ok.example.", dns.TypeA)
+	s.handleRequest(&testResponseWriter{}, req)
+
+	req = new(dns.Msg)
+	req.SetQuestion("OK.Example.", dns.TypeA)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil {
+		t.Fatal("Ответ не получен")
+	}
+	if hits := atomic.LoadUint64(&s.cacheHits); hits != 1 {
+		t.Errorf("Запрос в другом регистре должен попадать в кэш, попаданий: %d", hits)
+	}
+	if len(w.msg.Answer) != 1 {
+		t.Fatalf("Ожидалась одна запись, получено %v", w.msg.Answer)
+	}
+	// Вопрос возвращается клиенту в том регистре, в котором он задан
+	if w.msg.Question[0].Name != "OK.Example." {
+		t.Errorf("Регистр вопроса изменен: %s", w.msg.Question[0].Name)
+	}
+}
+
+func TestRemainingTTL(t *testing.T) {
+	stored := time.Now()
+	expires := stored.Add(300 * time.Second)
+	for _, tt := range []struct {
+		elapsed time.Duration
+		want    uint32
+	}{
+		{0, 300},
+		{100 * time.Second, 200},
+		{299500 * time.Millisecond, 0},
+		{400 * time.Second, 0},
+	} {
+		records := []dns.RR{
+			mustRR(t, "example.com. 300 IN A 192.0.2.1"),
+			mustRR(t, "example.com. 600 IN A 192.0.2.2"),
+		}
+		remainingTTL(records, expires, stored.Add(tt.elapsed))
+		for _, rr := range records {
+			if rr.Header().Ttl != tt.want {
+				t.Errorf("Через %s TTL=%d, ожидалось %d", tt.elapsed, rr.Header().Ttl, tt.want)
+			}
+		}
+	}
+}
+
+func TestCachedAnswerReportsRemainingTTL(t *testing.T) {
+	s := NewDNSServer()
+	// Запись с TTL 300, закэшированная 200 секунд назад
+	s.cache.Set(cacheKey("aged.example.", dns.TypeA), &CacheEntry{
+		Name:      "aged.example.",
+		Qtype:     dns.TypeA,
+		Records:   []dns.RR{mustRR(t, "aged.example. 300 IN A 192.0.2.1")},
+		ExpiresAt: time.Now().Add(100 * time.Second),
+	})
+
+	req := new(dns.Msg)
+	req.SetQuestion("aged.example.", dns.TypeA)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil || len(w.msg.Answer) != 1 {
+		t.Fatalf("Ожидался ответ из кэша, получено %v", w.msg)
+	}
+	if ttl := w.msg.Answer[0].Header().Ttl; ttl > 100 || ttl < 98 {
+		t.Errorf("Ожидался оставшийся TTL около 100, получено %d", ttl)
+	}
+
+	// Сама запись кэша не изменяется
+	records, _ := s.cache.Get(cacheKey("aged.example.", dns.TypeA))
+	if records[0].Header().Ttl != 300 {
+		t.Errorf("TTL в кэше не должен меняться, получено %d", records[0].Header().Ttl)
+	}
+}
+
+func TestOversizedAnswerRejected(t *testing.T) {
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		for i := 1; i <= 20; i++ {
+			rr, _ := dns.NewRR(fmt.Sprintf("%s 300 IN A 192.0.2.%d", r.Question[0].Name, i))
+			reply.Answer = append(reply.Answer, rr)
+		}
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+	s.cfg.MaxAnswerRecords = 10
+
+	key := cacheKey("flood.example.", dns.TypeA)
+	result := s.lookup(key, "flood.example.", "A", dns.TypeA)
+	if result.rcode != dns.RcodeServerFailure || len(result.answers) != 0 {
+		t.Errorf("Ответ с 20 записями при лимите 10 должен отбрасываться, получено rcode %d и %d записей", result.rcode, len(result.answers))
+	}
+	if _, ok := s.cache.Get(key); ok {
+		t.Errorf("Отброшенный ответ не должен кэшироваться")
+	}
+	if got := atomic.LoadUint64(&s.oversizedAnswers); got != 1 {
+		t.Errorf("Ожидался 1 отброшенный ответ, учтено %d", got)
+	}
+
+	s.cfg.MaxAnswerRecords = 20
+	okKey := cacheKey("many.example.", dns.TypeA)
+	if result := s.lookup(okKey, "many.example.", "A", dns.TypeA); len(result.answers) != 20 {
+		t.Errorf("Ответ в пределах лимита должен приниматься, получено %d записей", len(result.answers))
+	}
+}
+
+func TestZoneTTLOverridesGlobalClamp(t *testing.T) {
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		rr, _ := dns.NewRR(r.Question[0].Name + " 300 IN A 192.0.2.1")
+		reply.Answer = append(reply.Answer, rr)
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+	s.cfg.MinCacheTTL = Duration{time.Minute}
+	s.cfg.MaxCacheTTL = Duration{2 * time.Minute}
+	s.zoneTTL = normalizeZoneTTL(map[string]ZoneTTL{
+		"Slow-Zone.example": {Min: Duration{time.Hour}},
+		"fast-cdn.example":  {Max: Duration{10 * time.Second}},
+	})
+
+	tests := []struct {
+		name string
+		want time.Duration
+	}{
+		{"www.slow-zone.example.", time.Hour},
+		{"img.fast-cdn.example.", 10 * time.Second},
+		{"other.example.", 2 * time.Minute},
+	}
+	for _, tt := range tests {
+		key := cacheKey(tt.name, dns.TypeA)
+		s.lookup(key, tt.name, "A", dns.TypeA)
+		entry, ok := s.cache.GetEntry(key)
+		if !ok {
+			t.Fatalf("%s: ответ не закэширован", tt.name)
+		}
+		if entry.TTL != tt.want {
+			t.Errorf("%s: срок хранения %v, ожидался %v", tt.name, entry.TTL, tt.want)
+		}
+	}
+}
+
+func TestTrailingDotSharesCacheEntry(t *testing.T) {
+	s := NewDNSServer()
+	s.forwarders = []string{startOutcomeUpstream(t)}
+
+	if result := s.lookup(cacheKey("ok.example", dns.TypeA), "ok.example", "A", dns.TypeA); result.cached || len(result.answers) != 1 {
+		t.Fatalf("Первый запрос должен разрешаться, получено %+v", result)
+	}
+	if !s.lookup(cacheKey("ok.example.", dns.TypeA), "ok.example.", "A", dns.TypeA).cached {
+		t.Errorf("Имя с точкой в конце должно попадать в запись для имени без нее")
+	}
+	if s.cache.Len() != 1 {
+		t.Errorf("Ожидалась одна запись кэша, получено %d", s.cache.Len())
+	}
+	if entry, ok := s.cache.GetEntry(cacheKey("ok.example.", dns.TypeA)); !ok || entry.Name != "ok.example." {
+		t.Errorf("Имя записи должно храниться в полной форме, получено %+v", entry)
+	}
+}
+
+func TestLoadNormalizesCacheKeys(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "cache.json")
+	pe := newPersistedEntry("Saved.Example:1", newTestEntry(t, "Saved.Example", time.Hour))
+	data, err := json.Marshal([]persistedEntry{pe})
+	if err != nil {
+		t.Fatalf("Ошибка сериализации: %v", err)
+	}
+	if err := os.WriteFile(path, data, 0o600); err != nil {
+		t.Fatalf("Ошибка записи файла: %v", err)
+	}
+
+	cache := NewCache(0)
+	if n, err := cache.Load(path); err != nil || n != 1 {
+		t.Fatalf("Ожидалась загрузка 1 записи, загружено %d: %v", n, err)
+	}
+	if _, ok := cache.Get(cacheKey("saved.example.", dns.TypeA)); !ok {
+		t.Errorf("Запись с ненормализованным ключом должна находиться по полному имени")
+	}
+}
+
+// startNegativeUpstream отвечает NXDOMAIN на gone.example. и NODATA на
+// остальные имена, добавляя SOA с TTL 3600 и MINIMUM 60, и считает запросы
+func startNegativeUpstream(t *testing.T, queries *atomic.Int32) string {
+	return startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		queries.Add(1)
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		if r.Question[0].Name == "gone.example." {
+			reply.Rcode = dns.RcodeNameError
+		}
+		soa, _ := dns.NewRR("example. 3600 IN SOA ns.example. admin.example. 1 3600 600 86400 60")
+		reply.Ns = append(reply.Ns, soa)
+		w.WriteMsg(reply)
+	})
+}
+
+func TestNegativeAnswersCached(t *testing.T) {
+	var queries atomic.Int32
+	s := NewDNSServer()
+	s.forwarders = []string{startNegativeUpstream(t, &queries)}
+
+	for _, tt := range []struct {
+		name  string
+		rcode int
+	}{
+		{"gone.example.", dns.RcodeNameError},
+		{"empty.example.", dns.RcodeSuccess},
+	} {
+		queries.Store(0)
+		for i := 0; i < 2; i++ {
+			req := new(dns.Msg)
+			req.SetQuestion(tt.name, dns.TypeAAAA)
+			w := &testResponseWriter{}
+			s.handleRequest(w, req)
+
+			if w.msg == nil || w.msg.Rcode != tt.rcode || len(w.msg.Answer) != 0 {
+				t.Fatalf("%s, запрос %d: ожидался пустой ответ %s, получено %v", tt.name, i+1, dns.RcodeToString[tt.rcode], w.msg)
+			}
+			if len(w.msg.Ns) != 1 || w.msg.Ns[0].Header().Rrtype != dns.TypeSOA {
+				t.Errorf("%s, запрос %d: отрицательный ответ должен содержать SOA, получено %v", tt.name, i+1, w.msg.Ns)
+			}
+		}
+		if n := queries.Load(); n != 1 {
+			t.Errorf("%s: повторный запрос должен обслуживаться из кэша, запросов к серверу: %d", tt.name, n)
+		}
+
+		// Срок хранения - меньшее из TTL SOA и поля MINIMUM
+		entry, ok := s.cache.GetEntry(cacheKey(tt.name, dns.TypeAAAA))
+		if !ok || entry.TTL != time.Minute || entry.Rcode != tt.rcode {
+			t.Errorf("%s: ожидалась отрицательная запись на 1m, получено %+v", tt.name, entry)
+		}
+	}
+}
+
+func TestNegativeAnswersCachedInRecursiveMode(t *testing.T) {
+	var queries atomic.Int32
+	addr := startNegativeUpstream(t, &queries)
+	host, port, _ := net.SplitHostPort(addr)
+
+	s := NewDNSServer()
+	s.rootServers = []string{host}
+	s.authPort = port
+
+	for _, tt := range []struct {
+		name  string
+		rcode int
+	}{
+		{"gone.example.", dns.RcodeNameError},
+		{"empty.example.", dns.RcodeSuccess},
+	} {
+		queries.Store(0)
+		for i := 0; i < 2; i++ {
+			req := new(dns.Msg)
+			req.SetQuestion(tt.name, dns.TypeAAAA)
+			w := &testResponseWriter{}
+			s.handleRequest(w, req)
+
+			if w.msg == nil || w.msg.Rcode != tt.rcode || len(w.msg.Answer) != 0 {
+				t.Fatalf("%s, запрос %d: ожидался пустой ответ %s, получено %v", tt.name, i+1, dns.RcodeToString[tt.rcode], w.msg)
+			}
+		}
+		if n := queries.Load(); n != 1 {
+			t.Errorf("%s: повторный запрос должен обслуживаться из кэша, запросов к серверу: %d", tt.name, n)
+		}
+		entry, ok := s.cache.GetEntry(cacheKey(tt.name, dns.TypeAAAA))
+		if !ok || entry.TTL != time.Minute || entry.Rcode != tt.rcode {
+			t.Errorf("%s: ожидалась отрицательная запись на 1m, получено %+v", tt.name, entry)
+		}
+	}
+}
+
+func TestNegativeTTLLimitedByConfig(t *testing.T) {
+	var queries atomic.Int32
+	s := NewDNSServer()
+	s.forwarders = []string{startNegativeUpstream(t, &queries)}
+	s.cfg.MaxNegativeTTL = Duration{10 * time.Second}
+
+	s.lookup(cacheKey("gone.example.", dns.TypeA), "gone.example.", "A", dns.TypeA)
+	if entry, ok := s.cache.GetEntry(cacheKey("gone.example.", dns.TypeA)); !ok || entry.TTL != 10*time.Second {
+		t.Errorf("Срок хранения должен ограничиваться max_negative_ttl, получено %+v", entry)
+	}
+
+	s.cfg.MaxNegativeTTL = Duration{}
+	s.lookup(cacheKey("gone.example.", dns.TypeMX), "gone.example.", "MX", dns.TypeMX)
+	if _, ok := s.cache.GetEntry(cacheKey("gone.example.", dns.TypeMX)); ok {
+		t.Errorf("При max_negative_ttl = 0 отрицательные ответы не должны кэшироваться")
+	}
+}
+
+func TestNegativeTTL(t *testing.T) {
+	soa := mustRR(t, "example. 300 IN SOA ns.example. admin.example. 1 3600 600 86400 900")
+	a := mustRR(t, "www.example. 300 IN A 192.0.2.1")
+	tests := []struct {
+		rcode     int
+		answers   []dns.RR
+		authority []dns.RR
+		want      uint32
+	}{
+		{dns.RcodeNameError, nil, []dns.RR{soa}, 300},
+		{dns.RcodeSuccess, nil, []dns.RR{soa}, 300},
+		{dns.RcodeNameError, nil, nil, 0},
+		{dns.RcodeSuccess, []dns.RR{a}, []dns.RR{soa}, 0},
+		{dns.RcodeServerFailure, nil, []dns.RR{soa}, 0},
+	}
+	for i, tt := range tests {
+		if got := negativeTTL(tt.rcode, tt.answers, tt.authority); got != tt.want {
+			t.Errorf("Случай %d: negativeTTL = %d, ожидалось %d", i, got, tt.want)
+		}
+	}
+}
+
+func TestNegativeEntryPersisted(t *testing.T) {
+	soa := mustRR(t, "example. 300 IN SOA ns.example. admin.example. 1 3600 600 86400 300")
+	entry := &CacheEntry{
+		Name:      "gone.example.",
+		Qtype:     dns.TypeA,
+		Authority: []dns.RR{soa},
+		ExpiresAt: time.Now().Add(time.Minute),
+		TTL:       time.Minute,
+		Rcode:     dns.RcodeNameError,
+	}
+
+	restored, err := newPersistedEntry("gone.example.:1", entry).cacheEntry()
+	if err != nil {
+		t.Fatalf("Отрицательная запись должна восстанавливаться: %v", err)
+	}
+	if restored.Rcode != dns.RcodeNameError || len(restored.Authority) != 1 || !restored.negative() {
+		t.Errorf("Восстановлена запись %+v", restored)
+	}
+}
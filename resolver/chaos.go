@@ -0,0 +1,55 @@
+package resolver
+
+import (
+	"log/slog"
+	"os"
+	"strings"
+
+	"github.com/miekg/dns"
+)
+
+// serverVersion - ответ на version.bind по умолчанию
+const serverVersion = "ASTRACAT DNS Resolver"
+
+// chaosTXT возвращает текст ответа на диагностический запрос класса CHAOS
+// (version.bind, id.server и их синонимы)
+func (s *DNSServer) chaosTXT(name string) (string, bool) {
+	switch strings.ToLower(dns.Fqdn(name)) {
+	case "version.bind.", "version.server.":
+		return s.cfg.ServerVersion, true
+	case "id.server.", "hostname.bind.":
+		if s.cfg.ServerID != "" {
+			return s.cfg.ServerID, true
+		}
+		hostname, err := os.Hostname()
+		return hostname, err == nil
+	}
+	return "", false
+}
+
+// sendChaosResponse отвечает на запрос класса CHAOS. Рекурсия для CHAOS не
+// выполняется: известные TXT-имена получают ответ, остальные - REFUSED.
+func (s *DNSServer) sendChaosResponse(w dns.ResponseWriter, req *dns.Msg) {
+	question := req.Question[0]
+	if !s.cfg.ChaosResponses || question.Qtype != dns.TypeTXT {
+		s.sendErrorResponse(w, req, dns.RcodeRefused, "Запрос класса CHAOS отклонен")
+		return
+	}
+	text, ok := s.chaosTXT(question.Name)
+	if !ok || text == "" {
+		s.sendErrorResponse(w, req, dns.RcodeRefused, "Неизвестное имя класса CHAOS "+question.Name)
+		return
+	}
+
+	reply := new(dns.Msg)
+	reply.SetReply(req)
+	reply.Authoritative = true
+	reply.RecursionAvailable = true
+	reply.Answer = append(reply.Answer, &dns.TXT{
+		Hdr: dns.RR_Header{Name: question.Name, Rrtype: dns.TypeTXT, Class: dns.ClassCHAOS, Ttl: 0},
+		Txt: []string{text},
+	})
+	if err := w.WriteMsg(reply); err != nil {
+		slog.Error("Ошибка записи ответа", "error", err)
+	}
+}
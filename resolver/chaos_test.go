@@ -0,0 +1,54 @@
+package resolver
+
+import (
+	"testing"
+
+	"github.com/miekg/dns"
+)
+
+func chaosQuery(s *DNSServer, name string) *dns.Msg {
+	req := new(dns.Msg)
+	req.SetQuestion(name, dns.TypeTXT)
+	req.Question[0].Qclass = dns.ClassCHAOS
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+	return w.msg
+}
+
+func TestChaosVersionAndID(t *testing.T) {
+	s := NewDNSServer()
+	s.cfg.ServerVersion = "test-version"
+	s.cfg.ServerID = "resolver-1"
+
+	tests := []struct {
+		name string
+		want string
+	}{
+		{"version.bind.", "test-version"},
+		{"VERSION.SERVER.", "test-version"},
+		{"id.server.", "resolver-1"},
+		{"hostname.bind.", "resolver-1"},
+	}
+	for _, tt := range tests {
+		resp := chaosQuery(s, tt.name)
+		if resp == nil || resp.Rcode != dns.RcodeSuccess || len(resp.Answer) != 1 {
+			t.Fatalf("%s: ожидался один TXT-ответ, получено %v", tt.name, resp)
+		}
+		txt, ok := resp.Answer[0].(*dns.TXT)
+		if !ok || len(txt.Txt) != 1 || txt.Txt[0] != tt.want || txt.Hdr.Class != dns.ClassCHAOS {
+			t.Errorf("%s: получено %v, ожидался TXT CH %q", tt.name, resp.Answer[0], tt.want)
+		}
+	}
+}
+
+func TestChaosRefusedWhenDisabledOrUnknown(t *testing.T) {
+	s := NewDNSServer()
+	if resp := chaosQuery(s, "example.com."); resp == nil || resp.Rcode != dns.RcodeRefused {
+		t.Errorf("Неизвестное имя класса CHAOS должно получить REFUSED, получено %v", resp)
+	}
+
+	s.cfg.ChaosResponses = false
+	if resp := chaosQuery(s, "version.bind."); resp == nil || resp.Rcode != dns.RcodeRefused || len(resp.Answer) != 0 {
+		t.Errorf("При отключенных ответах CHAOS версия не должна раскрываться, получено %v", resp)
+	}
+}
@@ -0,0 +1,156 @@
+package resolver
+
+import (
+	"context"
+	"log/slog"
+	"net/netip"
+	"strings"
+
+	"github.com/miekg/dns"
+)
+
+const maxCNAMEChain = 8
+
+// chainEnd проходит по цепочке CNAME в ответе начиная с qname и возвращает
+// её последнее имя, а также признак наличия у него записей типа qtype
+func chainEnd(qname string, qtype uint16, answers []dns.RR) (string, bool) {
+	name := qname
+	seen := map[string]bool{strings.ToLower(dns.Fqdn(name)): true}
+
+	for i := 0; i < maxCNAMEChain; i++ {
+		next := ""
+		for _, rr := range answers {
+			if cname, ok := rr.(*dns.CNAME); ok && strings.EqualFold(dns.Fqdn(cname.Hdr.Name), dns.Fqdn(name)) {
+				next = cname.Target
+				break
+			}
+		}
+		if next == "" || seen[strings.ToLower(dns.Fqdn(next))] {
+			break
+		}
+		seen[strings.ToLower(dns.Fqdn(next))] = true
+		name = next
+	}
+
+	for _, rr := range answers {
+		if rr.Header().Rrtype == qtype && strings.EqualFold(dns.Fqdn(rr.Header().Name), dns.Fqdn(name)) {
+			return name, true
+		}
+	}
+	return name, false
+}
+
+// unrelatedOwner возвращает имя владельца первой записи ответа, не
+// относящейся к цепочке CNAME от qname, или пустую строку. Такие записи
+// вышестоящий сервер мог добавить для подмены кэша. Записи DNAME (и их
+// подписи) допускаются у предков имен цепочки.
+func unrelatedOwner(qname string, answers []dns.RR) string {
+	name := strings.ToLower(dns.Fqdn(qname))
+	chain := map[string]bool{name: true}
+	for i := 0; i < maxCNAMEChain; i++ {
+		next := ""
+		for _, rr := range answers {
+			if cname, ok := rr.(*dns.CNAME); ok && strings.EqualFold(dns.Fqdn(cname.Hdr.Name), name) {
+				next = strings.ToLower(dns.Fqdn(cname.Target))
+				break
+			}
+		}
+		if next == "" || chain[next] {
+			break
+		}
+		chain[next] = true
+		name = next
+	}
+
+	for _, rr := range answers {
+		owner := strings.ToLower(dns.Fqdn(rr.Header().Name))
+		if chain[owner] {
+			continue
+		}
+		rrtype := rr.Header().Rrtype
+		if sig, ok := rr.(*dns.RRSIG); ok {
+			rrtype = sig.TypeCovered
+		}
+		if rrtype == dns.TypeDNAME && ancestorOfAny(owner, chain) {
+			continue
+		}
+		return rr.Header().Name
+	}
+	return ""
+}
+
+// ancestorOfAny сообщает, является ли zone предком одного из имен names
+func ancestorOfAny(zone string, names map[string]bool) bool {
+	for name := range names {
+		if name != zone && dns.IsSubDomain(zone, name) {
+			return true
+		}
+	}
+	return false
+}
+
+// chainLinks возвращает для каждой цели CNAME из полной цепочки ответа
+// записи начиная с этой цели - готовый ответ на вопрос о ней самой
+func chainLinks(qname string, qtype uint16, answers []dns.RR) map[string][]dns.RR {
+	if qtype == dns.TypeCNAME {
+		return nil
+	}
+	if _, complete := chainEnd(qname, qtype, answers); !complete {
+		return nil
+	}
+
+	links := make(map[string][]dns.RR)
+	passed := make(map[string]bool)
+	name := qname
+	for i := 0; i < maxCNAMEChain; i++ {
+		next := ""
+		for _, rr := range answers {
+			if cname, ok := rr.(*dns.CNAME); ok && strings.EqualFold(dns.Fqdn(cname.Hdr.Name), dns.Fqdn(name)) {
+				next = cname.Target
+				break
+			}
+		}
+		passed[strings.ToLower(dns.Fqdn(name))] = true
+		if next == "" || passed[strings.ToLower(dns.Fqdn(next))] {
+			break
+		}
+
+		var records []dns.RR
+		for _, rr := range answers {
+			if !passed[strings.ToLower(dns.Fqdn(rr.Header().Name))] {
+				records = append(records, rr)
+			}
+		}
+		links[dns.Fqdn(next)] = records
+		name = next
+	}
+	return links
+}
+
+// followCNAME дополняет ответ, который заканчивается на CNAME без записей
+// запрошенного типа, разрешая цель CNAME. Возвращается вся цепочка:
+// записи CNAME, за которыми следует итоговый ответ. Отмена ctx прерывает
+// разрешение целей.
+func (s *DNSServer) followCNAME(ctx context.Context, qname string, qtype uint16, answers []dns.RR) []dns.RR {
+	if qtype == dns.TypeCNAME || len(answers) == 0 {
+		return answers
+	}
+
+	qtypeStr := dns.TypeToString[qtype]
+	for depth := 0; depth < maxCNAMEChain; depth++ {
+		target, complete := chainEnd(qname, qtype, answers)
+		if complete || strings.EqualFold(dns.Fqdn(target), dns.Fqdn(qname)) {
+			return answers
+		}
+
+		slog.Debug("Следование CNAME", "qname", qname, "target", target)
+		targetAnswers := s.lookupSubnet(ctx, cacheKey(target, qtype), target, qtypeStr, qtype, netip.Prefix{}).answers
+		if len(targetAnswers) == 0 {
+			return answers
+		}
+		answers = append(answers, targetAnswers...)
+	}
+
+	slog.Warn("Превышена длина цепочки CNAME", "qname", qname, "limit", maxCNAMEChain)
+	return answers
+}
@@ -0,0 +1,220 @@
+package resolver
+
+import (
+	"sync/atomic"
+	"testing"
+
+	"github.com/miekg/dns"
+)
+
+func TestHandleRequestFollowsCNAME(t *testing.T) {
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		switch r.Question[0].Name {
+		case "alias.example.":
+			rr, _ := dns.NewRR("alias.example. 300 IN CNAME target.example.")
+			reply.Answer = append(reply.Answer, rr)
+		case "target.example.":
+			rr, _ := dns.NewRR("target.example. 300 IN A 192.0.2.10")
+			reply.Answer = append(reply.Answer, rr)
+		}
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+
+	req := new(dns.Msg)
+	req.SetQuestion("alias.example.", dns.TypeA)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil {
+		t.Fatalf("Ответ не получен")
+	}
+	if len(w.msg.Answer) != 2 {
+		t.Fatalf("Ожидалась цепочка из 2 записей, получено %v", w.msg.Answer)
+	}
+	if cname, ok := w.msg.Answer[0].(*dns.CNAME); !ok || cname.Target != "target.example." {
+		t.Errorf("Первой должна идти запись CNAME, получено %v", w.msg.Answer[0])
+	}
+	if a, ok := w.msg.Answer[1].(*dns.A); !ok || a.A.String() != "192.0.2.10" {
+		t.Errorf("Второй должна идти A-запись цели, получено %v", w.msg.Answer[1])
+	}
+}
+
+func TestChainEndStopsOnLoop(t *testing.T) {
+	a, _ := dns.NewRR("a.example. 300 IN CNAME b.example.")
+	b, _ := dns.NewRR("b.example. 300 IN CNAME a.example.")
+
+	end, complete := chainEnd("a.example.", dns.TypeA, []dns.RR{a, b})
+	if complete {
+		t.Errorf("Зацикленная цепочка не может быть полной")
+	}
+	if end != "b.example." {
+		t.Errorf("Ожидался конец цепочки b.example., получено %s", end)
+	}
+}
+
+// queryCNAME отправляет A-запрос через handleRequest и возвращает ответ
+func queryCNAME(t *testing.T, s *DNSServer, name string) *dns.Msg {
+	t.Helper()
+	req := new(dns.Msg)
+	req.SetQuestion(name, dns.TypeA)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+	if w.msg == nil {
+		t.Fatalf("Ответ на %s не получен", name)
+	}
+	return w.msg
+}
+
+func TestCNAMEToAnotherZone(t *testing.T) {
+	var queries int32
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		atomic.AddInt32(&queries, 1)
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		switch r.Question[0].Name {
+		case "a.example.com.":
+			// Цель в другой зоне: сервер example.com не знает ее адреса
+			rr, _ := dns.NewRR("a.example.com. 300 IN CNAME b.other.net.")
+			reply.Answer = append(reply.Answer, rr)
+		case "b.other.net.":
+			rr, _ := dns.NewRR("b.other.net. 300 IN A 192.0.2.20")
+			reply.Answer = append(reply.Answer, rr)
+		}
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+
+	msg := queryCNAME(t, s, "a.example.com.")
+	if len(msg.Answer) != 2 {
+		t.Fatalf("Ожидалась цепочка из CNAME и A, получено %v", msg.Answer)
+	}
+	if cname, ok := msg.Answer[0].(*dns.CNAME); !ok || cname.Target != "b.other.net." {
+		t.Errorf("Первой должна идти исходная запись CNAME, получено %v", msg.Answer[0])
+	}
+	if a, ok := msg.Answer[1].(*dns.A); !ok || a.Hdr.Name != "b.other.net." || a.A.String() != "192.0.2.20" {
+		t.Errorf("Второй должна идти A-запись цели, получено %v", msg.Answer[1])
+	}
+
+	// Оба звена закэшированы: повторные запросы не уходят к серверу
+	before := atomic.LoadInt32(&queries)
+	if msg := queryCNAME(t, s, "a.example.com."); len(msg.Answer) != 2 {
+		t.Errorf("Повторный ответ должен содержать всю цепочку, получено %v", msg.Answer)
+	}
+	if msg := queryCNAME(t, s, "b.other.net."); len(msg.Answer) != 1 {
+		t.Errorf("Ожидалась A-запись цели, получено %v", msg.Answer)
+	}
+	if after := atomic.LoadInt32(&queries); after != before {
+		t.Errorf("Повторные запросы обращались к серверу %d раз", after-before)
+	}
+}
+
+func TestCNAMEChainLinksCachedIndependently(t *testing.T) {
+	var queries int32
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		atomic.AddInt32(&queries, 1)
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		if r.Question[0].Name == "a.example.com." {
+			for _, text := range []string{
+				"a.example.com. 300 IN CNAME b.other.net.",
+				"b.other.net. 300 IN CNAME c.third.org.",
+				"c.third.org. 300 IN A 192.0.2.30",
+			} {
+				rr, _ := dns.NewRR(text)
+				reply.Answer = append(reply.Answer, rr)
+			}
+		}
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+
+	if msg := queryCNAME(t, s, "a.example.com."); len(msg.Answer) != 3 {
+		t.Fatalf("Ожидалась цепочка из 3 записей, получено %v", msg.Answer)
+	}
+
+	msg := queryCNAME(t, s, "b.other.net.")
+	if len(msg.Answer) != 2 {
+		t.Fatalf("Ответ для b.other.net. должен начинаться с его CNAME, получено %v", msg.Answer)
+	}
+	if cname, ok := msg.Answer[0].(*dns.CNAME); !ok || cname.Hdr.Name != "b.other.net." {
+		t.Errorf("Первой должна идти запись CNAME b.other.net., получено %v", msg.Answer[0])
+	}
+	if msg := queryCNAME(t, s, "c.third.org."); len(msg.Answer) != 1 {
+		t.Errorf("Ожидалась A-запись c.third.org., получено %v", msg.Answer)
+	}
+	if n := atomic.LoadInt32(&queries); n != 1 {
+		t.Errorf("Звенья цепочки должны отдаваться из кэша, запросов к серверу: %d", n)
+	}
+}
+
+func TestUnrelatedAnswerOwner(t *testing.T) {
+	tests := []struct {
+		name    string
+		answers []string
+		want    string
+	}{
+		{"прямой ответ", []string{"www.example. 300 IN A 192.0.2.1"}, ""},
+		{"цепочка CNAME", []string{
+			"www.example. 300 IN CNAME cdn.other.",
+			"cdn.other. 300 IN CNAME edge.cdn.other.",
+			"EDGE.cdn.other. 300 IN A 192.0.2.2",
+		}, ""},
+		{"DNAME предка", []string{
+			"example. 300 IN DNAME example.net.",
+			"www.example. 300 IN CNAME www.example.net.",
+			"www.example.net. 300 IN A 192.0.2.3",
+		}, ""},
+		{"постороннее имя", []string{"bank.example. 300 IN A 192.0.2.66"}, "bank.example."},
+		{"посторонняя запись после цепочки", []string{
+			"www.example. 300 IN CNAME cdn.other.",
+			"cdn.other. 300 IN A 192.0.2.2",
+			"bank.example. 300 IN A 192.0.2.66",
+		}, "bank.example."},
+	}
+	for _, tt := range tests {
+		var answers []dns.RR
+		for _, text := range tt.answers {
+			answers = append(answers, mustRR(t, text))
+		}
+		if got := unrelatedOwner("www.example.", answers); got != tt.want {
+			t.Errorf("%s: получено %q, ожидалось %q", tt.name, got, tt.want)
+		}
+	}
+}
+
+func TestMismatchedAnswerOwnerRejected(t *testing.T) {
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		rr, _ := dns.NewRR("bank.example. 300 IN A 192.0.2.66")
+		reply.Answer = append(reply.Answer, rr)
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+
+	key := cacheKey("www.example.", dns.TypeA)
+	result := s.lookup(key, "www.example.", "A", dns.TypeA)
+	if result.rcode != dns.RcodeServerFailure || len(result.answers) != 0 {
+		t.Errorf("Ответ с записью для постороннего имени должен отвергаться, получено rcode %d и %v", result.rcode, result.answers)
+	}
+	if _, ok := s.cache.Get(key); ok {
+		t.Errorf("Отвергнутый ответ не должен кэшироваться")
+	}
+	if _, ok := s.cache.Get(cacheKey("bank.example.", dns.TypeA)); ok {
+		t.Errorf("Запись для постороннего имени не должна попасть в кэш")
+	}
+	if got := atomic.LoadUint64(&s.mismatchedAnswers); got != 1 {
+		t.Errorf("Ожидался 1 отвергнутый ответ, учтено %d", got)
+	}
+}
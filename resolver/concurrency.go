@@ -0,0 +1,32 @@
+package resolver
+
+// requestSlots ограничивает число одновременно обрабатываемых запросов.
+// Нулевое значение (nil) ограничения не накладывает.
+type requestSlots chan struct{}
+
+func newRequestSlots(limit int) requestSlots {
+	if limit <= 0 {
+		return nil
+	}
+	return make(requestSlots, limit)
+}
+
+// acquire занимает слот без ожидания и сообщает, удалось ли это
+func (s requestSlots) acquire() bool {
+	if s == nil {
+		return true
+	}
+	select {
+	case s <- struct{}{}:
+		return true
+	default:
+		return false
+	}
+}
+
+// release освобождает слот, занятый acquire
+func (s requestSlots) release() {
+	if s != nil {
+		<-s
+	}
+}
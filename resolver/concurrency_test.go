@@ -0,0 +1,89 @@
+package resolver
+
+import (
+	"fmt"
+	"sync"
+	"sync/atomic"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+func TestRequestSlots(t *testing.T) {
+	slots := newRequestSlots(2)
+	if !slots.acquire() || !slots.acquire() {
+		t.Fatal("Свободные слоты должны заниматься")
+	}
+	if slots.acquire() {
+		t.Error("Сверх лимита слот не должен выдаваться")
+	}
+	slots.release()
+	if !slots.acquire() {
+		t.Error("Освобожденный слот должен выдаваться снова")
+	}
+
+	var unlimited requestSlots
+	for i := 0; i < 10; i++ {
+		if !unlimited.acquire() {
+			t.Fatal("Без лимита слоты должны выдаваться всегда")
+		}
+	}
+}
+
+func TestInFlightLimitUnderFlood(t *testing.T) {
+	const limit = 2
+	var active, peak int32
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		n := atomic.AddInt32(&active, 1)
+		for {
+			p := atomic.LoadInt32(&peak)
+			if n <= p || atomic.CompareAndSwapInt32(&peak, p, n) {
+				break
+			}
+		}
+		time.Sleep(200 * time.Millisecond)
+		atomic.AddInt32(&active, -1)
+
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		rr, _ := dns.NewRR(r.Question[0].Name + " 300 IN A 192.0.2.1")
+		reply.Answer = append(reply.Answer, rr)
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+	s.slots = newRequestSlots(limit)
+
+	const flood = 20
+	var wg sync.WaitGroup
+	var refused int32
+	for i := 0; i < flood; i++ {
+		wg.Add(1)
+		go func(i int) {
+			defer wg.Done()
+			req := new(dns.Msg)
+			req.SetQuestion(fmt.Sprintf("host%d.example.", i), dns.TypeA)
+			w := &testResponseWriter{}
+			s.handleRequest(w, req)
+			if w.msg != nil && w.msg.Rcode == dns.RcodeRefused {
+				atomic.AddInt32(&refused, 1)
+			}
+		}(i)
+	}
+	wg.Wait()
+
+	if p := atomic.LoadInt32(&peak); p > limit {
+		t.Errorf("Одновременно обрабатывалось %d запросов при лимите %d", p, limit)
+	}
+	if refused == 0 {
+		t.Error("При перегрузке часть запросов должна получить REFUSED")
+	}
+	if got := atomic.LoadUint64(&s.overloadRefused); got != uint64(refused) {
+		t.Errorf("Счетчик отклоненных запросов %d, ожидалось %d", got, refused)
+	}
+	if len(s.slots) != 0 {
+		t.Errorf("После обработки остались занятые слоты: %d", len(s.slots))
+	}
+}
@@ -0,0 +1,438 @@
+package resolver
+
+import (
+	"bytes"
+	"encoding/json"
+	"fmt"
+	"os"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// Duration - длительность, записываемая в конфигурации строкой вида "5s" или "10m"
+type Duration struct {
+	time.Duration
+}
+
+// UnmarshalJSON разбирает длительность из строки
+func (d *Duration) UnmarshalJSON(data []byte) error {
+	var str string
+	if err := json.Unmarshal(data, &str); err != nil {
+		return fmt.Errorf("длительность должна быть строкой вида \"5s\": %w", err)
+	}
+	parsed, err := time.ParseDuration(str)
+	if err != nil {
+		return err
+	}
+	d.Duration = parsed
+	return nil
+}
+
+// MarshalJSON записывает длительность строкой
+func (d Duration) MarshalJSON() ([]byte, error) {
+	return json.Marshal(d.String())
+}
+
+// Config содержит все настраиваемые параметры сервера. Поля, отсутствующие
+// в файле конфигурации, сохраняют значения из DefaultConfig.
+type Config struct {
+	Listen            string   `json:"listen"` // Один или несколько адресов через запятую
+	Forwarders        []string `json:"forwarders"`
+	RootHints         string   `json:"root_hints"`
+	AddressFamily     string   `json:"address_family"` // dual, ipv4_only, ipv6_only, prefer_ipv4 или prefer_ipv6
+	UpstreamTimeout   Duration `json:"upstream_timeout"`
+	ResolutionTimeout Duration `json:"resolution_timeout"` // Общий срок разрешения со всеми вложенными запросами
+	MaxUDPSize        uint16   `json:"max_udp_size"`
+	UpstreamUDPSize   uint16   `json:"upstream_udp_size"` // Размер буфера EDNS в запросах к серверам (RFC 6891)
+
+	// RequireRD включает режим кэширующего пересылающего резолвера: сервер
+	// никогда не выполняет рекурсию сам, запросы с флагом RD пересылаются
+	// forwarders, а запросы без него получают REFUSED. Требует forwarders.
+	RequireRD bool `json:"require_rd"`
+
+	// HappyEyeballsDelay - задержка перед запросом к серверу другого
+	// семейства адресов, если предыдущий сервер еще не ответил (по образцу
+	// RFC 8305); 0 отключает параллельные запросы
+	HappyEyeballsDelay Duration `json:"happy_eyeballs_delay"`
+
+	// UpstreamRetries - сколько раз повторяется UDP-запрос к серверу, не
+	// ответившему за upstream_timeout, прежде чем перейти к следующему
+	UpstreamRetries int `json:"upstream_retries"`
+
+	// RootRetries - сколько раз повторяется опрос корневых серверов, если не
+	// ответил ни один из них (например, сеть хоста еще не поднялась), с
+	// удваивающейся паузой; 0 - сразу отвечать SERVFAIL
+	RootRetries int `json:"root_retries"`
+
+	// SourcePortMin и SourcePortMax - диапазон, из которого случайно
+	// выбирается локальный порт каждого UDP-запроса к вышестоящим серверам;
+	// сужается, если файрвол пропускает только часть портов
+	SourcePortMin int `json:"source_port_min"`
+	SourcePortMax int `json:"source_port_max"`
+
+	// MaxUpstreamQueries ограничивает число исходящих запросов одного
+	// разрешения; 0 снимает ограничение
+	MaxUpstreamQueries int `json:"max_upstream_queries"`
+
+	// MaxAnswerRecords ограничивает число записей во всех секциях ответа
+	// вышестоящего сервера; больший ответ отбрасывается как некорректный.
+	// 0 снимает ограничение
+	MaxAnswerRecords int `json:"max_answer_records"`
+
+	// MinimalResponses оставляет в ответах клиентам только секцию answer
+	// (и SOA для отрицательных ответов)
+	MinimalResponses bool `json:"minimal_responses"`
+
+	// EmptyQueryKeepalive отвечает NOERROR без записей на запросы без
+	// вопросов, которые некоторые системы мониторинга используют как проверку
+	// доступности; по умолчанию такие запросы получают FORMERR
+	EmptyQueryKeepalive bool `json:"empty_query_keepalive"`
+
+	// AnswerOrder - порядок записей A и AAAA в ответах клиентам: "fixed",
+	// "random" или "cyclic". Кэш всегда хранит исходный порядок.
+	AnswerOrder string `json:"answer_order"`
+
+	// DNS64 синтезирует AAAA-записи из A-записей для имен без AAAA (RFC 6147),
+	// встраивая IPv4-адрес в префикс NAT64 DNS64Prefix (RFC 6052)
+	DNS64       bool   `json:"dns64"`
+	DNS64Prefix string `json:"dns64_prefix"`
+
+	// ClientSubnet - режим EDNS Client Subnet (RFC 7871) для запросов к
+	// вышестоящим резолверам в режиме пересылки: "off" - опция не
+	// отправляется, "client" - отправляется подсеть клиента, усеченная до
+	// ecs_prefix_v4 / ecs_prefix_v6 бит, "zero" - отправляется пустая
+	// подсеть, запрещающая подбирать ответ по адресу клиента
+	ClientSubnet string `json:"client_subnet"`
+	ECSPrefixV4  int    `json:"ecs_prefix_v4"`
+	ECSPrefixV6  int    `json:"ecs_prefix_v6"`
+
+	// EDNSCookies добавляет DNS cookie (RFC 7873) в запросы к вышестоящим
+	// серверам и проверяет cookie в их ответах
+	EDNSCookies bool `json:"edns_cookies"`
+
+	// ChaosResponses включает ответы на version.bind и id.server класса
+	// CHAOS; ServerID по умолчанию - имя хоста
+	ChaosResponses bool   `json:"chaos_responses"`
+	ServerVersion  string `json:"server_version"`
+	ServerID       string `json:"server_id"`
+
+	// NSID - идентификатор сервера для клиентов, запросивших опцию NSID
+	// (RFC 5001), например имя узла anycast; пустое значение отключает опцию
+	NSID string `json:"nsid"`
+
+	// AllowLocalGlue разрешает loopback и link-local адреса серверов имен
+	// в направлениях (нужно только для тестовых и лабораторных зон)
+	AllowLocalGlue bool `json:"allow_local_glue"`
+
+	// GluePolicy - использование glue из направлений: "standard" - glue
+	// принимается для любых серверов имен, адреса серверов без glue
+	// разрешаются отдельно; "strict" - glue для серверов вне зоны отвечавшего
+	// сервера игнорируется, их адреса всегда разрешаются (надежнее, но
+	// медленнее); "fast" - при наличии glue серверы без него не разрешаются
+	GluePolicy string `json:"glue_policy"`
+
+	CacheFile       string   `json:"cache_file"`
+	MaxCacheEntries int      `json:"max_cache_entries"`
+	CleanupInterval Duration `json:"cleanup_interval"`
+	MinCacheTTL     Duration `json:"min_cache_ttl"`
+	MaxCacheTTL     Duration `json:"max_cache_ttl"`
+	StaleWindow     Duration `json:"stale_window"`
+	ServfailTTL     Duration `json:"servfail_ttl"`
+
+	// MaxNegativeTTL ограничивает срок хранения отрицательных ответов
+	// (NXDOMAIN и NODATA), который берется из SOA ответа (RFC 2308);
+	// 0 отключает отрицательное кэширование
+	MaxNegativeTTL Duration `json:"max_negative_ttl"`
+
+	// ZoneTTL переопределяет min_cache_ttl и max_cache_ttl для зоны (ключ) и
+	// ее поддоменов; действует правило ближайшей зоны
+	ZoneTTL map[string]ZoneTTL `json:"zone_ttl"`
+
+	// L2CacheDir включает второй уровень кэша на диске: записи, вытесненные
+	// из памяти по лимиту max_cache_entries, переносятся в этот каталог.
+	// L2CacheMaxEntries ограничивает число записей на диске (0 - без ограничения).
+	L2CacheDir        string `json:"l2_cache_dir"`
+	L2CacheMaxEntries int    `json:"l2_cache_max_entries"`
+
+	// PrefetchWindow ограничивает сверху запас до истечения записи, с
+	// которым она обновляется упреждающе (1/10 исходного TTL)
+	PrefetchInterval    Duration `json:"prefetch_interval"`
+	PrefetchWindow      Duration `json:"prefetch_window"`
+	PrefetchConcurrency int      `json:"prefetch_concurrency"`
+
+	// WarmupNames - популярные имена, которые разрешаются при запуске вместе
+	// с NS корня и их доменов верхнего уровня, чтобы первые клиенты не ждали
+	// рекурсии. Сервер сообщает о готовности после прогрева, но не позже
+	// warmup_timeout (0 - без ограничения).
+	WarmupNames   []string `json:"warmup_names"`
+	WarmupTimeout Duration `json:"warmup_timeout"`
+
+	NXDomainLimit    int      `json:"nxdomain_limit"`
+	NXDomainTTL      Duration `json:"nxdomain_ttl"`
+	QuarantinePeriod Duration `json:"quarantine_period"`
+
+	RateLimitQPS   float64 `json:"rate_limit_qps"`
+	RateLimitBurst int     `json:"rate_limit_burst"`
+
+	// MaxInFlight ограничивает число одновременно обрабатываемых запросов;
+	// сверх него запросы получают REFUSED. 0 снимает ограничение.
+	MaxInFlight int `json:"max_in_flight"`
+
+	// UDPListeners - число UDP-сокетов на каждый адрес прослушивания. Сокеты
+	// открываются с SO_REUSEPORT, и ядро распределяет между ними пакеты, так
+	// что прием не упирается в один цикл чтения. 0 - по числу процессоров.
+	UDPListeners int `json:"udp_listeners"`
+
+	// TCPFallback открывает TCP на тех же адресах listen, чтобы клиенты,
+	// получившие усеченный (TC) ответ по UDP, могли повторить запрос по TCP.
+	// TCPTimeout ограничивает чтение запроса, запись ответа и простой
+	// TCP-соединения (RFC 7766, раздел 6.2.3).
+	TCPFallback bool     `json:"tcp_fallback"`
+	TCPTimeout  Duration `json:"tcp_timeout"`
+
+	// ACLAllow и ACLDeny - сети клиентов в нотации CIDR; ACLAction -
+	// "refuse" (ответ REFUSED) или "drop" (без ответа) для запрещенных клиентов
+	ACLAllow  []string `json:"acl_allow"`
+	ACLDeny   []string `json:"acl_deny"`
+	ACLAction string   `json:"acl_action"`
+
+	MetricsListen string `json:"metrics_listen"`
+	HealthListen  string `json:"health_listen"`
+	TCPListen     string `json:"tcp_listen"` // DNS по TCP (RFC 7766), адреса через запятую
+	DoTListen     string `json:"dot_listen"`
+	DoHListen     string `json:"doh_listen"`
+	TLSCert       string `json:"tls_cert"`
+	TLSKey        string `json:"tls_key"`
+
+	// PaddingBlockSize - размер блока, до кратного которому дополняются
+	// ответы DoT и DoH клиентам, запросившим padding (RFC 7830, RFC 8467);
+	// 0 отключает дополнение
+	PaddingBlockSize int `json:"padding_block_size"`
+
+	Blocklist           string `json:"blocklist"`
+	BlocklistSubdomains bool   `json:"blocklist_subdomains"`
+	BlocklistSinkhole   bool   `json:"blocklist_sinkhole"`
+	// QueryLogFile - файл журнала запросов (JSON-строка на запрос); файл
+	// ротируется при достижении query_log_max_size байт, хранится
+	// query_log_keep предыдущих файлов
+	QueryLogFile    string `json:"query_log_file"`
+	QueryLogMaxSize int64  `json:"query_log_max_size"`
+	QueryLogKeep    int    `json:"query_log_keep"`
+
+	HostsFile string `json:"hosts_file"`
+	HostsTTL  uint32 `json:"hosts_ttl"`
+
+	// LocalZones - авторитетные зоны из мастер-файлов (RFC 1035): ключ - имя
+	// зоны, значение - путь к файлу. Ответы на имена в этих зонах даются без
+	// рекурсии с флагом AA.
+	LocalZones map[string]string `json:"local_zones"`
+
+	// ForwardZones - условная пересылка: запросы к зоне (ключ) и ее
+	// поддоменам пересылаются указанным серверам, остальные разрешаются
+	// как обычно
+	ForwardZones map[string][]string `json:"forward_zones"`
+}
+
+// ZoneTTL - границы срока хранения ответов одной зоны в кэше; нулевая
+// граница берется из min_cache_ttl или max_cache_ttl
+type ZoneTTL struct {
+	Min Duration `json:"min"`
+	Max Duration `json:"max"`
+}
+
+// DefaultConfig возвращает конфигурацию по умолчанию
+func DefaultConfig() Config {
+	return Config{
+		Listen:              defaultListen,
+		AddressFamily:       familyDual,
+		HappyEyeballsDelay:  Duration{happyEyeballsDelay},
+		UpstreamTimeout:     Duration{upstreamTimeout},
+		ResolutionTimeout:   Duration{resolveTimeout},
+		MaxUpstreamQueries:  maxUpstreamQuery,
+		MaxAnswerRecords:    maxAnswerRecords,
+		UpstreamRetries:     upstreamRetries,
+		SourcePortMin:       sourcePortMin,
+		SourcePortMax:       sourcePortMax,
+		MaxUDPSize:          maxUDPSize,
+		UpstreamUDPSize:     upstreamUDPSize,
+		MinimalResponses:    true,
+		AnswerOrder:         orderFixed,
+		DNS64Prefix:         dns64Prefix,
+		GluePolicy:          gluePolicyStandard,
+		ClientSubnet:        ecsOff,
+		ECSPrefixV4:         ecsPrefixV4,
+		ECSPrefixV6:         ecsPrefixV6,
+		EDNSCookies:         true,
+		ChaosResponses:      true,
+		ServerVersion:       serverVersion,
+		CacheFile:           cacheFilePath,
+		MaxCacheEntries:     maxCacheEntries,
+		CleanupInterval:     Duration{cleanupInterval},
+		MaxCacheTTL:         Duration{maxCacheTTL},
+		StaleWindow:         Duration{staleWindow},
+		ServfailTTL:         Duration{servfailTTL},
+		MaxNegativeTTL:      Duration{maxNegativeTTL},
+		PrefetchInterval:    Duration{prefetchInterval},
+		PrefetchWindow:      Duration{prefetchWindow},
+		PrefetchConcurrency: prefetchWorkers,
+		WarmupTimeout:       Duration{warmupTimeout},
+		NXDomainLimit:       nxdomainLimit,
+		NXDomainTTL:         Duration{nxdomainTTL},
+		QuarantinePeriod:    Duration{quarantinePeriod},
+		RateLimitQPS:        rateLimitQPS,
+		RateLimitBurst:      rateLimitBurst,
+		MaxInFlight:         maxInFlight,
+		UDPListeners:        1,
+		TCPFallback:         true,
+		TCPTimeout:          Duration{tcpTimeout},
+		ACLAction:           aclActionRefuse,
+		MetricsListen:       metricsListen,
+		HealthListen:        healthListen,
+		DoTListen:           dotListen,
+		DoHListen:           dohListen,
+		PaddingBlockSize:    paddingBlockSize,
+		BlocklistSubdomains: true,
+		HostsTTL:            defaultHostsTTL,
+		QueryLogMaxSize:     queryLogMaxSize,
+		QueryLogKeep:        queryLogKeep,
+	}
+}
+
+// LoadConfig загружает конфигурацию из JSON-файла поверх значений по умолчанию
+func LoadConfig(path string) (Config, error) {
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return Config{}, err
+	}
+	return parseConfig(data)
+}
+
+func parseConfig(data []byte) (Config, error) {
+	cfg := DefaultConfig()
+
+	decoder := json.NewDecoder(bytes.NewReader(data))
+	decoder.DisallowUnknownFields()
+	if err := decoder.Decode(&cfg); err != nil {
+		return Config{}, fmt.Errorf("ошибка разбора конфигурации: %w", err)
+	}
+
+	if err := cfg.validate(); err != nil {
+		return Config{}, err
+	}
+	return cfg, nil
+}
+
+func (cfg Config) validate() error {
+	if cfg.Listen == "" {
+		return fmt.Errorf("не задан адрес listen")
+	}
+	if cfg.UpstreamTimeout.Duration <= 0 {
+		return fmt.Errorf("upstream_timeout должен быть положительным")
+	}
+	if cfg.ResolutionTimeout.Duration <= 0 {
+		return fmt.Errorf("resolution_timeout должен быть положительным")
+	}
+	if cfg.L2CacheMaxEntries < 0 {
+		return fmt.Errorf("l2_cache_max_entries не может быть отрицательным")
+	}
+	if cfg.QueryLogMaxSize <= 0 {
+		return fmt.Errorf("query_log_max_size должен быть положительным")
+	}
+	if cfg.QueryLogKeep < 0 {
+		return fmt.Errorf("query_log_keep не может быть отрицательным")
+	}
+	if cfg.MaxInFlight < 0 {
+		return fmt.Errorf("max_in_flight не может быть отрицательным")
+	}
+	if cfg.UDPListeners < 0 {
+		return fmt.Errorf("udp_listeners не может быть отрицательным")
+	}
+	if cfg.RootRetries < 0 {
+		return fmt.Errorf("root_retries не может быть отрицательным")
+	}
+	if cfg.UpstreamRetries < 0 {
+		return fmt.Errorf("upstream_retries не может быть отрицательным")
+	}
+	if cfg.MaxUpstreamQueries < 0 {
+		return fmt.Errorf("max_upstream_queries не может быть отрицательным")
+	}
+	if cfg.MaxAnswerRecords < 0 {
+		return fmt.Errorf("max_answer_records не может быть отрицательным")
+	}
+	if cfg.CleanupInterval.Duration <= 0 {
+		return fmt.Errorf("cleanup_interval должен быть положительным")
+	}
+	if cfg.MinCacheTTL.Duration < 0 || cfg.MaxCacheTTL.Duration < 0 {
+		return fmt.Errorf("min_cache_ttl и max_cache_ttl не могут быть отрицательными")
+	}
+	if cfg.MaxNegativeTTL.Duration < 0 {
+		return fmt.Errorf("max_negative_ttl не может быть отрицательным")
+	}
+	if cfg.StaleWindow.Duration < 0 {
+		return fmt.Errorf("stale_window не может быть отрицательным")
+	}
+	if cfg.MaxCacheTTL.Duration > 0 && cfg.MinCacheTTL.Duration > cfg.MaxCacheTTL.Duration {
+		return fmt.Errorf("min_cache_ttl не может превышать max_cache_ttl")
+	}
+	if err := validZoneTTL(cfg.ZoneTTL); err != nil {
+		return err
+	}
+	if cfg.PrefetchInterval.Duration > 0 && cfg.PrefetchConcurrency < 1 {
+		return fmt.Errorf("prefetch_concurrency должен быть положительным")
+	}
+	if cfg.TCPTimeout.Duration <= 0 {
+		return fmt.Errorf("tcp_timeout должен быть положительным")
+	}
+	if cfg.WarmupTimeout.Duration < 0 {
+		return fmt.Errorf("warmup_timeout не может быть отрицательным")
+	}
+	if err := validWarmupNames(cfg.WarmupNames); err != nil {
+		return err
+	}
+	if cfg.PaddingBlockSize < 0 || cfg.PaddingBlockSize > dns.MaxMsgSize {
+		return fmt.Errorf("padding_block_size должен быть от 0 до %d", dns.MaxMsgSize)
+	}
+	if cfg.ACLAction != aclActionRefuse && cfg.ACLAction != aclActionDrop {
+		return fmt.Errorf("acl_action должен быть %q или %q", aclActionRefuse, aclActionDrop)
+	}
+	if err := validSourcePorts(cfg); err != nil {
+		return err
+	}
+	if err := validClientSubnet(cfg); err != nil {
+		return err
+	}
+	if err := validGluePolicy(cfg.GluePolicy); err != nil {
+		return err
+	}
+	if err := validAnswerOrder(cfg.AnswerOrder); err != nil {
+		return err
+	}
+	if cfg.DNS64 {
+		if err := validDNS64Prefix(cfg.DNS64Prefix); err != nil {
+			return err
+		}
+	}
+	if cfg.RequireRD && len(normalizeUpstreams(cfg.Forwarders)) == 0 {
+		return fmt.Errorf("require_rd требует непустого списка forwarders")
+	}
+	if err := validForwardZones(cfg.ForwardZones); err != nil {
+		return err
+	}
+	if cfg.HappyEyeballsDelay.Duration < 0 {
+		return fmt.Errorf("happy_eyeballs_delay не может быть отрицательным")
+	}
+	if err := validAddressFamily(cfg.AddressFamily); err != nil {
+		return err
+	}
+	if _, err := NewACL(cfg.ACLAllow, cfg.ACLDeny); err != nil {
+		return err
+	}
+	if cfg.MaxUDPSize < 512 {
+		return fmt.Errorf("max_udp_size не может быть меньше 512")
+	}
+	if cfg.UpstreamUDPSize < 512 {
+		return fmt.Errorf("upstream_udp_size не может быть меньше 512")
+	}
+	return nil
+}
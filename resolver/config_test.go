@@ -0,0 +1,103 @@
+package resolver
+
+import (
+	"testing"
+	"time"
+)
+
+func TestParseConfigMergesDefaults(t *testing.T) {
+	cfg, err := parseConfig([]byte(`{
+		"listen": "127.0.0.1:53",
+		"upstream_timeout": "2s",
+		"forwarders": ["1.1.1.1", "9.9.9.9:53"],
+		"rate_limit_qps": 10
+	}`))
+	if err != nil {
+		t.Fatalf("Ошибка разбора конфигурации: %v", err)
+	}
+
+	if cfg.Listen != "127.0.0.1:53" {
+		t.Errorf("Неверный адрес listen: %s", cfg.Listen)
+	}
+	if cfg.UpstreamTimeout.Duration != 2*time.Second {
+		t.Errorf("Неверный upstream_timeout: %v", cfg.UpstreamTimeout)
+	}
+	if len(cfg.Forwarders) != 2 || cfg.RateLimitQPS != 10 {
+		t.Errorf("Значения из файла не применены: %+v", cfg)
+	}
+
+	defaults := DefaultConfig()
+	if cfg.CleanupInterval != defaults.CleanupInterval {
+		t.Errorf("Отсутствующий cleanup_interval должен остаться по умолчанию, получено %v", cfg.CleanupInterval)
+	}
+	if cfg.MaxCacheEntries != defaults.MaxCacheEntries || cfg.RateLimitBurst != defaults.RateLimitBurst {
+		t.Errorf("Отсутствующие поля должны остаться по умолчанию: %+v", cfg)
+	}
+	if !cfg.BlocklistSubdomains {
+		t.Errorf("blocklist_subdomains по умолчанию должен быть включен")
+	}
+}
+
+func TestParseConfigErrors(t *testing.T) {
+	for _, data := range []string{
+		`{"listne": ":53"}`,
+		`{"upstream_timeout": "fast"}`,
+		`{"upstream_timeout": 5}`,
+		`{"upstream_timeout": "0s"}`,
+		`{"listen": ""}`,
+		`{"client_subnet": "full"}`,
+		`{"ecs_prefix_v4": 33}`,
+		`{"glue_policy": "lenient"}`,
+		`{"source_port_min": 0}`,
+		`{"source_port_min": 40000, "source_port_max": 30000}`,
+		`{"source_port_max": 70000}`,
+		`{"max_answer_records": -1}`,
+		`{"forward_zones": {"corp.example": []}}`,
+		`{"require_rd": true}`,
+		`{"zone_ttl": {"example.com": {"min": "1h", "max": "1m"}}}`,
+		`{"dns64": true, "dns64_prefix": "64:ff9b::/80"}`,
+		`{"warmup_names": ["bad..name"]}`,
+		`{"warmup_timeout": "-1s"}`,
+	} {
+		if _, err := parseConfig([]byte(data)); err == nil {
+			t.Errorf("Ожидалась ошибка для конфигурации %s", data)
+		}
+	}
+}
+
+func TestNewDNSServerWithConfig(t *testing.T) {
+	cfg := DefaultConfig()
+	cfg.Forwarders = []string{"192.0.2.53"}
+	cfg.MetricsListen = ""
+
+	s, err := NewDNSServerWithConfig(cfg)
+	if err != nil {
+		t.Fatalf("Ошибка создания сервера: %v", err)
+	}
+	if len(s.forwarders) != 1 || s.forwarders[0] != "192.0.2.53:53" {
+		t.Errorf("Вышестоящие резолверы должны нормализоваться, получено %v", s.forwarders)
+	}
+	if s.metricsAddr != "" {
+		t.Errorf("Сервер метрик должен быть отключен")
+	}
+}
+
+func TestNewDNSServerWithConfigRejectsInvalid(t *testing.T) {
+	// Такие значения могут прийти из флагов командной строки или кода,
+	// минуя проверку в LoadConfig
+	cfg := DefaultConfig()
+	cfg.SourcePortMin = 60000
+	cfg.SourcePortMax = 1024
+	if _, err := NewDNSServerWithConfig(cfg); err == nil {
+		t.Errorf("Ожидалась ошибка для source_port_min больше source_port_max")
+	}
+	if _, err := New(cfg); err == nil {
+		t.Errorf("New должен отклонять некорректную конфигурацию")
+	}
+
+	cfg = DefaultConfig()
+	cfg.Listen = ""
+	if _, err := NewDNSServerWithConfig(cfg); err == nil {
+		t.Errorf("Ожидалась ошибка для пустого listen")
+	}
+}
@@ -0,0 +1,150 @@
+package resolver
+
+import (
+	"crypto/hmac"
+	"crypto/rand"
+	"crypto/sha256"
+	"encoding/hex"
+	"fmt"
+	"sync"
+
+	"github.com/miekg/dns"
+)
+
+// Длины частей cookie по RFC 7873: клиентская всегда 8 байт, серверная - от 8 до 32
+const (
+	clientCookieLen    = 8
+	minServerCookieLen = 8
+	maxServerCookieLen = 32
+)
+
+// CookieJar хранит секрет для клиентских DNS cookie (RFC 7873) и серверные
+// cookie, полученные от каждого вышестоящего сервера
+type CookieJar struct {
+	secret []byte
+
+	mu      sync.RWMutex
+	servers map[string][]byte
+}
+
+// NewCookieJar создает хранилище cookie со случайным секретом
+func NewCookieJar() *CookieJar {
+	secret := make([]byte, 16)
+	rand.Read(secret)
+	return &CookieJar{secret: secret, servers: make(map[string][]byte)}
+}
+
+// newConfigCookieJar создает хранилище cookie, если они включены в конфигурации
+func newConfigCookieJar(cfg Config) *CookieJar {
+	if !cfg.EDNSCookies {
+		return nil
+	}
+	return NewCookieJar()
+}
+
+// clientCookie вычисляет клиентскую cookie для сервера. Cookie своя для
+// каждого адреса, чтобы серверы не могли связать запросы между собой.
+func (j *CookieJar) clientCookie(addr string) []byte {
+	mac := hmac.New(sha256.New, j.secret)
+	mac.Write([]byte(serverKey(addr)))
+	return mac.Sum(nil)[:clientCookieLen]
+}
+
+// ServerCookie возвращает запомненную серверную cookie
+func (j *CookieJar) ServerCookie(addr string) []byte {
+	j.mu.RLock()
+	defer j.mu.RUnlock()
+	return j.servers[serverKey(addr)]
+}
+
+// Apply добавляет в OPT-запись запроса cookie для сервера addr, заменяя
+// cookie, оставшуюся от запроса к другому серверу
+func (j *CookieJar) Apply(msg *dns.Msg, addr string) {
+	if j == nil {
+		return
+	}
+	opt := msg.IsEdns0()
+	if opt == nil {
+		msg.SetEdns0(dns.DefaultMsgSize, false)
+		opt = msg.IsEdns0()
+	}
+
+	options := opt.Option[:0]
+	for _, o := range opt.Option {
+		if o.Option() != dns.EDNS0COOKIE {
+			options = append(options, o)
+		}
+	}
+	opt.Option = append(options, &dns.EDNS0_COOKIE{
+		Code:   dns.EDNS0COOKIE,
+		Cookie: encodeCookie(j.clientCookie(addr), j.ServerCookie(addr)),
+	})
+}
+
+// Update проверяет cookie в ответе сервера addr и запоминает его серверную
+// cookie. Ответ с чужой клиентской cookie считается поддельным.
+func (j *CookieJar) Update(addr string, resp *dns.Msg) error {
+	if j == nil {
+		return nil
+	}
+	cookie := findCookie(resp)
+	if cookie == nil {
+		// Сервер не поддерживает cookie
+		return nil
+	}
+
+	client, server, err := decodeCookie(cookie.Cookie)
+	if err != nil {
+		return err
+	}
+	if !hmac.Equal(client, j.clientCookie(addr)) {
+		return fmt.Errorf("клиентская cookie в ответе не совпадает с отправленной")
+	}
+	if server == nil {
+		return nil
+	}
+
+	j.mu.Lock()
+	defer j.mu.Unlock()
+	j.servers[serverKey(addr)] = server
+	return nil
+}
+
+// findCookie возвращает опцию COOKIE из OPT-записи сообщения
+func findCookie(msg *dns.Msg) *dns.EDNS0_COOKIE {
+	opt := msg.IsEdns0()
+	if opt == nil {
+		return nil
+	}
+	for _, o := range opt.Option {
+		if cookie, ok := o.(*dns.EDNS0_COOKIE); ok {
+			return cookie
+		}
+	}
+	return nil
+}
+
+// encodeCookie кодирует клиентскую и серверную части в значение опции COOKIE
+func encodeCookie(client, server []byte) string {
+	return hex.EncodeToString(client) + hex.EncodeToString(server)
+}
+
+// decodeCookie разбирает значение опции COOKIE и проверяет длины частей
+func decodeCookie(cookie string) (client, server []byte, err error) {
+	raw, err := hex.DecodeString(cookie)
+	if err != nil {
+		return nil, nil, fmt.Errorf("некорректная cookie: %w", err)
+	}
+	if len(raw) < clientCookieLen {
+		return nil, nil, fmt.Errorf("cookie короче %d байт", clientCookieLen)
+	}
+	client, server = raw[:clientCookieLen], raw[clientCookieLen:]
+	if len(server) == 0 {
+		return client, nil, nil
+	}
+	if len(server) < minServerCookieLen || len(server) > maxServerCookieLen {
+		return nil, nil, fmt.Errorf("длина серверной cookie %d вне диапазона %d-%d байт",
+			len(server), minServerCookieLen, maxServerCookieLen)
+	}
+	return client, server, nil
+}
@@ -0,0 +1,120 @@
+package resolver
+
+import (
+	"context"
+	"sync"
+	"testing"
+
+	"github.com/miekg/dns"
+)
+
+const testServerCookie = "0102030405060708090a0b0c0d0e0f10"
+
+// startCookieUpstream запускает сервер, который запоминает полученные cookie
+// и отвечает клиентской cookie из запроса (или clientOverride) со своей серверной
+func startCookieUpstream(t *testing.T, clientOverride string) (string, func() []string) {
+	var mu sync.Mutex
+	var received []string
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		reply.SetEdns0(dns.DefaultMsgSize, false)
+		if cookie := findCookie(r); cookie != nil {
+			mu.Lock()
+			received = append(received, cookie.Cookie)
+			mu.Unlock()
+
+			client := cookie.Cookie[:2*clientCookieLen]
+			if clientOverride != "" {
+				client = clientOverride
+			}
+			opt := reply.IsEdns0()
+			opt.Option = append(opt.Option, &dns.EDNS0_COOKIE{
+				Code:   dns.EDNS0COOKIE,
+				Cookie: client + testServerCookie,
+			})
+		}
+		w.WriteMsg(reply)
+	})
+	return addr, func() []string {
+		mu.Lock()
+		defer mu.Unlock()
+		return append([]string(nil), received...)
+	}
+}
+
+func TestCookieSentAndServerCookieRemembered(t *testing.T) {
+	addr, received := startCookieUpstream(t, "")
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+
+	for i := 0; i < 2; i++ {
+		if _, err := s.forward(context.Background(), "example.com.", dns.TypeA); err != nil {
+			t.Fatalf("Запрос %d завершился ошибкой: %v", i+1, err)
+		}
+	}
+
+	cookies := received()
+	if len(cookies) != 2 {
+		t.Fatalf("Сервер должен получить cookie в обоих запросах, получено %d", len(cookies))
+	}
+	if len(cookies[0]) != 2*clientCookieLen {
+		t.Errorf("Первый запрос должен содержать только клиентскую cookie, получено %q", cookies[0])
+	}
+	if want := cookies[0] + testServerCookie; cookies[1] != want {
+		t.Errorf("Второй запрос должен повторить серверную cookie: получено %q, ожидалось %q", cookies[1], want)
+	}
+	if got := encodeCookie(nil, s.cookies.ServerCookie(addr)); got != testServerCookie {
+		t.Errorf("Запомнена серверная cookie %q, ожидалась %q", got, testServerCookie)
+	}
+}
+
+func TestCookieMismatchRejected(t *testing.T) {
+	addr, _ := startCookieUpstream(t, "ffffffffffffffff")
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+
+	if _, err := s.forward(context.Background(), "example.com.", dns.TypeA); err == nil {
+		t.Errorf("Ответ с чужой клиентской cookie должен быть отброшен")
+	}
+	if cookie := s.cookies.ServerCookie(addr); cookie != nil {
+		t.Errorf("Серверная cookie из поддельного ответа не должна запоминаться")
+	}
+}
+
+func TestCookiesDisabled(t *testing.T) {
+	addr, received := startCookieUpstream(t, "")
+	cfg := DefaultConfig()
+	cfg.EDNSCookies = false
+	s, err := NewDNSServerWithConfig(cfg)
+	if err != nil {
+		t.Fatalf("Ошибка создания сервера: %v", err)
+	}
+	s.forwarders = []string{addr}
+
+	if _, err := s.forward(context.Background(), "example.com.", dns.TypeA); err != nil {
+		t.Fatalf("Запрос завершился ошибкой: %v", err)
+	}
+	if cookies := received(); len(cookies) != 0 {
+		t.Errorf("При отключенных cookie запрос не должен их содержать: %v", cookies)
+	}
+}
+
+func TestDecodeCookie(t *testing.T) {
+	tests := []struct {
+		cookie string
+		ok     bool
+	}{
+		{"0102030405060708", true},
+		{"0102030405060708" + testServerCookie, true},
+		{"01020304", false},
+		{"0102030405060708aabb", false},
+		{"zz02030405060708", false},
+	}
+	for _, tt := range tests {
+		_, _, err := decodeCookie(tt.cookie)
+		if (err == nil) != tt.ok {
+			t.Errorf("decodeCookie(%q): ошибка %v, ожидался успех %v", tt.cookie, err, tt.ok)
+		}
+	}
+}
@@ -0,0 +1,435 @@
+package resolver
+
+import (
+	"context"
+	"fmt"
+	"log/slog"
+	"net"
+	"strings"
+	"sync"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// Политики использования glue из направлений (glue_policy)
+const (
+	gluePolicyStandard = "standard" // glue для любых серверов, разрешение серверов без glue
+	gluePolicyStrict   = "strict"   // glue только для серверов внутри зоны отвечавшего сервера
+	gluePolicyFast     = "fast"     // серверы без glue разрешаются, только если glue не хватило
+)
+
+// nsAddrWorkers - сколько адресов серверов имен разрешается одновременно
+const nsAddrWorkers = 8
+
+// rootRetryBackoff - пауза перед первым повтором опроса корневых серверов
+const rootRetryBackoff = 100 * time.Millisecond
+
+// maxLookupDepth - допустимая вложенность разрешения серверов имен без glue
+const maxLookupDepth = 4
+
+type lookupDepthKey struct{}
+
+// delegation - адреса авторитетных серверов зоны
+type delegation struct {
+	servers   []string
+	expiresAt time.Time
+}
+
+// DelegationCache хранит делегирования зон (NS и glue), чтобы обход
+// следующего имени в той же зоне начинался с ближайшего известного
+// делегирования, а не с корня. Адреса самих серверов имен хранятся
+// отдельно со своими TTL: один сервер часто обслуживает много зон.
+type DelegationCache struct {
+	mu    sync.Mutex
+	zones map[string]delegation
+	hosts map[string]delegation
+}
+
+// NewDelegationCache создает пустой кэш делегирований
+func NewDelegationCache() *DelegationCache {
+	return &DelegationCache{
+		zones: make(map[string]delegation),
+		hosts: make(map[string]delegation),
+	}
+}
+
+// Set сохраняет адреса серверов зоны на время ttl
+func (d *DelegationCache) Set(zone string, servers []string, ttl time.Duration) {
+	if len(servers) == 0 || ttl <= 0 {
+		return
+	}
+	d.mu.Lock()
+	defer d.mu.Unlock()
+	d.zones[zoneKey(zone)] = delegation{
+		servers:   append([]string(nil), servers...),
+		expiresAt: time.Now().Add(ttl),
+	}
+}
+
+// Get возвращает адреса серверов зоны, если делегирование известно
+func (d *DelegationCache) Get(zone string) ([]string, bool) {
+	d.mu.Lock()
+	defer d.mu.Unlock()
+	return d.get(zoneKey(zone), time.Now())
+}
+
+// SetHost сохраняет адреса сервера имен host на время ttl
+func (d *DelegationCache) SetHost(host string, addrs []string, ttl time.Duration) {
+	if len(addrs) == 0 || ttl <= 0 {
+		return
+	}
+	d.mu.Lock()
+	defer d.mu.Unlock()
+	d.hosts[zoneKey(host)] = delegation{
+		servers:   append([]string(nil), addrs...),
+		expiresAt: time.Now().Add(ttl),
+	}
+}
+
+// Host возвращает закэшированные адреса сервера имен host
+func (d *DelegationCache) Host(host string) ([]string, bool) {
+	d.mu.Lock()
+	defer d.mu.Unlock()
+	del, ok := d.hosts[zoneKey(host)]
+	if !ok || time.Now().After(del.expiresAt) {
+		return nil, false
+	}
+	return append([]string(nil), del.servers...), true
+}
+
+// Closest возвращает ближайшую к qname зону с известным делегированием.
+// Корень в кэше не хранится: его адреса известны из подсказок.
+func (d *DelegationCache) Closest(qname string) (string, []string, bool) {
+	d.mu.Lock()
+	defer d.mu.Unlock()
+
+	now := time.Now()
+	name := zoneKey(qname)
+	for off, end := 0, false; !end; off, end = dns.NextLabel(name, off) {
+		zone := name[off:]
+		if servers, ok := d.get(zone, now); ok {
+			return zone, servers, true
+		}
+	}
+	return "", nil, false
+}
+
+// RemoveExpired удаляет истёкшие делегирования и адреса серверов имен и
+// возвращает их количество
+func (d *DelegationCache) RemoveExpired(now time.Time) int {
+	d.mu.Lock()
+	defer d.mu.Unlock()
+
+	removed := 0
+	for _, entries := range []map[string]delegation{d.zones, d.hosts} {
+		for name, del := range entries {
+			if now.After(del.expiresAt) {
+				delete(entries, name)
+				removed++
+			}
+		}
+	}
+	return removed
+}
+
+func (d *DelegationCache) get(zone string, now time.Time) ([]string, bool) {
+	del, ok := d.zones[zone]
+	if !ok || now.After(del.expiresAt) {
+		return nil, false
+	}
+	return append([]string(nil), del.servers...), true
+}
+
+func zoneKey(zone string) string {
+	return strings.ToLower(dns.CanonicalName(zone))
+}
+
+type signaturesKey struct{}
+
+// withSignatures требует запрашивать у авторитетных серверов записи вместе
+// с подписями DNSSEC (флаг DO)
+func withSignatures(ctx context.Context) context.Context {
+	return context.WithValue(ctx, signaturesKey{}, true)
+}
+
+// wantSignatures сообщает, нужны ли разрешению ctx подписи DNSSEC
+func wantSignatures(ctx context.Context) bool {
+	want, _ := ctx.Value(signaturesKey{}).(bool)
+	return want
+}
+
+// inBailiwick оставляет в ответе сервера зоны zone только записи для имен
+// внутри этой зоны: за записи чужих зон он не отвечает, и цепочка CNAME за
+// пределы зоны разрешается отдельно
+func inBailiwick(resp *dns.Msg, zone string) *dns.Msg {
+	filter := func(records []dns.RR) []dns.RR {
+		var kept []dns.RR
+		for _, rr := range records {
+			if rr.Header().Rrtype == dns.TypeOPT || dns.IsSubDomain(zone, rr.Header().Name) {
+				kept = append(kept, rr)
+			}
+		}
+		return kept
+	}
+	resp.Answer = filter(resp.Answer)
+	resp.Ns = filter(resp.Ns)
+	resp.Extra = filter(resp.Extra)
+	return resp
+}
+
+// hasSOA сообщает, есть ли среди записей SOA
+func hasSOA(records []dns.RR) bool {
+	for _, rr := range records {
+		if rr.Header().Rrtype == dns.TypeSOA {
+			return true
+		}
+	}
+	return false
+}
+
+// referral извлекает из ответа-направления дочернюю зону, адреса её серверов
+// и TTL делегирования. Принимаются только зоны ниже текущей и не ниже qname.
+func (s *DNSServer) referral(ctx context.Context, resp *dns.Msg, zone, qname string) (string, []string, uint32, bool) {
+	var child string
+	var targets []string
+	var ttl uint32
+	for _, rr := range resp.Ns {
+		ns, ok := rr.(*dns.NS)
+		if !ok {
+			continue
+		}
+		owner := zoneKey(ns.Hdr.Name)
+		if owner == zoneKey(zone) || !dns.IsSubDomain(zone, owner) || !dns.IsSubDomain(owner, qname) {
+			continue
+		}
+		if child == "" {
+			child = owner
+			ttl = ns.Hdr.Ttl
+		}
+		if owner != child {
+			continue
+		}
+		targets = append(targets, zoneKey(ns.Ns))
+		if ns.Hdr.Ttl < ttl {
+			ttl = ns.Hdr.Ttl
+		}
+	}
+	if child == "" {
+		return "", nil, 0, false
+	}
+
+	var servers []string
+	seen := make(map[string]bool)
+	add := func(target string, ip net.IP) bool {
+		if !s.usableServerIP(ip) {
+			slog.Warn("Отброшен адрес сервера имен", "zone", child, "ns", target, "ip", ip.String())
+			return false
+		}
+		if addr := ip.String(); !seen[addr] {
+			seen[addr] = true
+			servers = append(servers, addr)
+		}
+		return true
+	}
+	var glueless []string
+	for _, target := range targets {
+		inZone := dns.IsSubDomain(zone, target)
+		// В строгом режиме glue для серверов имен вне зоны отвечавшего
+		// сервера игнорируется: их адреса всегда разрешаются отдельно
+		if s.cfg.GluePolicy == gluePolicyStrict && !inZone {
+			glueless = append(glueless, target)
+			continue
+		}
+
+		glue := false
+		var addrs []string
+		var addrTTL uint32
+		for _, rr := range resp.Extra {
+			if !strings.EqualFold(dns.Fqdn(rr.Header().Name), target) {
+				continue
+			}
+			var ip net.IP
+			switch a := rr.(type) {
+			case *dns.A:
+				ip = a.A
+			case *dns.AAAA:
+				ip = a.AAAA
+			default:
+				continue
+			}
+			glue = true
+			if add(target, ip) {
+				addrs = append(addrs, ip.String())
+				if addrTTL == 0 || rr.Header().Ttl < addrTTL {
+					addrTTL = rr.Header().Ttl
+				}
+			}
+		}
+		if glue {
+			// Запоминается только glue из зоны отвечавшего сервера: адресам
+			// чужих серверов имен из направления доверять нельзя
+			if inZone {
+				s.delegations.SetHost(target, addrs, clampTTL(addrTTL, 0, s.cfg.MaxCacheTTL.Duration))
+			}
+			continue
+		}
+		glueless = append(glueless, target)
+	}
+
+	// В быстром режиме серверов из glue достаточно: остальные разрешаются,
+	// только если glue не дал ни одного адреса
+	if s.cfg.GluePolicy == gluePolicyFast && len(servers) > 0 {
+		return child, servers, ttl, true
+	}
+	for _, addr := range s.resolveNSAddrs(ctx, glueless) {
+		if !seen[addr] {
+			seen[addr] = true
+			servers = append(servers, addr)
+		}
+	}
+	return child, servers, ttl, len(servers) > 0
+}
+
+// lookupRecords разрешает name обходом делегирований и возвращает записи
+// ответа в текстовом виде. Запросы идут в контексте ctx разрешения, которому
+// нужны эти записи, и расходуют его бюджет. Вложенность таких разрешений
+// (сервер имен без glue, чей сервер тоже без glue) ограничена maxLookupDepth.
+func (s *DNSServer) lookupRecords(ctx context.Context, name, qtype string) []string {
+	t, ok := dns.StringToType[qtype]
+	if !ok {
+		return nil
+	}
+	depth, _ := ctx.Value(lookupDepthKey{}).(int)
+	if depth >= maxLookupDepth {
+		slog.Debug("Превышена вложенность разрешения серверов имен", "name", name, "limit", maxLookupDepth)
+		return nil
+	}
+	resp, err := s.iterate(context.WithValue(ctx, lookupDepthKey{}, depth+1), name, t)
+	if err != nil {
+		slog.Debug("Не удалось разрешить имя сервера", "name", name, "qtype", qtype, "error", err)
+		return nil
+	}
+	var records []string
+	for _, rr := range resp.Answer {
+		records = append(records, rr.String())
+	}
+	return records
+}
+
+// validGluePolicy проверяет значение glue_policy
+func validGluePolicy(policy string) error {
+	switch policy {
+	case gluePolicyStandard, gluePolicyStrict, gluePolicyFast:
+		return nil
+	}
+	return fmt.Errorf("glue_policy должен быть %q, %q или %q", gluePolicyStandard, gluePolicyStrict, gluePolicyFast)
+}
+
+// resolveNSAddrs возвращает пригодные адреса серверов имен в порядке имен.
+// Известные адреса берутся из кэша делегирований, остальные разрешаются
+// запросами A и AAAA одновременно, не более nsAddrWorkers сразу, и
+// запоминаются.
+func (s *DNSServer) resolveNSAddrs(ctx context.Context, names []string) []string {
+	qtypes := [...]uint16{dns.TypeA, dns.TypeAAAA}
+	cached := make([][]string, len(names))
+	results := make([][]dns.RR, len(names)*len(qtypes))
+	sem := make(chan struct{}, nsAddrWorkers)
+	var wg sync.WaitGroup
+	for i, name := range names {
+		if addrs, ok := s.delegations.Host(name); ok {
+			cached[i] = addrs
+			continue
+		}
+		for j, qtype := range qtypes {
+			sem <- struct{}{}
+			wg.Add(1)
+			go func(slot int, name string, qtype uint16) {
+				defer func() {
+					<-sem
+					wg.Done()
+				}()
+				for _, res := range s.hostLookup(ctx, name, dns.TypeToString[qtype]) {
+					rr, err := dns.NewRR(res)
+					if err != nil || rr == nil || rr.Header().Rrtype != qtype {
+						continue
+					}
+					results[slot] = append(results[slot], rr)
+				}
+			}(i*len(qtypes)+j, name, qtype)
+		}
+	}
+	wg.Wait()
+
+	var addrs []string
+	for i, name := range names {
+		if cached[i] != nil {
+			addrs = append(addrs, cached[i]...)
+			continue
+		}
+		var found []string
+		var ttl uint32
+		for _, slot := range results[i*len(qtypes) : (i+1)*len(qtypes)] {
+			for _, rr := range slot {
+				var ip net.IP
+				switch a := rr.(type) {
+				case *dns.A:
+					ip = a.A
+				case *dns.AAAA:
+					ip = a.AAAA
+				}
+				if !s.usableServerIP(ip) {
+					slog.Warn("Отброшен адрес сервера имен", "ns", name, "ip", ip.String())
+					continue
+				}
+				found = append(found, ip.String())
+				if ttl == 0 || rr.Header().Ttl < ttl {
+					ttl = rr.Header().Ttl
+				}
+			}
+		}
+		s.delegations.SetHost(name, found, clampTTL(ttl, 0, s.cfg.MaxCacheTTL.Duration))
+		addrs = append(addrs, found...)
+	}
+	return addrs
+}
+
+// usableServerIP отклоняет loopback, link-local и неуказанные адреса, которые
+// могут направить запросы резолвера на него самого или во внутреннюю сеть
+func (s *DNSServer) usableServerIP(ip net.IP) bool {
+	if s.cfg.AllowLocalGlue {
+		return true
+	}
+	return !ip.IsLoopback() && !ip.IsLinkLocalUnicast() && !ip.IsLinkLocalMulticast() && !ip.IsUnspecified()
+}
+
+// retryRoots повторяет опрос корневых серверов до root_retries раз с
+// удваивающейся паузой, пока не истек срок разрешения. Возвращает первый
+// пригодный ответ или nil.
+func (s *DNSServer) retryRoots(ctx context.Context, servers []string, qname string, qtype uint16) *dns.Msg {
+	backoff := rootRetryBackoff
+	for attempt := 1; attempt <= s.cfg.RootRetries; attempt++ {
+		if budgetExhausted(ctx) {
+			return nil
+		}
+		select {
+		case <-time.After(backoff):
+		case <-ctx.Done():
+			return nil
+		}
+		backoff *= 2
+		slog.Debug("Повтор опроса корневых серверов", "qname", qname, "attempt", attempt)
+		if resp := s.queryZone(ctx, servers, qname, qtype); resp != nil {
+			return resp
+		}
+	}
+	return nil
+}
+
+// authAddr добавляет к адресу авторитетного сервера порт, если он не указан
+func (s *DNSServer) authAddr(server string) string {
+	if _, _, err := net.SplitHostPort(server); err == nil {
+		return server
+	}
+	return net.JoinHostPort(server, s.authPort)
+}
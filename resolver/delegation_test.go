@@ -0,0 +1,683 @@
+package resolver
+
+import (
+	"context"
+	"fmt"
+	"net"
+	"strings"
+	"sync"
+	"sync/atomic"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+func TestDelegationCacheClosest(t *testing.T) {
+	cache := NewDelegationCache()
+	cache.Set("example.", []string{"192.0.2.1"}, time.Hour)
+	cache.Set("sub.example.", []string{"192.0.2.2"}, time.Hour)
+	cache.Set("expired.example.", []string{"192.0.2.3"}, -time.Second)
+
+	tests := []struct {
+		qname string
+		zone  string
+	}{
+		{"www.sub.example.", "sub.example."},
+		{"WWW.Example.", "example."},
+		{"host.expired.example.", "example."},
+		{"example.", "example."},
+	}
+	for _, tt := range tests {
+		zone, _, ok := cache.Closest(tt.qname)
+		if !ok || zone != tt.zone {
+			t.Errorf("Closest(%s) = %s, ожидалось %s", tt.qname, zone, tt.zone)
+		}
+	}
+
+	if _, _, ok := cache.Closest("other.test."); ok {
+		t.Errorf("Для имени вне известных зон делегирование не должно находиться")
+	}
+}
+
+func TestSecondLookupStartsFromCachedDelegation(t *testing.T) {
+	childAddr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		reply.Authoritative = true
+		rr, _ := dns.NewRR(r.Question[0].Name + " 300 IN A 192.0.2.80")
+		reply.Answer = append(reply.Answer, rr)
+		w.WriteMsg(reply)
+	})
+	_, childPort, _ := net.SplitHostPort(childAddr)
+
+	var rootQueries atomic.Int32
+	rootAddr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		rootQueries.Add(1)
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		ns, _ := dns.NewRR("example. 3600 IN NS ns.example.")
+		glue, _ := dns.NewRR("ns.example. 3600 IN A 127.0.0.1")
+		reply.Ns = append(reply.Ns, ns)
+		reply.Extra = append(reply.Extra, glue)
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.cfg.AllowLocalGlue = true
+	s.rootServers = []string{rootAddr}
+	s.authPort = childPort
+
+	for _, name := range []string{"a.example.", "b.example."} {
+		rrset, _ := s.fetchFromAuthoritative(name, dns.TypeA)
+		if len(rrset) != 1 {
+			t.Fatalf("Ожидался 1 ответ для %s, получено %d", name, len(rrset))
+		}
+	}
+
+	if n := rootQueries.Load(); n != 1 {
+		t.Errorf("Второй запрос в известной зоне не должен обращаться к корню, запросов к корню: %d", n)
+	}
+	if _, ok := s.delegations.Get("example."); !ok {
+		t.Errorf("Делегирование example. должно быть закэшировано")
+	}
+}
+
+func TestRecursiveLookupSkipsRootForKnownZone(t *testing.T) {
+	childAddr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		reply.Authoritative = true
+		rr, _ := dns.NewRR(r.Question[0].Name + " 300 IN A 192.0.2.80")
+		reply.Answer = append(reply.Answer, rr)
+		w.WriteMsg(reply)
+	})
+	_, childPort, _ := net.SplitHostPort(childAddr)
+
+	var rootQueries atomic.Int32
+	rootAddr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		rootQueries.Add(1)
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		ns, _ := dns.NewRR("example. 3600 IN NS ns.example.")
+		glue, _ := dns.NewRR("ns.example. 3600 IN A 127.0.0.1")
+		reply.Ns = append(reply.Ns, ns)
+		reply.Extra = append(reply.Extra, glue)
+		w.WriteMsg(reply)
+	})
+
+	// Без пересылки запросы клиентов разрешаются обходом делегирований
+	s := NewDNSServer()
+	s.cfg.AllowLocalGlue = true
+	s.rootServers = []string{rootAddr}
+	s.authPort = childPort
+
+	for _, name := range []string{"a.example.", "b.example."} {
+		result := s.lookup(cacheKey(name, dns.TypeA), name, "A", dns.TypeA)
+		if result.rcode != dns.RcodeSuccess || len(result.answers) != 1 {
+			t.Fatalf("Ожидался 1 ответ для %s, получено rcode %d и %v", name, result.rcode, result.answers)
+		}
+	}
+
+	if n := rootQueries.Load(); n != 1 {
+		t.Errorf("Второе имя в известной зоне не должно разрешаться через корень, запросов к корню: %d", n)
+	}
+}
+
+func TestReferralDeduplicatesAndFiltersGlue(t *testing.T) {
+	resp := new(dns.Msg)
+	for _, s := range []string{
+		"example. 3600 IN NS ns1.example.",
+		"example. 3600 IN NS ns2.example.",
+		"example. 3600 IN NS ns3.example.",
+	} {
+		rr, _ := dns.NewRR(s)
+		resp.Ns = append(resp.Ns, rr)
+	}
+	for _, s := range []string{
+		"ns1.example. 3600 IN A 192.0.2.53",
+		"ns1.example. 3600 IN A 192.0.2.53",
+		"ns2.example. 3600 IN A 192.0.2.53",
+		"ns3.example. 3600 IN A 127.0.0.1",
+		"ns3.example. 3600 IN AAAA fe80::1",
+	} {
+		rr, _ := dns.NewRR(s)
+		resp.Extra = append(resp.Extra, rr)
+	}
+
+	s := NewDNSServer()
+	_, servers, _, ok := s.referral(context.Background(), resp, ".", "www.example.")
+	if !ok {
+		t.Fatalf("Направление должно быть распознано")
+	}
+	if len(servers) != 1 || servers[0] != "192.0.2.53" {
+		t.Errorf("Ожидался единственный адрес 192.0.2.53, получено %v", servers)
+	}
+
+	s.cfg.AllowLocalGlue = true
+	_, servers, _, _ = s.referral(context.Background(), resp, ".", "www.example.")
+	if len(servers) != 3 {
+		t.Errorf("С allow_local_glue должны остаться 3 уникальных адреса, получено %v", servers)
+	}
+}
+
+func TestQueryBudgetStopsEndlessDelegation(t *testing.T) {
+	var labels []string
+	for i := 1; i <= 20; i++ {
+		labels = append(labels, fmt.Sprintf("l%d", i))
+	}
+	qname := strings.Join(labels, ".") + ".example."
+	qlabels := dns.SplitDomainName(qname)
+
+	// Каждый ответ направляет к зоне на одну метку глубже, на тот же сервер
+	var queries atomic.Int32
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		n := int(queries.Add(1))
+		zone := strings.Join(qlabels[len(qlabels)-n:], ".") + "."
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		ns, _ := dns.NewRR(zone + " 3600 IN NS ns." + zone)
+		glue, _ := dns.NewRR("ns." + zone + " 3600 IN A 127.0.0.1")
+		reply.Ns = append(reply.Ns, ns)
+		reply.Extra = append(reply.Extra, glue)
+		w.WriteMsg(reply)
+	})
+	_, port, _ := net.SplitHostPort(addr)
+
+	s := NewDNSServer()
+	s.cfg.AllowLocalGlue = true
+	s.cfg.MaxUpstreamQueries = 5
+	s.rootServers = []string{addr}
+	s.authPort = port
+
+	if rrset, _ := s.fetchFromAuthoritative(qname, dns.TypeA); len(rrset) != 0 {
+		t.Fatalf("Бесконечная цепочка делегирований не должна дать ответа")
+	}
+	if n := queries.Load(); n != 5 {
+		t.Errorf("Ожидалось ровно 5 исходящих запросов, отправлено %d", n)
+	}
+	if n := atomic.LoadUint64(&s.queryBudgetExhausted); n != 1 {
+		t.Errorf("Счетчик исчерпанных бюджетов = %d, ожидалось 1", n)
+	}
+}
+
+func TestGluelessLookupSpendsQueryBudget(t *testing.T) {
+	var queries atomic.Int32
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		queries.Add(1)
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		q := r.Question[0]
+		switch {
+		case q.Name == "ns.noglue.test." && q.Qtype == dns.TypeA:
+			rr, _ := dns.NewRR("ns.noglue.test. 300 IN A 127.0.0.1")
+			reply.Answer = append(reply.Answer, rr)
+		case q.Name == "ns.noglue.test.":
+			soa, _ := dns.NewRR("test. 300 IN SOA ns.test. admin.test. 1 3600 600 86400 60")
+			reply.Ns = append(reply.Ns, soa)
+		default:
+			ns, _ := dns.NewRR("example. 3600 IN NS ns.noglue.test.")
+			reply.Ns = append(reply.Ns, ns)
+		}
+		w.WriteMsg(reply)
+	})
+	host, port, _ := net.SplitHostPort(addr)
+
+	s := NewDNSServer()
+	s.cfg.AllowLocalGlue = true
+	s.cfg.MaxUpstreamQueries = 2
+	s.rootServers = []string{host}
+	s.authPort = port
+
+	// Бюджет уходит на корень и адрес сервера имен без glue: на запрос к
+	// самому серверу зоны его уже не остается
+	result := s.lookup(cacheKey("www.example.", dns.TypeA), "www.example.", "A", dns.TypeA)
+	if result.rcode != dns.RcodeServerFailure {
+		t.Errorf("Ожидался SERVFAIL после исчерпания бюджета, получено %d", result.rcode)
+	}
+	if n := queries.Load(); n != 2 {
+		t.Errorf("Ожидалось 2 исходящих запроса, отправлено %d", n)
+	}
+	if n := atomic.LoadUint64(&s.queryBudgetExhausted); n != 1 {
+		t.Errorf("Счетчик исчерпанных бюджетов = %d, ожидалось 1", n)
+	}
+}
+
+func TestMutualReferralTerminates(t *testing.T) {
+	// example. направляет к a.example., а та - обратно к example.
+	var queries atomic.Int32
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		zone := "example."
+		if queries.Add(1)%2 == 0 {
+			zone = "a.example."
+		}
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		ns, _ := dns.NewRR(zone + " 3600 IN NS ns." + zone)
+		glue, _ := dns.NewRR("ns." + zone + " 3600 IN A 127.0.0.1")
+		reply.Ns = append(reply.Ns, ns)
+		reply.Extra = append(reply.Extra, glue)
+		w.WriteMsg(reply)
+	})
+	_, port, _ := net.SplitHostPort(addr)
+
+	s := NewDNSServer()
+	s.cfg.AllowLocalGlue = true
+	s.rootServers = []string{addr}
+	s.authPort = port
+
+	if rrset, _ := s.fetchFromAuthoritative("www.a.example.", dns.TypeA); len(rrset) != 0 {
+		t.Fatalf("Петля направлений не должна дать ответа")
+	}
+	// . -> example. -> a.example. -> (обратно к example., отвергнуто)
+	if n := queries.Load(); n != 3 {
+		t.Errorf("Обход должен завершиться после 3 запросов, отправлено %d", n)
+	}
+}
+
+func TestGlueReusedForOtherZone(t *testing.T) {
+	var childQueries atomic.Int32
+	childAddr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		childQueries.Add(1)
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		reply.Authoritative = true
+		rr, _ := dns.NewRR(r.Question[0].Name + " 300 IN A 192.0.2.80")
+		reply.Answer = append(reply.Answer, rr)
+		w.WriteMsg(reply)
+	})
+	_, childPort, _ := net.SplitHostPort(childAddr)
+
+	rootAddr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		if dns.IsSubDomain("example.", r.Question[0].Name) {
+			ns, _ := dns.NewRR("example. 3600 IN NS ns.example.")
+			glue, _ := dns.NewRR("ns.example. 3600 IN A 127.0.0.1")
+			reply.Ns = append(reply.Ns, ns)
+			reply.Extra = append(reply.Extra, glue)
+		} else {
+			// Зона other. обслуживается тем же сервером, но направление без glue
+			ns, _ := dns.NewRR("other. 3600 IN NS ns.example.")
+			reply.Ns = append(reply.Ns, ns)
+		}
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.cfg.AllowLocalGlue = true
+	s.rootServers = []string{rootAddr}
+	s.authPort = childPort
+
+	if rrset, _ := s.fetchFromAuthoritative("a.example.", dns.TypeA); len(rrset) != 1 {
+		t.Fatalf("Ожидался 1 ответ для a.example., получено %d", len(rrset))
+	}
+	if addrs, ok := s.delegations.Host("NS.Example."); !ok || len(addrs) != 1 || addrs[0] != "127.0.0.1" {
+		t.Fatalf("Адрес ns.example. из glue должен быть закэширован, получено %v", addrs)
+	}
+
+	// Адрес сервера имен берется из кэша, а не разрешается заново
+	if rrset, _ := s.fetchFromAuthoritative("b.other.", dns.TypeA); len(rrset) != 1 {
+		t.Fatalf("Ожидался 1 ответ для b.other., получено %d", len(rrset))
+	}
+	if n := childQueries.Load(); n != 2 {
+		t.Errorf("Ожидалось 2 запроса к серверу зон, получено %d", n)
+	}
+}
+
+func TestOutOfBailiwickGlueNotCached(t *testing.T) {
+	s := NewDNSServer()
+	s.cfg.AllowLocalGlue = true
+
+	resp := new(dns.Msg)
+	ns, _ := dns.NewRR("sub.example. 3600 IN NS ns.victim.test.")
+	glue, _ := dns.NewRR("ns.victim.test. 3600 IN A 192.0.2.66")
+	resp.Ns = append(resp.Ns, ns)
+	resp.Extra = append(resp.Extra, glue)
+
+	if _, _, _, ok := s.referral(context.Background(), resp, "example.", "www.sub.example."); !ok {
+		t.Fatal("Направление должно приниматься")
+	}
+	if _, ok := s.delegations.Host("ns.victim.test."); ok {
+		t.Error("Glue для сервера вне зоны отвечавшего сервера не должен кэшироваться")
+	}
+}
+
+func TestZoneServersResolvesNSAddressesConcurrently(t *testing.T) {
+	s := NewDNSServer()
+
+	var active, maxActive atomic.Int32
+	s.nameLookup = func(ctx context.Context, qname, qtype string) []string {
+		return []string{
+			"slow.example. 3600 IN NS ns1.slow.example.",
+			"slow.example. 3600 IN NS ns2.slow.example.",
+			"slow.example. 3600 IN NS ns3.slow.example.",
+		}
+	}
+	s.hostLookup = func(ctx context.Context, qname, qtype string) []string {
+		n := active.Add(1)
+		for {
+			m := maxActive.Load()
+			if n <= m || maxActive.CompareAndSwap(m, n) {
+				break
+			}
+		}
+		time.Sleep(50 * time.Millisecond)
+		active.Add(-1)
+
+		i := strings.TrimPrefix(strings.SplitN(qname, ".", 2)[0], "ns")
+		if qtype == "A" {
+			return []string{fmt.Sprintf("%s 3600 IN A 192.0.2.%s", qname, i)}
+		}
+		return []string{fmt.Sprintf("%s 3600 IN AAAA 2001:db8::%s", qname, i)}
+	}
+
+	servers, err := s.zoneServers(context.Background(), "slow.example.")
+	if err != nil {
+		t.Fatalf("Адреса серверов имен не разрешены: %v", err)
+	}
+	want := []string{"192.0.2.1", "2001:db8::1", "192.0.2.2", "2001:db8::2", "192.0.2.3", "2001:db8::3"}
+	if fmt.Sprint(servers) != fmt.Sprint(want) {
+		t.Errorf("Ожидались адреса %v, получено %v", want, servers)
+	}
+	if m := maxActive.Load(); m < 2 {
+		t.Errorf("Адреса серверов имен разрешались последовательно")
+	}
+
+	// Делегирование запомнено, и следующий обход начнется с него
+	if cached, ok := s.delegations.Get("slow.example."); !ok || len(cached) != len(want) {
+		t.Errorf("Делегирование не сохранено в кэше: %v", cached)
+	}
+}
+
+func TestReferralResolvesGluelessServersConcurrently(t *testing.T) {
+	resp := new(dns.Msg)
+	for i := 1; i <= 3; i++ {
+		resp.Ns = append(resp.Ns, mustRR(t, fmt.Sprintf("sub.example. 3600 IN NS ns%d.other.test.", i)))
+	}
+
+	s := NewDNSServer()
+	var active, maxActive atomic.Int32
+	s.hostLookup = func(ctx context.Context, host, qtype string) []string {
+		n := active.Add(1)
+		for {
+			m := maxActive.Load()
+			if n <= m || maxActive.CompareAndSwap(m, n) {
+				break
+			}
+		}
+		time.Sleep(50 * time.Millisecond)
+		active.Add(-1)
+
+		i := strings.TrimPrefix(strings.SplitN(host, ".", 2)[0], "ns")
+		if qtype == "A" {
+			return []string{fmt.Sprintf("%s 3600 IN A 192.0.2.%s", host, i)}
+		}
+		return []string{fmt.Sprintf("%s 3600 IN AAAA 2001:db8::%s", host, i)}
+	}
+
+	_, servers, _, ok := s.referral(context.Background(), resp, "example.", "www.sub.example.")
+	if !ok {
+		t.Fatal("Направление должно приниматься")
+	}
+	want := []string{"192.0.2.1", "2001:db8::1", "192.0.2.2", "2001:db8::2", "192.0.2.3", "2001:db8::3"}
+	if fmt.Sprint(servers) != fmt.Sprint(want) {
+		t.Errorf("Ожидались адреса %v, получено %v", want, servers)
+	}
+	if m := maxActive.Load(); m < 2 {
+		t.Errorf("Серверы имен без glue разрешались последовательно")
+	}
+	if addrs, ok := s.delegations.Host("ns2.other.test."); !ok || len(addrs) != 2 {
+		t.Errorf("Адреса сервера имен без glue должны кэшироваться, получено %v", addrs)
+	}
+}
+
+// glueReferral возвращает направление к sub.example. с двумя серверами имен:
+// ns.sub.example. внутри зоны и ns.other.test. вне ее, оба с glue
+func glueReferral() *dns.Msg {
+	resp := new(dns.Msg)
+	for _, s := range []string{
+		"sub.example. 3600 IN NS ns.sub.example.",
+		"sub.example. 3600 IN NS ns.other.test.",
+	} {
+		rr, _ := dns.NewRR(s)
+		resp.Ns = append(resp.Ns, rr)
+	}
+	for _, s := range []string{
+		"ns.sub.example. 3600 IN A 192.0.2.1",
+		"ns.other.test. 3600 IN A 192.0.2.66",
+	} {
+		rr, _ := dns.NewRR(s)
+		resp.Extra = append(resp.Extra, rr)
+	}
+	return resp
+}
+
+func TestGluePolicyStrictResolvesOutOfZoneServers(t *testing.T) {
+	s := NewDNSServer()
+	s.cfg.GluePolicy = gluePolicyStrict
+	var mu sync.Mutex
+	var lookups []string
+	s.hostLookup = func(ctx context.Context, host, qtype string) []string {
+		if qtype != "A" {
+			return nil
+		}
+		mu.Lock()
+		defer mu.Unlock()
+		lookups = append(lookups, host)
+		return []string{host + " 300 IN A 192.0.2.77"}
+	}
+
+	_, servers, _, ok := s.referral(context.Background(), glueReferral(), "example.", "www.sub.example.")
+	if !ok {
+		t.Fatal("Направление должно приниматься")
+	}
+	if fmt.Sprint(servers) != "[192.0.2.1 192.0.2.77]" {
+		t.Errorf("Ожидались glue внутри зоны и разрешенный адрес, получено %v", servers)
+	}
+	if fmt.Sprint(lookups) != "[ns.other.test.]" {
+		t.Errorf("Разрешаться должен только сервер вне зоны, разрешены %v", lookups)
+	}
+
+	// В обычном режиме glue принимается для обоих серверов
+	s.cfg.GluePolicy = gluePolicyStandard
+	s.delegations = NewDelegationCache()
+	lookups = nil
+	_, servers, _, _ = s.referral(context.Background(), glueReferral(), "example.", "www.sub.example.")
+	if fmt.Sprint(servers) != "[192.0.2.1 192.0.2.66]" || len(lookups) != 0 {
+		t.Errorf("Ожидались адреса из glue без разрешения, получено %v (разрешены %v)", servers, lookups)
+	}
+}
+
+func TestGluePolicyFastSkipsGluelessServers(t *testing.T) {
+	resp := glueReferral()
+	ns, _ := dns.NewRR("sub.example. 3600 IN NS ns.noglue.test.")
+	resp.Ns = append(resp.Ns, ns)
+
+	s := NewDNSServer()
+	s.cfg.GluePolicy = gluePolicyFast
+	var mu sync.Mutex
+	var lookups []string
+	s.hostLookup = func(ctx context.Context, host, qtype string) []string {
+		if qtype != "A" {
+			return nil
+		}
+		mu.Lock()
+		defer mu.Unlock()
+		lookups = append(lookups, host)
+		return []string{host + " 300 IN A 192.0.2.88"}
+	}
+
+	_, servers, _, ok := s.referral(context.Background(), resp, "example.", "www.sub.example.")
+	if !ok || len(servers) != 2 || len(lookups) != 0 {
+		t.Errorf("Ожидались только адреса из glue без разрешения, получено %v (разрешены %v)", servers, lookups)
+	}
+
+	// Без glue быстрый режим все же разрешает серверы имен
+	resp.Extra = nil
+	s.delegations = NewDelegationCache()
+	_, servers, _, ok = s.referral(context.Background(), resp, "example.", "www.sub.example.")
+	if !ok || len(lookups) != 3 || len(servers) != 1 {
+		t.Errorf("Без glue ожидалось разрешение всех серверов, получено %v (разрешены %v)", servers, lookups)
+	}
+
+	// В обычном режиме сервер без glue разрешается и при наличии glue
+	s.cfg.GluePolicy = gluePolicyStandard
+	s.delegations = NewDelegationCache()
+	lookups = nil
+	resp = glueReferral()
+	resp.Ns = append(resp.Ns, ns)
+	_, servers, _, _ = s.referral(context.Background(), resp, "example.", "www.sub.example.")
+	if fmt.Sprint(lookups) != "[ns.noglue.test.]" || len(servers) != 3 {
+		t.Errorf("Ожидалось разрешение сервера без glue, получено %v (разрешены %v)", servers, lookups)
+	}
+}
+
+func TestGluelessLookupUsesResolutionContext(t *testing.T) {
+	tr := &mockTransport{servers: map[string]func(*dns.Msg) *dns.Msg{
+		"198.41.0.4:53": func(*dns.Msg) *dns.Msg {
+			reply := new(dns.Msg)
+			reply.Ns = append(reply.Ns, mustRR(t, "example. 3600 IN NS ns.noglue.test."))
+			return reply
+		},
+	}}
+
+	s := NewDNSServer()
+	s.transport = tr
+	s.rootServers = []string{"198.41.0.4"}
+	var withDeadline atomic.Bool
+	s.hostLookup = func(ctx context.Context, host, qtype string) []string {
+		_, ok := ctx.Deadline()
+		withDeadline.Store(ok)
+		return nil
+	}
+
+	s.lookup(cacheKey("www.example.", dns.TypeA), "www.example.", "A", dns.TypeA)
+	if !withDeadline.Load() {
+		t.Error("Сервер имен без glue должен разрешаться в контексте разрешения с его сроком")
+	}
+}
+
+func TestAuthoritativeRefusedTriesNextServer(t *testing.T) {
+	var refusedQueries atomic.Int32
+	refusing := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		refusedQueries.Add(1)
+		reply := new(dns.Msg)
+		reply.SetRcode(r, dns.RcodeRefused)
+		w.WriteMsg(reply)
+	})
+	answering := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		reply.Authoritative = true
+		rr, _ := dns.NewRR(r.Question[0].Name + " 300 IN A 192.0.2.80")
+		reply.Answer = append(reply.Answer, rr)
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	// Отказавший сервер опрашивается первым, пока о задержках ничего не известно
+	s.rootServers = []string{refusing, answering}
+
+	rrset, _ := s.fetchFromAuthoritative("www.example.", dns.TypeA)
+	if len(rrset) != 1 {
+		t.Fatalf("Ожидался ответ второго сервера, получено %v", rrset)
+	}
+	if a, ok := rrset[0].(*dns.A); !ok || a.A.String() != "192.0.2.80" {
+		t.Errorf("Неверный ответ: %v", rrset[0])
+	}
+
+	// Если отказывают все серверы, разрешение не удается
+	s.rootServers = []string{refusing}
+	if rrset, _ := s.fetchFromAuthoritative("www.example.", dns.TypeA); len(rrset) != 0 {
+		t.Errorf("При отказе всех серверов ответа быть не должно, получено %v", rrset)
+	}
+	if refusedQueries.Load() == 0 {
+		t.Error("Отказавший сервер не опрашивался")
+	}
+}
+
+// flakyTransport не отвечает на первые fails запросов, а затем отвечает
+// A-записью
+type flakyTransport struct {
+	mu      sync.Mutex
+	fails   int
+	queries int
+}
+
+func (t *flakyTransport) Exchange(ctx context.Context, c *dns.Client, msg *dns.Msg, addr string) (*dns.Msg, time.Duration, error) {
+	t.mu.Lock()
+	t.queries++
+	failed := t.queries <= t.fails
+	t.mu.Unlock()
+	if failed {
+		return nil, 0, fmt.Errorf("сеть недоступна")
+	}
+	reply := new(dns.Msg)
+	reply.SetReply(msg)
+	rr, _ := dns.NewRR(msg.Question[0].Name + " 300 IN A 192.0.2.80")
+	reply.Answer = append(reply.Answer, rr)
+	return reply, time.Millisecond, nil
+}
+
+func TestRootRetriesAfterNetworkFailure(t *testing.T) {
+	tr := &flakyTransport{fails: 1}
+	s := NewDNSServer()
+	s.transport = tr
+	s.cfg.UpstreamRetries = 0
+	s.rootServers = []string{"198.41.0.4"}
+
+	// По умолчанию сбой всех корневых серверов сразу означает SERVFAIL
+	if rrset, _ := s.fetchFromAuthoritative("www.example.", dns.TypeA); len(rrset) != 0 {
+		t.Fatalf("Без root_retries ответа быть не должно, получено %v", rrset)
+	}
+
+	tr.queries = 0
+	s.cfg.RootRetries = 2
+	rrset, _ := s.fetchFromAuthoritative("www.example.", dns.TypeA)
+	if len(rrset) != 1 {
+		t.Fatalf("Ожидался ответ после повтора, получено %v", rrset)
+	}
+	if tr.queries != 2 {
+		t.Errorf("Ожидалось 2 запроса к корневому серверу, выполнено %d", tr.queries)
+	}
+}
+
+func TestClientLookupRetriesRoots(t *testing.T) {
+	tr := &flakyTransport{fails: 1}
+	s := NewDNSServer()
+	s.transport = tr
+	s.cfg.UpstreamRetries = 0
+	s.cfg.RootRetries = 2
+	s.rootServers = []string{"198.41.0.4"}
+
+	result := s.lookup(cacheKey("www.example.", dns.TypeA), "www.example.", "A", dns.TypeA)
+	if result.rcode != dns.RcodeSuccess || len(result.answers) != 1 {
+		t.Fatalf("Ожидался ответ после повтора опроса корня, получено rcode %d и %v", result.rcode, result.answers)
+	}
+	if tr.queries != 2 {
+		t.Errorf("Ожидалось 2 запроса к корневому серверу, выполнено %d", tr.queries)
+	}
+}
+
+func TestZoneServersFiltersUnusableAddresses(t *testing.T) {
+	s := NewDNSServer()
+	s.nameLookup = func(ctx context.Context, qname, qtype string) []string {
+		return []string{"local.example. 3600 IN NS ns.local.example."}
+	}
+	s.hostLookup = func(ctx context.Context, host, qtype string) []string {
+		if qtype == "A" {
+			return []string{host + " 3600 IN A 127.0.0.1", host + " 3600 IN A 192.0.2.9"}
+		}
+		return []string{host + " 3600 IN AAAA fe80::1"}
+	}
+
+	// Адреса для запроса DNSKEY проходят ту же проверку, что и glue
+	servers, err := s.zoneServers(context.Background(), "local.example.")
+	if err != nil {
+		t.Fatalf("Адреса серверов имен не разрешены: %v", err)
+	}
+	if fmt.Sprint(servers) != "[192.0.2.9]" {
+		t.Errorf("Ожидался только адрес 192.0.2.9, получено %v", servers)
+	}
+}
@@ -0,0 +1,159 @@
+package resolver
+
+import (
+	"crypto/sha256"
+	"encoding/hex"
+	"encoding/json"
+	"os"
+	"path/filepath"
+	"strings"
+	"sync"
+	"time"
+)
+
+// diskCacheExt - расширение файлов записей второго уровня кэша
+const diskCacheExt = ".json"
+
+// DiskCache - второй уровень кэша ответов: записи, вытесненные из памяти,
+// хранятся в каталоге по одному файлу на ключ. Так кэш может быть намного
+// больше, чем помещается в оперативную память.
+type DiskCache struct {
+	mu         sync.Mutex
+	dir        string
+	maxEntries int // 0 - без ограничения
+	count      int
+}
+
+// NewDiskCache открывает (и при необходимости создает) каталог второго
+// уровня кэша, хранящий не более maxEntries записей. Записи, оставшиеся от
+// прошлого запуска, сохраняются.
+func NewDiskCache(dir string, maxEntries int) (*DiskCache, error) {
+	if err := os.MkdirAll(dir, 0o755); err != nil {
+		return nil, err
+	}
+	files, err := os.ReadDir(dir)
+	if err != nil {
+		return nil, err
+	}
+
+	d := &DiskCache{dir: dir, maxEntries: maxEntries}
+	for _, f := range files {
+		switch {
+		case strings.HasSuffix(f.Name(), diskCacheExt):
+			d.count++
+		case strings.HasSuffix(f.Name(), ".tmp"):
+			// Незавершенная запись, прерванная остановкой сервера
+			os.Remove(filepath.Join(dir, f.Name()))
+		}
+	}
+	return d, nil
+}
+
+// path возвращает имя файла для ключа; ключ хэшируется, чтобы имя не
+// зависело от символов в доменном имени
+func (d *DiskCache) path(key string) string {
+	sum := sha256.Sum256([]byte(key))
+	return filepath.Join(d.dir, hex.EncodeToString(sum[:16])+diskCacheExt)
+}
+
+// Store записывает запись на диск. Если каталог заполнен, новая запись
+// отбрасывается.
+func (d *DiskCache) Store(key string, entry *CacheEntry) error {
+	data, err := json.Marshal(newPersistedEntry(key, entry))
+	if err != nil {
+		return err
+	}
+
+	d.mu.Lock()
+	defer d.mu.Unlock()
+
+	path := d.path(key)
+	_, statErr := os.Stat(path)
+	exists := statErr == nil
+	if !exists && d.maxEntries > 0 && d.count >= d.maxEntries {
+		return nil
+	}
+
+	tmpPath := path + ".tmp"
+	if err := os.WriteFile(tmpPath, data, 0o644); err != nil {
+		return err
+	}
+	if err := os.Rename(tmpPath, path); err != nil {
+		os.Remove(tmpPath)
+		return err
+	}
+	if !exists {
+		d.count++
+	}
+	return nil
+}
+
+// Take извлекает запись с диска, удаляя ее файл: запись переносится в память
+func (d *DiskCache) Take(key string) (*CacheEntry, bool) {
+	d.mu.Lock()
+	defer d.mu.Unlock()
+
+	path := d.path(key)
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return nil, false
+	}
+	if os.Remove(path) == nil {
+		d.count--
+	}
+
+	var pe persistedEntry
+	if err := json.Unmarshal(data, &pe); err != nil || pe.Key != key {
+		return nil, false
+	}
+	entry, err := pe.cacheEntry()
+	if err != nil {
+		return nil, false
+	}
+	return entry, true
+}
+
+// RemoveIf удаляет записи, для которых match возвращает true, и возвращает
+// их количество. Поврежденные файлы удаляются без учета в результате.
+func (d *DiskCache) RemoveIf(match func(key string, expiresAt time.Time) bool) int {
+	d.mu.Lock()
+	defer d.mu.Unlock()
+
+	files, err := os.ReadDir(d.dir)
+	if err != nil {
+		return 0
+	}
+
+	removed := 0
+	for _, f := range files {
+		if !strings.HasSuffix(f.Name(), diskCacheExt) {
+			continue
+		}
+		path := filepath.Join(d.dir, f.Name())
+		data, err := os.ReadFile(path)
+		if err != nil {
+			continue
+		}
+
+		var pe persistedEntry
+		corrupt := json.Unmarshal(data, &pe) != nil
+		if !corrupt && !match(pe.Key, pe.ExpiresAt) {
+			continue
+		}
+		if os.Remove(path) != nil {
+			continue
+		}
+		d.count--
+		if !corrupt {
+			removed++
+		}
+	}
+	return removed
+}
+
+// Len возвращает количество записей на диске
+func (d *DiskCache) Len() int {
+	d.mu.Lock()
+	defer d.mu.Unlock()
+	return d.count
+}
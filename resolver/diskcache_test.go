@@ -0,0 +1,141 @@
+package resolver
+
+import (
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+func newTieredCache(t *testing.T, maxEntries int) (*Cache, *DiskCache) {
+	t.Helper()
+	l2, err := NewDiskCache(t.TempDir(), 0)
+	if err != nil {
+		t.Fatalf("Не удалось открыть каталог кэша: %v", err)
+	}
+	cache := NewCache(maxEntries)
+	cache.SetSecondary(l2)
+	return cache, l2
+}
+
+func TestTieredCacheL1Hit(t *testing.T) {
+	cache, l2 := newTieredCache(t, 2)
+	key := cacheKey("a.example.", dns.TypeA)
+	cache.Set(key, newTestEntry(t, "a.example.", time.Minute))
+
+	if _, ok := cache.Get(key); !ok {
+		t.Fatal("Запись должна находиться в памяти")
+	}
+	if l2.Len() != 0 {
+		t.Errorf("Без вытеснения на диск ничего не пишется, записей на диске: %d", l2.Len())
+	}
+}
+
+func TestTieredCacheL1MissL2Hit(t *testing.T) {
+	cache, l2 := newTieredCache(t, 1)
+	keyA := cacheKey("a.example.", dns.TypeA)
+	keyB := cacheKey("b.example.", dns.TypeA)
+	cache.Set(keyA, newTestEntry(t, "a.example.", time.Minute))
+	cache.Set(keyB, newTestEntry(t, "b.example.", time.Minute))
+
+	if cache.Len() != 1 || l2.Len() != 1 {
+		t.Fatalf("Вытесненная запись должна перейти на диск: в памяти %d, на диске %d", cache.Len(), l2.Len())
+	}
+
+	entry, ok := cache.GetEntry(keyA)
+	if !ok {
+		t.Fatal("Запись, вытесненная на диск, должна находиться при промахе в памяти")
+	}
+	if entry.Name != "a.example." || len(entry.Records) != 1 {
+		t.Errorf("Запись восстановлена с диска неверно: %+v", entry)
+	}
+}
+
+func TestTieredCachePromotesOnAccess(t *testing.T) {
+	cache, l2 := newTieredCache(t, 1)
+	keyA := cacheKey("a.example.", dns.TypeA)
+	keyB := cacheKey("b.example.", dns.TypeA)
+	cache.Set(keyA, newTestEntry(t, "a.example.", time.Minute))
+	cache.Set(keyB, newTestEntry(t, "b.example.", time.Minute))
+
+	if _, ok := cache.Get(keyA); !ok {
+		t.Fatal("Запись a.example. не найдена")
+	}
+
+	// После обращения a.example. в памяти, а b.example. вытеснена на диск
+	dump := cache.Dump(time.Now())
+	if len(dump) != 1 || dump[0].Key != keyA {
+		t.Errorf("В памяти должна оказаться a.example., получено %+v", dump)
+	}
+	if l2.Len() != 1 {
+		t.Errorf("На диске должна остаться одна запись, получено %d", l2.Len())
+	}
+	if _, ok := cache.Get(keyB); !ok {
+		t.Error("Запись b.example. должна находиться на диске")
+	}
+}
+
+func TestTieredCacheSkipsExpired(t *testing.T) {
+	cache, l2 := newTieredCache(t, 1)
+	keyA := cacheKey("a.example.", dns.TypeA)
+	if err := l2.Store(keyA, newTestEntry(t, "a.example.", time.Minute)); err != nil {
+		t.Fatalf("Ошибка записи на диск: %v", err)
+	}
+	if err := l2.Store(cacheKey("b.example.", dns.TypeA), newTestEntry(t, "b.example.", -time.Minute)); err != nil {
+		t.Fatalf("Ошибка записи на диск: %v", err)
+	}
+
+	if removed := cache.RemoveExpired(time.Now()); removed != 1 {
+		t.Errorf("Ожидалось удаление одной истёкшей записи с диска, удалено %d", removed)
+	}
+	if _, ok := cache.Get(cacheKey("b.example.", dns.TypeA)); ok {
+		t.Error("Истёкшая запись не должна возвращаться с диска")
+	}
+	if _, ok := cache.Get(keyA); !ok {
+		t.Error("Неистёкшая запись должна возвращаться с диска")
+	}
+}
+
+func TestTieredCacheFlush(t *testing.T) {
+	cache, l2 := newTieredCache(t, 1)
+	for _, name := range []string{"a.example.", "b.example.", "c.example."} {
+		cache.Set(cacheKey(name, dns.TypeA), newTestEntry(t, name, time.Minute))
+	}
+
+	if removed := cache.RemoveName("a.example.", 0); removed != 1 {
+		t.Errorf("Ожидалось удаление a.example. с диска, удалено %d", removed)
+	}
+	if removed := cache.Flush(); removed != 2 {
+		t.Errorf("Полная очистка должна удалить 2 записи, удалено %d", removed)
+	}
+	if l2.Len() != 0 {
+		t.Errorf("После очистки на диске остались записи: %d", l2.Len())
+	}
+}
+
+func TestDiskCacheReopen(t *testing.T) {
+	dir := t.TempDir()
+	l2, err := NewDiskCache(dir, 1)
+	if err != nil {
+		t.Fatalf("Не удалось открыть каталог кэша: %v", err)
+	}
+	key := cacheKey("a.example.", dns.TypeA)
+	if err := l2.Store(key, newTestEntry(t, "a.example.", time.Minute)); err != nil {
+		t.Fatalf("Ошибка записи на диск: %v", err)
+	}
+	// Сверх лимита запись отбрасывается
+	if err := l2.Store(cacheKey("b.example.", dns.TypeA), newTestEntry(t, "b.example.", time.Minute)); err != nil {
+		t.Fatalf("Ошибка записи на диск: %v", err)
+	}
+
+	reopened, err := NewDiskCache(dir, 1)
+	if err != nil {
+		t.Fatalf("Не удалось повторно открыть каталог кэша: %v", err)
+	}
+	if reopened.Len() != 1 {
+		t.Fatalf("Ожидалась 1 запись после повторного открытия, получено %d", reopened.Len())
+	}
+	if _, ok := reopened.Take(key); !ok {
+		t.Error("Запись должна сохраняться между запусками")
+	}
+}
@@ -0,0 +1,98 @@
+package resolver
+
+import (
+	"context"
+	"fmt"
+	"log/slog"
+	"net"
+	"net/netip"
+
+	"github.com/miekg/dns"
+)
+
+// dns64Prefix - общеизвестный префикс NAT64 (RFC 6052, раздел 2.1)
+const dns64Prefix = "64:ff9b::/96"
+
+// validDNS64Prefix проверяет префикс NAT64: IPv6-префикс одной из длин,
+// допустимых RFC 6052
+func validDNS64Prefix(prefix string) error {
+	p, err := netip.ParsePrefix(prefix)
+	if err != nil || !p.Addr().Is6() || p.Addr().Is4In6() {
+		return fmt.Errorf("dns64_prefix должен быть IPv6-префиксом, получено %q", prefix)
+	}
+	switch p.Bits() {
+	case 32, 40, 48, 56, 64, 96:
+		return nil
+	}
+	return fmt.Errorf("длина dns64_prefix должна быть 32, 40, 48, 56, 64 или 96 (RFC 6052), получено %d", p.Bits())
+}
+
+// embedIPv4 встраивает IPv4-адрес в префикс NAT64 (RFC 6052, раздел 2.2).
+// Октет u (биты 64-71) всегда остается нулевым.
+func embedIPv4(prefix netip.Prefix, v4 netip.Addr) netip.Addr {
+	addr := prefix.Masked().Addr().As16()
+	pos := prefix.Bits() / 8
+	for _, octet := range v4.As4() {
+		if pos == 8 {
+			pos++
+		}
+		addr[pos] = octet
+		pos++
+	}
+	return netip.AddrFrom16(addr)
+}
+
+// dns64Applies сообщает, нужно ли синтезировать AAAA для ответа: включен
+// DNS64, запрошен AAAA, а ответ NOERROR без AAAA-записей. Клиенту, который
+// сам проверяет DNSSEC (DO и CD), синтезированные записи не передаются
+// (RFC 6147, раздел 5.5).
+func (s *DNSServer) dns64Applies(req *dns.Msg, rcode int, answers []dns.RR) bool {
+	if !s.cfg.DNS64 || req.Question[0].Qtype != dns.TypeAAAA || rcode != dns.RcodeSuccess {
+		return false
+	}
+	if opt := req.IsEdns0(); opt != nil && opt.Do() && req.CheckingDisabled {
+		return false
+	}
+	_, complete := chainEnd(req.Question[0].Name, dns.TypeAAAA, answers)
+	return !complete
+}
+
+// synthesizeDNS64 разрешает A-записи для конца цепочки CNAME ответа и
+// добавляет к ответу построенные из них AAAA-записи с тем же TTL. Если
+// A-записей нет, ответ возвращается без изменений.
+func (s *DNSServer) synthesizeDNS64(ctx context.Context, qname string, answers []dns.RR) []dns.RR {
+	prefix, err := netip.ParsePrefix(s.cfg.DNS64Prefix)
+	if err != nil {
+		return answers
+	}
+	target, _ := chainEnd(qname, dns.TypeAAAA, answers)
+	result := s.lookupSubnet(ctx, cacheKey(target, dns.TypeA), target, "A", dns.TypeA, netip.Prefix{})
+	if result.rcode != dns.RcodeSuccess {
+		return answers
+	}
+
+	var synthesized []dns.RR
+	found := false
+	for _, rr := range s.followCNAME(ctx, target, dns.TypeA, result.answers) {
+		switch rr := rr.(type) {
+		case *dns.CNAME:
+			// Продолжение цепочки за целью ответа на AAAA
+			synthesized = append(synthesized, dns.Copy(rr))
+		case *dns.A:
+			v4, ok := netip.AddrFromSlice(rr.A.To4())
+			if !ok {
+				continue
+			}
+			synthesized = append(synthesized, &dns.AAAA{
+				Hdr:  dns.RR_Header{Name: rr.Hdr.Name, Rrtype: dns.TypeAAAA, Class: dns.ClassINET, Ttl: rr.Hdr.Ttl},
+				AAAA: net.IP(embedIPv4(prefix, v4).AsSlice()),
+			})
+			found = true
+		}
+	}
+	if !found {
+		return answers
+	}
+	slog.Debug("Синтезированы AAAA-записи DNS64", "qname", qname, "target", target)
+	return append(answers, synthesized...)
+}
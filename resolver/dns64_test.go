@@ -0,0 +1,71 @@
+package resolver
+
+import (
+	"net/netip"
+	"testing"
+
+	"github.com/miekg/dns"
+)
+
+func TestEmbedIPv4(t *testing.T) {
+	v4 := netip.MustParseAddr("192.0.2.33")
+	// Примеры из RFC 6052, раздел 2.4
+	tests := []struct {
+		prefix string
+		want   string
+	}{
+		{"2001:db8::/32", "2001:db8:c000:221::"},
+		{"2001:db8:100::/40", "2001:db8:1c0:2:21::"},
+		{"2001:db8:122::/48", "2001:db8:122:c000:2:2100::"},
+		{"2001:db8:122:300::/56", "2001:db8:122:3c0:0:221::"},
+		{"2001:db8:122:344::/64", "2001:db8:122:344:c0:2:2100:0"},
+		{"64:ff9b::/96", "64:ff9b::c000:221"},
+	}
+	for _, tt := range tests {
+		got := embedIPv4(netip.MustParsePrefix(tt.prefix), v4)
+		if got != netip.MustParseAddr(tt.want) {
+			t.Errorf("embedIPv4(%s) = %s, ожидалось %s", tt.prefix, got, tt.want)
+		}
+	}
+}
+
+func TestDNS64SynthesizesAAAA(t *testing.T) {
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		// У имени есть только A-запись: на AAAA приходит пустой NOERROR
+		if r.Question[0].Qtype == dns.TypeA {
+			rr, _ := dns.NewRR(r.Question[0].Name + " 120 IN A 192.0.2.33")
+			reply.Answer = append(reply.Answer, rr)
+		}
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+	s.cfg.DNS64 = true
+
+	req := new(dns.Msg)
+	req.SetQuestion("v4only.example.", dns.TypeAAAA)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil || w.msg.Rcode != dns.RcodeSuccess || len(w.msg.Answer) != 1 {
+		t.Fatalf("Ожидалась одна синтезированная AAAA-запись, получено %v", w.msg)
+	}
+	aaaa, ok := w.msg.Answer[0].(*dns.AAAA)
+	if !ok || aaaa.AAAA.String() != "64:ff9b::c000:221" {
+		t.Errorf("Ожидалась AAAA 64:ff9b::c000:221, получено %v", w.msg.Answer[0])
+	}
+	if ok && aaaa.Hdr.Ttl > 120 {
+		t.Errorf("TTL синтезированной записи %d превышает TTL A-записи", aaaa.Hdr.Ttl)
+	}
+
+	// Без DNS64 ответ остается пустым
+	s.cfg.DNS64 = false
+	w = &testResponseWriter{}
+	s.handleRequest(w, req)
+	if w.msg == nil || len(w.msg.Answer) != 0 {
+		t.Errorf("Без dns64 AAAA-записи не должны синтезироваться, получено %v", w.msg)
+	}
+}
@@ -0,0 +1,175 @@
+package resolver
+
+import (
+	"encoding/base64"
+	"errors"
+	"fmt"
+	"io"
+	"log/slog"
+	"net"
+	"net/http"
+	"strings"
+	"sync/atomic"
+
+	"github.com/miekg/dns"
+)
+
+const (
+	dohPath           = "/dns-query"
+	dohContentType    = "application/dns-message"
+	dohMaxMessageSize = 65535
+)
+
+// messageWriter перехватывает ответ обработчика запросов вместо отправки
+// по сети: для DoH ответ передается по HTTP, для Resolver - вызывающему
+type messageWriter struct {
+	local  net.Addr
+	remote net.Addr
+	msg    *dns.Msg
+}
+
+func (w *messageWriter) LocalAddr() net.Addr  { return w.local }
+func (w *messageWriter) RemoteAddr() net.Addr { return w.remote }
+func (w *messageWriter) Network() string      { return "tcp" }
+
+func (w *messageWriter) WriteMsg(m *dns.Msg) error {
+	w.msg = m
+	return nil
+}
+
+func (w *messageWriter) Write(b []byte) (int, error) {
+	m := new(dns.Msg)
+	if err := m.Unpack(b); err != nil {
+		return 0, err
+	}
+	w.msg = m
+	return len(b), nil
+}
+
+func (w *messageWriter) Close() error        { return nil }
+func (w *messageWriter) TsigStatus() error   { return nil }
+func (w *messageWriter) TsigTimersOnly(bool) {}
+func (w *messageWriter) Hijack()             {}
+
+// dohHandler обрабатывает запросы DNS-over-HTTPS (RFC 8484) в форматах
+// GET ?dns=<base64url> и POST application/dns-message
+func (s *DNSServer) dohHandler(w http.ResponseWriter, r *http.Request) {
+	var wire []byte
+	switch r.Method {
+	case http.MethodGet:
+		encoded := r.URL.Query().Get("dns")
+		if encoded == "" {
+			http.Error(w, "отсутствует параметр dns", http.StatusBadRequest)
+			return
+		}
+		// RFC 8484 запрещает дополнение "=", но часть клиентов его
+		// добавляет - такие запросы тоже принимаются
+		decoded, err := base64.RawURLEncoding.DecodeString(strings.TrimRight(encoded, "="))
+		if err != nil {
+			http.Error(w, "некорректный base64url в параметре dns", http.StatusBadRequest)
+			return
+		}
+		wire = decoded
+	case http.MethodPost:
+		if r.Header.Get("Content-Type") != dohContentType {
+			http.Error(w, "ожидается Content-Type "+dohContentType, http.StatusUnsupportedMediaType)
+			return
+		}
+		body, err := io.ReadAll(io.LimitReader(r.Body, dohMaxMessageSize+1))
+		if err != nil {
+			http.Error(w, "не удалось прочитать тело запроса", http.StatusBadRequest)
+			return
+		}
+		if len(body) > dohMaxMessageSize {
+			http.Error(w, "сообщение слишком большое", http.StatusRequestEntityTooLarge)
+			return
+		}
+		wire = body
+	default:
+		w.Header().Set("Allow", "GET, POST")
+		http.Error(w, "метод не поддерживается", http.StatusMethodNotAllowed)
+		return
+	}
+
+	req := new(dns.Msg)
+	if err := req.Unpack(wire); err != nil {
+		http.Error(w, "некорректное DNS-сообщение", http.StatusBadRequest)
+		return
+	}
+
+	rw := &messageWriter{}
+	if remote, err := net.ResolveTCPAddr("tcp", r.RemoteAddr); err == nil {
+		rw.remote = remote
+	}
+	if s.cfg.ACLAction == aclActionDrop && !s.acl.Allowed(net.ParseIP(clientIP(rw))) {
+		http.Error(w, "доступ запрещен", http.StatusForbidden)
+		return
+	}
+	s.handleEncrypted(rw, req)
+	if rw.msg == nil {
+		http.Error(w, "ответ не сформирован", http.StatusInternalServerError)
+		return
+	}
+
+	packed, err := rw.msg.Pack()
+	if err != nil {
+		http.Error(w, "не удалось упаковать ответ", http.StatusInternalServerError)
+		return
+	}
+
+	w.Header().Set("Content-Type", dohContentType)
+	if maxAge, ok := dohMaxAge(rw.msg); ok {
+		w.Header().Set("Cache-Control", fmt.Sprintf("max-age=%d", maxAge))
+	}
+	w.Write(packed)
+}
+
+// dohMaxAge возвращает срок свежести HTTP-ответа (RFC 8484, раздел 5.1):
+// наименьший TTL записей ответа, а для отрицательного ответа - срок
+// отрицательного кэширования по SOA (RFC 2308, раздел 5)
+func dohMaxAge(msg *dns.Msg) (uint32, bool) {
+	if len(msg.Answer) > 0 {
+		return minTTL(msg.Answer), true
+	}
+	for _, rr := range msg.Ns {
+		if soa, ok := rr.(*dns.SOA); ok {
+			return min(soa.Hdr.Ttl, soa.Minttl), true
+		}
+	}
+	return 0, false
+}
+
+// newDoHServer создает HTTP-сервер DNS-over-HTTPS. Чтение запроса, запись
+// ответа и простой соединения ограничены tcp_timeout, как и у DoT, чтобы
+// медленные клиенты не удерживали соединения бесконечно.
+func (s *DNSServer) newDoHServer(addr string) *http.Server {
+	mux := http.NewServeMux()
+	mux.HandleFunc(dohPath, s.dohHandler)
+
+	timeout := s.cfg.TCPTimeout.Duration
+	return &http.Server{
+		Addr:              addr,
+		Handler:           mux,
+		ReadHeaderTimeout: timeout,
+		ReadTimeout:       timeout,
+		WriteTimeout:      timeout,
+		IdleTimeout:       timeout,
+	}
+}
+
+// startDoH запускает DNS-over-HTTPS сервер
+func (s *DNSServer) startDoH(addr string) {
+	server := s.newDoHServer(addr)
+	s.dnsServerMu.Lock()
+	if atomic.LoadUint32(&s.stopping) == 1 {
+		s.dnsServerMu.Unlock()
+		return
+	}
+	s.dohServers = append(s.dohServers, server)
+	s.dnsServerMu.Unlock()
+
+	slog.Info("DNS-over-HTTPS сервер запущен", "url", "https://"+addr+dohPath)
+	if err := server.ListenAndServeTLS(s.tlsCertFile, s.tlsKeyFile); err != nil && !errors.Is(err, http.ErrServerClosed) {
+		slog.Error("Ошибка сервера DNS-over-HTTPS", "error", err)
+	}
+}
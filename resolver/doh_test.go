@@ -0,0 +1,178 @@
+package resolver
+
+import (
+	"bytes"
+	"context"
+	"encoding/base64"
+	"io"
+	"net"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// readDoHResponse проверяет заголовки DoH-ответа и разбирает DNS-сообщение
+func readDoHResponse(t *testing.T, resp *http.Response) *dns.Msg {
+	t.Helper()
+	defer resp.Body.Close()
+	if resp.StatusCode != http.StatusOK {
+		t.Fatalf("Ожидался статус 200, получено %d", resp.StatusCode)
+	}
+	if ct := resp.Header.Get("Content-Type"); ct != dohContentType {
+		t.Errorf("Неверный Content-Type: %s", ct)
+	}
+	if cc := resp.Header.Get("Cache-Control"); cc != "max-age=300" {
+		t.Errorf("Ожидался Cache-Control max-age=300, получено %q", cc)
+	}
+	body, _ := io.ReadAll(resp.Body)
+	msg := new(dns.Msg)
+	if err := msg.Unpack(body); err != nil {
+		t.Fatalf("Не удалось разобрать ответ: %v", err)
+	}
+	return msg
+}
+
+func newDoHTestServer(t *testing.T) *httptest.Server {
+	s := NewDNSServer()
+	s.forwarders = []string{startOutcomeUpstream(t)}
+	srv := httptest.NewServer(http.HandlerFunc(s.dohHandler))
+	t.Cleanup(srv.Close)
+	return srv
+}
+
+func packTestQuery(t *testing.T) []byte {
+	t.Helper()
+	msg := new(dns.Msg)
+	msg.SetQuestion("ok.example.", dns.TypeA)
+	msg.Id = 0
+	wire, err := msg.Pack()
+	if err != nil {
+		t.Fatalf("Не удалось упаковать запрос: %v", err)
+	}
+	return wire
+}
+
+func TestDoHGet(t *testing.T) {
+	srv := newDoHTestServer(t)
+
+	resp, err := http.Get(srv.URL + dohPath + "?dns=" + base64.RawURLEncoding.EncodeToString(packTestQuery(t)))
+	if err != nil {
+		t.Fatalf("Ошибка GET-запроса: %v", err)
+	}
+	msg := readDoHResponse(t, resp)
+	if len(msg.Answer) != 1 {
+		t.Errorf("Ожидалась одна запись, получено %v", msg.Answer)
+	}
+}
+
+func TestDoHPost(t *testing.T) {
+	srv := newDoHTestServer(t)
+
+	resp, err := http.Post(srv.URL+dohPath, dohContentType, bytes.NewReader(packTestQuery(t)))
+	if err != nil {
+		t.Fatalf("Ошибка POST-запроса: %v", err)
+	}
+	msg := readDoHResponse(t, resp)
+	if len(msg.Answer) != 1 {
+		t.Errorf("Ожидалась одна запись, получено %v", msg.Answer)
+	}
+}
+
+func TestDoHMalformedBase64(t *testing.T) {
+	srv := newDoHTestServer(t)
+
+	resp, err := http.Get(srv.URL + dohPath + "?dns=not*valid*base64!")
+	if err != nil {
+		t.Fatalf("Ошибка GET-запроса: %v", err)
+	}
+	resp.Body.Close()
+	if resp.StatusCode != http.StatusBadRequest {
+		t.Errorf("Ожидался статус 400 для некорректного base64, получено %d", resp.StatusCode)
+	}
+}
+
+func TestDoHGetPaddedNegativeAnswer(t *testing.T) {
+	srv := newDoHTestServer(t)
+
+	msg := new(dns.Msg)
+	msg.SetQuestion("missing.example.", dns.TypeA)
+	msg.Id = 0
+	wire, err := msg.Pack()
+	if err != nil {
+		t.Fatalf("Не удалось упаковать запрос: %v", err)
+	}
+
+	// Дополненный base64url принимается, а отрицательный ответ получает
+	// срок свежести по SOA
+	resp, err := http.Get(srv.URL + dohPath + "?dns=" + base64.URLEncoding.EncodeToString(wire))
+	if err != nil {
+		t.Fatalf("Ошибка GET-запроса: %v", err)
+	}
+	// SOA вышестоящего сервера: TTL 300 и MINIMUM 300
+	if cc := resp.Header.Get("Cache-Control"); cc != "max-age=300" {
+		t.Errorf("Ожидался Cache-Control max-age=300 по SOA, получено %q", cc)
+	}
+	reply := readDoHResponse(t, resp)
+	if reply.Rcode != dns.RcodeNameError {
+		t.Errorf("Ожидался NXDOMAIN, получено %s", dns.RcodeToString[reply.Rcode])
+	}
+}
+
+func TestDoHServerTimeouts(t *testing.T) {
+	s := NewDNSServer()
+	s.cfg.TCPTimeout = Duration{3 * time.Second}
+	srv := s.newDoHServer("127.0.0.1:0")
+
+	for name, got := range map[string]time.Duration{
+		"ReadHeaderTimeout": srv.ReadHeaderTimeout,
+		"ReadTimeout":       srv.ReadTimeout,
+		"WriteTimeout":      srv.WriteTimeout,
+		"IdleTimeout":       srv.IdleTimeout,
+	} {
+		if got != 3*time.Second {
+			t.Errorf("%s = %v, ожидалось значение tcp_timeout 3s", name, got)
+		}
+	}
+}
+
+func TestShutdownStopsDoH(t *testing.T) {
+	s := NewDNSServer()
+	s.cacheFile = ""
+	s.tlsCertFile, s.tlsKeyFile = writeTestCertFiles(t)
+	addr := freeTCPAddr(t)
+
+	done := make(chan struct{})
+	go func() {
+		s.startDoH(addr)
+		close(done)
+	}()
+
+	deadline := time.Now().Add(2 * time.Second)
+	for {
+		conn, err := net.Dial("tcp", addr)
+		if err == nil {
+			conn.Close()
+			break
+		}
+		if time.Now().After(deadline) {
+			t.Fatalf("Сервер DNS-over-HTTPS не запустился: %v", err)
+		}
+		time.Sleep(10 * time.Millisecond)
+	}
+
+	if err := s.Shutdown(context.Background()); err != nil {
+		t.Fatalf("Ошибка остановки: %v", err)
+	}
+	select {
+	case <-done:
+	case <-time.After(2 * time.Second):
+		t.Fatalf("Сервер DNS-over-HTTPS не остановился после Shutdown")
+	}
+	if conn, err := net.Dial("tcp", addr); err == nil {
+		conn.Close()
+		t.Errorf("После Shutdown порт DNS-over-HTTPS не должен принимать соединения")
+	}
+}
@@ -0,0 +1,68 @@
+package resolver
+
+import (
+	"crypto/tls"
+	"fmt"
+	"log/slog"
+	"net"
+	"sync/atomic"
+
+	"github.com/miekg/dns"
+)
+
+// loadTLSConfig загружает сертификат и ключ для шифрованных транспортов
+func loadTLSConfig(certFile, keyFile string) (*tls.Config, error) {
+	cert, err := tls.LoadX509KeyPair(certFile, keyFile)
+	if err != nil {
+		return nil, fmt.Errorf("не удалось загрузить сертификат %s и ключ %s: %w", certFile, keyFile, err)
+	}
+	return &tls.Config{
+		Certificates: []tls.Certificate{cert},
+		MinVersion:   tls.VersionTLS12,
+	}, nil
+}
+
+// serveDoT обслуживает DNS-over-TLS (RFC 7858) на открытом TLS-слушателе.
+// Сообщения с двухбайтовым префиксом длины разбирает dns.Server,
+// а запросы проходят через общий handleRequest с дополнением ответов.
+// Соединения закрываются после tcp_timeout простоя.
+func (s *DNSServer) serveDoT(listener net.Listener) error {
+	server := s.withTCPTimeouts(&dns.Server{
+		Listener: listener,
+		Net:      "tcp-tls",
+		Handler:  dns.HandlerFunc(s.handleEncrypted),
+	})
+	// Сервер регистрируется для Shutdown только после запуска: остановить
+	// еще не запущенный dns.Server нельзя. Если остановка уже началась,
+	// сервер сразу закрывается сам
+	server.NotifyStartedFunc = func() {
+		s.dnsServerMu.Lock()
+		defer s.dnsServerMu.Unlock()
+		if atomic.LoadUint32(&s.stopping) == 1 {
+			go server.Shutdown()
+			return
+		}
+		s.dotServers = append(s.dotServers, server)
+	}
+	return server.ActivateAndServe()
+}
+
+// startDoT запускает DNS-over-TLS сервер
+func (s *DNSServer) startDoT(addr string) {
+	tlsConfig, err := loadTLSConfig(s.tlsCertFile, s.tlsKeyFile)
+	if err != nil {
+		slog.Error("DNS-over-TLS не запущен", "error", err)
+		return
+	}
+
+	listener, err := tls.Listen("tcp", addr, tlsConfig)
+	if err != nil {
+		slog.Error("Не удалось открыть порт DNS-over-TLS", "addr", addr, "error", err)
+		return
+	}
+
+	slog.Info("DNS-over-TLS сервер запущен", "addr", addr)
+	if err := s.serveDoT(listener); err != nil {
+		slog.Error("Ошибка сервера DNS-over-TLS", "error", err)
+	}
+}
@@ -0,0 +1,160 @@
+package resolver
+
+import (
+	"context"
+	"crypto/ecdsa"
+	"crypto/elliptic"
+	"crypto/rand"
+	"crypto/tls"
+	"crypto/x509"
+	"crypto/x509/pkix"
+	"encoding/pem"
+	"math/big"
+	"net"
+	"os"
+	"path/filepath"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// generateTestCert создает самоподписанный сертификат для 127.0.0.1
+func generateTestCert(t *testing.T) tls.Certificate {
+	t.Helper()
+	key, err := ecdsa.GenerateKey(elliptic.P256(), rand.Reader)
+	if err != nil {
+		t.Fatalf("Не удалось создать ключ: %v", err)
+	}
+	template := &x509.Certificate{
+		SerialNumber: big.NewInt(1),
+		Subject:      pkix.Name{CommonName: "dns-resolver-test"},
+		IPAddresses:  []net.IP{net.IPv4(127, 0, 0, 1)},
+		NotBefore:    time.Now().Add(-time.Hour),
+		NotAfter:     time.Now().Add(time.Hour),
+		KeyUsage:     x509.KeyUsageDigitalSignature,
+		ExtKeyUsage:  []x509.ExtKeyUsage{x509.ExtKeyUsageServerAuth},
+	}
+	der, err := x509.CreateCertificate(rand.Reader, template, template, &key.PublicKey, key)
+	if err != nil {
+		t.Fatalf("Не удалось создать сертификат: %v", err)
+	}
+	return tls.Certificate{Certificate: [][]byte{der}, PrivateKey: key}
+}
+
+// writeTestCertFiles сохраняет самоподписанный сертификат и ключ в PEM-файлы
+func writeTestCertFiles(t *testing.T) (certFile, keyFile string) {
+	t.Helper()
+	cert := generateTestCert(t)
+	keyDER, err := x509.MarshalPKCS8PrivateKey(cert.PrivateKey)
+	if err != nil {
+		t.Fatalf("Не удалось сериализовать ключ: %v", err)
+	}
+	dir := t.TempDir()
+	certFile = filepath.Join(dir, "cert.pem")
+	keyFile = filepath.Join(dir, "key.pem")
+	if err := os.WriteFile(certFile, pem.EncodeToMemory(&pem.Block{Type: "CERTIFICATE", Bytes: cert.Certificate[0]}), 0o600); err != nil {
+		t.Fatalf("Не удалось записать сертификат: %v", err)
+	}
+	if err := os.WriteFile(keyFile, pem.EncodeToMemory(&pem.Block{Type: "PRIVATE KEY", Bytes: keyDER}), 0o600); err != nil {
+		t.Fatalf("Не удалось записать ключ: %v", err)
+	}
+	return certFile, keyFile
+}
+
+func TestDoTServesQueries(t *testing.T) {
+	s := NewDNSServer()
+	s.forwarders = []string{startOutcomeUpstream(t)}
+
+	listener, err := tls.Listen("tcp", "127.0.0.1:0", &tls.Config{Certificates: []tls.Certificate{generateTestCert(t)}})
+	if err != nil {
+		t.Fatalf("Не удалось открыть TLS-порт: %v", err)
+	}
+	defer listener.Close()
+	go s.serveDoT(listener)
+
+	client := &dns.Client{
+		Net:       "tcp-tls",
+		Timeout:   2 * time.Second,
+		TLSConfig: &tls.Config{InsecureSkipVerify: true},
+	}
+	msg := new(dns.Msg)
+	msg.SetQuestion("ok.example.", dns.TypeA)
+
+	resp, _, err := client.Exchange(msg, listener.Addr().String())
+	if err != nil {
+		t.Fatalf("Ошибка запроса DNS-over-TLS: %v", err)
+	}
+	if resp.Rcode != dns.RcodeSuccess || len(resp.Answer) != 1 {
+		t.Fatalf("Ожидался NOERROR с одной записью, получено %v", resp)
+	}
+	if a, ok := resp.Answer[0].(*dns.A); !ok || a.A.String() != "192.0.2.1" {
+		t.Errorf("Неверная запись в ответе: %v", resp.Answer[0])
+	}
+}
+
+func TestDoTClosesIdleConnection(t *testing.T) {
+	s := NewDNSServer()
+	s.cfg.TCPTimeout = Duration{100 * time.Millisecond}
+
+	listener, err := tls.Listen("tcp", "127.0.0.1:0", &tls.Config{Certificates: []tls.Certificate{generateTestCert(t)}})
+	if err != nil {
+		t.Fatalf("Не удалось открыть TLS-порт: %v", err)
+	}
+	defer listener.Close()
+	go s.serveDoT(listener)
+
+	conn, err := tls.Dial("tcp", listener.Addr().String(), &tls.Config{InsecureSkipVerify: true})
+	if err != nil {
+		t.Fatalf("Не удалось подключиться: %v", err)
+	}
+	defer conn.Close()
+
+	// Клиент молчит: сервер должен закрыть соединение по tcp_timeout
+	conn.SetReadDeadline(time.Now().Add(2 * time.Second))
+	if _, err := conn.Read(make([]byte, 1)); err == nil {
+		t.Fatalf("От сервера не ожидалось данных")
+	} else if ne, ok := err.(net.Error); ok && ne.Timeout() {
+		t.Errorf("Сервер не закрыл простаивающее соединение за tcp_timeout")
+	}
+}
+
+func TestShutdownStopsDoT(t *testing.T) {
+	s := NewDNSServer()
+	s.cacheFile = ""
+
+	listener, err := tls.Listen("tcp", "127.0.0.1:0", &tls.Config{Certificates: []tls.Certificate{generateTestCert(t)}})
+	if err != nil {
+		t.Fatalf("Не удалось открыть TLS-порт: %v", err)
+	}
+	defer listener.Close()
+	done := make(chan error, 1)
+	go func() { done <- s.serveDoT(listener) }()
+
+	deadline := time.Now().Add(2 * time.Second)
+	for {
+		s.dnsServerMu.Lock()
+		registered := len(s.dotServers)
+		s.dnsServerMu.Unlock()
+		if registered == 1 {
+			break
+		}
+		if time.Now().After(deadline) {
+			t.Fatalf("Сервер DNS-over-TLS не зарегистрирован для остановки")
+		}
+		time.Sleep(10 * time.Millisecond)
+	}
+
+	if err := s.Shutdown(context.Background()); err != nil {
+		t.Fatalf("Ошибка остановки: %v", err)
+	}
+	select {
+	case <-done:
+	case <-time.After(2 * time.Second):
+		t.Fatalf("Сервер DNS-over-TLS не остановился после Shutdown")
+	}
+	if conn, err := tls.Dial("tcp", listener.Addr().String(), &tls.Config{InsecureSkipVerify: true}); err == nil {
+		conn.Close()
+		t.Errorf("После Shutdown порт DNS-over-TLS не должен принимать соединения")
+	}
+}
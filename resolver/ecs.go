@@ -0,0 +1,174 @@
+package resolver
+
+import (
+	"context"
+	"fmt"
+	"net/netip"
+
+	"github.com/miekg/dns"
+)
+
+// Режимы EDNS Client Subnet (RFC 7871)
+const (
+	ecsOff    = "off"    // Опция не отправляется
+	ecsClient = "client" // Отправляется усеченная подсеть клиента
+	ecsZero   = "zero"   // Отправляется пустая подсеть: ответ не должен зависеть от клиента
+)
+
+// Длины префиксов, рекомендованные RFC 7871 (раздел 11.1) для защиты
+// приватности клиентов
+const (
+	ecsPrefixV4 = 24
+	ecsPrefixV6 = 56
+)
+
+// validClientSubnet проверяет значения client_subnet и длины префиксов
+func validClientSubnet(cfg Config) error {
+	switch cfg.ClientSubnet {
+	case ecsOff, ecsClient, ecsZero:
+	default:
+		return fmt.Errorf("client_subnet должен быть %q, %q или %q", ecsOff, ecsClient, ecsZero)
+	}
+	if cfg.ECSPrefixV4 < 0 || cfg.ECSPrefixV4 > 32 {
+		return fmt.Errorf("ecs_prefix_v4 должен быть от 0 до 32")
+	}
+	if cfg.ECSPrefixV6 < 0 || cfg.ECSPrefixV6 > 128 {
+		return fmt.Errorf("ecs_prefix_v6 должен быть от 0 до 128")
+	}
+	return nil
+}
+
+// requestSubnet возвращает подсеть, передаваемую вышестоящим резолверам
+// вместо запроса клиента client. Нулевое значение означает, что опция не
+// отправляется. Подсеть, указанная клиентом в запросе, используется вместо
+// его адреса, но не длиннее префикса из настроек. Частные и служебные адреса
+// вышестоящим серверам не раскрываются.
+func (s *DNSServer) requestSubnet(req *dns.Msg, client string) netip.Prefix {
+	// При рекурсии ECS авторитетным серверам не передается
+	if len(s.forwarders) == 0 || s.cfg.ClientSubnet == ecsOff || s.cfg.ClientSubnet == "" {
+		return netip.Prefix{}
+	}
+
+	addr, err := netip.ParseAddr(client)
+	bits := -1
+	if opt := findSubnet(req); opt != nil {
+		var ok bool
+		if addr, ok = netip.AddrFromSlice(opt.Address); !ok {
+			return netip.Prefix{}
+		}
+		err = nil
+		bits = int(opt.SourceNetmask)
+	}
+	if err != nil {
+		return netip.Prefix{}
+	}
+	addr = addr.Unmap()
+
+	limit := s.cfg.ECSPrefixV6
+	if addr.Is4() {
+		limit = s.cfg.ECSPrefixV4
+	}
+	if bits < 0 || bits > limit {
+		bits = limit
+	}
+	if s.cfg.ClientSubnet == ecsZero || bits == 0 {
+		if addr.Is4() {
+			return netip.PrefixFrom(netip.IPv4Unspecified(), 0)
+		}
+		return netip.PrefixFrom(netip.IPv6Unspecified(), 0)
+	}
+
+	if !addr.IsGlobalUnicast() || addr.IsPrivate() {
+		return netip.Prefix{}
+	}
+	prefix, err := addr.Prefix(bits)
+	if err != nil {
+		return netip.Prefix{}
+	}
+	return prefix
+}
+
+// findSubnet возвращает опцию ECS из OPT-записи сообщения
+func findSubnet(msg *dns.Msg) *dns.EDNS0_SUBNET {
+	opt := msg.IsEdns0()
+	if opt == nil {
+		return nil
+	}
+	for _, o := range opt.Option {
+		if subnet, ok := o.(*dns.EDNS0_SUBNET); ok {
+			return subnet
+		}
+	}
+	return nil
+}
+
+// addSubnet добавляет в OPT-запись запроса опцию ECS с подсетью prefix
+func addSubnet(msg *dns.Msg, prefix netip.Prefix) {
+	opt := msg.IsEdns0()
+	if opt == nil {
+		msg.SetEdns0(dns.DefaultMsgSize, false)
+		opt = msg.IsEdns0()
+	}
+
+	var family uint16 = 1
+	if prefix.Addr().Is6() {
+		family = 2
+	}
+	opt.Option = append(opt.Option, &dns.EDNS0_SUBNET{
+		Code:          dns.EDNS0SUBNET,
+		Family:        family,
+		SourceNetmask: uint8(prefix.Bits()),
+		Address:       prefix.Addr().AsSlice(),
+	})
+}
+
+// subnetScope возвращает SCOPE PREFIX-LENGTH из опции ECS ответа. 0 (в том
+// числе при отсутствии опции) означает, что ответ годится для любых клиентов.
+func subnetScope(resp *dns.Msg) int {
+	if opt := findSubnet(resp); opt != nil {
+		return int(opt.SourceScope)
+	}
+	return 0
+}
+
+// echoSubnet возвращает клиенту его опцию ECS (RFC 7871, раздел 7.2.2).
+// В режиме client область ответа указывается равной подсети запроса: ответ
+// мог быть подобран именно для нее.
+func (s *DNSServer) echoSubnet(reply, req *dns.Msg) {
+	subnet := findSubnet(req)
+	opt := reply.IsEdns0()
+	if subnet == nil || opt == nil || s.cfg.ClientSubnet == ecsOff || s.cfg.ClientSubnet == "" {
+		return
+	}
+	echo := *subnet
+	echo.SourceScope = 0
+	if s.cfg.ClientSubnet == ecsClient && len(s.forwarders) > 0 {
+		echo.SourceScope = echo.SourceNetmask
+	}
+	opt.Option = append(opt.Option, &echo)
+}
+
+// subnetKey возвращает ключ кэша для ответа, подобранного для подсети
+// prefix. Ответы для пустой подсети общие для всех клиентов.
+func subnetKey(key string, prefix netip.Prefix) string {
+	if prefix.Bits() <= 0 {
+		return key
+	}
+	return key + "/" + prefix.String()
+}
+
+type subnetCtxKey struct{}
+
+// withSubnet передает подсеть клиента в запросы разрешения ctx
+func withSubnet(ctx context.Context, prefix netip.Prefix) context.Context {
+	if !prefix.IsValid() {
+		return ctx
+	}
+	return context.WithValue(ctx, subnetCtxKey{}, prefix)
+}
+
+// contextSubnet возвращает подсеть клиента, переданную в ctx
+func contextSubnet(ctx context.Context) (netip.Prefix, bool) {
+	prefix, ok := ctx.Value(subnetCtxKey{}).(netip.Prefix)
+	return prefix, ok
+}
@@ -0,0 +1,171 @@
+package resolver
+
+import (
+	"context"
+	"net"
+	"net/netip"
+	"sync"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// ecsTransport отвечает A-записью и возвращает опцию ECS из запроса с
+// областью scope (отрицательное значение - ответ без опции ECS)
+type ecsTransport struct {
+	scope int
+
+	mu      sync.Mutex
+	subnets []*dns.EDNS0_SUBNET // Опции ECS полученных запросов (nil - без опции)
+}
+
+func (t *ecsTransport) Exchange(ctx context.Context, c *dns.Client, msg *dns.Msg, addr string) (*dns.Msg, time.Duration, error) {
+	subnet := findSubnet(msg)
+	t.mu.Lock()
+	t.subnets = append(t.subnets, subnet)
+	t.mu.Unlock()
+
+	reply := new(dns.Msg)
+	reply.SetReply(msg)
+	reply.RecursionAvailable = true
+	q := msg.Question[0]
+	reply.Answer = append(reply.Answer, &dns.A{
+		Hdr: dns.RR_Header{Name: q.Name, Rrtype: dns.TypeA, Class: dns.ClassINET, Ttl: 300},
+		A:   net.IPv4(192, 0, 2, 1),
+	})
+	if subnet != nil && t.scope >= 0 {
+		echo := *subnet
+		echo.SourceScope = uint8(t.scope)
+		reply.SetEdns0(dns.DefaultMsgSize, false)
+		opt := reply.IsEdns0()
+		opt.Option = append(opt.Option, &echo)
+	}
+	return reply, time.Millisecond, nil
+}
+
+func (t *ecsTransport) sent() []*dns.EDNS0_SUBNET {
+	t.mu.Lock()
+	defer t.mu.Unlock()
+	return append([]*dns.EDNS0_SUBNET(nil), t.subnets...)
+}
+
+func newECSServer(t *testing.T, mode string, scope int) (*DNSServer, *ecsTransport) {
+	t.Helper()
+	cfg := DefaultConfig()
+	cfg.ClientSubnet = mode
+	cfg.EDNSCookies = false
+	// Адрес нигде не прослушивается: ответ может дать только транспорт
+	cfg.Forwarders = []string{"192.0.2.53:53"}
+	s, err := NewDNSServerWithConfig(cfg)
+	if err != nil {
+		t.Fatalf("Ошибка создания сервера: %v", err)
+	}
+	tr := &ecsTransport{scope: scope}
+	s.transport = tr
+	return s, tr
+}
+
+func queryFrom(t *testing.T, s *DNSServer, client string) *dns.Msg {
+	t.Helper()
+	req := new(dns.Msg)
+	req.SetQuestion("cdn.example.", dns.TypeA)
+	w := &testResponseWriter{remote: &net.UDPAddr{IP: net.ParseIP(client), Port: 40000}}
+	s.handleRequest(w, req)
+	if w.msg == nil || w.msg.Rcode != dns.RcodeSuccess {
+		t.Fatalf("Запрос от %s не разрешен: %v", client, w.msg)
+	}
+	return w.msg
+}
+
+func TestECSAttachedToForwardedQuery(t *testing.T) {
+	s, tr := newECSServer(t, ecsClient, 24)
+	queryFrom(t, s, "198.51.100.77")
+	queryFrom(t, s, "2001:db8:1:2:3::1")
+
+	sent := tr.sent()
+	if len(sent) != 2 || sent[0] == nil || sent[1] == nil {
+		t.Fatalf("Ожидалась опция ECS в обоих запросах, получено %v", sent)
+	}
+	if sent[0].Family != 1 || sent[0].SourceNetmask != ecsPrefixV4 || !sent[0].Address.Equal(net.ParseIP("198.51.100.0")) {
+		t.Errorf("Подсеть IPv4 клиента передана неверно: %s", sent[0])
+	}
+	if sent[1].Family != 2 || sent[1].SourceNetmask != ecsPrefixV6 || !sent[1].Address.Equal(net.ParseIP("2001:db8:1::")) {
+		t.Errorf("Подсеть IPv6 клиента передана неверно: %s", sent[1])
+	}
+}
+
+func TestECSSubnetsCachedSeparately(t *testing.T) {
+	s, tr := newECSServer(t, ecsClient, 24)
+	queryFrom(t, s, "198.51.100.7")
+	queryFrom(t, s, "203.0.113.9")
+	if n := len(tr.sent()); n != 2 {
+		t.Fatalf("Клиенты из разных подсетей должны разрешаться отдельно, запросов: %d", n)
+	}
+	if s.cache.Len() != 2 {
+		t.Errorf("Ожидались 2 записи кэша для разных подсетей, получено %d", s.cache.Len())
+	}
+
+	// Клиент из той же подсети получает ответ из кэша
+	queryFrom(t, s, "198.51.100.200")
+	if n := len(tr.sent()); n != 2 {
+		t.Errorf("Ответ для известной подсети должен браться из кэша, запросов: %d", n)
+	}
+
+	// Полная очистка имени удаляет ответы для всех подсетей
+	if removed := s.FlushName("cdn.example.", dns.TypeA); removed != 2 {
+		t.Errorf("Ожидалось удаление 2 записей, удалено %d", removed)
+	}
+}
+
+func TestECSGlobalScopeShared(t *testing.T) {
+	// Сервер не учитывает подсеть (SCOPE 0): ответ общий для всех клиентов
+	s, tr := newECSServer(t, ecsClient, 0)
+	queryFrom(t, s, "198.51.100.7")
+	queryFrom(t, s, "203.0.113.9")
+	if n := len(tr.sent()); n != 1 {
+		t.Errorf("Ответ с нулевой областью должен быть общим, запросов: %d", n)
+	}
+}
+
+func TestECSPrivacyModes(t *testing.T) {
+	s, tr := newECSServer(t, ecsZero, 0)
+	queryFrom(t, s, "198.51.100.7")
+	if sent := tr.sent(); len(sent) != 1 || sent[0] == nil || sent[0].SourceNetmask != 0 {
+		t.Errorf("В режиме zero ожидалась пустая подсеть, получено %v", sent)
+	}
+
+	s, tr = newECSServer(t, ecsOff, 0)
+	queryFrom(t, s, "198.51.100.7")
+	if sent := tr.sent(); len(sent) != 1 || sent[0] != nil {
+		t.Errorf("В режиме off опция ECS не должна отправляться, получено %v", sent)
+	}
+
+	// Частный адрес клиента не раскрывается
+	s, tr = newECSServer(t, ecsClient, 24)
+	queryFrom(t, s, "10.1.2.3")
+	if sent := tr.sent(); len(sent) != 1 || sent[0] != nil {
+		t.Errorf("Частный адрес не должен передаваться, получено %v", sent)
+	}
+}
+
+func TestRequestSubnetFromClientOption(t *testing.T) {
+	s, _ := newECSServer(t, ecsClient, 24)
+
+	tests := []struct {
+		netmask uint8
+		want    string
+	}{
+		{16, "198.51.0.0/16"},   // Клиент сам сократил подсеть
+		{32, "198.51.100.0/24"}, // Длиннее настроек - усекается
+		{0, "0.0.0.0/0"},        // Клиент запретил передавать подсеть
+	}
+	for _, tt := range tests {
+		req := new(dns.Msg)
+		req.SetQuestion("cdn.example.", dns.TypeA)
+		addSubnet(req, netip.PrefixFrom(netip.MustParseAddr("198.51.100.77"), int(tt.netmask)))
+		if got := s.requestSubnet(req, "203.0.113.1"); got.String() != tt.want {
+			t.Errorf("Подсеть /%d: получено %s, ожидалось %s", tt.netmask, got, tt.want)
+		}
+	}
+}
@@ -0,0 +1,33 @@
+package resolver
+
+import (
+	"log/slog"
+
+	"github.com/miekg/dns"
+)
+
+// addEDE добавляет в ответ опцию Extended DNS Error (RFC 8914) с причиной
+// ошибки. Опция передается только клиентам, отправившим запрос с EDNS.
+func (s *DNSServer) addEDE(reply, req *dns.Msg, code uint16, text string) {
+	edns0 := req.IsEdns0()
+	if edns0 == nil {
+		return
+	}
+	opt := reply.IsEdns0()
+	if opt == nil {
+		reply.SetEdns0(responseSize(req, s.cfg.MaxUDPSize), edns0.Do())
+		opt = reply.IsEdns0()
+	}
+	opt.Option = append(opt.Option, &dns.EDNS0_EDE{InfoCode: code, ExtraText: text})
+}
+
+// sendExtendedError отправляет ошибочный ответ с расширенным кодом ошибки
+func (s *DNSServer) sendExtendedError(w dns.ResponseWriter, req *dns.Msg, rcode int, code uint16, errMsg string) {
+	slog.Debug("Ответ с ошибкой", "rcode", dns.RcodeToString[rcode], "ede", dns.ExtendedErrorCodeToString[code], "reason", errMsg)
+	reply := new(dns.Msg)
+	reply.SetRcode(req, rcode)
+	reply.RecursionAvailable = true
+	s.addEDE(reply, req, code, errMsg)
+	s.addNSID(reply, req)
+	w.WriteMsg(reply)
+}
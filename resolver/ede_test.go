@@ -0,0 +1,135 @@
+package resolver
+
+import (
+	"fmt"
+	"strings"
+	"testing"
+
+	"github.com/miekg/dns"
+)
+
+// responseEDE возвращает опцию Extended DNS Error из ответа
+func responseEDE(m *dns.Msg) *dns.EDNS0_EDE {
+	opt := m.IsEdns0()
+	if opt == nil {
+		return nil
+	}
+	for _, o := range opt.Option {
+		if ede, ok := o.(*dns.EDNS0_EDE); ok {
+			return ede
+		}
+	}
+	return nil
+}
+
+func TestBlockedResponseCarriesEDE(t *testing.T) {
+	s := NewDNSServer()
+	s.blocklist, _ = parseBlocklist(strings.NewReader(sampleBlocklist), true, false)
+
+	req := new(dns.Msg)
+	req.SetQuestion("ads.example.com.", dns.TypeA)
+	req.SetEdns0(1232, false)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil || w.msg.Rcode != dns.RcodeNameError {
+		t.Fatalf("Ожидался NXDOMAIN, получено %v", w.msg)
+	}
+	ede := responseEDE(w.msg)
+	if ede == nil {
+		t.Fatal("В ответе нет опции Extended DNS Error")
+	}
+	if ede.InfoCode != dns.ExtendedErrorCodeBlocked {
+		t.Errorf("Ожидался код Blocked, получен %s", dns.ExtendedErrorCodeToString[ede.InfoCode])
+	}
+
+	// Клиент без EDNS получает обычный ответ без OPT-записи
+	req = new(dns.Msg)
+	req.SetQuestion("ads.example.com.", dns.TypeA)
+	w = &testResponseWriter{}
+	s.handleRequest(w, req)
+	if w.msg == nil || w.msg.IsEdns0() != nil {
+		t.Errorf("Ответ клиенту без EDNS не должен содержать OPT-запись")
+	}
+}
+
+func TestServfailCarriesEDE(t *testing.T) {
+	s := NewDNSServer()
+	s.forwarders = []string{startOutcomeUpstream(t)}
+
+	query := func() *dns.EDNS0_EDE {
+		req := new(dns.Msg)
+		req.SetQuestion("broken.example.", dns.TypeA)
+		req.SetEdns0(1232, false)
+		w := &testResponseWriter{}
+		s.handleRequest(w, req)
+		if w.msg == nil || w.msg.Rcode != dns.RcodeServerFailure {
+			t.Fatalf("Ожидался SERVFAIL, получено %v", w.msg)
+		}
+		return responseEDE(w.msg)
+	}
+
+	if ede := query(); ede == nil || ede.InfoCode != dns.ExtendedErrorCodeNetworkError {
+		t.Errorf("Ожидался код Network Error, получено %v", ede)
+	}
+	// Повторный запрос обслуживается кэшем сбоев
+	if ede := query(); ede == nil || ede.InfoCode != dns.ExtendedErrorCodeCachedError {
+		t.Errorf("Ожидался код Cached Error, получено %v", ede)
+	}
+}
+
+func TestOversizedAnswerCarriesEDE(t *testing.T) {
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		for i := 1; i <= 20; i++ {
+			rr, _ := dns.NewRR(fmt.Sprintf("%s 300 IN A 192.0.2.%d", r.Question[0].Name, i))
+			reply.Answer = append(reply.Answer, rr)
+		}
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+	s.cfg.MaxAnswerRecords = 10
+
+	req := new(dns.Msg)
+	req.SetQuestion("flood.example.", dns.TypeA)
+	req.SetEdns0(1232, false)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil || w.msg.Rcode != dns.RcodeServerFailure {
+		t.Fatalf("Ожидался SERVFAIL, получено %v", w.msg)
+	}
+	// Код Other равен 0 и все равно должен передаваться клиенту
+	if ede := responseEDE(w.msg); ede == nil || ede.InfoCode != dns.ExtendedErrorCodeOther {
+		t.Errorf("Ожидался код Other, получено %v", ede)
+	}
+}
+
+func TestMismatchedAnswerCarriesEDE(t *testing.T) {
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		rr, _ := dns.NewRR("bank.example. 300 IN A 192.0.2.66")
+		reply.Answer = append(reply.Answer, rr)
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+
+	req := new(dns.Msg)
+	req.SetQuestion("www.example.", dns.TypeA)
+	req.SetEdns0(1232, false)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil || w.msg.Rcode != dns.RcodeServerFailure {
+		t.Fatalf("Ожидался SERVFAIL, получено %v", w.msg)
+	}
+	if ede := responseEDE(w.msg); ede == nil || ede.InfoCode != dns.ExtendedErrorCodeOther {
+		t.Errorf("Ожидался код Other, получено %v", ede)
+	}
+}
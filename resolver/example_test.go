@@ -0,0 +1,34 @@
+package resolver_test
+
+import (
+	"context"
+	"fmt"
+	"time"
+
+	"github.com/miekg/dns"
+
+	"dns_resolver/resolver"
+)
+
+func ExampleResolver() {
+	cfg := resolver.DefaultConfig()
+	cfg.Forwarders = []string{"1.1.1.1"}
+
+	r, err := resolver.New(cfg)
+	if err != nil {
+		fmt.Println("ошибка конфигурации:", err)
+		return
+	}
+
+	ctx, cancel := context.WithTimeout(context.Background(), 5*time.Second)
+	defer cancel()
+
+	records, err := r.Resolve(ctx, "example.com", dns.TypeA)
+	if err != nil {
+		fmt.Println("ошибка:", err)
+		return
+	}
+	for _, rr := range records {
+		fmt.Println(rr)
+	}
+}
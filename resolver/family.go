@@ -0,0 +1,174 @@
+package resolver
+
+import (
+	"context"
+	"fmt"
+	"net"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// happyEyeballsDelay - задержка перед запросом к серверу следующего семейства
+// адресов, пока предыдущий не ответил
+const happyEyeballsDelay = 50 * time.Millisecond
+
+// Политики выбора семейства адресов вышестоящих серверов
+const (
+	familyDual       = "dual"        // Оба семейства в порядке RTT
+	familyIPv4Only   = "ipv4_only"   // Только IPv4
+	familyIPv6Only   = "ipv6_only"   // Только IPv6
+	familyPreferIPv4 = "prefer_ipv4" // Сначала IPv4, затем IPv6
+	familyPreferIPv6 = "prefer_ipv6" // Сначала IPv6, затем IPv4
+)
+
+// validAddressFamily проверяет название политики семейства адресов
+func validAddressFamily(policy string) error {
+	switch policy {
+	case familyDual, familyIPv4Only, familyIPv6Only, familyPreferIPv4, familyPreferIPv6:
+		return nil
+	}
+	return fmt.Errorf("address_family должен быть одним из %q, %q, %q, %q, %q",
+		familyDual, familyIPv4Only, familyIPv6Only, familyPreferIPv4, familyPreferIPv6)
+}
+
+// orderByFamily отбрасывает или переупорядочивает адреса серверов согласно
+// политике, сохраняя исходный порядок внутри каждого семейства. Адреса,
+// не являющиеся IP, не фильтруются.
+func orderByFamily(servers []string, policy string) []string {
+	if policy == familyDual || policy == "" {
+		return servers
+	}
+
+	var v4, v6, other []string
+	for _, server := range servers {
+		ip := net.ParseIP(serverKey(server))
+		switch {
+		case ip == nil:
+			other = append(other, server)
+		case ip.To4() != nil:
+			v4 = append(v4, server)
+		default:
+			v6 = append(v6, server)
+		}
+	}
+
+	ordered := make([]string, 0, len(servers))
+	switch policy {
+	case familyIPv4Only:
+		ordered = append(ordered, v4...)
+	case familyIPv6Only:
+		ordered = append(ordered, v6...)
+	case familyPreferIPv4:
+		ordered = append(append(ordered, v4...), v6...)
+	case familyPreferIPv6:
+		ordered = append(append(ordered, v6...), v4...)
+	}
+	return append(ordered, other...)
+}
+
+// candidates возвращает серверы для опроса: самые быстрые первыми,
+// с учетом политики семейства адресов
+func (s *DNSServer) candidates(servers []string) []string {
+	return orderByFamily(s.rtt.Sort(servers), s.cfg.AddressFamily)
+}
+
+// queryZone опрашивает серверы зоны и возвращает первый пригодный ответ или
+// nil. Если среди серверов есть адреса обоих семейств, запросы к ним
+// чередуются и запускаются с задержкой happy_eyeballs_delay, не дожидаясь
+// ответа предыдущего сервера (по образцу RFC 8305): медленный путь одного
+// семейства не задерживает ответ по другому.
+func (s *DNSServer) queryZone(ctx context.Context, servers []string, qname string, qtype uint16) *dns.Msg {
+	ordered := s.candidates(servers)
+	if delay := s.cfg.HappyEyeballsDelay.Duration; delay > 0 && (s.cfg.AddressFamily == familyDual || s.cfg.AddressFamily == "") {
+		if interleaved, ok := interleaveFamilies(ordered); ok {
+			return s.raceServers(ctx, interleaved, delay, qname, qtype)
+		}
+	}
+	for _, server := range ordered {
+		if resp := s.queryUsable(ctx, server, qname, qtype); resp != nil {
+			return resp
+		}
+	}
+	return nil
+}
+
+// interleaveFamilies чередует адреса IPv4 и IPv6, начиная с семейства первого,
+// самого быстрого по RTT, сервера; адреса, не являющиеся IP, идут последними.
+// ok равно false, если представлено только одно семейство.
+func interleaveFamilies(servers []string) ([]string, bool) {
+	var v4, v6, other []string
+	for _, server := range servers {
+		ip := net.ParseIP(serverKey(server))
+		switch {
+		case ip == nil:
+			other = append(other, server)
+		case ip.To4() != nil:
+			v4 = append(v4, server)
+		default:
+			v6 = append(v6, server)
+		}
+	}
+	if len(v4) == 0 || len(v6) == 0 {
+		return servers, false
+	}
+
+	first, second := v4, v6
+	if ip := net.ParseIP(serverKey(servers[0])); ip != nil && ip.To4() == nil {
+		first, second = v6, v4
+	}
+	ordered := make([]string, 0, len(servers))
+	for i := 0; i < len(first) || i < len(second); i++ {
+		if i < len(first) {
+			ordered = append(ordered, first[i])
+		}
+		if i < len(second) {
+			ordered = append(ordered, second[i])
+		}
+	}
+	return append(ordered, other...), true
+}
+
+// raceServers запускает запросы к серверам по очереди: следующий - через
+// delay после предыдущего или сразу после его неудачи. Возвращается первый
+// пригодный ответ, оставшиеся запросы отменяются.
+func (s *DNSServer) raceServers(ctx context.Context, servers []string, delay time.Duration, qname string, qtype uint16) *dns.Msg {
+	ctx, cancel := context.WithCancel(ctx)
+	defer cancel()
+
+	results := make(chan *dns.Msg, len(servers))
+	next, pending := 0, 0
+	start := func() {
+		server := servers[next]
+		next++
+		pending++
+		go func() {
+			results <- s.queryUsable(ctx, server, qname, qtype)
+		}()
+	}
+
+	start()
+	timer := time.NewTimer(delay)
+	defer timer.Stop()
+	for pending > 0 {
+		select {
+		case resp := <-results:
+			pending--
+			if resp != nil {
+				return resp
+			}
+			if next < len(servers) {
+				start()
+				timer.Reset(delay)
+			}
+		case <-timer.C:
+			if next < len(servers) {
+				start()
+				timer.Reset(delay)
+			}
+		case <-ctx.Done():
+			return nil
+		}
+	}
+	return nil
+}
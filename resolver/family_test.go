@@ -0,0 +1,155 @@
+package resolver
+
+import (
+	"context"
+	"reflect"
+	"sync"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+func TestOrderByFamily(t *testing.T) {
+	servers := []string{"[2001:db8::1]:53", "192.0.2.1:53", "ns.example", "2001:db8::2", "192.0.2.2"}
+	tests := []struct {
+		policy string
+		want   []string
+	}{
+		{familyDual, servers},
+		{familyIPv4Only, []string{"192.0.2.1:53", "192.0.2.2", "ns.example"}},
+		{familyIPv6Only, []string{"[2001:db8::1]:53", "2001:db8::2", "ns.example"}},
+		{familyPreferIPv4, []string{"192.0.2.1:53", "192.0.2.2", "[2001:db8::1]:53", "2001:db8::2", "ns.example"}},
+		{familyPreferIPv6, []string{"[2001:db8::1]:53", "2001:db8::2", "192.0.2.1:53", "192.0.2.2", "ns.example"}},
+	}
+	for _, tt := range tests {
+		if got := orderByFamily(servers, tt.policy); !reflect.DeepEqual(got, tt.want) {
+			t.Errorf("orderByFamily(%s) = %v, ожидалось %v", tt.policy, got, tt.want)
+		}
+	}
+}
+
+func TestIPv4OnlySkipsIPv6Servers(t *testing.T) {
+	v4 := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		rr, _ := dns.NewRR(r.Question[0].Name + " 300 IN A 192.0.2.1")
+		reply.Answer = append(reply.Answer, rr)
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.cfg.AddressFamily = familyIPv4Only
+	s.cfg.UpstreamTimeout = Duration{200 * time.Millisecond}
+	s.rootServers = []string{"[2001:db8::1]:53", v4}
+
+	rrset, _ := s.fetchFromAuthoritative("example.com.", dns.TypeA)
+	if len(rrset) != 1 {
+		t.Fatalf("Ожидалась одна запись от IPv4-сервера, получено %d", len(rrset))
+	}
+
+	s.rtt.mu.RLock()
+	_, tried := s.rtt.servers["2001:db8::1"]
+	s.rtt.mu.RUnlock()
+	if tried {
+		t.Errorf("В режиме ipv4_only IPv6-сервер не должен опрашиваться")
+	}
+}
+
+func TestConfigRejectsUnknownAddressFamily(t *testing.T) {
+	if _, err := parseConfig([]byte(`{"address_family": "ipv5"}`)); err == nil {
+		t.Errorf("Ожидалась ошибка для неизвестной политики address_family")
+	}
+}
+
+func TestInterleaveFamilies(t *testing.T) {
+	got, ok := interleaveFamilies([]string{"[2001:db8::1]:53", "2001:db8::2", "192.0.2.1", "ns.example"})
+	want := []string{"[2001:db8::1]:53", "192.0.2.1", "2001:db8::2", "ns.example"}
+	if !ok || !reflect.DeepEqual(got, want) {
+		t.Errorf("interleaveFamilies = %v, %v, ожидалось %v", got, ok, want)
+	}
+	if _, ok := interleaveFamilies([]string{"192.0.2.1", "192.0.2.2"}); ok {
+		t.Errorf("Для серверов одного семейства чередование не нужно")
+	}
+}
+
+// delayedTransport отвечает A-записью с адресом, заданным для сервера,
+// после задержки этого сервера
+type delayedTransport struct {
+	mu      sync.Mutex
+	delays  map[string]time.Duration
+	answers map[string]string
+	queried []string
+}
+
+func (t *delayedTransport) Exchange(ctx context.Context, c *dns.Client, msg *dns.Msg, addr string) (*dns.Msg, time.Duration, error) {
+	t.mu.Lock()
+	t.queried = append(t.queried, addr)
+	t.mu.Unlock()
+
+	select {
+	case <-time.After(t.delays[addr]):
+	case <-ctx.Done():
+		return nil, 0, ctx.Err()
+	}
+	reply := new(dns.Msg)
+	reply.SetReply(msg)
+	rr, _ := dns.NewRR(msg.Question[0].Name + " 300 IN A " + t.answers[addr])
+	reply.Answer = append(reply.Answer, rr)
+	return reply, t.delays[addr], nil
+}
+
+func TestHappyEyeballsPrefersFasterFamily(t *testing.T) {
+	tr := &delayedTransport{
+		delays:  map[string]time.Duration{"[2001:db8::53]:53": 3 * time.Second, "192.0.2.53:53": 0},
+		answers: map[string]string{"[2001:db8::53]:53": "192.0.2.6", "192.0.2.53:53": "192.0.2.4"},
+	}
+
+	s := NewDNSServer()
+	s.transport = tr
+	s.cfg.HappyEyeballsDelay = Duration{20 * time.Millisecond}
+	// IPv6-сервер идет первым: о задержках серверов еще ничего не известно
+	s.rootServers = []string{"2001:db8::53", "192.0.2.53"}
+
+	start := time.Now()
+	rrset, _ := s.fetchFromAuthoritative("example.com.", dns.TypeA)
+	if elapsed := time.Since(start); elapsed > time.Second {
+		t.Errorf("Медленный IPv6-путь задержал ответ на %v", elapsed)
+	}
+	if len(rrset) != 1 {
+		t.Fatalf("Ожидалась одна запись, получено %v", rrset)
+	}
+	if a, ok := rrset[0].(*dns.A); !ok || a.A.String() != "192.0.2.4" {
+		t.Errorf("Ожидался ответ IPv4-сервера, получено %v", rrset[0])
+	}
+
+	tr.mu.Lock()
+	defer tr.mu.Unlock()
+	if want := []string{"[2001:db8::53]:53", "192.0.2.53:53"}; !reflect.DeepEqual(tr.queried, want) {
+		t.Errorf("Опрошены серверы %v, ожидалось %v", tr.queried, want)
+	}
+}
+
+func TestHappyEyeballsOnClientLookups(t *testing.T) {
+	tr := &delayedTransport{
+		delays:  map[string]time.Duration{"[2001:db8::53]:53": 3 * time.Second, "192.0.2.53:53": 0},
+		answers: map[string]string{"[2001:db8::53]:53": "192.0.2.6", "192.0.2.53:53": "192.0.2.4"},
+	}
+
+	s := NewDNSServer()
+	s.transport = tr
+	s.cfg.HappyEyeballsDelay = Duration{20 * time.Millisecond}
+	s.rootServers = []string{"2001:db8::53", "192.0.2.53"}
+
+	start := time.Now()
+	result := s.lookup(cacheKey("example.com.", dns.TypeA), "example.com.", "A", dns.TypeA)
+	if elapsed := time.Since(start); elapsed > time.Second {
+		t.Errorf("Медленный IPv6-путь задержал ответ клиенту на %v", elapsed)
+	}
+	if len(result.answers) != 1 {
+		t.Fatalf("Ожидалась одна запись, получено %v", result.answers)
+	}
+	if a, ok := result.answers[0].(*dns.A); !ok || a.A.String() != "192.0.2.4" {
+		t.Errorf("Ожидался ответ IPv4-сервера, получено %v", result.answers[0])
+	}
+}
@@ -0,0 +1,129 @@
+package resolver
+
+import (
+	"context"
+	"net/netip"
+	"sync/atomic"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// fuzzSeeds возвращает начальный корпус: корректные запросы, затрагивающие
+// разные ветви обработки
+func fuzzSeeds(t testing.TB) [][]byte {
+	t.Helper()
+	var msgs []*dns.Msg
+	add := func(name string, qtype uint16, edit func(m *dns.Msg)) {
+		m := new(dns.Msg)
+		m.SetQuestion(name, qtype)
+		if edit != nil {
+			edit(m)
+		}
+		msgs = append(msgs, m)
+	}
+
+	add("example.com.", dns.TypeA, nil)
+	add("example.com.", dns.TypeAAAA, func(m *dns.Msg) { m.SetEdns0(1232, true) })
+	add("example.com.", dns.TypeANY, nil)
+	add("example.com.", dns.TypeHTTPS, nil)
+	add("1.2.0.192.in-addr.arpa.", dns.TypePTR, nil)
+	add("xn--e1afmkfd.xn--p1ai.", dns.TypeA, nil)
+	add("version.bind.", dns.TypeTXT, func(m *dns.Msg) { m.Question[0].Qclass = dns.ClassCHAOS })
+	add("example.com.", dns.TypeSOA, func(m *dns.Msg) { m.Opcode = dns.OpcodeNotify })
+	add("example.com.", dns.TypeA, func(m *dns.Msg) {
+		m.Question = append(m.Question, dns.Question{Name: "example.org.", Qtype: dns.TypeA, Qclass: dns.ClassINET})
+	})
+	add("example.com.", dns.TypeA, func(m *dns.Msg) {
+		m.SetEdns0(1232, false)
+		addSubnet(m, netip.MustParsePrefix("198.51.100.0/24"))
+		opt := m.IsEdns0()
+		opt.Option = append(opt.Option,
+			&dns.EDNS0_PADDING{Padding: make([]byte, 8)},
+			&dns.EDNS0_COOKIE{Code: dns.EDNS0COOKIE, Cookie: "0102030405060708"},
+		)
+	})
+
+	seeds := make([][]byte, 0, len(msgs))
+	for _, m := range msgs {
+		wire, err := m.Pack()
+		if err != nil {
+			t.Fatalf("Не удалось упаковать затравку %s: %v", m.Question[0].Name, err)
+		}
+		seeds = append(seeds, wire)
+	}
+	return seeds
+}
+
+// FuzzHandleRequest подает произвольные байты на вход разбора запроса и
+// обработчика, а ответ упаковывает обратно, как это делает сервер. Запуск:
+//
+//	go test ./resolver -run '^$' -fuzz FuzzHandleRequest
+func FuzzHandleRequest(f *testing.F) {
+	for _, seed := range fuzzSeeds(f) {
+		f.Add(seed)
+	}
+
+	cfg := DefaultConfig()
+	cfg.RateLimitQPS = 0
+	cfg.NXDomainLimit = 1 << 30
+	cfg.ClientSubnet = ecsClient
+	// Адрес нигде не прослушивается: ответы дает только транспорт
+	cfg.Forwarders = []string{"192.0.2.53:53"}
+	s, err := NewDNSServerWithConfig(cfg)
+	if err != nil {
+		f.Fatalf("Ошибка создания сервера: %v", err)
+	}
+	s.transport = &cannedTransport{}
+
+	f.Fuzz(func(t *testing.T, data []byte) {
+		req := new(dns.Msg)
+		if err := req.Unpack(data); err != nil {
+			// Такие сообщения сервер отклоняет с FORMERR до вызова обработчика
+			return
+		}
+
+		w := &testResponseWriter{}
+		s.handleRequest(w, req)
+		if atomic.LoadUint64(&s.handlerPanics) != 0 {
+			t.Fatalf("Паника при обработке запроса %v", req)
+		}
+		if w.msg == nil {
+			return
+		}
+		if _, err := w.msg.Pack(); err != nil {
+			t.Errorf("Ответ не упаковывается: %v\nзапрос: %v", err, req)
+		}
+	})
+}
+
+// panicTransport имитирует ошибку программы при обработке запроса
+type panicTransport struct{}
+
+func (panicTransport) Exchange(ctx context.Context, c *dns.Client, msg *dns.Msg, addr string) (*dns.Msg, time.Duration, error) {
+	panic("сбой транспорта")
+}
+
+func TestHandlerPanicAnswersSERVFAIL(t *testing.T) {
+	s := NewDNSServer()
+	s.transport = panicTransport{}
+	s.forwarders = []string{"192.0.2.53:53"}
+
+	req := new(dns.Msg)
+	req.SetQuestion("example.com.", dns.TypeA)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil || w.msg.Rcode != dns.RcodeServerFailure {
+		t.Fatalf("Ожидался SERVFAIL после паники, получено %v", w.msg)
+	}
+	if got := atomic.LoadUint64(&s.handlerPanics); got != 1 {
+		t.Errorf("Ожидалась одна учтенная паника, получено %d", got)
+	}
+
+	// Сервер продолжает работать: ключ разрешения не остался занятым
+	if n := s.inflight.waiting(cacheKey("example.com.", dns.TypeA)); n != 0 {
+		t.Errorf("После паники остались ожидающие запросы: %d", n)
+	}
+}
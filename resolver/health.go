@@ -0,0 +1,88 @@
+package resolver
+
+import (
+	"fmt"
+	"log/slog"
+	"net/http"
+	"sync/atomic"
+	"time"
+)
+
+const (
+	// heartbeatTimeout - через сколько интервалов очистки без сигнала
+	// фоновый цикл считается зависшим
+	heartbeatTimeout = 2
+	// stallTimeout - через сколько сроков resolution_timeout без единого
+	// завершенного запроса обработка считается зависшей
+	stallTimeout = 2
+)
+
+// beat отмечает, что фоновый цикл обслуживания сервера работает
+func (s *DNSServer) beat(now time.Time) {
+	atomic.StoreInt64(&s.heartbeat, now.UnixNano())
+}
+
+// requestStarted отмечает начало обработки запроса. Переход из простоя
+// в работу начинает отсчет времени без прогресса заново.
+func (s *DNSServer) requestStarted(now time.Time) {
+	if atomic.AddInt64(&s.activeRequests, 1) == 1 {
+		atomic.StoreInt64(&s.lastProgress, now.UnixNano())
+	}
+}
+
+// requestDone отмечает завершение обработки запроса
+func (s *DNSServer) requestDone(now time.Time) {
+	atomic.StoreInt64(&s.lastProgress, now.UnixNano())
+	atomic.AddInt64(&s.activeRequests, -1)
+}
+
+// healthy сообщает, принимает ли сервер запросы, был ли недавно сигнал
+// от фонового цикла и завершаются ли начатые запросы. Сервер, который
+// принимает запросы, но не может их обработать, считается неработоспособным.
+// Возвращает причину, если сервер неработоспособен.
+func (s *DNSServer) healthy(now time.Time) (bool, string) {
+	if atomic.LoadUint32(&s.serving) == 0 {
+		return false, "DNS-сервер не принимает запросы"
+	}
+	last := atomic.LoadInt64(&s.heartbeat)
+	if last == 0 {
+		return false, "нет сигнала от фонового цикла"
+	}
+	limit := heartbeatTimeout * s.cfg.CleanupInterval.Duration
+	if age := now.Sub(time.Unix(0, last)); age > limit {
+		return false, fmt.Sprintf("последний сигнал фонового цикла %s назад", age.Round(time.Second))
+	}
+	if active := atomic.LoadInt64(&s.activeRequests); active > 0 {
+		stall := stallTimeout * s.cfg.ResolutionTimeout.Duration
+		progress := time.Unix(0, atomic.LoadInt64(&s.lastProgress))
+		if age := now.Sub(progress); age > stall {
+			return false, fmt.Sprintf("%d запросов обрабатываются без прогресса %s", active, age.Round(time.Second))
+		}
+	}
+	return true, ""
+}
+
+// healthHandler отвечает 200, если сервер работоспособен, и 503 иначе
+func (s *DNSServer) healthHandler(w http.ResponseWriter, r *http.Request) {
+	w.Header().Set("Content-Type", "text/plain; charset=utf-8")
+	if ok, reason := s.healthy(time.Now()); !ok {
+		w.WriteHeader(http.StatusServiceUnavailable)
+		fmt.Fprintln(w, reason)
+		return
+	}
+	fmt.Fprintln(w, "ok")
+}
+
+// startHealthServer запускает HTTP-сервер проверки работоспособности и
+// служебных запросов, доступных только с локального адреса
+func (s *DNSServer) startHealthServer(addr string) {
+	mux := http.NewServeMux()
+	mux.HandleFunc("/healthz", s.healthHandler)
+	mux.HandleFunc("/cache", s.cacheHandler)
+	mux.HandleFunc("/cache/flush", s.flushHandler)
+
+	slog.Info("Проверка работоспособности доступна", "url", "http://"+addr+"/healthz")
+	if err := http.ListenAndServe(addr, mux); err != nil {
+		slog.Error("Ошибка сервера проверки работоспособности", "error", err)
+	}
+}
@@ -0,0 +1,58 @@
+package resolver
+
+import (
+	"net/http"
+	"net/http/httptest"
+	"sync/atomic"
+	"testing"
+	"time"
+)
+
+func healthStatus(s *DNSServer) int {
+	rec := httptest.NewRecorder()
+	s.healthHandler(rec, httptest.NewRequest(http.MethodGet, "/healthz", nil))
+	return rec.Code
+}
+
+func TestHealthEndpoint(t *testing.T) {
+	s := NewDNSServer()
+
+	if code := healthStatus(s); code != http.StatusServiceUnavailable {
+		t.Errorf("До запуска сервера ожидался код 503, получен %d", code)
+	}
+
+	atomic.StoreUint32(&s.serving, 1)
+	s.beat(time.Now())
+	if code := healthStatus(s); code != http.StatusOK {
+		t.Errorf("Работающий сервер должен отвечать 200, получен %d", code)
+	}
+
+	// Фоновый цикл давно не подавал сигнал
+	s.beat(time.Now().Add(-3 * s.cfg.CleanupInterval.Duration))
+	if code := healthStatus(s); code != http.StatusServiceUnavailable {
+		t.Errorf("При зависшем фоновом цикле ожидался код 503, получен %d", code)
+	}
+
+	s.beat(time.Now())
+	atomic.StoreUint32(&s.serving, 0)
+	if code := healthStatus(s); code != http.StatusServiceUnavailable {
+		t.Errorf("Остановленный сервер должен отвечать 503, получен %d", code)
+	}
+}
+
+func TestHealthDetectsStalledRequests(t *testing.T) {
+	s := NewDNSServer()
+	atomic.StoreUint32(&s.serving, 1)
+	s.beat(time.Now())
+
+	// Запрос начат давно и с тех пор ни один запрос не завершился
+	s.requestStarted(time.Now().Add(-3 * s.cfg.ResolutionTimeout.Duration))
+	if code := healthStatus(s); code != http.StatusServiceUnavailable {
+		t.Errorf("При зависшей обработке запросов ожидался код 503, получен %d", code)
+	}
+
+	s.requestDone(time.Now())
+	if code := healthStatus(s); code != http.StatusOK {
+		t.Errorf("После завершения запроса ожидался код 200, получен %d", code)
+	}
+}
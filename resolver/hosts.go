@@ -0,0 +1,125 @@
+package resolver
+
+import (
+	"bufio"
+	"fmt"
+	"io"
+	"net"
+	"os"
+	"strings"
+
+	"github.com/miekg/dns"
+)
+
+const defaultHostsTTL = 300
+
+// HostsFile - статические записи A/AAAA и соответствующие им PTR в формате
+// /etc/hosts, которые переопределяют результаты рекурсии
+type HostsFile struct {
+	addrs map[string][]net.IP
+	names map[string][]string // Имена по адресу для обратных запросов
+	ttl   uint32
+}
+
+// LoadHostsFile загружает hosts-файл
+func LoadHostsFile(path string, ttl uint32) (*HostsFile, error) {
+	f, err := os.Open(path)
+	if err != nil {
+		return nil, err
+	}
+	defer f.Close()
+
+	return parseHostsFile(f, ttl)
+}
+
+func parseHostsFile(r io.Reader, ttl uint32) (*HostsFile, error) {
+	h := &HostsFile{addrs: make(map[string][]net.IP), names: make(map[string][]string), ttl: ttl}
+
+	scanner := bufio.NewScanner(r)
+	for lineNum := 1; scanner.Scan(); lineNum++ {
+		line := scanner.Text()
+		if idx := strings.Index(line, "#"); idx >= 0 {
+			line = line[:idx]
+		}
+		fields := strings.Fields(line)
+		if len(fields) == 0 {
+			continue
+		}
+		if len(fields) < 2 {
+			return nil, fmt.Errorf("строка %d: ожидается адрес и хотя бы одно имя", lineNum)
+		}
+
+		ip := net.ParseIP(fields[0])
+		if ip == nil {
+			return nil, fmt.Errorf("строка %d: некорректный адрес %s", lineNum, fields[0])
+		}
+		for _, name := range fields[1:] {
+			name = strings.ToLower(dns.Fqdn(name))
+			h.addrs[name] = append(h.addrs[name], ip)
+			h.names[ip.String()] = append(h.names[ip.String()], name)
+		}
+	}
+	if err := scanner.Err(); err != nil {
+		return nil, err
+	}
+	return h, nil
+}
+
+// Lookup возвращает синтезированные записи для A/AAAA/PTR запроса. Второе
+// значение сообщает, что имя присутствует в hosts-файле и ответ окончателен.
+func (h *HostsFile) Lookup(name string, qtype uint16) ([]dns.RR, bool) {
+	if h == nil {
+		return nil, false
+	}
+	if qtype == dns.TypePTR {
+		return h.lookupPTR(name)
+	}
+	if qtype != dns.TypeA && qtype != dns.TypeAAAA {
+		return nil, false
+	}
+
+	ips, ok := h.addrs[strings.ToLower(dns.Fqdn(name))]
+	if !ok {
+		return nil, false
+	}
+
+	var records []dns.RR
+	hdr := dns.RR_Header{Name: dns.Fqdn(name), Rrtype: qtype, Class: dns.ClassINET, Ttl: h.ttl}
+	for _, ip := range ips {
+		if ip4 := ip.To4(); ip4 != nil {
+			if qtype == dns.TypeA {
+				records = append(records, &dns.A{Hdr: hdr, A: ip4})
+			}
+		} else if qtype == dns.TypeAAAA {
+			records = append(records, &dns.AAAA{Hdr: hdr, AAAA: ip})
+		}
+	}
+	return records, true
+}
+
+// lookupPTR возвращает PTR-записи для адреса из hosts-файла
+func (h *HostsFile) lookupPTR(name string) ([]dns.RR, bool) {
+	ip, ok := parseReverseName(name)
+	if !ok {
+		return nil, false
+	}
+	names, ok := h.names[ip.String()]
+	if !ok {
+		return nil, false
+	}
+
+	records := make([]dns.RR, 0, len(names))
+	hdr := dns.RR_Header{Name: dns.Fqdn(name), Rrtype: dns.TypePTR, Class: dns.ClassINET, Ttl: h.ttl}
+	for _, target := range names {
+		records = append(records, &dns.PTR{Hdr: hdr, Ptr: target})
+	}
+	return records, true
+}
+
+// Len возвращает количество имен в hosts-файле
+func (h *HostsFile) Len() int {
+	if h == nil {
+		return 0
+	}
+	return len(h.addrs)
+}
@@ -0,0 +1,95 @@
+package resolver
+
+import (
+	"strings"
+	"testing"
+
+	"github.com/miekg/dns"
+)
+
+const sampleHosts = `# Внутренние имена
+10.0.0.5      intranet.corp intranet
+10.0.0.6      intranet.corp
+fd00::5       intranet.corp
+2001:db8::1   v6only.corp
+`
+
+func TestHostsFileLookup(t *testing.T) {
+	hosts, err := parseHostsFile(strings.NewReader(sampleHosts), 60)
+	if err != nil {
+		t.Fatalf("Ошибка разбора hosts-файла: %v", err)
+	}
+
+	records, ok := hosts.Lookup("Intranet.Corp.", dns.TypeA)
+	if !ok || len(records) != 2 {
+		t.Fatalf("Ожидалось 2 A-записи, получено %v", records)
+	}
+	if a := records[0].(*dns.A); a.A.String() != "10.0.0.5" || a.Hdr.Ttl != 60 {
+		t.Errorf("Неверная A-запись: %v", a)
+	}
+
+	records, ok = hosts.Lookup("intranet.corp", dns.TypeAAAA)
+	if !ok || len(records) != 1 {
+		t.Fatalf("Ожидалась 1 AAAA-запись, получено %v", records)
+	}
+	if aaaa := records[0].(*dns.AAAA); aaaa.AAAA.String() != "fd00::5" {
+		t.Errorf("Неверная AAAA-запись: %v", aaaa)
+	}
+
+	if records, ok := hosts.Lookup("v6only.corp.", dns.TypeA); !ok || len(records) != 0 {
+		t.Errorf("Для имени только с IPv6 ожидался пустой ответ на A, получено %v", records)
+	}
+	if _, ok := hosts.Lookup("intranet.corp.", dns.TypeMX); ok {
+		t.Errorf("Запросы кроме A/AAAA не должны обслуживаться из hosts-файла")
+	}
+	if _, ok := hosts.Lookup("example.com.", dns.TypeA); ok {
+		t.Errorf("Отсутствующее имя не должно находиться в hosts-файле")
+	}
+}
+
+func TestHandleRequestHostsOverride(t *testing.T) {
+	s := NewDNSServer()
+	s.hosts, _ = parseHostsFile(strings.NewReader(sampleHosts), 60)
+
+	req := new(dns.Msg)
+	req.SetQuestion("intranet.", dns.TypeA)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil || len(w.msg.Answer) != 1 {
+		t.Fatalf("Ожидалась запись из hosts-файла, получено %v", w.msg)
+	}
+	if a, ok := w.msg.Answer[0].(*dns.A); !ok || a.A.String() != "10.0.0.5" {
+		t.Errorf("Неверный ответ: %v", w.msg.Answer[0])
+	}
+}
+
+func TestHostsFileRejectsInvalidAddress(t *testing.T) {
+	if _, err := parseHostsFile(strings.NewReader("not-an-ip host.corp\n"), 60); err == nil {
+		t.Errorf("Ожидалась ошибка для некорректного адреса")
+	}
+}
+
+func TestHostsFileReverseLookup(t *testing.T) {
+	hosts, err := parseHostsFile(strings.NewReader(sampleHosts), 60)
+	if err != nil {
+		t.Fatalf("Ошибка разбора hosts-файла: %v", err)
+	}
+
+	records, ok := hosts.Lookup("5.0.0.10.in-addr.arpa.", dns.TypePTR)
+	if !ok || len(records) != 2 {
+		t.Fatalf("Ожидалось 2 PTR-записи, получено %v", records)
+	}
+	if ptr := records[0].(*dns.PTR); ptr.Ptr != "intranet.corp." {
+		t.Errorf("Неверная PTR-запись: %v", ptr)
+	}
+
+	v6 := "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa."
+	if records, ok := hosts.Lookup(v6, dns.TypePTR); !ok || records[0].(*dns.PTR).Ptr != "v6only.corp." {
+		t.Errorf("Ожидалась PTR-запись для IPv6-адреса, получено %v", records)
+	}
+
+	if _, ok := hosts.Lookup("9.9.9.9.in-addr.arpa.", dns.TypePTR); ok {
+		t.Errorf("Адрес вне hosts-файла должен разрешаться рекурсивно")
+	}
+}
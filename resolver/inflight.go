@@ -0,0 +1,63 @@
+package resolver
+
+import (
+	"sync"
+
+	"github.com/miekg/dns"
+)
+
+// inflightCall - выполняющееся разрешение, результат которого ожидают
+// все одновременные запросы с тем же ключом
+type inflightCall struct {
+	done   chan struct{}
+	result lookupResult
+	dups   int // Количество ожидающих запросов
+}
+
+// inflightGroup объединяет одновременные разрешения одного и того же вопроса,
+// чтобы при всплеске запросов к незакэшированному имени к вышестоящим
+// серверам уходил только один запрос. Нулевое значение готово к работе.
+type inflightGroup struct {
+	mu    sync.Mutex
+	calls map[string]*inflightCall
+}
+
+// Do выполняет fn для ключа, если разрешение этого ключа ещё не идет, иначе
+// дожидается уже идущего. Второе значение сообщает, что результат получен
+// от чужого разрешения.
+func (g *inflightGroup) Do(key string, fn func() lookupResult) (lookupResult, bool) {
+	g.mu.Lock()
+	if g.calls == nil {
+		g.calls = make(map[string]*inflightCall)
+	}
+	if call, ok := g.calls[key]; ok {
+		call.dups++
+		g.mu.Unlock()
+		<-call.done
+		return call.result.copy(), true
+	}
+	call := &inflightCall{done: make(chan struct{}), result: lookupResult{rcode: dns.RcodeServerFailure}}
+	g.calls[key] = call
+	g.mu.Unlock()
+
+	defer func() {
+		g.mu.Lock()
+		delete(g.calls, key)
+		g.mu.Unlock()
+		close(call.done)
+	}()
+
+	result := fn()
+	call.result = result.copy()
+	return result, false
+}
+
+// waiting возвращает количество запросов, ожидающих разрешения ключа
+func (g *inflightGroup) waiting(key string) int {
+	g.mu.Lock()
+	defer g.mu.Unlock()
+	if call, ok := g.calls[key]; ok {
+		return call.dups
+	}
+	return 0
+}
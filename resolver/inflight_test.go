@@ -0,0 +1,131 @@
+package resolver
+
+import (
+	"sync"
+	"sync/atomic"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+func TestConcurrentLookupsAreCoalesced(t *testing.T) {
+	const clients = 10
+	var queries atomic.Int32
+	release := make(chan struct{})
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		queries.Add(1)
+		<-release
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		rr, _ := dns.NewRR("herd.example. 300 IN A 192.0.2.7")
+		reply.Answer = append(reply.Answer, rr)
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+	key := cacheKey("herd.example.", dns.TypeA)
+
+	var wg sync.WaitGroup
+	results := make([][]dns.RR, clients)
+	for i := 0; i < clients; i++ {
+		wg.Add(1)
+		go func(i int) {
+			defer wg.Done()
+			results[i] = s.lookup(key, "herd.example.", "A", dns.TypeA).answers
+		}(i)
+	}
+
+	// Отпускаем вышестоящий сервер только после того, как все остальные
+	// запросы присоединились к идущему разрешению
+	deadline := time.Now().Add(5 * time.Second)
+	for s.inflight.waiting(key) < clients-1 {
+		if time.Now().After(deadline) {
+			close(release)
+			t.Fatalf("Запросы не объединились: ожидающих %d", s.inflight.waiting(key))
+		}
+		time.Sleep(time.Millisecond)
+	}
+	close(release)
+	wg.Wait()
+
+	if n := queries.Load(); n != 1 {
+		t.Errorf("Ожидался 1 запрос к вышестоящему серверу, получено %d", n)
+	}
+	for i, answers := range results {
+		if len(answers) != 1 {
+			t.Errorf("Клиент %d получил %d записей вместо 1", i, len(answers))
+		}
+	}
+	if n := atomic.LoadUint64(&s.coalescedQueries); n != clients-1 {
+		t.Errorf("Ожидалось %d объединенных запросов, получено %d", clients-1, n)
+	}
+}
+
+func TestConcurrentClientQueriesAreCoalesced(t *testing.T) {
+	const clients = 10
+	var queries atomic.Int32
+	release := make(chan struct{})
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		queries.Add(1)
+		<-release
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		rr, _ := dns.NewRR("crowd.example. 300 IN A 192.0.2.8")
+		reply.Answer = append(reply.Answer, rr)
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+	key := cacheKey("crowd.example.", dns.TypeA)
+
+	// Одинаковые запросы клиентов не отклоняются, а ждут общего разрешения
+	var wg sync.WaitGroup
+	writers := make([]*testResponseWriter, clients)
+	for i := 0; i < clients; i++ {
+		writers[i] = &testResponseWriter{}
+		wg.Add(1)
+		go func(w *testResponseWriter) {
+			defer wg.Done()
+			req := new(dns.Msg)
+			req.SetQuestion("crowd.example.", dns.TypeA)
+			s.handleRequest(w, req)
+		}(writers[i])
+	}
+
+	deadline := time.Now().Add(5 * time.Second)
+	for s.inflight.waiting(key) < clients-1 {
+		if time.Now().After(deadline) {
+			close(release)
+			t.Fatalf("Запросы клиентов не объединились: ожидающих %d", s.inflight.waiting(key))
+		}
+		time.Sleep(time.Millisecond)
+	}
+	close(release)
+	wg.Wait()
+
+	if n := queries.Load(); n != 1 {
+		t.Errorf("Ожидался 1 запрос к вышестоящему серверу, получено %d", n)
+	}
+	for i, w := range writers {
+		if w.msg == nil || w.msg.Rcode != dns.RcodeSuccess || len(w.msg.Answer) != 1 {
+			t.Errorf("Клиент %d получил %v вместо NOERROR с одной записью", i, w.msg)
+		}
+	}
+}
+
+func TestInflightGroupForgetsFinishedCalls(t *testing.T) {
+	var g inflightGroup
+	calls := 0
+	for i := 0; i < 2; i++ {
+		g.Do("key", func() lookupResult {
+			calls++
+			return lookupResult{rcode: dns.RcodeSuccess}
+		})
+	}
+	if calls != 2 {
+		t.Errorf("Завершенное разрешение не должно переиспользоваться, вызовов: %d", calls)
+	}
+}
@@ -0,0 +1,98 @@
+package resolver
+
+import (
+	"sync"
+	"time"
+)
+
+// infraCacheTTL - срок хранения сведений о возможностях сервера
+const infraCacheTTL = 15 * time.Minute
+
+// serverInfo - наблюдаемые возможности вышестоящего или авторитетного сервера
+type serverInfo struct {
+	noEDNS      bool   // Сервер не понимает OPT-запись (RFC 6891, раздел 7)
+	udpSize     uint16 // Размер буфера UDP, объявленный сервером в ответе
+	lastError   string
+	lastErrorAt time.Time
+	expiresAt   time.Time
+}
+
+// InfraCache хранит сведения о возможностях серверов по их IP, чтобы не
+// определять их заново при каждом запросе
+type InfraCache struct {
+	mu      sync.Mutex
+	ttl     time.Duration
+	servers map[string]*serverInfo
+}
+
+// NewInfraCache создает пустой кэш возможностей серверов
+func NewInfraCache(ttl time.Duration) *InfraCache {
+	return &InfraCache{ttl: ttl, servers: make(map[string]*serverInfo)}
+}
+
+// update изменяет сведения о сервере и продлевает срок их хранения
+func (c *InfraCache) update(addr string, fn func(*serverInfo)) {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+
+	now := time.Now()
+	key := serverKey(addr)
+	info, ok := c.servers[key]
+	if !ok || now.After(info.expiresAt) {
+		info = &serverInfo{}
+		c.servers[key] = info
+	}
+	fn(info)
+	info.expiresAt = now.Add(c.ttl)
+}
+
+// get возвращает копию сведений о сервере, если они не устарели
+func (c *InfraCache) get(addr string) (serverInfo, bool) {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+
+	info, ok := c.servers[serverKey(addr)]
+	if !ok || time.Now().After(info.expiresAt) {
+		return serverInfo{}, false
+	}
+	return *info, true
+}
+
+// SetNoEDNS отмечает, что сервер не поддерживает EDNS
+func (c *InfraCache) SetNoEDNS(addr string) {
+	c.update(addr, func(info *serverInfo) { info.noEDNS = true })
+}
+
+// NoEDNS сообщает, что запросы к серверу нужно отправлять без OPT-записи
+func (c *InfraCache) NoEDNS(addr string) bool {
+	info, _ := c.get(addr)
+	return info.noEDNS
+}
+
+// RecordUDPSize запоминает размер буфера UDP, объявленный сервером
+func (c *InfraCache) RecordUDPSize(addr string, size uint16) {
+	c.update(addr, func(info *serverInfo) { info.udpSize = size })
+}
+
+// RecordError запоминает последнюю ошибку обмена с сервером
+func (c *InfraCache) RecordError(addr string, err error) {
+	c.update(addr, func(info *serverInfo) {
+		info.lastError = err.Error()
+		info.lastErrorAt = time.Now()
+	})
+}
+
+// RemoveExpired удаляет устаревшие сведения и возвращает их количество
+func (c *InfraCache) RemoveExpired(now time.Time) int {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+
+	removed := 0
+	for key, info := range c.servers {
+		if now.After(info.expiresAt) {
+			delete(c.servers, key)
+			removed++
+		}
+	}
+	return removed
+}
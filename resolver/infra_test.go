@@ -0,0 +1,70 @@
+package resolver
+
+import (
+	"context"
+	"sync"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+func TestInfraCacheExpiry(t *testing.T) {
+	c := NewInfraCache(time.Minute)
+	c.SetNoEDNS("192.0.2.1:53")
+	if !c.NoEDNS("192.0.2.1") {
+		t.Errorf("Сведения о сервере должны находиться по IP без порта")
+	}
+	if n := c.RemoveExpired(time.Now().Add(2 * time.Minute)); n != 1 {
+		t.Errorf("Ожидалось удаление 1 записи, удалено %d", n)
+	}
+	if c.NoEDNS("192.0.2.1:53") {
+		t.Errorf("Устаревшие сведения не должны использоваться")
+	}
+}
+
+func TestEDNSIncapableServerQueriedWithoutOPT(t *testing.T) {
+	var mu sync.Mutex
+	var withOPT []bool
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		mu.Lock()
+		withOPT = append(withOPT, r.IsEdns0() != nil)
+		mu.Unlock()
+
+		// Старый сервер: FORMERR без OPT на любой запрос с EDNS
+		reply := new(dns.Msg)
+		if r.IsEdns0() != nil {
+			reply.SetRcode(r, dns.RcodeFormatError)
+		} else {
+			reply.SetReply(r)
+			rr, _ := dns.NewRR(r.Question[0].Name + " 300 IN A 192.0.2.1")
+			reply.Answer = append(reply.Answer, rr)
+		}
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+	for i := 0; i < 2; i++ {
+		resp, err := s.forward(context.Background(), "example.com.", dns.TypeA)
+		if err != nil || len(resp.Answer) != 1 {
+			t.Fatalf("Запрос %d: ожидался ответ без EDNS, получено %v, %v", i+1, resp, err)
+		}
+	}
+
+	if !s.infra.NoEDNS(addr) {
+		t.Errorf("Сервер должен быть отмечен как не поддерживающий EDNS")
+	}
+	mu.Lock()
+	defer mu.Unlock()
+	// Первый запрос с OPT, повтор и второй запрос - уже без него
+	want := []bool{true, false, false}
+	if len(withOPT) != len(want) {
+		t.Fatalf("Ожидалось %d запросов, получено %d", len(want), len(withOPT))
+	}
+	for i := range want {
+		if withOPT[i] != want[i] {
+			t.Errorf("Запрос %d: OPT=%v, ожидалось %v", i+1, withOPT[i], want[i])
+		}
+	}
+}
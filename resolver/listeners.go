@@ -0,0 +1,69 @@
+package resolver
+
+import (
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// tcpTimeout - срок чтения запроса, записи ответа и простоя TCP-соединения
+// клиента
+const tcpTimeout = 10 * time.Second
+
+// Транспорты, на которых сервер принимает запросы клиентов
+const (
+	transportUDP = "udp" // DNS по UDP, адреса listen
+	transportTCP = "tcp" // DNS по TCP, адреса tcp_listen
+	transportDoT = "dot" // DNS-over-TLS, адрес dot_listen
+	transportDoH = "doh" // DNS-over-HTTPS, адрес doh_listen
+)
+
+// listenerSpec - включенный транспорт и адрес, на котором он принимает запросы
+type listenerSpec struct {
+	Transport string
+	Addr      string
+}
+
+// listenerPlan возвращает транспорты, которые запустит Start: UDP на адресах
+// listen, TCP на тех же адресах (tcp_fallback) и на адресах tcp_listen, DoT и
+// DoH на своих адресах, если заданы сертификат и ключ. Каждый транспорт
+// настраивается и отключается независимо.
+func (s *DNSServer) listenerPlan(listen string) []listenerSpec {
+	var plan []listenerSpec
+	for _, addr := range splitListen(listen) {
+		plan = append(plan, listenerSpec{Transport: transportUDP, Addr: addr})
+	}
+	var tcp []string
+	if s.cfg.TCPFallback {
+		tcp = append(tcp, splitListen(listen)...)
+	}
+	tcp = append(tcp, splitListen(s.cfg.TCPListen)...)
+	seen := make(map[string]bool)
+	for _, addr := range tcp {
+		if seen[addr] {
+			continue
+		}
+		seen[addr] = true
+		plan = append(plan, listenerSpec{Transport: transportTCP, Addr: addr})
+	}
+	if s.tlsCertFile != "" && s.tlsKeyFile != "" {
+		if s.dotAddr != "" {
+			plan = append(plan, listenerSpec{Transport: transportDoT, Addr: s.dotAddr})
+		}
+		if s.dohAddr != "" {
+			plan = append(plan, listenerSpec{Transport: transportDoH, Addr: s.dohAddr})
+		}
+	}
+	return plan
+}
+
+// withTCPTimeouts ограничивает сроком tcp_timeout чтение запроса, запись
+// ответа и простой соединения клиента на потоковом транспорте (TCP, DoT),
+// чтобы брошенные соединения не занимали сервер (RFC 7766, раздел 6.2.3)
+func (s *DNSServer) withTCPTimeouts(server *dns.Server) *dns.Server {
+	timeout := s.cfg.TCPTimeout.Duration
+	server.ReadTimeout = timeout
+	server.WriteTimeout = timeout
+	server.IdleTimeout = func() time.Duration { return timeout }
+	return server
+}
@@ -0,0 +1,135 @@
+package resolver
+
+import (
+	"context"
+	"net"
+	"reflect"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+func TestListenerPlanFromConfig(t *testing.T) {
+	cfg, err := parseConfig([]byte(`{
+		"listen": "127.0.0.1:5353",
+		"dot_listen": "",
+		"doh_listen": "127.0.0.1:8443",
+		"tls_cert": "cert.pem",
+		"tls_key": "key.pem"
+	}`))
+	if err != nil {
+		t.Fatalf("Ошибка разбора конфигурации: %v", err)
+	}
+	s, err := NewDNSServerWithConfig(cfg)
+	if err != nil {
+		t.Fatalf("Ошибка создания сервера: %v", err)
+	}
+
+	want := []listenerSpec{
+		{Transport: transportUDP, Addr: "127.0.0.1:5353"},
+		{Transport: transportTCP, Addr: "127.0.0.1:5353"},
+		{Transport: transportDoH, Addr: "127.0.0.1:8443"},
+	}
+	if got := s.listenerPlan(cfg.Listen); !reflect.DeepEqual(got, want) {
+		t.Errorf("listenerPlan = %v, ожидалось %v", got, want)
+	}
+
+	// Без сертификата шифрованные транспорты не запускаются; адрес из
+	// tcp_listen, совпадающий с адресом listen, не открывается дважды
+	cfg.TLSCert = ""
+	cfg.TCPListen = "127.0.0.1:5353"
+	s, err = NewDNSServerWithConfig(cfg)
+	if err != nil {
+		t.Fatalf("Ошибка создания сервера: %v", err)
+	}
+	want = []listenerSpec{
+		{Transport: transportUDP, Addr: "127.0.0.1:5353"},
+		{Transport: transportTCP, Addr: "127.0.0.1:5353"},
+	}
+	if got := s.listenerPlan(cfg.Listen); !reflect.DeepEqual(got, want) {
+		t.Errorf("listenerPlan без сертификата = %v, ожидалось %v", got, want)
+	}
+
+	// Без tcp_fallback TCP открывается только на адресах tcp_listen
+	cfg.TCPFallback = false
+	cfg.TCPListen = ""
+	s, err = NewDNSServerWithConfig(cfg)
+	if err != nil {
+		t.Fatalf("Ошибка создания сервера: %v", err)
+	}
+	want = []listenerSpec{{Transport: transportUDP, Addr: "127.0.0.1:5353"}}
+	if got := s.listenerPlan(cfg.Listen); !reflect.DeepEqual(got, want) {
+		t.Errorf("listenerPlan без tcp_fallback = %v, ожидалось %v", got, want)
+	}
+}
+
+// freeTCPAddr возвращает свободный TCP-адрес на loopback
+func freeTCPAddr(t *testing.T) string {
+	t.Helper()
+	ln, err := net.Listen("tcp4", "127.0.0.1:0")
+	if err != nil {
+		t.Skipf("TCP недоступен: %v", err)
+	}
+	defer ln.Close()
+	return ln.Addr().String()
+}
+
+func TestStartServesTCPListener(t *testing.T) {
+	udp := freeUDPAddr(t, "udp4", "127.0.0.1:0")
+	tcp := freeTCPAddr(t)
+
+	s := NewDNSServer()
+	s.cacheFile = ""
+	s.metricsAddr = ""
+	s.cfg.HealthListen = ""
+	s.cfg.TCPListen = tcp
+	go s.Start(udp)
+	waitServing(t, s)
+	defer s.Shutdown(context.Background())
+
+	req := new(dns.Msg)
+	req.SetQuestion("example.com.", dns.TypeANY)
+	c := &dns.Client{Net: "tcp", Timeout: time.Second}
+	resp, _, err := c.Exchange(req, tcp)
+	if err != nil {
+		t.Fatalf("Запрос по TCP остался без ответа: %v", err)
+	}
+	if len(resp.Answer) == 0 {
+		t.Errorf("По TCP получен пустой ответ")
+	}
+}
+
+func TestStartServesTCPFallbackAfterTruncation(t *testing.T) {
+	addr := freeUDPAddr(t, "udp4", "127.0.0.1:0")
+
+	s := NewDNSServer()
+	s.cacheFile = ""
+	s.metricsAddr = ""
+	s.cfg.HealthListen = ""
+	s.forwarders = []string{startLargeAnswerUpstream(t)}
+	go s.Start(addr)
+	waitServing(t, s)
+	defer s.Shutdown(context.Background())
+
+	req := new(dns.Msg)
+	req.SetQuestion("large.example.", dns.TypeA)
+	udp := &dns.Client{Net: "udp", Timeout: time.Second}
+	resp, _, err := udp.Exchange(req, addr)
+	if err != nil {
+		t.Fatalf("Запрос по UDP остался без ответа: %v", err)
+	}
+	if !resp.Truncated {
+		t.Fatalf("Ответ из 60 записей по UDP без EDNS должен быть усечен")
+	}
+
+	// Повтор по TCP на тот же адрес получает полный ответ
+	tcp := &dns.Client{Net: "tcp", Timeout: time.Second}
+	resp, _, err = tcp.Exchange(req, addr)
+	if err != nil {
+		t.Fatalf("Запрос по TCP на адрес listen остался без ответа: %v", err)
+	}
+	if resp.Truncated || len(resp.Answer) != 60 {
+		t.Errorf("По TCP ожидался полный ответ из 60 записей, получено %d (TC=%v)", len(resp.Answer), resp.Truncated)
+	}
+}
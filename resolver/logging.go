@@ -0,0 +1,74 @@
+package resolver
+
+import (
+	"fmt"
+	"io"
+	"log/slog"
+	"os"
+	"strings"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// SetupLogging настраивает журнал по переменным окружения LOG_LEVEL
+// (debug, info, warn, error) и LOG_FORMAT (text, json)
+func SetupLogging() {
+	logger, err := newLogger(os.Stdout, os.Getenv("LOG_LEVEL"), os.Getenv("LOG_FORMAT"))
+	if err != nil {
+		fmt.Fprintf(os.Stderr, "Некорректные настройки журнала: %v\n", err)
+		os.Exit(1)
+	}
+	slog.SetDefault(logger)
+}
+
+// newLogger создает логгер с заданным уровнем и форматом; пустые значения
+// означают уровень info и текстовый формат
+func newLogger(w io.Writer, level, format string) (*slog.Logger, error) {
+	var lvl slog.Level
+	if level != "" {
+		if err := lvl.UnmarshalText([]byte(level)); err != nil {
+			return nil, fmt.Errorf("неизвестный уровень журнала %q", level)
+		}
+	}
+
+	opts := &slog.HandlerOptions{Level: lvl}
+	switch strings.ToLower(format) {
+	case "", "text":
+		return slog.New(slog.NewTextHandler(w, opts)), nil
+	case "json":
+		return slog.New(slog.NewJSONHandler(w, opts)), nil
+	default:
+		return nil, fmt.Errorf("неизвестный формат журнала %q", format)
+	}
+}
+
+// rcodeRecorder запоминает код отправленного клиенту ответа
+type rcodeRecorder struct {
+	dns.ResponseWriter
+	rcode   int
+	written bool
+}
+
+func (r *rcodeRecorder) WriteMsg(m *dns.Msg) error {
+	r.rcode = m.Rcode
+	r.written = true
+	return r.ResponseWriter.WriteMsg(m)
+}
+
+// logQuery записывает в журнал итог обработки запроса
+func logQuery(w *rcodeRecorder, req *dns.Msg, cacheHit bool, elapsed time.Duration) {
+	attrs := []any{"client", clientIP(w)}
+	if len(req.Question) > 0 {
+		q := req.Question[0]
+		attrs = append(attrs, "qname", q.Name, "qtype", dns.TypeToString[q.Qtype])
+	}
+	attrs = append(attrs, "cache_hit", cacheHit, "duration", elapsed)
+
+	if !w.written {
+		slog.Warn("Запрос обработан без ответа", attrs...)
+		return
+	}
+	attrs = append(attrs, "rcode", dns.RcodeToString[w.rcode])
+	slog.Info("Запрос обработан", attrs...)
+}
@@ -0,0 +1,77 @@
+package resolver
+
+import (
+	"bytes"
+	"encoding/json"
+	"log/slog"
+	"net"
+	"testing"
+
+	"github.com/miekg/dns"
+)
+
+func TestNewLoggerLevels(t *testing.T) {
+	var buf bytes.Buffer
+	logger, err := newLogger(&buf, "warn", "text")
+	if err != nil {
+		t.Fatalf("Неожиданная ошибка: %v", err)
+	}
+	logger.Info("не должно попасть в журнал")
+	logger.Warn("предупреждение")
+	if bytes.Contains(buf.Bytes(), []byte("не должно")) || !bytes.Contains(buf.Bytes(), []byte("предупреждение")) {
+		t.Errorf("Уровень журнала не применён: %q", buf.String())
+	}
+
+	if _, err := newLogger(&buf, "verbose", ""); err == nil {
+		t.Errorf("Ожидалась ошибка для неизвестного уровня")
+	}
+	if _, err := newLogger(&buf, "", "xml"); err == nil {
+		t.Errorf("Ожидалась ошибка для неизвестного формата")
+	}
+}
+
+func TestQueryLogFields(t *testing.T) {
+	var buf bytes.Buffer
+	logger, err := newLogger(&buf, "info", "json")
+	if err != nil {
+		t.Fatalf("Неожиданная ошибка: %v", err)
+	}
+	prev := slog.Default()
+	slog.SetDefault(logger)
+	defer slog.SetDefault(prev)
+
+	s := NewDNSServer()
+	s.forwarders = []string{startOutcomeUpstream(t)}
+
+	req := new(dns.Msg)
+	req.SetQuestion("missing.example.", dns.TypeA)
+	w := &testResponseWriter{remote: &net.UDPAddr{IP: net.ParseIP("192.0.2.10"), Port: 5353}}
+	s.handleRequest(w, req)
+
+	var entry map[string]any
+	for _, line := range bytes.Split(bytes.TrimSpace(buf.Bytes()), []byte("\n")) {
+		var e map[string]any
+		if err := json.Unmarshal(line, &e); err == nil && e["msg"] == "Запрос обработан" {
+			entry = e
+		}
+	}
+	if entry == nil {
+		t.Fatalf("Запись о запросе не найдена в журнале: %s", buf.String())
+	}
+
+	want := map[string]any{
+		"client":    "192.0.2.10",
+		"qname":     "missing.example.",
+		"qtype":     "A",
+		"rcode":     "NXDOMAIN",
+		"cache_hit": false,
+	}
+	for key, value := range want {
+		if entry[key] != value {
+			t.Errorf("Поле %s: ожидалось %v, получено %v", key, value, entry[key])
+		}
+	}
+	if _, ok := entry["duration"]; !ok {
+		t.Errorf("В записи отсутствует длительность обработки")
+	}
+}
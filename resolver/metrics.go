@@ -0,0 +1,154 @@
+package resolver
+
+import (
+	"errors"
+	"fmt"
+	"io"
+	"log/slog"
+	"net"
+	"net/http"
+	"sort"
+	"strconv"
+	"sync"
+	"sync/atomic"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// latencyBuckets - границы гистограммы времени обработки запроса в секундах
+var latencyBuckets = []float64{0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1, 2.5, 5}
+
+// Histogram - гистограмма длительностей в формате Prometheus
+type Histogram struct {
+	bounds    []float64
+	counts    []uint64 // по одному счетчику на границу, плюс +Inf
+	count     uint64
+	sumMicros uint64
+}
+
+// NewHistogram создает гистограмму с заданными границами в секундах
+func NewHistogram(bounds []float64) *Histogram {
+	return &Histogram{
+		bounds: bounds,
+		counts: make([]uint64, len(bounds)+1),
+	}
+}
+
+// Observe учитывает одно измерение
+func (h *Histogram) Observe(d time.Duration) {
+	seconds := d.Seconds()
+	idx := len(h.bounds)
+	for i, bound := range h.bounds {
+		if seconds <= bound {
+			idx = i
+			break
+		}
+	}
+	atomic.AddUint64(&h.counts[idx], 1)
+	atomic.AddUint64(&h.count, 1)
+	atomic.AddUint64(&h.sumMicros, uint64(d.Microseconds()))
+}
+
+func (h *Histogram) write(w io.Writer, name, help string) {
+	fmt.Fprintf(w, "# HELP %s %s\n# TYPE %s histogram\n", name, help, name)
+	var cumulative uint64
+	for i, bound := range h.bounds {
+		cumulative += atomic.LoadUint64(&h.counts[i])
+		fmt.Fprintf(w, "%s_bucket{le=\"%s\"} %d\n", name, strconv.FormatFloat(bound, 'g', -1, 64), cumulative)
+	}
+	cumulative += atomic.LoadUint64(&h.counts[len(h.bounds)])
+	fmt.Fprintf(w, "%s_bucket{le=\"+Inf\"} %d\n", name, cumulative)
+	fmt.Fprintf(w, "%s_sum %g\n", name, float64(atomic.LoadUint64(&h.sumMicros))/1e6)
+	fmt.Fprintf(w, "%s_count %d\n", name, atomic.LoadUint64(&h.count))
+}
+
+// TypeCounter считает запросы по типам. Типы, неизвестные библиотеке dns,
+// учитываются вместе под меткой OTHER, чтобы число меток было ограничено.
+type TypeCounter struct {
+	counts sync.Map // map[string]*uint64
+}
+
+// Inc учитывает запрос типа qtype
+func (c *TypeCounter) Inc(qtype uint16) {
+	name, ok := dns.TypeToString[qtype]
+	if !ok {
+		name = "OTHER"
+	}
+	v, ok := c.counts.Load(name)
+	if !ok {
+		v, _ = c.counts.LoadOrStore(name, new(uint64))
+	}
+	atomic.AddUint64(v.(*uint64), 1)
+}
+
+// Get возвращает число запросов типа с меткой name
+func (c *TypeCounter) Get(name string) uint64 {
+	if v, ok := c.counts.Load(name); ok {
+		return atomic.LoadUint64(v.(*uint64))
+	}
+	return 0
+}
+
+func (c *TypeCounter) write(w io.Writer, name, help string) {
+	fmt.Fprintf(w, "# HELP %s %s\n# TYPE %s counter\n", name, help, name)
+	var labels []string
+	c.counts.Range(func(key, _ any) bool {
+		labels = append(labels, key.(string))
+		return true
+	})
+	sort.Strings(labels)
+	for _, label := range labels {
+		fmt.Fprintf(w, "%s{qtype=\"%s\"} %d\n", name, label, c.Get(label))
+	}
+}
+
+func writeCounter(w io.Writer, name, help string, value *uint64) {
+	fmt.Fprintf(w, "# HELP %s %s\n# TYPE %s counter\n%s %d\n", name, help, name, name, atomic.LoadUint64(value))
+}
+
+// writeMetrics выводит все метрики сервера в текстовом формате Prometheus
+func (s *DNSServer) writeMetrics(w io.Writer) {
+	writeCounter(w, "dns_resolver_queries_total", "Количество полученных запросов.", &s.queriesTotal)
+	s.qtypeQueries.write(w, "dns_resolver_queries_by_type_total", "Количество запросов по типу записи.")
+	writeCounter(w, "dns_resolver_cache_hits_total", "Количество попаданий в кэш.", &s.cacheHits)
+	writeCounter(w, "dns_resolver_cache_misses_total", "Количество промахов кэша.", &s.cacheMisses)
+	writeCounter(w, "dns_resolver_nxdomain_total", "Количество ответов NXDOMAIN.", &s.nxdomainResponses)
+	writeCounter(w, "dns_resolver_upstream_timeouts_total", "Количество таймаутов запросов к вышестоящим серверам.", &s.upstreamTimeouts)
+	writeCounter(w, "dns_resolver_coalesced_queries_total", "Количество запросов, дождавшихся уже идущего разрешения того же вопроса.", &s.coalescedQueries)
+	writeCounter(w, "dns_resolver_stale_answers_total", "Количество устаревших ответов, отданных из кэша при недоступности вышестоящих серверов.", &s.staleAnswers)
+	writeCounter(w, "dns_resolver_resolution_deadline_exceeded_total", "Количество разрешений, прерванных по общему сроку resolution_timeout.", &s.resolutionDeadlines)
+	writeCounter(w, "dns_resolver_query_budget_exhausted_total", "Количество разрешений, прерванных по лимиту исходящих запросов max_upstream_queries.", &s.queryBudgetExhausted)
+	writeCounter(w, "dns_resolver_oversized_answers_total", "Количество ответов вышестоящих серверов, отброшенных по лимиту max_answer_records.", &s.oversizedAnswers)
+	writeCounter(w, "dns_resolver_mismatched_answers_total", "Количество ответов, отвергнутых из-за записей для имен вне цепочки CNAME запроса.", &s.mismatchedAnswers)
+	writeCounter(w, "dns_resolver_overload_refused_total", "Количество запросов, отклоненных из-за лимита одновременных запросов max_in_flight.", &s.overloadRefused)
+	writeCounter(w, "dns_resolver_handler_panics_total", "Количество запросов, при обработке которых произошла паника (клиент получил SERVFAIL).", &s.handlerPanics)
+	writeCounter(w, "dns_resolver_dnssec_secure_total", "Количество ответов, прошедших проверку DNSSEC.", &s.secureQueries)
+	writeCounter(w, "dns_resolver_dnssec_insecure_total", "Количество ответов без DNSSEC.", &s.insecureQueries)
+	writeCounter(w, "dns_resolver_dnssec_bogus_total", "Количество ответов с проваленной проверкой DNSSEC.", &s.bogusQueries)
+	writeCounter(w, "dns_resolver_dnssec_indeterminate_total", "Количество ответов с неопределенным статусом DNSSEC.", &s.indeterminateQueries)
+	s.latency.write(w, "dns_resolver_request_duration_seconds", "Время обработки запроса.")
+}
+
+// metricsHandler отдает метрики по HTTP
+func (s *DNSServer) metricsHandler(w http.ResponseWriter, r *http.Request) {
+	w.Header().Set("Content-Type", "text/plain; version=0.0.4; charset=utf-8")
+	s.writeMetrics(w)
+}
+
+// startMetricsServer запускает HTTP-сервер метрик
+func (s *DNSServer) startMetricsServer(addr string) {
+	mux := http.NewServeMux()
+	mux.HandleFunc("/metrics", s.metricsHandler)
+
+	slog.Info("Метрики доступны", "url", "http://"+addr+"/metrics")
+	if err := http.ListenAndServe(addr, mux); err != nil {
+		slog.Error("Ошибка сервера метрик", "error", err)
+	}
+}
+
+// isTimeout сообщает, является ли ошибка таймаутом сети
+func isTimeout(err error) bool {
+	var netErr net.Error
+	return errors.As(err, &netErr) && netErr.Timeout()
+}
@@ -0,0 +1,83 @@
+package resolver
+
+import (
+	"io"
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"sync/atomic"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+func TestMetricsEndpointFormat(t *testing.T) {
+	s := NewDNSServer()
+	atomic.AddUint64(&s.queriesTotal, 3)
+	atomic.AddUint64(&s.cacheHits, 2)
+	s.latency.Observe(3 * time.Millisecond)
+	s.latency.Observe(2 * time.Second)
+
+	srv := httptest.NewServer(http.HandlerFunc(s.metricsHandler))
+	defer srv.Close()
+
+	resp, err := http.Get(srv.URL + "/metrics")
+	if err != nil {
+		t.Fatalf("Ошибка запроса метрик: %v", err)
+	}
+	defer resp.Body.Close()
+	body, _ := io.ReadAll(resp.Body)
+	text := string(body)
+
+	if ct := resp.Header.Get("Content-Type"); !strings.HasPrefix(ct, "text/plain") {
+		t.Errorf("Неверный Content-Type: %s", ct)
+	}
+
+	for _, want := range []string{
+		"# TYPE dns_resolver_queries_total counter\n",
+		"dns_resolver_queries_total 3\n",
+		"dns_resolver_cache_hits_total 2\n",
+		"dns_resolver_nxdomain_total 0\n",
+		"# TYPE dns_resolver_request_duration_seconds histogram\n",
+		"dns_resolver_request_duration_seconds_bucket{le=\"0.001\"} 0\n",
+		"dns_resolver_request_duration_seconds_bucket{le=\"0.005\"} 1\n",
+		"dns_resolver_request_duration_seconds_bucket{le=\"2.5\"} 2\n",
+		"dns_resolver_request_duration_seconds_bucket{le=\"+Inf\"} 2\n",
+		"dns_resolver_request_duration_seconds_count 2\n",
+	} {
+		if !strings.Contains(text, want) {
+			t.Errorf("В метриках отсутствует строка %q", want)
+		}
+	}
+}
+
+func TestQueriesByTypeCounter(t *testing.T) {
+	s := NewDNSServer()
+	s.transport = &cannedTransport{}
+	s.forwarders = []string{"192.0.2.53:53"}
+
+	for _, qtype := range []uint16{dns.TypeA, dns.TypeA, dns.TypeAAAA, dns.TypeMX, 65280} {
+		req := new(dns.Msg)
+		req.SetQuestion("example.com.", qtype)
+		s.handleRequest(&testResponseWriter{}, req)
+	}
+
+	for label, want := range map[string]uint64{"A": 2, "AAAA": 1, "MX": 1, "OTHER": 1, "TXT": 0} {
+		if got := s.qtypeQueries.Get(label); got != want {
+			t.Errorf("Счетчик запросов %s = %d, ожидалось %d", label, got, want)
+		}
+	}
+
+	var buf strings.Builder
+	s.writeMetrics(&buf)
+	for _, want := range []string{
+		"# TYPE dns_resolver_queries_by_type_total counter\n",
+		"dns_resolver_queries_by_type_total{qtype=\"A\"} 2\n",
+		"dns_resolver_queries_by_type_total{qtype=\"OTHER\"} 1\n",
+	} {
+		if !strings.Contains(buf.String(), want) {
+			t.Errorf("В метриках отсутствует строка %q", want)
+		}
+	}
+}
@@ -0,0 +1,155 @@
+package resolver
+
+import (
+	"errors"
+	"math"
+	"strings"
+	"unicode/utf8"
+
+	"github.com/miekg/dns"
+)
+
+// Параметры punycode (RFC 3492, раздел 5)
+const (
+	punyBase        = 36
+	punyTMin        = 1
+	punyTMax        = 26
+	punySkew        = 38
+	punyDamp        = 700
+	punyInitialBias = 72
+	punyInitialN    = 128
+)
+
+var errPunycode = errors.New("некорректный punycode")
+
+// validQueryName проверяет метки имени из запроса. Интернационализированные
+// имена передаются только в виде A-меток (RFC 5890), поэтому метка с байтами
+// вне ASCII или метка "xn--" с некорректным punycode считается ошибкой.
+func validQueryName(name string) bool {
+	buf := make([]byte, 256)
+	n, err := dns.PackDomainName(dns.Fqdn(name), buf, 0, nil, false)
+	if err != nil {
+		return false
+	}
+
+	for off := 0; off < n && buf[off] != 0; {
+		length := int(buf[off])
+		label := string(buf[off+1 : off+1+length])
+		off += 1 + length
+
+		for i := 0; i < len(label); i++ {
+			if label[i] >= utf8.RuneSelf {
+				return false
+			}
+		}
+		if len(label) > 4 && strings.EqualFold(label[:4], "xn--") && !validALabel(label[4:]) {
+			return false
+		}
+	}
+	return true
+}
+
+// validALabel проверяет, что часть A-метки после "xn--" декодируется и
+// действительно содержит символы вне ASCII
+func validALabel(encoded string) bool {
+	decoded, err := decodePunycode(encoded)
+	if err != nil {
+		return false
+	}
+	for _, r := range decoded {
+		if r >= utf8.RuneSelf {
+			return true
+		}
+	}
+	return false
+}
+
+// decodePunycode декодирует строку punycode (RFC 3492, раздел 6.2)
+func decodePunycode(s string) (string, error) {
+	var output []rune
+	pos := 0
+	if delim := strings.LastIndexByte(s, '-'); delim >= 0 {
+		for _, r := range s[:delim] {
+			if r >= utf8.RuneSelf {
+				return "", errPunycode
+			}
+			output = append(output, r)
+		}
+		pos = delim + 1
+	}
+
+	n, bias, i := punyInitialN, punyInitialBias, 0
+	for pos < len(s) {
+		oldi, w := i, 1
+		for k := punyBase; ; k += punyBase {
+			if pos >= len(s) {
+				return "", errPunycode
+			}
+			digit, ok := punyDigit(s[pos])
+			pos++
+			if !ok || digit > (math.MaxInt32-i)/w {
+				return "", errPunycode
+			}
+			i += digit * w
+
+			t := k - bias
+			if t < punyTMin {
+				t = punyTMin
+			} else if t > punyTMax {
+				t = punyTMax
+			}
+			if digit < t {
+				break
+			}
+			if w > math.MaxInt32/(punyBase-t) {
+				return "", errPunycode
+			}
+			w *= punyBase - t
+		}
+
+		points := len(output) + 1
+		bias = punyAdapt(i-oldi, points, oldi == 0)
+		if i/points > math.MaxInt32-n {
+			return "", errPunycode
+		}
+		n += i / points
+		i %= points
+		if n > utf8.MaxRune || (n >= 0xD800 && n <= 0xDFFF) {
+			return "", errPunycode
+		}
+
+		output = append(output, 0)
+		copy(output[i+1:], output[i:])
+		output[i] = rune(n)
+		i++
+	}
+	return string(output), nil
+}
+
+func punyDigit(c byte) (int, bool) {
+	switch {
+	case c >= 'a' && c <= 'z':
+		return int(c - 'a'), true
+	case c >= 'A' && c <= 'Z':
+		return int(c - 'A'), true
+	case c >= '0' && c <= '9':
+		return int(c-'0') + 26, true
+	}
+	return 0, false
+}
+
+// punyAdapt пересчитывает смещение после каждого декодированного символа
+func punyAdapt(delta, points int, first bool) int {
+	if first {
+		delta /= punyDamp
+	} else {
+		delta /= 2
+	}
+	delta += delta / points
+	k := 0
+	for delta > ((punyBase-punyTMin)*punyTMax)/2 {
+		delta /= punyBase - punyTMin
+		k += punyBase
+	}
+	return k + (punyBase-punyTMin+1)*delta/(delta+punySkew)
+}
@@ -0,0 +1,86 @@
+package resolver
+
+import (
+	"sync/atomic"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+func TestDecodePunycode(t *testing.T) {
+	for encoded, want := range map[string]string{
+		"d1acufc":   "домен",
+		"p1ai":      "рф",
+		"bcher-kva": "bücher",
+	} {
+		got, err := decodePunycode(encoded)
+		if err != nil || got != want {
+			t.Errorf("decodePunycode(%s) = %q, %v; ожидалось %q", encoded, got, err, want)
+		}
+	}
+	for _, encoded := range []string{"a-_", "zz", "99999999999"} {
+		if _, err := decodePunycode(encoded); err == nil {
+			t.Errorf("decodePunycode(%s) должен вернуть ошибку", encoded)
+		}
+	}
+}
+
+func TestValidQueryName(t *testing.T) {
+	for name, want := range map[string]bool{
+		"example.com.":              true,
+		"xn--d1acufc.xn--p1ai.":     true,
+		"XN--D1ACUFC.xn--p1ai.":     true,
+		"_dmarc.example.com.":       true,
+		".":                         true,
+		"xn--a-_.example.":          false, // некорректный punycode
+		"xn--example.com.":          false, // A-метка без символов вне ASCII
+		"пример.example.":           false, // сырой UTF-8 вместо A-метки
+		"bad\\200label.example.com": false,
+	} {
+		if got := validQueryName(name); got != want {
+			t.Errorf("validQueryName(%q) = %v, ожидалось %v", name, got, want)
+		}
+	}
+}
+
+func TestPunycodeNameResolvedAndCached(t *testing.T) {
+	s := NewDNSServer()
+	tr := &cannedTransport{}
+	s.transport = tr
+	s.forwarders = []string{"192.0.2.53:53"}
+
+	for _, name := range []string{"xn--d1acufc.xn--p1ai.", "XN--D1ACUFC.XN--P1AI."} {
+		req := new(dns.Msg)
+		req.SetQuestion(name, dns.TypeA)
+		w := &testResponseWriter{}
+		s.handleRequest(w, req)
+		if w.msg == nil || w.msg.Rcode != dns.RcodeSuccess || len(w.msg.Answer) != 1 {
+			t.Fatalf("Ожидался ответ с одной записью для %s, получено %v", name, w.msg)
+		}
+		if owner := w.msg.Answer[0].Header().Name; owner != "xn--d1acufc.xn--p1ai." {
+			t.Errorf("A-метка в ответе искажена: %s", owner)
+		}
+	}
+	if n := atomic.LoadUint64(&tr.queries); n != 1 {
+		t.Errorf("Повторный запрос должен обслуживаться кэшем, запросов к серверу: %d", n)
+	}
+
+	entries := s.cache.Dump(time.Now())
+	if len(entries) != 1 || entries[0].Name != "xn--d1acufc.xn--p1ai." {
+		t.Errorf("В кэше ожидалась одна запись для A-метки, получено %v", entries)
+	}
+}
+
+func TestMalformedLabelFormErr(t *testing.T) {
+	s := NewDNSServer()
+	for _, name := range []string{"xn--a-_.example.", "пример.example."} {
+		req := new(dns.Msg)
+		req.SetQuestion(name, dns.TypeA)
+		w := &testResponseWriter{}
+		s.handleRequest(w, req)
+		if w.msg == nil || w.msg.Rcode != dns.RcodeFormatError {
+			t.Errorf("Для %q ожидался FORMERR, получено %v", name, w.msg)
+		}
+	}
+}
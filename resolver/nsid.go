@@ -0,0 +1,39 @@
+package resolver
+
+import (
+	"encoding/hex"
+
+	"github.com/miekg/dns"
+)
+
+// requestsNSID сообщает, запросил ли клиент идентификатор сервера: по
+// RFC 5001 запрос содержит опцию NSID с пустыми данными
+func requestsNSID(req *dns.Msg) bool {
+	opt := req.IsEdns0()
+	if opt == nil {
+		return false
+	}
+	for _, o := range opt.Option {
+		if o.Option() == dns.EDNS0NSID {
+			return true
+		}
+	}
+	return false
+}
+
+// addNSID добавляет в OPT-запись ответа идентификатор сервера nsid, если
+// клиент его запросил и идентификатор задан
+func (s *DNSServer) addNSID(reply, req *dns.Msg) {
+	if s.cfg.NSID == "" || !requestsNSID(req) {
+		return
+	}
+	opt := reply.IsEdns0()
+	if opt == nil {
+		reply.SetEdns0(responseSize(req, s.cfg.MaxUDPSize), req.IsEdns0().Do())
+		opt = reply.IsEdns0()
+	}
+	opt.Option = append(opt.Option, &dns.EDNS0_NSID{
+		Code: dns.EDNS0NSID,
+		Nsid: hex.EncodeToString([]byte(s.cfg.NSID)),
+	})
+}
@@ -0,0 +1,59 @@
+package resolver
+
+import (
+	"encoding/hex"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// nsidQuery отправляет запрос к закэшированному имени и возвращает опцию
+// NSID ответа или nil
+func nsidQuery(t *testing.T, s *DNSServer, withNSID bool) *dns.EDNS0_NSID {
+	t.Helper()
+	req := new(dns.Msg)
+	req.SetQuestion("example.com.", dns.TypeA)
+	req.SetEdns0(dns.DefaultMsgSize, false)
+	if withNSID {
+		opt := req.IsEdns0()
+		opt.Option = append(opt.Option, &dns.EDNS0_NSID{Code: dns.EDNS0NSID})
+	}
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+	if w.msg == nil || w.msg.Rcode != dns.RcodeSuccess {
+		t.Fatalf("Ожидался успешный ответ, получено %v", w.msg)
+	}
+	opt := w.msg.IsEdns0()
+	if opt == nil {
+		t.Fatalf("В ответе нет OPT-записи")
+	}
+	for _, o := range opt.Option {
+		if nsid, ok := o.(*dns.EDNS0_NSID); ok {
+			return nsid
+		}
+	}
+	return nil
+}
+
+func TestNSIDReturnedOnRequest(t *testing.T) {
+	s := NewDNSServer()
+	s.cfg.NSID = "anycast-ams-1"
+	s.cache.Set(cacheKey("example.com.", dns.TypeA), newTestEntry(t, "example.com.", time.Hour))
+
+	nsid := nsidQuery(t, s, true)
+	if nsid == nil {
+		t.Fatalf("Ожидалась опция NSID в ответе")
+	}
+	if id, err := hex.DecodeString(nsid.Nsid); err != nil || string(id) != "anycast-ams-1" {
+		t.Errorf("NSID = %q, ожидался идентификатор anycast-ams-1", nsid.Nsid)
+	}
+
+	if nsidQuery(t, s, false) != nil {
+		t.Errorf("NSID не должен передаваться клиенту, который его не запросил")
+	}
+	s.cfg.NSID = ""
+	if nsidQuery(t, s, true) != nil {
+		t.Errorf("Без настроенного nsid опция не должна передаваться")
+	}
+}
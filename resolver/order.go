@@ -0,0 +1,65 @@
+package resolver
+
+import (
+	"fmt"
+	"math/rand/v2"
+	"sync/atomic"
+
+	"github.com/miekg/dns"
+)
+
+// Порядок адресных записей в ответах клиентам (аналог rrset-order в BIND)
+const (
+	orderFixed  = "fixed"  // Как получено от сервера и хранится в кэше
+	orderRandom = "random" // Случайная перестановка для каждого ответа
+	orderCyclic = "cyclic" // Сдвиг на одну запись с каждым ответом
+)
+
+// validAnswerOrder проверяет значение answer_order
+func validAnswerOrder(order string) error {
+	switch order {
+	case orderFixed, orderRandom, orderCyclic:
+		return nil
+	}
+	return fmt.Errorf("answer_order должен быть %q, %q или %q", orderFixed, orderRandom, orderCyclic)
+}
+
+// orderAnswers возвращает ответ с переставленными записями A и AAAA для
+// распределения нагрузки между адресами. Переставляются только записи внутри
+// одного набора (одинаковые имя и тип), цепочка CNAME остается на месте.
+// Исходный срез не изменяется: он может быть общим для нескольких запросов.
+func (s *DNSServer) orderAnswers(answers []dns.RR) []dns.RR {
+	if s.cfg.AnswerOrder == orderFixed || s.cfg.AnswerOrder == "" || len(answers) < 2 {
+		return answers
+	}
+
+	ordered := append([]dns.RR(nil), answers...)
+	shift := int(atomic.AddUint64(&s.answerRotation, 1))
+	for start := 0; start < len(ordered); {
+		end := start + 1
+		for end < len(ordered) && sameAddressSet(ordered[start], ordered[end]) {
+			end++
+		}
+		if set := ordered[start:end]; len(set) > 1 && isAddress(set[0]) {
+			switch s.cfg.AnswerOrder {
+			case orderRandom:
+				rand.Shuffle(len(set), func(i, j int) { set[i], set[j] = set[j], set[i] })
+			case orderCyclic:
+				rotated := append(append([]dns.RR(nil), set[shift%len(set):]...), set[:shift%len(set)]...)
+				copy(set, rotated)
+			}
+		}
+		start = end
+	}
+	return ordered
+}
+
+func isAddress(rr dns.RR) bool {
+	t := rr.Header().Rrtype
+	return t == dns.TypeA || t == dns.TypeAAAA
+}
+
+// sameAddressSet сообщает, относятся ли записи к одному набору
+func sameAddressSet(a, b dns.RR) bool {
+	return a.Header().Rrtype == b.Header().Rrtype && dns.CanonicalName(a.Header().Name) == dns.CanonicalName(b.Header().Name)
+}
@@ -0,0 +1,75 @@
+package resolver
+
+import (
+	"fmt"
+	"strings"
+	"testing"
+
+	"github.com/miekg/dns"
+)
+
+// startMultiAUpstream отвечает четырьмя A-записями в фиксированном порядке
+func startMultiAUpstream(t *testing.T) string {
+	return startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		for i := 1; i <= 4; i++ {
+			rr, _ := dns.NewRR(fmt.Sprintf("%s 300 IN A 192.0.2.%d", r.Question[0].Name, i))
+			reply.Answer = append(reply.Answer, rr)
+		}
+		w.WriteMsg(reply)
+	})
+}
+
+// answerOrder возвращает адреса ответа на запрос через запятую
+func answerOrder(t *testing.T, s *DNSServer, name string) string {
+	req := new(dns.Msg)
+	req.SetQuestion(name, dns.TypeA)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+	if w.msg == nil || len(w.msg.Answer) != 4 {
+		t.Fatalf("Ожидалось 4 записи, получено %v", w.msg)
+	}
+	var addrs []string
+	for _, rr := range w.msg.Answer {
+		addrs = append(addrs, rr.(*dns.A).A.String())
+	}
+	return strings.Join(addrs, ",")
+}
+
+func TestAnswerOrderRandomizesAddresses(t *testing.T) {
+	s := NewDNSServer()
+	s.forwarders = []string{startMultiAUpstream(t)}
+	s.cfg.AnswerOrder = orderRandom
+
+	orders := make(map[string]bool)
+	for i := 0; i < 20; i++ {
+		orders[answerOrder(t, s, "pool.example.")] = true
+	}
+	if len(orders) < 2 {
+		t.Errorf("Повторные запросы должны получать разный порядок записей, получено %v", orders)
+	}
+
+	// В кэше сохраняется исходный порядок
+	records, ok := s.cache.Get(cacheKey("pool.example.", dns.TypeA))
+	if !ok || records[0].(*dns.A).A.String() != "192.0.2.1" || records[3].(*dns.A).A.String() != "192.0.2.4" {
+		t.Errorf("Кэш должен хранить записи в исходном порядке, получено %v", records)
+	}
+}
+
+func TestAnswerOrderCyclicRotates(t *testing.T) {
+	s := NewDNSServer()
+	s.forwarders = []string{startMultiAUpstream(t)}
+	s.cfg.AnswerOrder = orderCyclic
+
+	first := answerOrder(t, s, "pool.example.")
+	second := answerOrder(t, s, "pool.example.")
+	if first == second {
+		t.Errorf("Последовательные ответы должны быть сдвинуты: %s и %s", first, second)
+	}
+
+	s.cfg.AnswerOrder = orderFixed
+	if got := answerOrder(t, s, "pool.example."); got != "192.0.2.1,192.0.2.2,192.0.2.3,192.0.2.4" {
+		t.Errorf("В режиме fixed порядок не должен меняться, получено %s", got)
+	}
+}
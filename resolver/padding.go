@@ -0,0 +1,68 @@
+package resolver
+
+import (
+	"github.com/miekg/dns"
+)
+
+// paddingBlockSize - рекомендованный RFC 8467 размер блока для ответов
+const paddingBlockSize = 468
+
+// wantsPadding сообщает, добавил ли клиент в запрос опцию EDNS Padding.
+// По RFC 8467 сервер дополняет ответы только таким клиентам.
+func wantsPadding(req *dns.Msg) bool {
+	opt := req.IsEdns0()
+	if opt == nil {
+		return false
+	}
+	for _, o := range opt.Option {
+		if _, ok := o.(*dns.EDNS0_PADDING); ok {
+			return true
+		}
+	}
+	return false
+}
+
+// padMessage добавляет в OPT-запись опцию Padding (RFC 7830) такой длины,
+// чтобы размер сообщения стал кратен block. Сообщение без OPT-записи не
+// меняется. Размер считается с учетом сжатия, поэтому m.Compress должен
+// быть уже установлен.
+func padMessage(m *dns.Msg, block int) {
+	opt := m.IsEdns0()
+	if opt == nil || block <= 0 {
+		return
+	}
+
+	options := opt.Option[:0]
+	for _, o := range opt.Option {
+		if _, ok := o.(*dns.EDNS0_PADDING); !ok {
+			options = append(options, o)
+		}
+	}
+	padding := &dns.EDNS0_PADDING{}
+	opt.Option = append(options, padding)
+
+	if rem := m.Len() % block; rem != 0 {
+		padding.Padding = make([]byte, block-rem)
+	}
+}
+
+// paddingWriter дополняет ответы шифрованных транспортов, чтобы размер
+// ответа не выдавал, на какой вопрос он дан
+type paddingWriter struct {
+	dns.ResponseWriter
+	block int
+}
+
+func (w *paddingWriter) WriteMsg(m *dns.Msg) error {
+	padMessage(m, w.block)
+	return w.ResponseWriter.WriteMsg(m)
+}
+
+// handleEncrypted обрабатывает запросы, пришедшие по DoT или DoH, дополняя
+// ответы клиентам, запросившим padding
+func (s *DNSServer) handleEncrypted(w dns.ResponseWriter, req *dns.Msg) {
+	if s.cfg.PaddingBlockSize > 0 && wantsPadding(req) {
+		w = &paddingWriter{ResponseWriter: w, block: s.cfg.PaddingBlockSize}
+	}
+	s.handleRequest(w, req)
+}
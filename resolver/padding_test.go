@@ -0,0 +1,99 @@
+package resolver
+
+import (
+	"bytes"
+	"io"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+
+	"github.com/miekg/dns"
+)
+
+func TestPadMessageRoundsToBlock(t *testing.T) {
+	for _, block := range []int{128, paddingBlockSize} {
+		m := new(dns.Msg)
+		m.SetQuestion("example.com.", dns.TypeA)
+		m.Response = true
+		m.Compress = true
+		m.Answer = append(m.Answer, mustRR(t, "example.com. 300 IN A 192.0.2.1"))
+		m.SetEdns0(1232, false)
+
+		padMessage(m, block)
+		wire, err := m.Pack()
+		if err != nil {
+			t.Fatalf("Не удалось упаковать ответ: %v", err)
+		}
+		if len(wire)%block != 0 {
+			t.Errorf("Размер ответа %d не кратен блоку %d", len(wire), block)
+		}
+
+		// Повторное дополнение заменяет прежнюю опцию, а не добавляет вторую
+		padMessage(m, block)
+		if wire2, _ := m.Pack(); len(wire2) != len(wire) {
+			t.Errorf("Повторное дополнение изменило размер: %d вместо %d", len(wire2), len(wire))
+		}
+	}
+}
+
+func TestPadMessageWithoutOPT(t *testing.T) {
+	m := new(dns.Msg)
+	m.SetQuestion("example.com.", dns.TypeA)
+	padMessage(m, paddingBlockSize)
+	if m.IsEdns0() != nil {
+		t.Error("Ответ без OPT-записи не должен дополняться")
+	}
+}
+
+// dohExchange отправляет запрос через DoH и возвращает ответ в виде байтов
+func dohExchange(t *testing.T, srv *httptest.Server, msg *dns.Msg) []byte {
+	t.Helper()
+	wire, err := msg.Pack()
+	if err != nil {
+		t.Fatalf("Не удалось упаковать запрос: %v", err)
+	}
+	resp, err := http.Post(srv.URL+dohPath, dohContentType, bytes.NewReader(wire))
+	if err != nil {
+		t.Fatalf("Ошибка POST-запроса: %v", err)
+	}
+	defer resp.Body.Close()
+	body, _ := io.ReadAll(resp.Body)
+	if resp.StatusCode != http.StatusOK {
+		t.Fatalf("Ожидался статус 200, получено %d", resp.StatusCode)
+	}
+	return body
+}
+
+func TestDoHPaddedResponse(t *testing.T) {
+	srv := newDoHTestServer(t)
+
+	padded := new(dns.Msg)
+	padded.SetQuestion("ok.example.", dns.TypeA)
+	padded.SetEdns0(1232, false)
+	opt := padded.IsEdns0()
+	opt.Option = append(opt.Option, &dns.EDNS0_PADDING{Padding: make([]byte, 16)})
+
+	body := dohExchange(t, srv, padded)
+	if len(body)%paddingBlockSize != 0 {
+		t.Errorf("Размер ответа %d не кратен %d", len(body), paddingBlockSize)
+	}
+	resp := new(dns.Msg)
+	if err := resp.Unpack(body); err != nil {
+		t.Fatalf("Не удалось разобрать ответ: %v", err)
+	}
+	if !wantsPadding(resp) {
+		t.Error("В ответе нет опции Padding")
+	}
+
+	// Клиенту, не запросившему padding, ответ не дополняется
+	plain := new(dns.Msg)
+	plain.SetQuestion("ok.example.", dns.TypeA)
+	plain.SetEdns0(1232, false)
+	resp = new(dns.Msg)
+	if err := resp.Unpack(dohExchange(t, srv, plain)); err != nil {
+		t.Fatalf("Не удалось разобрать ответ: %v", err)
+	}
+	if wantsPadding(resp) {
+		t.Error("Ответ клиенту без padding не должен дополняться")
+	}
+}
@@ -0,0 +1,75 @@
+package resolver
+
+import (
+	"context"
+	"log/slog"
+	"sync"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+const (
+	prefetchFraction = 10              // Обновлять, когда остается 1/10 исходного TTL
+	prefetchMinLead  = 5 * time.Second // Минимальный запас до истечения записи
+)
+
+// startPrefetcher периодически обновляет записи кэша, срок которых скоро
+// истекает, чтобы клиенты не ждали повторного разрешения
+func (s *DNSServer) startPrefetcher() {
+	ticker := time.NewTicker(s.cfg.PrefetchInterval.Duration)
+	defer ticker.Stop()
+
+	for now := range ticker.C {
+		if n := s.prefetch(now); n > 0 {
+			slog.Debug("Упреждающее обновление кэша", "entries", n)
+		}
+	}
+}
+
+// prefetch обновляет истекающие записи, выполняя не более
+// prefetch_concurrency разрешений одновременно, и возвращает число
+// запущенных обновлений
+func (s *DNSServer) prefetch(now time.Time) int {
+	items := s.cache.Expiring(now, s.prefetchLead)
+
+	sem := make(chan struct{}, s.cfg.PrefetchConcurrency)
+	var wg sync.WaitGroup
+	started := 0
+	for _, item := range items {
+		// Запись уже обновляется предыдущим проходом
+		if _, busy := s.prefetching.LoadOrStore(item.key, struct{}{}); busy {
+			continue
+		}
+
+		sem <- struct{}{}
+		wg.Add(1)
+		started++
+		go func(key string, entry *CacheEntry) {
+			defer func() {
+				s.prefetching.Delete(key)
+				<-sem
+				wg.Done()
+			}()
+			qtypeStr := dns.TypeToString[entry.Qtype]
+			s.inflight.Do(key, func() lookupResult {
+				return s.resolve(context.Background(), cacheKey(entry.Name, entry.Qtype), entry.Name, qtypeStr, entry.Qtype, entry.Subnet)
+			})
+		}(item.key, item.entry)
+	}
+	wg.Wait()
+	return started
+}
+
+// prefetchLead возвращает, за сколько до истечения обновлять запись: десятую
+// долю исходного TTL, но не меньше prefetchMinLead и не больше
+// prefetch_window. Короткоживущие записи так успевают обновиться, а
+// долгоживущие не обновляются слишком рано. Для записей без сохраненного
+// TTL используется prefetch_window.
+func (s *DNSServer) prefetchLead(entry *CacheEntry) time.Duration {
+	window := s.cfg.PrefetchWindow.Duration
+	if entry.TTL <= 0 {
+		return window
+	}
+	return min(max(entry.TTL/prefetchFraction, prefetchMinLead), window)
+}
@@ -0,0 +1,119 @@
+package resolver
+
+import (
+	"fmt"
+	"sync/atomic"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+func TestPrefetchLimitsConcurrency(t *testing.T) {
+	const entries, limit = 20, 3
+	var active, maxActive, queries atomic.Int32
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		queries.Add(1)
+		n := active.Add(1)
+		for {
+			m := maxActive.Load()
+			if n <= m || maxActive.CompareAndSwap(m, n) {
+				break
+			}
+		}
+		time.Sleep(20 * time.Millisecond)
+		active.Add(-1)
+
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		rr, _ := dns.NewRR(r.Question[0].Name + " 300 IN A 192.0.2.1")
+		reply.Answer = append(reply.Answer, rr)
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+	s.cfg.PrefetchConcurrency = limit
+	s.cfg.PrefetchWindow = Duration{time.Minute}
+
+	for i := 0; i < entries; i++ {
+		name := fmt.Sprintf("prefetch%d.example.", i)
+		s.cache.Set(cacheKey(name, dns.TypeA), newTestEntry(t, name, 10*time.Second))
+	}
+	// Запись с большим запасом времени не должна обновляться
+	s.cache.Set(cacheKey("fresh.example.", dns.TypeA), newTestEntry(t, "fresh.example.", time.Hour))
+
+	if started := s.prefetch(time.Now()); started != entries {
+		t.Errorf("Ожидалось %d обновлений, запущено %d", entries, started)
+	}
+	if n := queries.Load(); n != entries {
+		t.Errorf("Ожидалось %d запросов к вышестоящему серверу, получено %d", entries, n)
+	}
+	if m := maxActive.Load(); m > limit {
+		t.Errorf("Одновременно выполнялось %d обновлений при лимите %d", m, limit)
+	}
+}
+
+func TestPrefetchSkipsEntriesAlreadyRefreshing(t *testing.T) {
+	s := NewDNSServer()
+	s.forwarders = []string{"127.0.0.1:1"}
+	key := cacheKey("busy.example.", dns.TypeA)
+	s.cache.Set(key, newTestEntry(t, "busy.example.", 10*time.Second))
+	s.prefetching.Store(key, struct{}{})
+
+	if started := s.prefetch(time.Now()); started != 0 {
+		t.Errorf("Обновляемая запись не должна запускаться повторно, запущено %d", started)
+	}
+}
+
+func TestPrefetchLeadFollowsTTL(t *testing.T) {
+	s := NewDNSServer()
+	s.cfg.PrefetchWindow = Duration{time.Minute}
+
+	// Без TTL используется prefetch_window; 1/10 короткого TTL поднимается
+	// до prefetchMinLead, а длинного - ограничивается prefetch_window
+	for _, tc := range []struct {
+		ttl, lead time.Duration
+	}{
+		{0, time.Minute},
+		{30 * time.Second, prefetchMinLead},
+		{5 * time.Minute, 30 * time.Second},
+		{7 * 24 * time.Hour, time.Minute},
+	} {
+		if lead := s.prefetchLead(&CacheEntry{TTL: tc.ttl}); lead != tc.lead {
+			t.Errorf("TTL %v: ожидался запас %v, получено %v", tc.ttl, tc.lead, lead)
+		}
+	}
+}
+
+func TestPrefetchFiresAtFractionOfTTL(t *testing.T) {
+	s := NewDNSServer()
+	tr := &cannedTransport{}
+	s.transport = tr
+	s.forwarders = []string{"192.0.2.53:53"}
+	s.cfg.PrefetchWindow = Duration{time.Minute}
+
+	now := time.Now()
+	add := func(name string, ttl, remaining time.Duration) {
+		entry := newTestEntry(t, name, 0)
+		entry.TTL = ttl
+		entry.ExpiresAt = now.Add(remaining)
+		s.cache.Set(cacheKey(name, dns.TypeA), entry)
+	}
+	// Короткий TTL: обновление за prefetchMinLead до истечения
+	add("short-due.example.", 30*time.Second, 3*time.Second)
+	add("short-early.example.", 30*time.Second, 20*time.Second)
+	// Средний TTL: обновление в последние 10% срока
+	add("medium-due.example.", 5*time.Minute, 20*time.Second)
+	add("medium-early.example.", 5*time.Minute, 45*time.Second)
+	// Длинный TTL: не раньше, чем за prefetch_window
+	add("long-due.example.", 7*24*time.Hour, 30*time.Second)
+	add("long-early.example.", 7*24*time.Hour, 2*time.Hour)
+
+	if started := s.prefetch(now); started != 3 {
+		t.Errorf("Ожидалось 3 обновления, запущено %d", started)
+	}
+	if n := atomic.LoadUint64(&tr.queries); n != 3 {
+		t.Errorf("Ожидалось 3 запроса к вышестоящему серверу, выполнено %d", n)
+	}
+}
@@ -0,0 +1,181 @@
+package resolver
+
+import (
+	"encoding/json"
+	"fmt"
+	"log/slog"
+	"os"
+	"sync"
+	"sync/atomic"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+const (
+	queryLogMaxSize = 100 << 20 // Размер файла журнала запросов до ротации
+	queryLogKeep    = 5         // Сколько ротированных файлов хранится
+	queryLogBuffer  = 4096      // Записей в очереди к фоновой горутине
+)
+
+// queryLogEntry - одна строка журнала запросов
+type queryLogEntry struct {
+	Time     time.Time `json:"time"`
+	Client   string    `json:"client"`
+	Name     string    `json:"qname"`
+	Type     string    `json:"qtype"`
+	Rcode    string    `json:"rcode,omitempty"` // Пусто, если ответ не отправлен
+	Latency  float64   `json:"latency_ms"`
+	CacheHit bool      `json:"cache_hit"`
+}
+
+// QueryLog пишет журнал запросов в файл, по одной JSON-строке на запрос.
+// Записи передаются фоновой горутине через канал, поэтому запись на диск не
+// задерживает обработку запросов; при переполнении очереди записи
+// отбрасываются. Когда файл превышает maxSize, он переименовывается в
+// path.1 (старые копии сдвигаются до path.<keep>) и начинается новый.
+type QueryLog struct {
+	path    string
+	maxSize int64
+	keep    int
+	entries chan queryLogEntry
+	done    chan struct{}
+	mu      sync.RWMutex // Защищает closed от записи в закрытый канал
+	closed  bool
+	file    *os.File
+	size    int64
+	dropped uint64
+}
+
+// OpenQueryLog открывает файл журнала запросов и запускает запись в него
+func OpenQueryLog(path string, maxSize int64, keep int) (*QueryLog, error) {
+	l := &QueryLog{
+		path:    path,
+		maxSize: maxSize,
+		keep:    keep,
+		entries: make(chan queryLogEntry, queryLogBuffer),
+		done:    make(chan struct{}),
+	}
+	if err := l.open(); err != nil {
+		return nil, err
+	}
+	go l.run()
+	return l, nil
+}
+
+// Log ставит запись в очередь, не дожидаясь записи на диск
+func (l *QueryLog) Log(entry queryLogEntry) {
+	if l == nil {
+		return
+	}
+	l.mu.RLock()
+	defer l.mu.RUnlock()
+	if l.closed {
+		return
+	}
+	select {
+	case l.entries <- entry:
+	default:
+		atomic.AddUint64(&l.dropped, 1)
+	}
+}
+
+// Close дописывает записи из очереди и закрывает файл. Записи, переданные
+// после Close, отбрасываются.
+func (l *QueryLog) Close() error {
+	if l == nil {
+		return nil
+	}
+	l.mu.Lock()
+	if l.closed {
+		l.mu.Unlock()
+		return nil
+	}
+	l.closed = true
+	close(l.entries)
+	l.mu.Unlock()
+	<-l.done
+	if n := atomic.LoadUint64(&l.dropped); n > 0 {
+		slog.Warn("Записи журнала запросов отброшены из-за переполнения очереди", "path", l.path, "dropped", n)
+	}
+	return l.file.Close()
+}
+
+// Record ставит в очередь запись об обработанном запросе
+func (l *QueryLog) Record(w *rcodeRecorder, req *dns.Msg, cacheHit bool, elapsed time.Duration) {
+	if l == nil {
+		return
+	}
+	entry := queryLogEntry{
+		Time:     time.Now(),
+		Client:   clientIP(w),
+		Latency:  float64(elapsed) / float64(time.Millisecond),
+		CacheHit: cacheHit,
+	}
+	if len(req.Question) > 0 {
+		entry.Name = req.Question[0].Name
+		entry.Type = dns.TypeToString[req.Question[0].Qtype]
+	}
+	if w.written {
+		entry.Rcode = dns.RcodeToString[w.rcode]
+	}
+	l.Log(entry)
+}
+
+func (l *QueryLog) run() {
+	defer close(l.done)
+	for entry := range l.entries {
+		line, err := json.Marshal(entry)
+		if err != nil {
+			continue
+		}
+		line = append(line, '\n')
+		if l.size > 0 && l.size+int64(len(line)) > l.maxSize {
+			if err := l.rotate(); err != nil {
+				slog.Warn("Не удалось ротировать журнал запросов", "path", l.path, "error", err)
+			}
+		}
+		n, err := l.file.Write(line)
+		l.size += int64(n)
+		if err != nil {
+			slog.Warn("Ошибка записи журнала запросов", "path", l.path, "error", err)
+		}
+	}
+}
+
+// open открывает файл журнала для дозаписи
+func (l *QueryLog) open() error {
+	f, err := os.OpenFile(l.path, os.O_WRONLY|os.O_CREATE|os.O_APPEND, 0o640)
+	if err != nil {
+		return err
+	}
+	info, err := f.Stat()
+	if err != nil {
+		f.Close()
+		return err
+	}
+	l.file = f
+	l.size = info.Size()
+	return nil
+}
+
+// rotate сдвигает ротированные копии, переименовывает текущий файл в path.1
+// и открывает новый. Если переименовать не удалось, запись продолжается в
+// прежний файл.
+func (l *QueryLog) rotate() error {
+	l.file.Close()
+	var err error
+	if l.keep > 0 {
+		os.Remove(fmt.Sprintf("%s.%d", l.path, l.keep))
+		for i := l.keep - 1; i >= 1; i-- {
+			os.Rename(fmt.Sprintf("%s.%d", l.path, i), fmt.Sprintf("%s.%d", l.path, i+1))
+		}
+		err = os.Rename(l.path, l.path+".1")
+	} else {
+		err = os.Remove(l.path)
+	}
+	if oerr := l.open(); oerr != nil {
+		return oerr
+	}
+	return err
+}
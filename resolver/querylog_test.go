@@ -0,0 +1,103 @@
+package resolver
+
+import (
+	"bufio"
+	"encoding/json"
+	"net"
+	"os"
+	"path/filepath"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+func TestQueryLogWritesEntries(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "queries.log")
+	qlog, err := OpenQueryLog(path, queryLogMaxSize, queryLogKeep)
+	if err != nil {
+		t.Fatalf("Не удалось открыть журнал запросов: %v", err)
+	}
+
+	s := NewDNSServer()
+	s.queryLog = qlog
+	s.transport = &cannedTransport{}
+	s.forwarders = []string{"192.0.2.53:53"}
+
+	client := &net.UDPAddr{IP: net.ParseIP("192.0.2.10"), Port: 5353}
+	for _, q := range []struct {
+		name  string
+		qtype uint16
+	}{
+		{"example.com.", dns.TypeA},
+		{"example.com.", dns.TypeA},
+		{"example.com.", dns.TypeMX},
+	} {
+		req := new(dns.Msg)
+		req.SetQuestion(q.name, q.qtype)
+		s.handleRequest(&testResponseWriter{remote: client}, req)
+	}
+	if err := qlog.Close(); err != nil {
+		t.Fatalf("Ошибка закрытия журнала: %v", err)
+	}
+
+	f, err := os.Open(path)
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer f.Close()
+	var entries []queryLogEntry
+	scanner := bufio.NewScanner(f)
+	for scanner.Scan() {
+		var e queryLogEntry
+		if err := json.Unmarshal(scanner.Bytes(), &e); err != nil {
+			t.Fatalf("Некорректная строка журнала %q: %v", scanner.Text(), err)
+		}
+		entries = append(entries, e)
+	}
+
+	if len(entries) != 3 {
+		t.Fatalf("Ожидалось 3 записи в журнале, получено %d", len(entries))
+	}
+	for i, e := range entries {
+		if e.Client != "192.0.2.10" || e.Name != "example.com." || e.Rcode != "NOERROR" {
+			t.Errorf("Запись %d: неверные поля %+v", i, e)
+		}
+		if e.Time.IsZero() || time.Since(e.Time) > time.Minute || e.Latency < 0 {
+			t.Errorf("Запись %d: неверное время или задержка %+v", i, e)
+		}
+	}
+	if entries[0].Type != "A" || entries[2].Type != "MX" {
+		t.Errorf("Неверные типы запросов: %s, %s", entries[0].Type, entries[2].Type)
+	}
+	if entries[0].CacheHit || !entries[1].CacheHit {
+		t.Errorf("Повторный запрос должен отмечаться как ответ из кэша: %+v", entries[:2])
+	}
+}
+
+func TestQueryLogRotates(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "queries.log")
+	qlog, err := OpenQueryLog(path, 200, 2)
+	if err != nil {
+		t.Fatalf("Не удалось открыть журнал запросов: %v", err)
+	}
+	for i := 0; i < 20; i++ {
+		qlog.Log(queryLogEntry{Time: time.Now(), Client: "192.0.2.10", Name: "example.com.", Type: "A", Rcode: "NOERROR"})
+	}
+	if err := qlog.Close(); err != nil {
+		t.Fatalf("Ошибка закрытия журнала: %v", err)
+	}
+
+	for _, name := range []string{path, path + ".1", path + ".2"} {
+		info, err := os.Stat(name)
+		if err != nil {
+			t.Fatalf("Ожидался файл %s: %v", name, err)
+		}
+		if info.Size() > 200 {
+			t.Errorf("Размер %s превышает лимит: %d", name, info.Size())
+		}
+	}
+	if _, err := os.Stat(path + ".3"); !os.IsNotExist(err) {
+		t.Errorf("Хранится больше query_log_keep ротированных файлов")
+	}
+}
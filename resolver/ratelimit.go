@@ -0,0 +1,98 @@
+package resolver
+
+import (
+	"net"
+	"sync"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// tokenBucket - корзина токенов одного клиента
+type tokenBucket struct {
+	mu       sync.Mutex
+	tokens   float64
+	lastSeen time.Time
+}
+
+// RateLimiter ограничивает частоту запросов от каждого IP-адреса клиента
+type RateLimiter struct {
+	rate    float64  // токенов в секунду
+	burst   float64  // максимальный размер корзины
+	buckets sync.Map // map[string]*tokenBucket
+}
+
+// NewRateLimiter создает ограничитель; rate <= 0 отключает ограничение
+func NewRateLimiter(rate float64, burst int) *RateLimiter {
+	return &RateLimiter{rate: rate, burst: float64(burst)}
+}
+
+// Allow сообщает, может ли клиент выполнить ещё один запрос
+func (l *RateLimiter) Allow(ip string) bool {
+	return l.allowAt(ip, time.Now())
+}
+
+func (l *RateLimiter) allowAt(ip string, now time.Time) bool {
+	if l == nil || l.rate <= 0 {
+		return true
+	}
+
+	value, _ := l.buckets.LoadOrStore(ip, &tokenBucket{tokens: l.burst, lastSeen: now})
+	bucket := value.(*tokenBucket)
+
+	bucket.mu.Lock()
+	defer bucket.mu.Unlock()
+
+	if elapsed := now.Sub(bucket.lastSeen).Seconds(); elapsed > 0 {
+		bucket.tokens += elapsed * l.rate
+		if bucket.tokens > l.burst {
+			bucket.tokens = l.burst
+		}
+	}
+	bucket.lastSeen = now
+
+	if bucket.tokens < 1 {
+		return false
+	}
+	bucket.tokens--
+	return true
+}
+
+// Cleanup удаляет корзины клиентов, неактивных дольше idle
+func (l *RateLimiter) Cleanup(now time.Time, idle time.Duration) int {
+	if l == nil {
+		return 0
+	}
+
+	removed := 0
+	l.buckets.Range(func(key, value interface{}) bool {
+		bucket := value.(*tokenBucket)
+		bucket.mu.Lock()
+		lastSeen := bucket.lastSeen
+		bucket.mu.Unlock()
+		if now.Sub(lastSeen) > idle {
+			l.buckets.Delete(key)
+			removed++
+		}
+		return true
+	})
+	return removed
+}
+
+// clientIP возвращает IP-адрес клиента без порта
+func clientIP(w dns.ResponseWriter) string {
+	switch addr := w.RemoteAddr().(type) {
+	case *net.UDPAddr:
+		return addr.IP.String()
+	case *net.TCPAddr:
+		return addr.IP.String()
+	case nil:
+		return ""
+	default:
+		host, _, err := net.SplitHostPort(addr.String())
+		if err != nil {
+			return addr.String()
+		}
+		return host
+	}
+}
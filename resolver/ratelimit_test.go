@@ -0,0 +1,60 @@
+package resolver
+
+import (
+	"net"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+func TestRateLimiterBurst(t *testing.T) {
+	limiter := NewRateLimiter(1, 5)
+	now := time.Now()
+
+	for i := 0; i < 5; i++ {
+		if !limiter.allowAt("192.0.2.1", now) {
+			t.Fatalf("Запрос %d в пределах лимита был отклонен", i+1)
+		}
+	}
+	if limiter.allowAt("192.0.2.1", now) {
+		t.Errorf("Запрос сверх лимита должен быть отклонен")
+	}
+
+	if !limiter.allowAt("192.0.2.2", now) {
+		t.Errorf("Другой клиент не должен зависеть от чужого лимита")
+	}
+
+	if !limiter.allowAt("192.0.2.1", now.Add(time.Second)) {
+		t.Errorf("Через секунду корзина должна пополниться")
+	}
+}
+
+func TestRateLimiterCleanup(t *testing.T) {
+	limiter := NewRateLimiter(1, 5)
+	now := time.Now()
+	limiter.allowAt("192.0.2.1", now.Add(-time.Hour))
+	limiter.allowAt("192.0.2.2", now)
+
+	if removed := limiter.Cleanup(now, time.Minute); removed != 1 {
+		t.Errorf("Ожидалось удаление 1 корзины, удалено %d", removed)
+	}
+}
+
+func TestHandleRequestRefusesRateLimitedClient(t *testing.T) {
+	s := NewDNSServer()
+	s.rateLimiter = NewRateLimiter(1, 2)
+	client := &net.UDPAddr{IP: net.ParseIP("198.51.100.7"), Port: 53000}
+	for i := 0; i < 2; i++ {
+		s.rateLimiter.Allow("198.51.100.7")
+	}
+
+	req := new(dns.Msg)
+	req.SetQuestion("example.com.", dns.TypeA)
+	w := &testResponseWriter{remote: client}
+	s.handleRequest(w, req)
+
+	if w.msg == nil || w.msg.Rcode != dns.RcodeRefused {
+		t.Errorf("Ожидался REFUSED для клиента, превысившего лимит, получено %v", w.msg)
+	}
+}
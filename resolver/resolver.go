@@ -0,0 +1,127 @@
+// Package resolver реализует рекурсивный DNS-резолвер с кэшем, проверкой
+// DNSSEC и серверами UDP, DNS-over-TLS и DNS-over-HTTPS. Resolver позволяет
+// встраивать разрешение имен в другие программы без запуска сервера.
+package resolver
+
+import (
+	"context"
+	"errors"
+
+	"github.com/miekg/dns"
+)
+
+// Error - ошибка разрешения с кодом ответа DNS, который получил бы клиент
+// сервера в той же ситуации
+type Error struct {
+	Rcode int
+	msg   string
+}
+
+func (e *Error) Error() string {
+	return e.msg
+}
+
+var (
+	// ErrNXDOMAIN - имя не существует
+	ErrNXDOMAIN = &Error{Rcode: dns.RcodeNameError, msg: "имя не существует (NXDOMAIN)"}
+	// ErrServFail - не удалось получить ответ от вышестоящих серверов
+	ErrServFail = &Error{Rcode: dns.RcodeServerFailure, msg: "не удалось разрешить имя (SERVFAIL)"}
+	// ErrBlocked - имя заблокировано списком фильтрации
+	ErrBlocked = &Error{Rcode: dns.RcodeNameError, msg: "имя заблокировано списком фильтрации"}
+	// ErrRefused - запрос отклонен политикой сервера
+	ErrRefused = &Error{Rcode: dns.RcodeRefused, msg: "запрос отклонен (REFUSED)"}
+)
+
+// Rcode возвращает код ответа DNS для ошибки Resolve: NOERROR для nil и
+// SERVFAIL для ошибок без кода (например, отмены контекста)
+func Rcode(err error) int {
+	if err == nil {
+		return dns.RcodeSuccess
+	}
+	var e *Error
+	if errors.As(err, &e) {
+		return e.Rcode
+	}
+	return dns.RcodeServerFailure
+}
+
+// Resolver разрешает имена с использованием локальных зон, hosts-файла,
+// списка блокировки, кэша, DNS64 и пересылки или рекурсии согласно
+// конфигурации
+type Resolver struct {
+	server *DNSServer
+}
+
+// New создает резолвер с заданной конфигурацией или возвращает ошибку,
+// если конфигурация некорректна. Сетевые серверы не запускаются; фоновые
+// задачи кэша не используются.
+func New(cfg Config) (*Resolver, error) {
+	server, err := NewDNSServerWithConfig(cfg)
+	if err != nil {
+		return nil, err
+	}
+	return &Resolver{server: server}, nil
+}
+
+// Resolve возвращает записи типа qtype для имени name, следуя цепочкам
+// CNAME. Запрос проходит тот же путь, что и запрос клиента сервера:
+// локальные зоны, hosts-файл, список блокировки (в том числе режим
+// blocklist_sinkhole), кэш и разрешение, DNS64. Пустой результат без ошибки
+// означает, что имя существует, но записей этого типа у него нет. Отмена
+// ctx прерывает разрешение.
+func (r *Resolver) Resolve(ctx context.Context, name string, qtype uint16) ([]dns.RR, error) {
+	if err := ctx.Err(); err != nil {
+		return nil, err
+	}
+
+	// EDNS нужен, чтобы ответ на заблокированное имя содержал код
+	// Blocked (RFC 8914) и отличался от обычного NXDOMAIN
+	req := new(dns.Msg)
+	req.SetQuestion(dns.Fqdn(name), qtype)
+	req.SetEdns0(maxUDPSize, false)
+
+	w := &messageWriter{}
+	r.server.answer(ctx, w, req)
+	if err := ctx.Err(); err != nil {
+		return nil, err
+	}
+	if w.msg == nil {
+		return nil, ErrServFail
+	}
+	return w.msg.Answer, replyError(w.msg)
+}
+
+// replyError преобразует код ответа сервера в ошибку Resolve
+func replyError(reply *dns.Msg) error {
+	switch reply.Rcode {
+	case dns.RcodeSuccess:
+		return nil
+	case dns.RcodeNameError:
+		if hasEDE(reply, dns.ExtendedErrorCodeBlocked) {
+			return ErrBlocked
+		}
+		if len(reply.Answer) > 0 {
+			return nil
+		}
+		return ErrNXDOMAIN
+	case dns.RcodeServerFailure:
+		return ErrServFail
+	case dns.RcodeRefused:
+		return ErrRefused
+	}
+	return &Error{Rcode: reply.Rcode, msg: "запрос не выполнен (" + dns.RcodeToString[reply.Rcode] + ")"}
+}
+
+// hasEDE сообщает, содержит ли ответ расширенный код ошибки code
+func hasEDE(reply *dns.Msg, code uint16) bool {
+	opt := reply.IsEdns0()
+	if opt == nil {
+		return false
+	}
+	for _, o := range opt.Option {
+		if ede, ok := o.(*dns.EDNS0_EDE); ok && ede.InfoCode == code {
+			return true
+		}
+	}
+	return false
+}
@@ -0,0 +1,98 @@
+package resolver
+
+import (
+	"context"
+	"errors"
+	"os"
+	"path/filepath"
+	"strings"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+func TestResolveUsesServerPipeline(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "corp.zone")
+	if err := os.WriteFile(path, []byte(sampleZone), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	upstream := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		if r.Question[0].Qtype == dns.TypeA {
+			rr, _ := dns.NewRR(r.Question[0].Name + " 120 IN A 192.0.2.33")
+			reply.Answer = append(reply.Answer, rr)
+		}
+		w.WriteMsg(reply)
+	})
+
+	cfg := DefaultConfig()
+	cfg.CacheFile = ""
+	cfg.LocalZones = map[string]string{"corp.example": path}
+	cfg.DNS64 = true
+	r, err := New(cfg)
+	if err != nil {
+		t.Fatalf("Ошибка создания резолвера: %v", err)
+	}
+	r.server.forwarders = []string{upstream}
+	r.server.blocklist, _ = parseBlocklist(strings.NewReader(sampleBlocklist), true, false)
+	ctx := context.Background()
+
+	records, err := r.Resolve(ctx, "ns1.corp.example", dns.TypeA)
+	if err != nil || len(records) != 1 {
+		t.Fatalf("Ожидалась A-запись из локальной зоны, получено %v (%v)", records, err)
+	}
+	if _, err := r.Resolve(ctx, "missing.corp.example", dns.TypeA); !errors.Is(err, ErrNXDOMAIN) {
+		t.Errorf("Для имени вне локальной зоны ожидался ErrNXDOMAIN, получено %v", err)
+	}
+
+	records, err = r.Resolve(ctx, "v4only.example", dns.TypeAAAA)
+	if err != nil || len(records) != 1 {
+		t.Fatalf("Ожидалась синтезированная DNS64 AAAA-запись, получено %v (%v)", records, err)
+	}
+	if aaaa, ok := records[0].(*dns.AAAA); !ok || aaaa.AAAA.String() != "64:ff9b::c000:221" {
+		t.Errorf("Ожидалась AAAA 64:ff9b::c000:221, получено %v", records[0])
+	}
+
+	if _, err := r.Resolve(ctx, "ads.example.com", dns.TypeA); !errors.Is(err, ErrBlocked) {
+		t.Errorf("Для заблокированного имени ожидался ErrBlocked, получено %v", err)
+	}
+	r.server.blocklist.sinkhole = true
+	records, err = r.Resolve(ctx, "ads.example.com", dns.TypeA)
+	if err != nil || len(records) != 1 {
+		t.Fatalf("В режиме sinkhole ожидалась запись 0.0.0.0, получено %v (%v)", records, err)
+	}
+	if a, ok := records[0].(*dns.A); !ok || !a.A.Equal([]byte{0, 0, 0, 0}) {
+		t.Errorf("Ожидался ответ 0.0.0.0, получено %v", records[0])
+	}
+}
+
+func TestResolveCancelsResolution(t *testing.T) {
+	// Вышестоящий сервер не отвечает: разрешение ждало бы таймаута
+	upstream := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {})
+
+	cfg := DefaultConfig()
+	cfg.CacheFile = ""
+	r, err := New(cfg)
+	if err != nil {
+		t.Fatalf("Ошибка создания резолвера: %v", err)
+	}
+	r.server.forwarders = []string{upstream}
+
+	ctx, cancel := context.WithTimeout(context.Background(), 50*time.Millisecond)
+	defer cancel()
+	start := time.Now()
+	_, err = r.Resolve(ctx, "slow.example", dns.TypeA)
+	if !errors.Is(err, context.DeadlineExceeded) {
+		t.Fatalf("Ожидалась ошибка истечения контекста, получено %v", err)
+	}
+	if elapsed := time.Since(start); elapsed > time.Second {
+		t.Errorf("Разрешение не прервано отменой контекста: %v", elapsed)
+	}
+
+	// Прерванное разрешение не должно попадать в кэш сбоев
+	if _, ok := r.server.servfailCache.Load(cacheKey("slow.example.", dns.TypeA)); ok {
+		t.Errorf("Отмененное разрешение сохранено в кэше SERVFAIL")
+	}
+}
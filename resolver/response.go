@@ -0,0 +1,64 @@
+package resolver
+
+import (
+	"github.com/miekg/dns"
+)
+
+// responseSize возвращает максимальный размер UDP-ответа клиенту: 512 байт
+// без EDNS или объявленный клиентом размер буфера в пределах maxSize
+func responseSize(req *dns.Msg, maxSize uint16) uint16 {
+	size := uint16(dns.MinMsgSize)
+	if edns0 := req.IsEdns0(); edns0 != nil {
+		size = edns0.UDPSize()
+		if size < dns.MinMsgSize {
+			size = dns.MinMsgSize
+		}
+		if size > maxSize {
+			size = maxSize
+		}
+	}
+	return size
+}
+
+// truncatingWriter сжимает имена во всех ответах (RFC 1035, 4.1.4) и
+// обрезает UDP-ответы до размера буфера клиента, устанавливая флаг TC,
+// чтобы клиент повторил запрос по TCP
+type truncatingWriter struct {
+	dns.ResponseWriter
+	size uint16
+}
+
+func (w *truncatingWriter) WriteMsg(m *dns.Msg) error {
+	m.Compress = true
+	if w.Network() == "udp" {
+		m.Truncate(int(w.size))
+	}
+	return w.ResponseWriter.WriteMsg(m)
+}
+
+// isDNSSECType сообщает, относится ли тип записи к DNSSEC
+func isDNSSECType(rrtype uint16) bool {
+	switch rrtype {
+	case dns.TypeRRSIG, dns.TypeNSEC, dns.TypeNSEC3, dns.TypeDNSKEY, dns.TypeDS:
+		return true
+	}
+	return false
+}
+
+// stripDNSSEC убирает записи DNSSEC из ответа клиенту, не установившему
+// флаг DO (RFC 4035, 3.2.1). Записи запрошенного типа остаются: клиент,
+// явно спросивший DNSKEY или DS, должен их получить.
+func stripDNSSEC(m *dns.Msg, qtype uint16) {
+	filter := func(records []dns.RR) []dns.RR {
+		kept := records[:0]
+		for _, rr := range records {
+			if rrtype := rr.Header().Rrtype; !isDNSSECType(rrtype) || rrtype == qtype {
+				kept = append(kept, rr)
+			}
+		}
+		return kept
+	}
+	m.Answer = filter(m.Answer)
+	m.Ns = filter(m.Ns)
+	m.Extra = filter(m.Extra)
+}
@@ -0,0 +1,186 @@
+package resolver
+
+import (
+	"fmt"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// startLargeAnswerUpstream отвечает на любой вопрос 60 A-записями (больше 512 байт)
+func startLargeAnswerUpstream(t *testing.T) string {
+	return startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		for i := 0; i < 60; i++ {
+			rr, _ := dns.NewRR(fmt.Sprintf("%s 300 IN A 192.0.2.%d", r.Question[0].Name, i+1))
+			reply.Answer = append(reply.Answer, rr)
+		}
+		w.WriteMsg(reply)
+	})
+}
+
+func TestResponseSizeFollowsClientBuffer(t *testing.T) {
+	s := NewDNSServer()
+	s.forwarders = []string{startLargeAnswerUpstream(t)}
+
+	req := new(dns.Msg)
+	req.SetQuestion("large.example.", dns.TypeA)
+	req.SetEdns0(4096, false)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil {
+		t.Fatalf("Ответ не получен")
+	}
+	if w.msg.Truncated || len(w.msg.Answer) != 60 {
+		t.Errorf("Клиент с буфером 4096 должен получить полный ответ, TC=%v, записей %d", w.msg.Truncated, len(w.msg.Answer))
+	}
+	if size := w.msg.Len(); size <= 512 {
+		t.Errorf("Ожидался ответ больше 512 байт, получено %d", size)
+	}
+
+	plain := new(dns.Msg)
+	plain.SetQuestion("large.example.", dns.TypeA)
+	w = &testResponseWriter{}
+	s.handleRequest(w, plain)
+
+	if w.msg == nil || !w.msg.Truncated {
+		t.Fatalf("Ответ клиенту без EDNS должен быть обрезан с флагом TC")
+	}
+	if size := w.msg.Len(); size > 512 {
+		t.Errorf("Ответ клиенту без EDNS превышает 512 байт: %d", size)
+	}
+}
+
+func TestReplyDOFollowsRequest(t *testing.T) {
+	s := NewDNSServer()
+	s.forwarders = []string{startLargeAnswerUpstream(t)}
+	s.dnssecEnabled = false
+
+	for _, do := range []bool{false, true} {
+		req := new(dns.Msg)
+		req.SetQuestion("large.example.", dns.TypeA)
+		req.SetEdns0(1232, do)
+		w := &testResponseWriter{}
+		s.handleRequest(w, req)
+
+		if w.msg == nil || w.msg.IsEdns0() == nil {
+			t.Fatalf("DO=%v: ответ клиенту с EDNS должен содержать OPT", do)
+		}
+		if opt := w.msg.IsEdns0(); opt.Do() != do {
+			t.Errorf("Флаг DO ответа %v, в запросе %v", opt.Do(), do)
+		}
+		if size := w.msg.IsEdns0().UDPSize(); size != 1232 {
+			t.Errorf("DO=%v: объявлен буфер %d, ожидалось 1232", do, size)
+		}
+	}
+}
+
+func TestResponseSize(t *testing.T) {
+	tests := []struct {
+		edns uint16 // 0 - запрос без EDNS
+		want uint16
+	}{
+		{0, 512},
+		{256, 512},
+		{1232, 1232},
+		{65000, 4096},
+	}
+	for _, tt := range tests {
+		req := new(dns.Msg)
+		req.SetQuestion("example.com.", dns.TypeA)
+		if tt.edns > 0 {
+			req.SetEdns0(tt.edns, false)
+		}
+		if got := responseSize(req, 4096); got != tt.want {
+			t.Errorf("responseSize(EDNS %d) = %d, ожидалось %d", tt.edns, got, tt.want)
+		}
+	}
+}
+
+func TestResponsesAreCompressed(t *testing.T) {
+	req := new(dns.Msg)
+	req.SetQuestion("host1.sub.example.com.", dns.TypeA)
+	reply := new(dns.Msg)
+	reply.SetReply(req)
+	for i := 1; i <= 10; i++ {
+		rr, _ := dns.NewRR(fmt.Sprintf("host%d.sub.example.com. 300 IN A 192.0.2.%d", i, i))
+		reply.Answer = append(reply.Answer, rr)
+	}
+
+	tw := &testResponseWriter{}
+	w := &truncatingWriter{ResponseWriter: tw, size: dns.MaxMsgSize}
+	if err := w.WriteMsg(reply); err != nil {
+		t.Fatalf("Ошибка записи: %v", err)
+	}
+
+	compressed := tw.msg.Len()
+	plain := tw.msg.Copy()
+	plain.Compress = false
+	uncompressed := plain.Len()
+	if !tw.msg.Compress || compressed*3 > uncompressed*2 {
+		t.Errorf("Ответ должен сжиматься: %d байт со сжатием, %d без", compressed, uncompressed)
+	}
+}
+
+func TestDNSSECRecordsOnlyForDOClients(t *testing.T) {
+	s := NewDNSServer()
+	// Подпись в тесте не настоящая: проверяется только состав ответа
+	s.dnssecEnabled = false
+	s.cache.Set(cacheKey("signed.example.", dns.TypeA), &CacheEntry{
+		Name:  "signed.example.",
+		Qtype: dns.TypeA,
+		Records: []dns.RR{
+			mustRR(t, "signed.example. 300 IN A 192.0.2.1"),
+			mustRR(t, "signed.example. 300 IN RRSIG A 8 2 300 20300101000000 20200101000000 12345 example. AAAA"),
+		},
+		ExpiresAt: time.Now().Add(time.Minute),
+	})
+
+	query := func(do bool) *dns.Msg {
+		req := new(dns.Msg)
+		req.SetQuestion("signed.example.", dns.TypeA)
+		req.SetEdns0(1232, do)
+		w := &testResponseWriter{}
+		s.handleRequest(w, req)
+		if w.msg == nil {
+			t.Fatal("Ответ не получен")
+		}
+		return w.msg
+	}
+	countRRSIG := func(m *dns.Msg) int {
+		n := 0
+		for _, rr := range m.Answer {
+			if rr.Header().Rrtype == dns.TypeRRSIG {
+				n++
+			}
+		}
+		return n
+	}
+
+	if resp := query(false); countRRSIG(resp) != 0 || len(resp.Answer) != 1 {
+		t.Errorf("Клиент без DO не должен получать RRSIG, ответ: %v", resp.Answer)
+	}
+	if resp := query(true); countRRSIG(resp) != 1 {
+		t.Errorf("Клиент с DO должен получать RRSIG, ответ: %v", resp.Answer)
+	}
+}
+
+func TestStripDNSSECKeepsRequestedType(t *testing.T) {
+	m := new(dns.Msg)
+	m.Answer = []dns.RR{
+		mustRR(t, "example. 300 IN DNSKEY 257 3 8 AwEAAQ=="),
+		mustRR(t, "example. 300 IN RRSIG DNSKEY 8 1 300 20300101000000 20200101000000 12345 example. AAAA"),
+	}
+	m.Ns = []dns.RR{mustRR(t, "example. 300 IN NSEC a.example. A RRSIG NSEC")}
+
+	stripDNSSEC(m, dns.TypeDNSKEY)
+	if len(m.Answer) != 1 || m.Answer[0].Header().Rrtype != dns.TypeDNSKEY {
+		t.Errorf("Запрошенный тип DNSKEY должен остаться, получено %v", m.Answer)
+	}
+	if len(m.Ns) != 0 {
+		t.Errorf("NSEC должна убираться, получено %v", m.Ns)
+	}
+}
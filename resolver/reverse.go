@@ -0,0 +1,124 @@
+package resolver
+
+import (
+	"net"
+	"strconv"
+	"strings"
+
+	"github.com/miekg/dns"
+)
+
+const (
+	reverseV4Suffix = "in-addr.arpa."
+	reverseV6Suffix = "ip6.arpa."
+)
+
+// parseReverseName возвращает адрес, которому соответствует полное имя
+// в зоне in-addr.arpa или ip6.arpa
+func parseReverseName(name string) (net.IP, bool) {
+	labels, suffix := reverseLabels(name)
+	switch suffix {
+	case reverseV4Suffix:
+		if len(labels) != 4 {
+			return nil, false
+		}
+		ip := make(net.IP, 0, net.IPv4len)
+		for i := len(labels) - 1; i >= 0; i-- {
+			octet, ok := parseOctet(labels[i])
+			if !ok {
+				return nil, false
+			}
+			ip = append(ip, octet)
+		}
+		return net.IP(ip).To16(), true
+	case reverseV6Suffix:
+		if len(labels) != 32 {
+			return nil, false
+		}
+		ip := make(net.IP, net.IPv6len)
+		for i, label := range labels {
+			nibble, ok := parseNibble(label)
+			if !ok {
+				return nil, false
+			}
+			// Метки идут от младшего полубайта к старшему
+			pos := 31 - i
+			if pos%2 == 0 {
+				ip[pos/2] |= nibble << 4
+			} else {
+				ip[pos/2] |= nibble
+			}
+		}
+		return ip, true
+	}
+	return nil, false
+}
+
+// validReverseName проверяет, что имя в обратной зоне может соответствовать
+// адресу или сети. Имена вне обратных зон считаются корректными.
+func validReverseName(name string) bool {
+	labels, suffix := reverseLabels(name)
+	switch suffix {
+	case reverseV4Suffix:
+		for _, label := range labels {
+			// Бесклассовое делегирование (RFC 2317) использует метки вида 0/25 или 0-25
+			if strings.ContainsAny(label, "/-") {
+				return true
+			}
+		}
+		if len(labels) > 4 {
+			return false
+		}
+		for _, label := range labels {
+			if _, ok := parseOctet(label); !ok {
+				return false
+			}
+		}
+	case reverseV6Suffix:
+		if len(labels) > 32 {
+			return false
+		}
+		for _, label := range labels {
+			if _, ok := parseNibble(label); !ok {
+				return false
+			}
+		}
+	}
+	return true
+}
+
+// reverseLabels разделяет имя на метки перед суффиксом обратной зоны
+func reverseLabels(name string) ([]string, string) {
+	name = strings.ToLower(dns.Fqdn(name))
+	for _, suffix := range []string{reverseV4Suffix, reverseV6Suffix} {
+		if name == suffix {
+			return nil, suffix
+		}
+		if strings.HasSuffix(name, "."+suffix) {
+			return dns.SplitDomainName(strings.TrimSuffix(name, "."+suffix)), suffix
+		}
+	}
+	return nil, ""
+}
+
+func parseOctet(label string) (byte, bool) {
+	if label == "" || len(label) > 3 || (len(label) > 1 && label[0] == '0') {
+		return 0, false
+	}
+	n, err := strconv.Atoi(label)
+	if err != nil || n < 0 || n > 255 {
+		return 0, false
+	}
+	return byte(n), true
+}
+
+func parseNibble(label string) (byte, bool) {
+	if len(label) != 1 {
+		return 0, false
+	}
+	n, err := strconv.ParseUint(label, 16, 4)
+	if err != nil {
+		return 0, false
+	}
+	return byte(n), true
+}
@@ -0,0 +1,105 @@
+package resolver
+
+import (
+	"strings"
+	"testing"
+
+	"github.com/miekg/dns"
+)
+
+func TestParseReverseName(t *testing.T) {
+	tests := []struct {
+		name string
+		want string
+	}{
+		{"8.8.8.8.in-addr.arpa.", "8.8.8.8"},
+		{"1.2.0.192.IN-ADDR.ARPA", "192.0.2.1"},
+		{"b.a.9.8.7.6.5.0.4.0.0.0.3.0.0.0.2.0.0.0.1.0.0.0.0.0.0.0.1.2.3.4.ip6.arpa.", "4321:0:1:2:3:4:567:89ab"},
+	}
+	for _, tt := range tests {
+		ip, ok := parseReverseName(tt.name)
+		if !ok || ip.String() != tt.want {
+			t.Errorf("parseReverseName(%s) = %v, ожидалось %s", tt.name, ip, tt.want)
+		}
+	}
+
+	for _, name := range []string{"2.0.192.in-addr.arpa.", "256.2.0.192.in-addr.arpa.", "example.com.", "1.ip6.arpa."} {
+		if ip, ok := parseReverseName(name); ok {
+			t.Errorf("parseReverseName(%s) = %v, ожидалась ошибка", name, ip)
+		}
+	}
+}
+
+func TestValidReverseName(t *testing.T) {
+	valid := []string{
+		"in-addr.arpa.",
+		"10.in-addr.arpa.",
+		"8.8.8.8.in-addr.arpa.",
+		"1.0/25.2.0.192.in-addr.arpa.", // RFC 2317
+		"8.b.d.0.1.0.0.2.ip6.arpa.",
+		"www.example.com.",
+	}
+	for _, name := range valid {
+		if !validReverseName(name) {
+			t.Errorf("Имя %s должно считаться корректным", name)
+		}
+	}
+
+	invalid := []string{
+		"foo.in-addr.arpa.",
+		"1.2.3.4.5.in-addr.arpa.",
+		"01.2.0.192.in-addr.arpa.",
+		"ab.ip6.arpa.",
+		strings.Repeat("0.", 33) + "ip6.arpa.",
+	}
+	for _, name := range invalid {
+		if validReverseName(name) {
+			t.Errorf("Имя %s должно считаться некорректным", name)
+		}
+	}
+}
+
+func TestPTRQueryForwardedAndCached(t *testing.T) {
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		if r.Question[0].Qtype == dns.TypePTR && r.Question[0].Name == "8.8.8.8.in-addr.arpa." {
+			rr, _ := dns.NewRR("8.8.8.8.in-addr.arpa. 3600 IN PTR dns.google.")
+			reply.Answer = append(reply.Answer, rr)
+		}
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+
+	req := new(dns.Msg)
+	req.SetQuestion("8.8.8.8.in-addr.arpa.", dns.TypePTR)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil || len(w.msg.Answer) != 1 {
+		t.Fatalf("Ожидалась одна PTR-запись, получено %v", w.msg)
+	}
+	if ptr, ok := w.msg.Answer[0].(*dns.PTR); !ok || ptr.Ptr != "dns.google." {
+		t.Errorf("Неверная PTR-запись: %v", w.msg.Answer[0])
+	}
+
+	if _, ok := s.cache.Get(cacheKey("8.8.8.8.IN-ADDR.ARPA", dns.TypePTR)); !ok {
+		t.Errorf("PTR-ответ должен находиться в кэше независимо от регистра имени")
+	}
+}
+
+func TestInvalidReverseNameRejectedLocally(t *testing.T) {
+	s := NewDNSServer()
+	s.forwarders = []string{"127.0.0.1:1"}
+
+	req := new(dns.Msg)
+	req.SetQuestion("not-an-address.in-addr.arpa.", dns.TypePTR)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil || w.msg.Rcode != dns.RcodeNameError {
+		t.Errorf("Ожидался NXDOMAIN для некорректного обратного имени, получено %v", w.msg)
+	}
+}
@@ -0,0 +1,78 @@
+package resolver
+
+import (
+	"bufio"
+	"fmt"
+	"net"
+	"os"
+	"strings"
+)
+
+// defaultRootServers - встроенные адреса корневых серверов (a-m.root-servers.net)
+var defaultRootServers = []string{
+	"198.41.0.4", "2001:503:ba3e::2:30",
+	"170.247.170.2", "2801:1b8:10::b",
+	"192.33.4.12", "2001:500:2::c",
+	"199.7.91.13", "2001:500:2d::d",
+	"192.203.230.10", "2001:500:a8::e",
+	"192.5.5.241", "2001:500:2f::f",
+	"192.112.36.4", "2001:500:12::d0d",
+	"198.97.190.53", "2001:500:1::53",
+	"192.36.148.17", "2001:7fe::53",
+	"192.58.128.30", "2001:503:c27::2:30",
+	"193.0.14.129", "2001:7fd::1",
+	"199.7.83.42", "2001:500:9f::42",
+	"202.12.27.33", "2001:dc3::35",
+}
+
+// loadRootHints загружает адреса корневых серверов из файла в формате
+// named.root или из списка IP-адресов по одному в строке
+func loadRootHints(path string) ([]string, error) {
+	f, err := os.Open(path)
+	if err != nil {
+		return nil, err
+	}
+	defer f.Close()
+
+	return parseRootHints(bufio.NewScanner(f))
+}
+
+// parseRootHints извлекает адреса из A/AAAA записей и строк с одиночным IP
+func parseRootHints(scanner *bufio.Scanner) ([]string, error) {
+	var roots []string
+	for scanner.Scan() {
+		line := scanner.Text()
+		if idx := strings.Index(line, ";"); idx >= 0 {
+			line = line[:idx]
+		}
+
+		fields := strings.Fields(line)
+		var candidate string
+		switch {
+		case len(fields) == 1:
+			candidate = fields[0]
+		case len(fields) >= 3:
+			rrType := strings.ToUpper(fields[len(fields)-2])
+			if rrType == "A" || rrType == "AAAA" {
+				candidate = fields[len(fields)-1]
+			}
+		}
+		if candidate == "" {
+			continue
+		}
+
+		ip := net.ParseIP(candidate)
+		if ip == nil {
+			return nil, fmt.Errorf("некорректный адрес корневого сервера: %s", candidate)
+		}
+		roots = append(roots, ip.String())
+	}
+	if err := scanner.Err(); err != nil {
+		return nil, err
+	}
+
+	if len(roots) == 0 {
+		return nil, fmt.Errorf("адреса корневых серверов не найдены")
+	}
+	return roots, nil
+}
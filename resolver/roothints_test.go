@@ -0,0 +1,101 @@
+package resolver
+
+import (
+	"bufio"
+	"net"
+	"os"
+	"path/filepath"
+	"strings"
+	"sync/atomic"
+	"testing"
+
+	"github.com/miekg/dns"
+)
+
+const sampleRootHints = `;       This file holds the information on root name servers needed to
+;       initialize cache of Internet domain name servers
+;
+.                        3600000      NS    A.ROOT-SERVERS.NET.
+A.ROOT-SERVERS.NET.      3600000      A     198.41.0.4
+A.ROOT-SERVERS.NET.      3600000      AAAA  2001:503:ba3e::2:30
+;
+; FORMERLY NS1.ISI.EDU
+;
+.                        3600000      NS    B.ROOT-SERVERS.NET.
+B.ROOT-SERVERS.NET.      3600000      A     170.247.170.2
+; End of file
+`
+
+func TestParseRootHintsNamedRoot(t *testing.T) {
+	roots, err := parseRootHints(bufio.NewScanner(strings.NewReader(sampleRootHints)))
+	if err != nil {
+		t.Fatalf("Ошибка разбора корневых подсказок: %v", err)
+	}
+
+	want := []string{"198.41.0.4", "2001:503:ba3e::2:30", "170.247.170.2"}
+	if len(roots) != len(want) {
+		t.Fatalf("Ожидалось %v, получено %v", want, roots)
+	}
+	for i := range want {
+		if roots[i] != want[i] {
+			t.Errorf("Ожидалось %s, получено %s", want[i], roots[i])
+		}
+	}
+}
+
+func TestParseRootHintsPlainList(t *testing.T) {
+	roots, err := parseRootHints(bufio.NewScanner(strings.NewReader("10.0.0.1\n\n  10.0.0.2 ; private root\n")))
+	if err != nil {
+		t.Fatalf("Ошибка разбора списка адресов: %v", err)
+	}
+	if len(roots) != 2 || roots[0] != "10.0.0.1" || roots[1] != "10.0.0.2" {
+		t.Errorf("Неверный список корневых серверов: %v", roots)
+	}
+}
+
+func TestParseRootHintsRejectsGarbage(t *testing.T) {
+	if _, err := parseRootHints(bufio.NewScanner(strings.NewReader("not-an-ip\n"))); err == nil {
+		t.Errorf("Ожидалась ошибка для некорректного адреса")
+	}
+	if _, err := parseRootHints(bufio.NewScanner(strings.NewReader("; only comments\n"))); err == nil {
+		t.Errorf("Ожидалась ошибка для файла без адресов")
+	}
+}
+
+func TestRootHintsServeClientQueries(t *testing.T) {
+	var queries atomic.Int32
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		queries.Add(1)
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		reply.Authoritative = true
+		rr, _ := dns.NewRR(r.Question[0].Name + " 300 IN A 192.0.2.44")
+		reply.Answer = append(reply.Answer, rr)
+		w.WriteMsg(reply)
+	})
+	host, port, _ := net.SplitHostPort(addr)
+
+	path := filepath.Join(t.TempDir(), "named.root")
+	if err := os.WriteFile(path, []byte("A.ROOT-SERVERS.NET. 3600000 A "+host+"\n"), 0o644); err != nil {
+		t.Fatalf("Не удалось записать подсказки: %v", err)
+	}
+	cfg := DefaultConfig()
+	cfg.RootHints = path
+	s, err := NewDNSServerWithConfig(cfg)
+	if err != nil {
+		t.Fatalf("Ошибка создания сервера: %v", err)
+	}
+	s.authPort = port
+
+	req := new(dns.Msg)
+	req.SetQuestion("hinted.example.", dns.TypeA)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil || w.msg.Rcode != dns.RcodeSuccess || len(w.msg.Answer) != 1 {
+		t.Fatalf("Ожидался ответ от сервера из подсказок, получено %v", w.msg)
+	}
+	if n := queries.Load(); n != 1 {
+		t.Errorf("Ожидался 1 запрос к корневому серверу из подсказок, получено %d", n)
+	}
+}
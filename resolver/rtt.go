@@ -0,0 +1,97 @@
+package resolver
+
+import (
+	"net"
+	"sort"
+	"sync"
+	"time"
+)
+
+const (
+	rttSmoothing      = 0.3 // Вес нового измерения в сглаженном RTT
+	rttFailurePenalty = 1 * time.Second
+	rttMax            = 10 * time.Second
+)
+
+// serverStats - статистика отклика одного сервера
+type serverStats struct {
+	srtt     time.Duration
+	failures int
+}
+
+// RTTTracker хранит сглаженное время отклика серверов, чтобы сначала
+// опрашивать самые быстрые и надежные из них
+type RTTTracker struct {
+	mu      sync.RWMutex
+	servers map[string]*serverStats
+}
+
+// NewRTTTracker создает пустой трекер RTT
+func NewRTTTracker() *RTTTracker {
+	return &RTTTracker{servers: make(map[string]*serverStats)}
+}
+
+// Record учитывает успешный ответ сервера за время rtt
+func (t *RTTTracker) Record(addr string, rtt time.Duration) {
+	t.mu.Lock()
+	defer t.mu.Unlock()
+
+	key := serverKey(addr)
+	stats, ok := t.servers[key]
+	if !ok {
+		t.servers[key] = &serverStats{srtt: rtt}
+		return
+	}
+	stats.srtt = time.Duration((1-rttSmoothing)*float64(stats.srtt) + rttSmoothing*float64(rtt))
+	stats.failures = 0
+}
+
+// RecordFailure штрафует сервер, не ответивший на запрос
+func (t *RTTTracker) RecordFailure(addr string) {
+	t.mu.Lock()
+	defer t.mu.Unlock()
+
+	key := serverKey(addr)
+	stats, ok := t.servers[key]
+	if !ok {
+		stats = &serverStats{}
+		t.servers[key] = stats
+	}
+	stats.failures++
+	stats.srtt *= 2
+	if stats.srtt < rttFailurePenalty {
+		stats.srtt = rttFailurePenalty
+	}
+	if stats.srtt > rttMax {
+		stats.srtt = rttMax
+	}
+}
+
+// Sort возвращает копию списка серверов, упорядоченную от самого быстрого
+// к самому медленному. Серверы без статистики идут первыми, чтобы их опробовать.
+func (t *RTTTracker) Sort(addrs []string) []string {
+	sorted := make([]string, len(addrs))
+	copy(sorted, addrs)
+
+	t.mu.RLock()
+	defer t.mu.RUnlock()
+
+	score := func(addr string) time.Duration {
+		if stats, ok := t.servers[serverKey(addr)]; ok {
+			return stats.srtt
+		}
+		return 0
+	}
+	sort.SliceStable(sorted, func(i, j int) bool {
+		return score(sorted[i]) < score(sorted[j])
+	})
+	return sorted
+}
+
+// serverKey приводит адрес сервера к IP без порта
+func serverKey(addr string) string {
+	if host, _, err := net.SplitHostPort(addr); err == nil {
+		return host
+	}
+	return addr
+}
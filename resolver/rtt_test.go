@@ -0,0 +1,50 @@
+package resolver
+
+import (
+	"testing"
+	"time"
+)
+
+func TestRTTTrackerOrdersByLatency(t *testing.T) {
+	tracker := NewRTTTracker()
+	tracker.Record("192.0.2.1:53", 120*time.Millisecond)
+	tracker.Record("192.0.2.2:53", 20*time.Millisecond)
+	tracker.Record("192.0.2.3:53", 60*time.Millisecond)
+
+	got := tracker.Sort([]string{"192.0.2.1", "192.0.2.2", "192.0.2.3", "192.0.2.4"})
+	want := []string{"192.0.2.4", "192.0.2.2", "192.0.2.3", "192.0.2.1"}
+	for i := range want {
+		if got[i] != want[i] {
+			t.Fatalf("Ожидался порядок %v, получен %v", want, got)
+		}
+	}
+}
+
+func TestRTTTrackerPenalizesFailures(t *testing.T) {
+	tracker := NewRTTTracker()
+	tracker.Record("192.0.2.1", 10*time.Millisecond)
+	tracker.Record("192.0.2.2", 200*time.Millisecond)
+	tracker.RecordFailure("192.0.2.1")
+
+	got := tracker.Sort([]string{"192.0.2.1", "192.0.2.2"})
+	if got[0] != "192.0.2.2" {
+		t.Errorf("Сервер с таймаутом должен опрашиваться последним, получен порядок %v", got)
+	}
+
+	for i := 0; i < 10; i++ {
+		tracker.RecordFailure("192.0.2.1")
+	}
+	if srtt := tracker.servers["192.0.2.1"].srtt; srtt != rttMax {
+		t.Errorf("Штраф должен ограничиваться %v, получено %v", rttMax, srtt)
+	}
+}
+
+func TestRTTTrackerSmoothing(t *testing.T) {
+	tracker := NewRTTTracker()
+	tracker.Record("192.0.2.1", 100*time.Millisecond)
+	tracker.Record("192.0.2.1", 200*time.Millisecond)
+
+	if srtt := tracker.servers["192.0.2.1"].srtt; srtt != 130*time.Millisecond {
+		t.Errorf("Ожидался сглаженный RTT 130ms, получено %v", srtt)
+	}
+}
@@ -0,0 +1,1616 @@
+package resolver
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"log/slog"
+	"net"
+	"net/http"
+	"net/netip"
+	"os"
+	"runtime"
+	"runtime/debug"
+	"strings"
+	"sync"
+	"sync/atomic"
+	"time"
+
+	"encoding/base32"
+	"github.com/miekg/dns"
+	
+)
+
+// DNSServer содержит все необходимые компоненты для DNS-сервера
+type DNSServer struct {
+	cfg              Config
+	nxdomainCounter  sync.Map // map[string]int
+	nxdomainLastSeen sync.Map // map[string]time.Time
+	quarantined      sync.Map // map[string]time.Time
+	servfailCache    sync.Map // map[string]time.Time - до какого момента отвечать SERVFAIL из кэша
+	dnssecEnabled    bool
+	trustAnchor      *dns.DNSKEY // Root trust anchor
+	keyCache         sync.Map    // map[string]*dns.DNSKEY
+	keyCacheTime     sync.Map    // map[string]time.Time
+	dsCache          sync.Map    // map[string][]*dns.DS
+	dsCacheTime      sync.Map    // map[string]time.Time
+	rrsigCache       sync.Map    // map[string]*dns.RRSIG
+	rrsigCacheTime   sync.Map    // map[string]time.Time
+	cache            *Cache      // Кэш ответов
+	cacheFile        string      // Файл для сохранения кэша между перезапусками
+	forwarders       []string    // Вышестоящие резолверы; если заданы, рекурсия не выполняется
+	forwardZones     map[string][]string
+	zoneTTL          map[string]ZoneTTL
+	rootServers      []string    // Адреса корневых серверов
+	authPort         string      // Порт авторитетных серверов, если он не указан в адресе
+	delegations      *DelegationCache
+	rateLimiter      *RateLimiter
+	slots            requestSlots // Ограничение одновременно обрабатываемых запросов
+	acl              *ACL
+	metricsAddr      string // Адрес HTTP-сервера метрик; пустой отключает его
+	rtt              *RTTTracker
+	transport        queryTransport // Обмен с вышестоящими и авторитетными серверами
+	nameLookup       func(ctx context.Context, qname, qtype string) []string
+	hostLookup       func(ctx context.Context, host, qtype string) []string
+	cookies          *CookieJar
+	infra            *InfraCache
+	dotAddr          string // Адрес DNS-over-TLS; используется при заданных сертификате и ключе
+	dohAddr          string // Адрес DNS-over-HTTPS; используется при заданных сертификате и ключе
+	tlsCertFile      string
+	tlsKeyFile       string
+	blocklist        *Blocklist
+	hosts            *HostsFile
+	zones            *ZoneStore
+	queryLog         *QueryLog
+	inflight         inflightGroup // Одновременные разрешения одинаковых вопросов
+	prefetching      sync.Map      // map[string]struct{} - ключи, обновляемые упреждающе
+	heartbeat        int64         // Время последнего сигнала фонового цикла (UnixNano)
+	serving          uint32        // 1, пока DNS-серверы принимают запросы
+	stopping         uint32        // 1 после вызова Shutdown
+	activeRequests   int64         // Запросы, обрабатываемые в данный момент
+	lastProgress     int64         // Время последнего завершенного запроса (UnixNano)
+	answerRotation   uint64        // Счетчик сдвига для answer_order "cyclic"
+	dnsServers       []*dns.Server  // Серверы UDP и TCP
+	dotServers       []*dns.Server  // Серверы DNS-over-TLS
+	dohServers       []*http.Server // Серверы DNS-over-HTTPS
+	dnsServerMu      sync.Mutex
+
+	// Метрики
+	secureQueries        uint64
+	insecureQueries      uint64
+	bogusQueries         uint64
+	indeterminateQueries uint64
+	cacheHits            uint64
+	cacheMisses          uint64
+	queriesTotal         uint64
+	nxdomainResponses    uint64
+	upstreamTimeouts     uint64
+	coalescedQueries     uint64
+	staleAnswers         uint64
+	resolutionDeadlines  uint64
+	queryBudgetExhausted uint64
+	oversizedAnswers     uint64
+	mismatchedAnswers    uint64
+	overloadRefused      uint64
+	handlerPanics        uint64
+	qtypeQueries         TypeCounter
+	latency              *Histogram
+}
+
+const (
+	nxdomainLimit    = 3
+	keyCacheTTL      = 24 * time.Hour
+	dsCacheTTL       = 24 * time.Hour
+	rrsigCacheTTL    = 1 * time.Hour
+	nxdomainTTL      = 30 * time.Minute
+	quarantinePeriod = 30 * time.Second
+	maxUDPSize       = 4096
+	upstreamUDPSize  = 1232
+	maxCacheEntries  = 10000
+	maxCacheTTL      = 24 * time.Hour
+	staleWindow      = 24 * time.Hour
+	servfailTTL      = 5 * time.Second
+	maxNegativeTTL   = 3 * time.Hour
+	prefetchInterval = time.Minute
+	prefetchWindow   = time.Minute
+	prefetchWorkers  = 8
+	staleAnswerTTL   = 30 // TTL устаревших ответов (RFC 8767)
+	maxReferrals     = 16 // Максимальная глубина обхода делегирований
+	anyHINFOTTL      = 3600
+	cacheFilePath    = "dns_cache.json"
+	upstreamTimeout  = 5 * time.Second
+	resolveTimeout   = 10 * time.Second // Общий срок разрешения одного вопроса
+	maxUpstreamQuery = 50               // Исходящих запросов на одно разрешение
+	maxAnswerRecords = 512              // Записей в одном ответе вышестоящего сервера
+	cleanupInterval  = 5 * time.Minute
+	drainInterval    = 10 * time.Millisecond
+	defaultListen    = ":5454"
+	rateLimitQPS     = 50 // Запросов в секунду от одного клиента
+	rateLimitBurst   = 100
+	rateLimitIdleTTL = 10 * time.Minute
+	maxInFlight      = 1000 // Одновременно обрабатываемых запросов
+	metricsListen    = ":9153"
+	dotListen        = ":853"
+	dohListen        = ":443"
+	healthListen     = ":8080"
+)
+
+// DNSSECValidationResult представляет результат проверки DNSSEC
+type DNSSECValidationResult int
+
+const (
+	DNSSEC_SECURE DNSSECValidationResult = iota
+	DNSSEC_INSECURE
+	DNSSEC_BOGUS
+	DNSSEC_INDETERMINATE
+)
+
+var base32HexNoPad = base32.HexEncoding.WithPadding(base32.NoPadding)
+
+// NewDNSServer создает DNS-сервер с настройками по умолчанию
+func NewDNSServer() *DNSServer {
+	return newDNSServer(DefaultConfig())
+}
+
+// NewDNSServerWithConfig проверяет конфигурацию и создает новый DNS-сервер.
+// Конфигурация проверяется и здесь, а не только в LoadConfig: значения
+// могут быть изменены флагами командной строки или заданы в коде.
+func NewDNSServerWithConfig(cfg Config) (*DNSServer, error) {
+	if err := cfg.validate(); err != nil {
+		return nil, err
+	}
+	return newDNSServer(cfg), nil
+}
+
+// newDNSServer создает и инициализирует DNS-сервер с проверенной конфигурацией
+func newDNSServer(cfg Config) *DNSServer {
+	server := &DNSServer{
+		cfg:           cfg,
+		dnssecEnabled: true,
+		cache:         NewStaleCache(cfg.MaxCacheEntries, cfg.StaleWindow.Duration),
+		cacheFile:     cfg.CacheFile,
+		forwarders:    normalizeUpstreams(cfg.Forwarders),
+		forwardZones:  normalizeForwardZones(cfg.ForwardZones),
+		zoneTTL:       normalizeZoneTTL(cfg.ZoneTTL),
+		rootServers:   defaultRootServers,
+		authPort:      "53",
+		delegations:   NewDelegationCache(),
+		rateLimiter:   NewRateLimiter(cfg.RateLimitQPS, cfg.RateLimitBurst),
+		slots:         newRequestSlots(cfg.MaxInFlight),
+		acl:           newConfigACL(cfg),
+		metricsAddr:   cfg.MetricsListen,
+		latency:       NewHistogram(latencyBuckets),
+		rtt:           NewRTTTracker(),
+		transport:     newConfigTransport(cfg),
+		cookies:       newConfigCookieJar(cfg),
+		infra:         NewInfraCache(infraCacheTTL),
+		dotAddr:       cfg.DoTListen,
+		dohAddr:       cfg.DoHListen,
+		tlsCertFile:   cfg.TLSCert,
+		tlsKeyFile:    cfg.TLSKey,
+	}
+	// NS зоны и адреса серверов имен разрешаются через nameLookup и
+	// hostLookup, тесты подменяют их
+	server.nameLookup = server.lookupRecords
+	server.hostLookup = server.lookupRecords
+	server.initializeTrustAnchor()
+	server.loadDataFiles()
+	return server
+}
+
+// loadDataFiles загружает файлы, указанные в конфигурации
+func (s *DNSServer) loadDataFiles() {
+	if s.cfg.L2CacheDir != "" {
+		l2, err := NewDiskCache(s.cfg.L2CacheDir, s.cfg.L2CacheMaxEntries)
+		if err != nil {
+			slog.Warn("Не удалось открыть каталог второго уровня кэша", "path", s.cfg.L2CacheDir, "error", err)
+		} else {
+			s.cache.SetSecondary(l2)
+			slog.Info("Подключен второй уровень кэша", "path", s.cfg.L2CacheDir, "entries", l2.Len())
+		}
+	}
+	if s.cfg.Blocklist != "" {
+		list, err := LoadBlocklist(s.cfg.Blocklist, s.cfg.BlocklistSubdomains, s.cfg.BlocklistSinkhole)
+		if err != nil {
+			slog.Warn("Не удалось загрузить список блокировки", "path", s.cfg.Blocklist, "error", err)
+		} else {
+			s.blocklist = list
+			slog.Info("Загружен список блокировки", "path", s.cfg.Blocklist, "domains", list.Len())
+		}
+	}
+	if s.cfg.HostsFile != "" {
+		hosts, err := LoadHostsFile(s.cfg.HostsFile, s.cfg.HostsTTL)
+		if err != nil {
+			slog.Warn("Не удалось загрузить hosts-файл", "path", s.cfg.HostsFile, "error", err)
+		} else {
+			s.hosts = hosts
+			slog.Info("Загружен hosts-файл", "path", s.cfg.HostsFile, "names", hosts.Len())
+		}
+	}
+	if s.cfg.QueryLogFile != "" {
+		qlog, err := OpenQueryLog(s.cfg.QueryLogFile, s.cfg.QueryLogMaxSize, s.cfg.QueryLogKeep)
+		if err != nil {
+			slog.Warn("Не удалось открыть журнал запросов", "path", s.cfg.QueryLogFile, "error", err)
+		} else {
+			s.queryLog = qlog
+			slog.Info("Журнал запросов включен", "path", s.cfg.QueryLogFile)
+		}
+	}
+	if len(s.cfg.LocalZones) > 0 {
+		zones, err := LoadZones(s.cfg.LocalZones)
+		if err != nil {
+			slog.Warn("Не удалось загрузить локальные зоны", "error", err)
+		} else {
+			s.zones = zones
+			slog.Info("Загружены локальные зоны", "zones", zones.Len())
+		}
+	}
+	if s.cfg.RootHints != "" {
+		roots, err := loadRootHints(s.cfg.RootHints)
+		if err != nil {
+			slog.Warn("Не удалось загрузить корневые подсказки, используются встроенные", "path", s.cfg.RootHints, "error", err)
+		} else {
+			s.rootServers = roots
+			slog.Info("Загружены корневые подсказки", "path", s.cfg.RootHints, "servers", len(roots))
+		}
+	}
+	if len(s.forwarders) > 0 {
+		slog.Info("Режим пересылки", "upstreams", s.forwarders)
+	}
+	if len(s.forwardZones) > 0 {
+		slog.Info("Условная пересылка", "zones", len(s.forwardZones))
+	}
+}
+
+// initializeTrustAnchor инициализирует доверенный корень
+func (s *DNSServer) initializeTrustAnchor() {
+	// Root KSK-2017 (RFC 8624)
+	keyStr := ". IN DNSKEY 257 3 8 AwEAAaz/tAm8yTn4Mfeh5ZRzF9YWcJnJzRc5Diz20y+O3j2YiD6ZGyXaK0r1W/0WZi8c9I0HPObYJw8FXQzG00kHvU1OqqCtKkRBOhB4wR5KJ4QkhzN5ZU5lFsNhqVCKVCYyUMxMEJlJQZlNq6q+aIzHVMZQnR4ggr3H8H9U9F92F6VK7S9ZQ1Y="
+
+	rr, err := dns.NewRR(keyStr)
+	if err != nil {
+		slog.Error("Не удалось разобрать доверенную точку", "error", err)
+		return
+	}
+
+	if dnskey, ok := rr.(*dns.DNSKEY); ok {
+		s.trustAnchor = dnskey
+		slog.Debug("Доверенная точка успешно инициализирована")
+	}
+}
+
+// startCleaner запускает фоновую очистку кэшей
+func (s *DNSServer) startCleaner() {
+	s.beat(time.Now())
+	ticker := time.NewTicker(s.cfg.CleanupInterval.Duration)
+	defer ticker.Stop()
+	for range ticker.C {
+		now := time.Now()
+		s.beat(now)
+		nxdomainCount := 0
+		quarantineCount := 0
+		keyCacheCount := 0
+		dsCacheCount := 0
+		rrsigCacheCount := 0
+		servfailCount := 0
+		answerCacheCount := s.cache.RemoveExpired(now)
+		rateLimitCount := s.rateLimiter.Cleanup(now, rateLimitIdleTTL)
+		delegationCount := s.delegations.RemoveExpired(now)
+		infraCount := s.infra.RemoveExpired(now)
+
+		// Очистка кэша сбоев разрешения
+		s.servfailCache.Range(func(key, value interface{}) bool {
+			if until, ok := value.(time.Time); ok && now.After(until) {
+				s.servfailCache.Delete(key)
+				servfailCount++
+			}
+			return true
+		})
+
+		// Очистка nxdomainCounter
+		s.nxdomainLastSeen.Range(func(key, value interface{}) bool {
+			if lastSeen, ok := value.(time.Time); ok {
+				if now.Sub(lastSeen) > s.cfg.NXDomainTTL.Duration {
+					s.nxdomainCounter.Delete(key)
+					s.nxdomainLastSeen.Delete(key)
+					nxdomainCount++
+				}
+			}
+			return true
+		})
+
+		// Освобождение доменов из карантина
+		s.quarantined.Range(func(key, value interface{}) bool {
+			if releaseTime, ok := value.(time.Time); ok {
+				if now.After(releaseTime) {
+					s.quarantined.Delete(key)
+					quarantineCount++
+				}
+			}
+			return true
+		})
+
+		// Очистка кэша ключей
+		s.keyCacheTime.Range(func(key, value interface{}) bool {
+			if cacheTime, ok := value.(time.Time); ok {
+				if now.Sub(cacheTime) > keyCacheTTL {
+					s.keyCache.Delete(key)
+					s.keyCacheTime.Delete(key)
+					keyCacheCount++
+				}
+			}
+			return true
+		})
+
+		// Очистка кэша DS
+		s.dsCacheTime.Range(func(key, value interface{}) bool {
+			if cacheTime, ok := value.(time.Time); ok {
+				if now.Sub(cacheTime) > dsCacheTTL {
+					s.dsCache.Delete(key)
+					s.dsCacheTime.Delete(key)
+					dsCacheCount++
+				}
+			}
+			return true
+		})
+
+		// Очистка кэша RRSIG
+		s.rrsigCacheTime.Range(func(key, value interface{}) bool {
+			if cacheTime, ok := value.(time.Time); ok {
+				if now.Sub(cacheTime) > rrsigCacheTTL {
+					s.rrsigCache.Delete(key)
+					s.rrsigCacheTime.Delete(key)
+					rrsigCacheCount++
+				}
+			}
+			return true
+		})
+
+		slog.Info("Очистка кэшей",
+			"nxdomain_counters", nxdomainCount,
+			"quarantine_released", quarantineCount,
+			"dnskeys", keyCacheCount,
+			"ds", dsCacheCount,
+			"rrsigs", rrsigCacheCount,
+			"answers", answerCacheCount,
+			"answers_left", s.cache.Len(),
+			"rate_limit_buckets", rateLimitCount,
+			"delegations", delegationCount,
+			"infra", infraCount,
+			"servfail", servfailCount)
+
+		// Вывод метрик
+		slog.Info("Метрики",
+			"secure", atomic.LoadUint64(&s.secureQueries),
+			"insecure", atomic.LoadUint64(&s.insecureQueries),
+			"bogus", atomic.LoadUint64(&s.bogusQueries),
+			"indeterminate", atomic.LoadUint64(&s.indeterminateQueries),
+			"cache_hits", atomic.LoadUint64(&s.cacheHits),
+			"cache_misses", atomic.LoadUint64(&s.cacheMisses))
+
+		s.saveCache()
+	}
+}
+
+// loadCache восстанавливает кэш ответов, сохранённый до перезапуска
+func (s *DNSServer) loadCache() {
+	if s.cacheFile == "" {
+		return
+	}
+	n, err := s.cache.Load(s.cacheFile)
+	if err != nil {
+		if !errors.Is(err, os.ErrNotExist) {
+			slog.Warn("Не удалось загрузить кэш", "path", s.cacheFile, "error", err)
+		}
+		return
+	}
+	slog.Info("Кэш загружен", "path", s.cacheFile, "entries", n)
+}
+
+// saveCache сохраняет кэш ответов на диск
+func (s *DNSServer) saveCache() {
+	if s.cacheFile == "" {
+		return
+	}
+	n, err := s.cache.Save(s.cacheFile)
+	if err != nil {
+		slog.Warn("Не удалось сохранить кэш", "path", s.cacheFile, "error", err)
+		return
+	}
+	slog.Debug("Кэш сохранён", "path", s.cacheFile, "entries", n)
+}
+
+// handleRequest обрабатывает входящие DNS-запросы
+func (s *DNSServer) handleRequest(w dns.ResponseWriter, req *dns.Msg) {
+	start := time.Now()
+	s.requestStarted(start)
+	defer func() { s.requestDone(time.Now()) }()
+	udpSize := responseSize(req, s.cfg.MaxUDPSize)
+	w = &truncatingWriter{ResponseWriter: w, size: udpSize}
+	recorder := &rcodeRecorder{ResponseWriter: w}
+	w = recorder
+	cacheHit := false
+	defer func() {
+		elapsed := time.Since(start)
+		logQuery(recorder, req, cacheHit, elapsed)
+		s.queryLog.Record(recorder, req, cacheHit, elapsed)
+	}()
+
+	// Ошибка в обработке одного запроса, даже вызванная специально
+	// сформированным сообщением, не должна останавливать сервер
+	defer func() {
+		if r := recover(); r != nil {
+			atomic.AddUint64(&s.handlerPanics, 1)
+			slog.Error("Паника при обработке запроса", "panic", r, "stack", string(debug.Stack()))
+			if !recorder.written {
+				s.sendErrorResponse(w, req, dns.RcodeServerFailure, "Ошибка обработки запроса")
+			}
+		}
+	}()
+
+	// После начала остановки новые запросы не принимаются: сервер только
+	// дорабатывает уже начатые
+	if atomic.LoadUint32(&s.stopping) == 1 {
+		s.sendErrorResponse(w, req, dns.RcodeRefused, "Сервер останавливается")
+		return
+	}
+
+	// Клиенты, не допущенные ACL, получают REFUSED или не получают ответа вовсе
+	if !s.acl.Allowed(net.ParseIP(clientIP(w))) {
+		if s.cfg.ACLAction == aclActionDrop {
+			return
+		}
+		s.sendExtendedError(w, req, dns.RcodeRefused, dns.ExtendedErrorCodeProhibited, "Клиент "+clientIP(w)+" не допущен ACL")
+		return
+	}
+
+	// Ограничение частоты запросов от одного клиента против злоупотреблений и усиления
+	if !s.rateLimiter.Allow(clientIP(w)) {
+		s.sendErrorResponse(w, req, dns.RcodeRefused, "Превышен лимит запросов от клиента "+clientIP(w))
+		return
+	}
+
+	// При перегрузке новые запросы сразу получают REFUSED, а не копят
+	// горутины и память в ожидании вышестоящих серверов
+	if !s.slots.acquire() {
+		atomic.AddUint64(&s.overloadRefused, 1)
+		s.sendErrorResponse(w, req, dns.RcodeRefused, "Превышен лимит одновременных запросов")
+		return
+	}
+	defer s.slots.release()
+
+	atomic.AddUint64(&s.queriesTotal, 1)
+	defer func() {
+		s.latency.Observe(time.Since(start))
+	}()
+
+	cacheHit = s.answer(context.Background(), w, req)
+}
+
+// answer отвечает на запрос: локальные зоны, hosts-файл, список блокировки,
+// кэш и разрешение, DNS64 и проверка DNSSEC. Через answer проходят запросы
+// сетевых клиентов после ACL и ограничений частоты, а также запросы
+// Resolver. Отмена ctx прерывает разрешение. Возвращает true, если ответ
+// взят из кэша.
+func (s *DNSServer) answer(ctx context.Context, w dns.ResponseWriter, req *dns.Msg) (cacheHit bool) {
+	// Поддерживаются только обычные запросы: NOTIFY, UPDATE и прочие
+	// коды операций обрабатываются авторитетными серверами
+	if req.Opcode != dns.OpcodeQuery {
+		s.sendErrorResponse(w, req, dns.RcodeNotImplemented, "Неподдерживаемый OPCODE "+dns.OpcodeToString[req.Opcode])
+		return
+	}
+
+	if len(req.Question) == 0 {
+		if s.cfg.EmptyQueryKeepalive {
+			s.sendErrorResponse(w, req, dns.RcodeSuccess, "Проверка доступности пустым запросом")
+			return
+		}
+		s.sendErrorResponse(w, req, dns.RcodeFormatError, "Нет вопросов в запросе")
+		return
+	}
+
+	// Несколько вопросов в одном запросе не поддерживаются (RFC 9619): вместо
+	// молчаливого ответа только на первый вопрос возвращаем FORMERR
+	if len(req.Question) > 1 {
+		s.sendErrorResponse(w, req, dns.RcodeFormatError, "Запрос содержит несколько вопросов")
+		return
+	}
+
+	question := req.Question[0]
+	s.qtypeQueries.Inc(question.Qtype)
+	if !validQueryName(question.Name) {
+		s.sendErrorResponse(w, req, dns.RcodeFormatError, "Некорректная метка в имени "+question.Name)
+		return
+	}
+	queryKey := cacheKey(question.Name, question.Qtype)
+
+	// Диагностические запросы version.bind и id.server класса CHAOS
+	if question.Qclass == dns.ClassCHAOS {
+		s.sendChaosResponse(w, req)
+		return
+	}
+
+	// Рекурсия выполняется только для класса IN
+	if question.Qclass != dns.ClassINET {
+		s.sendErrorResponse(w, req, dns.RcodeRefused, "Неподдерживаемый класс "+dns.ClassToString[question.Qclass])
+		return
+	}
+
+	// Локальные авторитетные зоны отвечают без рекурсии с флагом AA
+	if zone := s.zones.Find(question.Name); zone != nil {
+		slog.Debug("Ответ из локальной зоны", "qname", question.Name, "zone", zone.origin)
+		answer, ns, rcode := zone.Lookup(question.Name, question.Qtype)
+		reply := new(dns.Msg)
+		reply.SetRcode(req, rcode)
+		reply.Authoritative = true
+		reply.RecursionAvailable = true
+		reply.Answer = answer
+		reply.Ns = ns
+		if err := w.WriteMsg(reply); err != nil {
+			slog.Error("Ошибка записи ответа", "error", err)
+		}
+		return
+	}
+
+	// Статические записи из hosts-файла имеют приоритет над кэшем и рекурсией
+	if records, ok := s.hosts.Lookup(question.Name, question.Qtype); ok {
+		slog.Debug("Ответ из hosts-файла", "qname", question.Name)
+		reply := new(dns.Msg)
+		reply.SetReply(req)
+		reply.RecursionAvailable = true
+		reply.Answer = records
+		if err := w.WriteMsg(reply); err != nil {
+			slog.Error("Ошибка записи ответа", "error", err)
+		}
+		return
+	}
+
+	// В режиме require_rd запросы без RD не обслуживаются: сервер не
+	// выполняет итеративное разрешение, а только пересылает запросы
+	if s.cfg.RequireRD && !req.RecursionDesired {
+		s.sendErrorResponse(w, req, dns.RcodeRefused, "Запрос без флага RD в режиме require_rd")
+		return
+	}
+
+	// Блокировка доменов из списка фильтрации до любой рекурсии
+	if s.blocklist.Blocked(question.Name) {
+		s.sendBlockedResponse(w, req)
+		return
+	}
+
+	// Имя в обратной зоне, которое не может соответствовать адресу или сети,
+	// заведомо не существует
+	if !validReverseName(question.Name) {
+		s.sendErrorResponse(w, req, dns.RcodeNameError, "Некорректное имя в обратной зоне")
+		return
+	}
+
+	// ANY не разрешается рекурсивно: большинство серверов его не поддерживает,
+	// а ответы используются для усиления атак (RFC 8482)
+	if question.Qtype == dns.TypeANY {
+		s.sendANYResponse(w, req)
+		return
+	}
+
+	// Проверка, не находится ли домен в карантине
+	if releaseTime, isQuarantined := s.quarantined.Load(strings.ToLower(dns.CanonicalName(question.Name))); isQuarantined {
+		if releaseTimeT, ok := releaseTime.(time.Time); ok {
+			if time.Now().Before(releaseTimeT) {
+				s.sendErrorResponse(w, req, dns.RcodeNameError, "Домен временно в карантине")
+				return
+			}
+		}
+	}
+
+	// SetReply копирует ID, OPCODE и флаги RD/CD запроса; мы не являемся
+	// авторитетным сервером, поэтому AA всегда сброшен, а RA установлен
+	reply := new(dns.Msg)
+	reply.SetReply(req)
+	reply.Compress = true
+	reply.Authoritative = false
+	reply.RecursionAvailable = true
+
+	// Обработка EDNS0 и флага DNSSEC
+	clientRequestsDNSSEC := false
+	if edns0 := req.IsEdns0(); edns0 != nil {
+		clientRequestsDNSSEC = edns0.Do()
+		// Флаг DO ответа повторяет флаг запроса (RFC 3225)
+		reply.SetEdns0(responseSize(req, s.cfg.MaxUDPSize), clientRequestsDNSSEC)
+		s.echoSubnet(reply, req)
+		s.addNSID(reply, req)
+	}
+
+	qtypeStr, ok := dns.TypeToString[question.Qtype]
+	if !ok {
+		s.sendErrorResponse(w, req, dns.RcodeNotImplemented, "Неподдерживаемый QTYPE")
+		return
+	}
+
+	slog.Debug("Разрешение", "qname", question.Name, "qtype", qtypeStr, "dnssec", clientRequestsDNSSEC)
+
+	subnet := s.requestSubnet(req, clientIP(w))
+	result := s.lookupSubnet(ctx, queryKey, question.Name, qtypeStr, question.Qtype, subnet)
+	cacheHit = result.cached
+	rcode := result.rcode
+	if rcode == dns.RcodeServerFailure {
+		if result.hasEDE {
+			s.sendExtendedError(w, req, dns.RcodeServerFailure, result.ede, "Не удалось разрешить "+question.Name)
+		} else {
+			s.sendErrorResponse(w, req, dns.RcodeServerFailure, "Не удалось разрешить "+question.Name)
+		}
+		return
+	}
+	answers := s.followCNAME(ctx, question.Name, question.Qtype, result.answers)
+	if s.dns64Applies(req, rcode, answers) {
+		answers = s.synthesizeDNS64(ctx, question.Name, answers)
+	}
+	answers = s.orderAnswers(answers)
+	reply.Answer = append(reply.Answer, answers...)
+	hasValidAnswer := len(answers) > 0
+	if result.hasEDE {
+		s.addEDE(reply, req, result.ede, "")
+	}
+
+	// Отслеживание результата проверки DNSSEC
+	dnssecValidationResult := DNSSEC_INDETERMINATE // По умолчанию
+	isDNSSECValidationAttempted := false
+
+	// Обработка NXDOMAIN
+	if !hasValidAnswer && rcode == dns.RcodeNameError {
+		if clientRequestsDNSSEC {
+			isDNSSECValidationAttempted = true
+			validationResult := s.validateNegativeResponse(question.Name, reply)
+			dnssecValidationResult = validationResult
+			switch validationResult {
+			case DNSSEC_SECURE:
+				atomic.AddUint64(&s.secureQueries, 1)
+				slog.Debug("Успешная проверка DNSSEC для отрицательного ответа", "qname", question.Name)
+			case DNSSEC_BOGUS:
+				s.sendExtendedError(w, req, dns.RcodeServerFailure, dns.ExtendedErrorCodeDNSBogus, "Проверка DNSSEC провалилась для отрицательного ответа")
+				atomic.AddUint64(&s.bogusQueries, 1)
+				return
+			case DNSSEC_INDETERMINATE:
+				slog.Debug("Недостаточно информации для проверки DNSSEC для отрицательного ответа", "qname", question.Name)
+				atomic.AddUint64(&s.indeterminateQueries, 1)
+			case DNSSEC_INSECURE:
+				slog.Debug("Домен не защищен (без DNSSEC) для отрицательного ответа", "qname", question.Name)
+				atomic.AddUint64(&s.insecureQueries, 1)
+			}
+		}
+
+		// Увеличение счетчика NXDOMAIN
+		counter, _ := s.nxdomainCounter.LoadOrStore(strings.ToLower(dns.CanonicalName(question.Name)), 0)
+		count := counter.(int) + 1
+		s.nxdomainCounter.Store(strings.ToLower(dns.CanonicalName(question.Name)), count)
+		s.nxdomainLastSeen.Store(strings.ToLower(dns.CanonicalName(question.Name)), time.Now())
+
+		if count >= s.cfg.NXDomainLimit {
+			slog.Warn("Достигнут лимит NXDOMAIN, домен переведён в карантин", "qname", question.Name, "period", s.cfg.QuarantinePeriod.Duration)
+			s.quarantined.Store(strings.ToLower(dns.CanonicalName(question.Name)), time.Now().Add(s.cfg.QuarantinePeriod.Duration))
+		}
+
+		reply.Rcode = dns.RcodeNameError
+		s.responseSections(reply, result, true)
+		if !clientRequestsDNSSEC {
+			stripDNSSEC(reply, question.Qtype)
+		}
+		atomic.AddUint64(&s.nxdomainResponses, 1)
+		// Применение результата DNSSEC перед отправкой
+		if isDNSSECValidationAttempted && dnssecValidationResult == DNSSEC_SECURE {
+			reply.MsgHdr.AuthenticatedData = true
+		}
+		if err := w.WriteMsg(reply); err != nil {
+			slog.Error("Ошибка записи ответа", "error", err)
+		}
+		return
+	}
+
+	// Обработка проверки DNSSEC
+	if s.dnssecEnabled && clientRequestsDNSSEC && hasValidAnswer {
+		isDNSSECValidationAttempted = true
+		// Проверка наличия RRSIG в ответе
+		hasRRSIGs := false
+		for _, rr := range reply.Answer {
+			if _, ok := rr.(*dns.RRSIG); ok {
+				hasRRSIGs = true
+				break
+			}
+		}
+		
+		slog.Debug("Проверка наличия RRSIG в ответе", "qname", question.Name, "has_rrsigs", hasRRSIGs)
+		if hasRRSIGs {
+			validationResult := s.validateDNSSEC(question.Name, reply)
+			dnssecValidationResult = validationResult
+			switch validationResult {
+			case DNSSEC_SECURE:
+				atomic.AddUint64(&s.secureQueries, 1)
+				slog.Debug("Успешная проверка DNSSEC", "qname", question.Name)
+			case DNSSEC_BOGUS:
+				s.sendExtendedError(w, req, dns.RcodeServerFailure, dns.ExtendedErrorCodeDNSBogus, "Проверка DNSSEC провалилась")
+				atomic.AddUint64(&s.bogusQueries, 1)
+				return
+			case DNSSEC_INDETERMINATE:
+				slog.Debug("Недостаточно информации для проверки DNSSEC", "qname", question.Name)
+				atomic.AddUint64(&s.indeterminateQueries, 1)
+			case DNSSEC_INSECURE:
+				slog.Debug("Домен не защищен (без DNSSEC)", "qname", question.Name)
+				atomic.AddUint64(&s.insecureQueries, 1)
+			}
+		} else {
+			slog.Debug("RRSIG не найдены, попытка получения от авторитетных серверов", "qname", question.Name)
+			_, rrsigsFromAuth := s.fetchFromAuthoritative(question.Name, question.Qtype)
+			for _, rrsig := range rrsigsFromAuth {
+				if rrsig.TypeCovered == question.Qtype {
+					reply.Answer = append(reply.Answer, rrsig)
+					hasRRSIGs = true
+					slog.Debug("Добавлен RRSIG из авторитетного сервера", "rrsig", rrsig.String())
+				}
+			}
+			if hasRRSIGs {
+				validationResult := s.validateDNSSEC(question.Name, reply)
+				dnssecValidationResult = validationResult
+				switch validationResult {
+				case DNSSEC_SECURE:
+					atomic.AddUint64(&s.secureQueries, 1)
+					slog.Debug("Успешная проверка DNSSEC", "qname", question.Name)
+				case DNSSEC_BOGUS:
+					s.sendExtendedError(w, req, dns.RcodeServerFailure, dns.ExtendedErrorCodeDNSBogus, "Проверка DNSSEC провалилась")
+					atomic.AddUint64(&s.bogusQueries, 1)
+					return
+				case DNSSEC_INDETERMINATE:
+					slog.Debug("Недостаточно информации для проверки DNSSEC", "qname", question.Name)
+					atomic.AddUint64(&s.indeterminateQueries, 1)
+				case DNSSEC_INSECURE:
+					slog.Debug("Домен не защищен (без DNSSEC)", "qname", question.Name)
+					atomic.AddUint64(&s.insecureQueries, 1)
+				}
+			} else {
+				slog.Debug("RRSIG не доступны после дополнительного поиска, попытка получить DNSKEY/DS для диагностики", "qname", question.Name)
+				rrs, keys, dsRecs, err := s.fetchDNSSECRecordsAsync(question.Name)
+				if err != nil {
+					slog.Debug("Ошибка получения DNSSEC записей для диагностики", "qname", question.Name, "error", err)
+					atomic.AddUint64(&s.indeterminateQueries, 1)
+					dnssecValidationResult = DNSSEC_INDETERMINATE
+				} else {
+					if len(rrs) == 0 && len(keys) == 0 && len(dsRecs) == 0 {
+						slog.Debug("DNSSEC записи не найдены, обработка как INSECURE", "qname", question.Name)
+						atomic.AddUint64(&s.insecureQueries, 1)
+						dnssecValidationResult = DNSSEC_INSECURE
+					} else {
+						slog.Debug("DNSSEC артефакты присутствуют, но нет подходящих RRSIG, обработка как INDETERMINATE", "qname", question.Name)
+						atomic.AddUint64(&s.indeterminateQueries, 1)
+						dnssecValidationResult = DNSSEC_INDETERMINATE
+					}
+				}
+			}
+		}
+	}
+
+	// Применение результата DNSSEC перед отправкой
+	if isDNSSECValidationAttempted && dnssecValidationResult == DNSSEC_SECURE {
+		reply.MsgHdr.AuthenticatedData = true
+	}
+
+	s.responseSections(reply, result, !hasValidAnswer)
+	if !clientRequestsDNSSEC {
+		stripDNSSEC(reply, question.Qtype)
+	}
+	if err := w.WriteMsg(reply); err != nil {
+		slog.Error("Ошибка записи ответа", "error", err)
+	}
+}
+
+// lookupResult - ответ на вопрос вместе с секциями authority и additional
+// ответа вышестоящего сервера
+type lookupResult struct {
+	answers    []dns.RR
+	authority  []dns.RR
+	additional []dns.RR
+	rcode      int  // NOERROR, NXDOMAIN или SERVFAIL
+	cached     bool // Ответ взят из кэша
+
+	// ede - расширенный код ошибки (RFC 8914) для SERVFAIL или устаревшего
+	// ответа. Код 0 (Other) допустим, поэтому наличие кода отмечает hasEDE.
+	ede    uint16
+	hasEDE bool
+}
+
+// copy возвращает глубокую копию результата
+func (r lookupResult) copy() lookupResult {
+	r.answers = copyRecords(r.answers)
+	r.authority = copyRecords(r.authority)
+	r.additional = copyRecords(r.additional)
+	return r
+}
+
+// lookup возвращает ответ из кэша или выполняет рекурсивное разрешение
+func (s *DNSServer) lookup(queryKey, qname, qtypeStr string, qtype uint16) lookupResult {
+	return s.lookupSubnet(context.Background(), queryKey, qname, qtypeStr, qtype, netip.Prefix{})
+}
+
+// lookupSubnet возвращает ответ для клиента из подсети subnet (ECS). Ответ,
+// подобранный для этой подсети, предпочитается общему ответу из кэша.
+// Отмена ctx прерывает разрешение.
+func (s *DNSServer) lookupSubnet(ctx context.Context, queryKey, qname, qtypeStr string, qtype uint16, subnet netip.Prefix) lookupResult {
+	resolveKey := subnetKey(queryKey, subnet)
+	entry, ok := s.cache.GetEntry(resolveKey)
+	if !ok && resolveKey != queryKey {
+		entry, ok = s.cache.GetEntry(queryKey)
+	}
+	if ok {
+		atomic.AddUint64(&s.cacheHits, 1)
+		now := time.Now()
+		remainingTTL(entry.Records, entry.ExpiresAt, now)
+		remainingTTL(entry.Authority, entry.ExpiresAt, now)
+		remainingTTL(entry.Additional, entry.ExpiresAt, now)
+		slog.Debug("Ответ из кэша", "qname", qname, "qtype", qtypeStr)
+		return lookupResult{
+			answers:    entry.Records,
+			authority:  entry.Authority,
+			additional: entry.Additional,
+			rcode:      entry.Rcode,
+			cached:     true,
+		}
+	}
+	atomic.AddUint64(&s.cacheMisses, 1)
+
+	// Недавний сбой разрешения не повторяется до истечения servfail_ttl (RFC 9520)
+	var result lookupResult
+	if until, ok := s.servfailCache.Load(resolveKey); ok && time.Now().Before(until.(time.Time)) {
+		slog.Debug("SERVFAIL из кэша сбоев", "qname", qname, "qtype", qtypeStr)
+		result = lookupResult{rcode: dns.RcodeServerFailure, cached: true, ede: dns.ExtendedErrorCodeCachedError, hasEDE: true}
+	} else {
+		var shared bool
+		result, shared = s.inflight.Do(resolveKey, func() lookupResult {
+			return s.resolve(ctx, queryKey, qname, qtypeStr, qtype, subnet)
+		})
+		if shared {
+			atomic.AddUint64(&s.coalescedQueries, 1)
+			slog.Debug("Ответ получен от одновременного разрешения", "qname", qname, "qtype", qtypeStr)
+		}
+		// Разрешение, прерванное отменой ctx, не считается сбоем имени
+		if result.rcode == dns.RcodeServerFailure && s.cfg.ServfailTTL.Duration > 0 && ctx.Err() == nil {
+			s.servfailCache.Store(resolveKey, time.Now().Add(s.cfg.ServfailTTL.Duration))
+		}
+	}
+
+	// Если разрешение не удалось, лучше отдать устаревший ответ, чем SERVFAIL
+	if result.rcode == dns.RcodeServerFailure {
+		stale, ok := s.cache.GetStale(resolveKey)
+		if !ok && resolveKey != queryKey {
+			stale, ok = s.cache.GetStale(queryKey)
+		}
+		if ok {
+			for _, rr := range stale {
+				rr.Header().Ttl = staleAnswerTTL
+			}
+			atomic.AddUint64(&s.staleAnswers, 1)
+			slog.Warn("Отдан устаревший ответ из кэша", "qname", qname, "qtype", qtypeStr)
+			return lookupResult{answers: stale, rcode: dns.RcodeSuccess, cached: true, ede: dns.ExtendedErrorCodeStaleAnswer, hasEDE: true}
+		}
+	}
+	return result
+}
+
+// resolve выполняет пересылку или рекурсивное разрешение и кэширует ответ.
+// Ответ, который вышестоящий резолвер подобрал для подсети subnet, кэшируется
+// отдельно для этой подсети. Срок разрешения ограничен также сроком parent.
+func (s *DNSServer) resolve(parent context.Context, queryKey, qname, qtypeStr string, qtype uint16, subnet netip.Prefix) lookupResult {
+	ctx, cancel := s.resolutionContext(parent)
+	defer cancel()
+	ctx = withSubnet(ctx, subnet)
+
+	result := lookupResult{rcode: dns.RcodeSuccess}
+	var scoped netip.Prefix
+	if upstreams := s.forwardersFor(qname); len(upstreams) > 0 {
+		// Режим пересылки: запрос к вышестоящим резолверам вместо рекурсии
+		resp, err := s.forwardTo(ctx, upstreams, qname, qtype)
+		if err != nil {
+			slog.Warn("Ошибка пересылки", "qname", qname, "qtype", qtypeStr, "error", err)
+			result.rcode = dns.RcodeServerFailure
+			result.ede, result.hasEDE = dns.ExtendedErrorCodeNetworkError, true
+		} else {
+			result.answers = resp.Answer
+			result.authority = resp.Ns
+			result.additional = withoutOPT(resp.Extra)
+			result.rcode = resp.Rcode
+			if subnetScope(resp) > 0 {
+				scoped = subnet
+			}
+		}
+	} else {
+		// Итеративное разрешение от ближайшего известного делегирования.
+		// DNSKEY и DS запрашиваются с подписями, как и при пересылке.
+		if qtype == dns.TypeDNSKEY || qtype == dns.TypeDS {
+			ctx = withSignatures(ctx)
+		}
+		resp, err := s.iterate(ctx, qname, qtype)
+		if err != nil {
+			slog.Warn("Ошибка разрешения", "qname", qname, "qtype", qtypeStr, "error", err)
+			result.rcode = dns.RcodeServerFailure
+			result.ede, result.hasEDE = dns.ExtendedErrorCodeNoReachableAuthority, true
+		} else {
+			result.answers = resp.Answer
+			result.authority = resp.Ns
+			result.additional = withoutOPT(resp.Extra)
+			result.rcode = resp.Rcode
+		}
+	}
+	// Записи для имен вне цепочки CNAME от qname - признак попытки подмены
+	// кэша: такой ответ отвергается целиком
+	if owner := unrelatedOwner(qname, result.answers); owner != "" {
+		atomic.AddUint64(&s.mismatchedAnswers, 1)
+		slog.Warn("Ответ содержит записи для постороннего имени", "qname", qname, "qtype", qtypeStr, "owner", owner)
+		result = lookupResult{rcode: dns.RcodeServerFailure, ede: dns.ExtendedErrorCodeOther, hasEDE: true}
+	}
+	// Ответ с чрезмерным числом записей считается некорректным: он не
+	// попадает ни в кэш, ни к клиенту
+	if limit := s.cfg.MaxAnswerRecords; limit > 0 {
+		if n := len(result.answers) + len(result.authority) + len(result.additional); n > limit {
+			atomic.AddUint64(&s.oversizedAnswers, 1)
+			slog.Warn("Ответ содержит слишком много записей", "qname", qname, "qtype", qtypeStr, "records", n, "limit", limit)
+			result = lookupResult{rcode: dns.RcodeServerFailure, ede: dns.ExtendedErrorCodeOther, hasEDE: true}
+		}
+	}
+	if errors.Is(ctx.Err(), context.DeadlineExceeded) {
+		atomic.AddUint64(&s.resolutionDeadlines, 1)
+		slog.Warn("Превышено время разрешения", "qname", qname, "qtype", qtypeStr, "timeout", s.cfg.ResolutionTimeout.Duration)
+	} else if budgetExhausted(ctx) {
+		atomic.AddUint64(&s.queryBudgetExhausted, 1)
+		slog.Warn("Исчерпан лимит исходящих запросов", "qname", qname, "qtype", qtypeStr, "limit", s.cfg.MaxUpstreamQueries)
+	}
+
+	// Записи с нулевым TTL не кэшируются (RFC 1035), остальные хранятся
+	// в пределах [min_cache_ttl, max_cache_ttl] или границ зоны из zone_ttl
+	if ttl := minTTL(result.answers); ttl > 0 {
+		stored := s.cacheTTL(qname, ttl)
+		s.cache.Set(subnetKey(queryKey, scoped), &CacheEntry{
+			Name:       dns.Fqdn(qname),
+			Qtype:      qtype,
+			Records:    copyRecords(result.answers),
+			Authority:  copyRecords(result.authority),
+			Additional: copyRecords(result.additional),
+			ExpiresAt:  time.Now().Add(stored),
+			TTL:        stored,
+			Subnet:     scoped,
+			Rcode:      result.rcode,
+		})
+
+		// Каждое звено цепочки CNAME кэшируется отдельно, чтобы запрос
+		// к цели, в том числе из другой зоны, не требовал нового разрешения
+		for target, records := range chainLinks(qname, qtype, result.answers) {
+			stored := s.cacheTTL(target, minTTL(records))
+			s.cache.Set(subnetKey(cacheKey(target, qtype), scoped), &CacheEntry{
+				Name:      target,
+				Qtype:     qtype,
+				Records:   copyRecords(records),
+				ExpiresAt: time.Now().Add(stored),
+				TTL:       stored,
+				Subnet:    scoped,
+			})
+		}
+	}
+
+	// Отрицательные ответы кэшируются на срок из SOA (RFC 2308), но не
+	// дольше max_negative_ttl, чтобы повторные запросы к несуществующим
+	// именам не уходили каждый раз к вышестоящим серверам
+	if ttl := negativeTTL(result.rcode, result.answers, result.authority); ttl > 0 && s.cfg.MaxNegativeTTL.Duration > 0 {
+		stored := clampTTL(ttl, 0, s.cfg.MaxNegativeTTL.Duration)
+		s.cache.Set(subnetKey(queryKey, scoped), &CacheEntry{
+			Name:      dns.Fqdn(qname),
+			Qtype:     qtype,
+			Authority: copyRecords(result.authority),
+			ExpiresAt: time.Now().Add(stored),
+			TTL:       stored,
+			Subnet:    scoped,
+			Rcode:     result.rcode,
+		})
+	}
+
+	return result
+}
+
+// resolutionContext ограничивает одно разрешение со всеми вложенными
+// запросами (направления, адреса серверов имен) сроком resolution_timeout
+// и числом исходящих запросов max_upstream_queries. Отмена parent
+// прерывает разрешение.
+func (s *DNSServer) resolutionContext(parent context.Context) (context.Context, context.CancelFunc) {
+	ctx := withQueryBudget(parent, s.cfg.MaxUpstreamQueries)
+	return context.WithTimeout(ctx, s.cfg.ResolutionTimeout.Duration)
+}
+
+// withoutOPT убирает OPT-запись из секции additional: EDNS согласуется
+// с каждым клиентом отдельно
+func withoutOPT(extra []dns.RR) []dns.RR {
+	var records []dns.RR
+	for _, rr := range extra {
+		if rr.Header().Rrtype != dns.TypeOPT {
+			records = append(records, rr)
+		}
+	}
+	return records
+}
+
+// responseSections заполняет секции authority и additional ответа клиенту.
+// В режиме минимальных ответов передается только SOA отрицательного ответа.
+func (s *DNSServer) responseSections(reply *dns.Msg, result lookupResult, negative bool) {
+	if !s.cfg.MinimalResponses {
+		reply.Ns = append(reply.Ns, result.authority...)
+		reply.Extra = append(reply.Extra, result.additional...)
+		return
+	}
+	if negative {
+		for _, rr := range result.authority {
+			if rr.Header().Rrtype == dns.TypeSOA {
+				reply.Ns = append(reply.Ns, rr)
+			}
+		}
+	}
+}
+
+// validateDNSSEC проверяет DNSSEC-подпись ответа
+func (s *DNSServer) validateDNSSEC(qname string, reply *dns.Msg) DNSSECValidationResult {
+	slog.Debug("Начало проверки DNSSEC", "qname", qname)
+	
+	// Извлечение RRSIG и RRSET
+	var rrsigs []*dns.RRSIG
+	var rrset []dns.RR
+	
+	for _, rr := range reply.Answer {
+		if rrsig, ok := rr.(*dns.RRSIG); ok {
+			rrsigs = append(rrsigs, rrsig)
+		} else {
+			rrset = append(rrset, rr)
+		}
+	}
+	
+	if len(rrsigs) == 0 {
+		slog.Debug("Нет RRSIG записей для проверки", "qname", qname)
+		return DNSSEC_INDETERMINATE
+	}
+	
+	if len(rrset) == 0 {
+		slog.Debug("Нет RRSET для проверки", "qname", qname)
+		return DNSSEC_INDETERMINATE
+	}
+	
+	// Проверка каждой подписи
+	for _, rrsig := range rrsigs {
+		slog.Debug("Проверка RRSIG", "rrsig", rrsig.String())
+		
+		// Получение DNSKEY
+		dnskey, err := s.getDNSKEY(rrsig.SignerName, rrsig.KeyTag, rrsig.Algorithm)
+		if err != nil {
+			slog.Warn("Не удалось получить DNSKEY", "signer", rrsig.SignerName, "error", err)
+			return DNSSEC_INDETERMINATE
+		}
+		
+		if dnskey == nil {
+			slog.Warn("DNSKEY не найден", "signer", rrsig.SignerName)
+			return DNSSEC_INDETERMINATE
+		}
+		
+		// Проверка подписи
+		err = rrsig.Verify(dnskey, rrset)
+		if err != nil {
+			slog.Warn("Проверка подписи провалилась", "qname", qname, "error", err)
+			return DNSSEC_BOGUS
+		}
+		
+		slog.Debug("Подпись проверена успешно", "qname", qname)
+	}
+	
+	return DNSSEC_SECURE
+}
+
+// validateNegativeResponse проверяет DNSSEC для отрицательных ответов
+func (s *DNSServer) validateNegativeResponse(qname string, reply *dns.Msg) DNSSECValidationResult {
+	slog.Debug("Проверка DNSSEC для отрицательного ответа", "qname", qname)
+	
+	// Поиск NSEC/NSEC3 и RRSIG записей
+	var nsecRecords []dns.RR
+	var rrsigs []*dns.RRSIG
+	
+	for _, rr := range reply.Ns {
+		switch rr.(type) {
+		case *dns.NSEC:
+			nsecRecords = append(nsecRecords, rr)
+		case *dns.NSEC3:
+			nsecRecords = append(nsecRecords, rr)
+		case *dns.RRSIG:
+			if rrsig, ok := rr.(*dns.RRSIG); ok {
+				if rrsig.TypeCovered == dns.TypeNSEC || rrsig.TypeCovered == dns.TypeNSEC3 {
+					rrsigs = append(rrsigs, rrsig)
+				}
+			}
+		}
+	}
+	
+	if len(nsecRecords) == 0 || len(rrsigs) == 0 {
+		slog.Debug("Нет NSEC/NSEC3 или RRSIG записей для проверки отрицательного ответа", "qname", qname)
+		return DNSSEC_INDETERMINATE
+	}
+	
+	// Проверка подписей
+	for _, rrsig := range rrsigs {
+		dnskey, err := s.getDNSKEY(rrsig.SignerName, rrsig.KeyTag, rrsig.Algorithm)
+		if err != nil || dnskey == nil {
+			slog.Warn("Не удалось получить DNSKEY для отрицательного ответа", "qname", qname, "error", err)
+			return DNSSEC_INDETERMINATE
+		}
+		
+		err = rrsig.Verify(dnskey, nsecRecords)
+		if err != nil {
+			slog.Warn("Проверка подписи отрицательного ответа провалилась", "qname", qname, "error", err)
+			return DNSSEC_BOGUS
+		}
+	}
+	
+	return DNSSEC_SECURE
+}
+
+// getDNSKEY получает DNSKEY по имени, тегу и алгоритму
+func (s *DNSServer) getDNSKEY(signerName string, keyTag uint16, algorithm uint8) (*dns.DNSKEY, error) {
+	cacheKey := fmt.Sprintf("%s:%d:%d", signerName, keyTag, algorithm)
+	
+	// Проверка кэша
+	if cached, ok := s.keyCache.Load(cacheKey); ok {
+		if cachedTime, ok := s.keyCacheTime.Load(cacheKey); ok {
+			if time.Since(cachedTime.(time.Time)) < keyCacheTTL {
+				atomic.AddUint64(&s.cacheHits, 1)
+				return cached.(*dns.DNSKEY), nil
+			}
+		}
+	}
+	
+	atomic.AddUint64(&s.cacheMisses, 1)
+	
+	// Получение обходом делегирований
+	ctx, cancel := s.resolutionContext(context.Background())
+	results := s.lookupRecords(ctx, signerName, "DNSKEY")
+	cancel()
+	for _, res := range results {
+		if rr, err := dns.NewRR(res); err == nil {
+			if dnskey, ok := rr.(*dns.DNSKEY); ok {
+				if dnskey.KeyTag() == keyTag && dnskey.Algorithm == algorithm {
+					// Кэширование
+					s.keyCache.Store(cacheKey, dnskey)
+					s.keyCacheTime.Store(cacheKey, time.Now())
+					return dnskey, nil
+				}
+			}
+		}
+	}
+	
+	// Если не найдено через резолвер, попробуем получить через авторитетные серверы
+	dnskey, err := s.fetchDNSKEYFromAuthoritative(signerName, keyTag, algorithm)
+	if err == nil && dnskey != nil {
+		s.keyCache.Store(cacheKey, dnskey)
+		s.keyCacheTime.Store(cacheKey, time.Now())
+		return dnskey, nil
+	}
+	
+	return nil, fmt.Errorf("DNSKEY не найден для %s, tag=%d, alg=%d", signerName, keyTag, algorithm)
+}
+
+// fetchDNSKEYFromAuthoritative получает DNSKEY напрямую от авторитетных серверов
+func (s *DNSServer) fetchDNSKEYFromAuthoritative(signerName string, keyTag uint16, algorithm uint8) (*dns.DNSKEY, error) {
+	ctx, cancel := s.resolutionContext(context.Background())
+	defer cancel()
+	nsIPs, err := s.zoneServers(ctx, signerName)
+	if err != nil {
+		return nil, err
+	}
+
+	// Запрос DNSKEY у первого доступного NS сервера
+	c := &dns.Client{Timeout: s.cfg.UpstreamTimeout.Duration}
+	msg := &dns.Msg{}
+	msg.SetQuestion(dns.Fqdn(signerName), dns.TypeDNSKEY)
+
+	for _, nsIP := range s.candidates(nsIPs) {
+		resp, err := s.exchange(ctx, c, msg, s.authAddr(nsIP))
+		if err != nil {
+			continue
+		}
+
+		for _, rr := range resp.Answer {
+			if dnskey, ok := rr.(*dns.DNSKEY); ok {
+				if dnskey.KeyTag() == keyTag && dnskey.Algorithm == algorithm {
+					return dnskey, nil
+				}
+			}
+		}
+	}
+
+	return nil, fmt.Errorf("не удалось получить DNSKEY от авторитетных серверов")
+}
+
+// zoneServers возвращает адреса авторитетных серверов зоны из кэша
+// делегирований или разрешает их через NS-записи
+func (s *DNSServer) zoneServers(ctx context.Context, zone string) ([]string, error) {
+	if servers, ok := s.delegations.Get(zone); ok {
+		return servers, nil
+	}
+
+	// Получение NS записей
+	nsResults := s.nameLookup(ctx, zone, "NS")
+	if len(nsResults) == 0 {
+		return nil, fmt.Errorf("NS записи не найдены для %s", zone)
+	}
+
+	var nsNames []string
+	var nsTTL uint32
+	for _, nsRes := range nsResults {
+		if rr, err := dns.NewRR(nsRes); err == nil {
+			if ns, ok := rr.(*dns.NS); ok {
+				if nsTTL == 0 || ns.Hdr.Ttl < nsTTL {
+					nsTTL = ns.Hdr.Ttl
+				}
+				nsNames = append(nsNames, ns.Ns)
+			}
+		}
+	}
+
+	nsIPs := s.resolveNSAddrs(ctx, nsNames)
+	if len(nsIPs) == 0 {
+		return nil, fmt.Errorf("IP адреса NS серверов не найдены для %s", zone)
+	}
+
+	s.delegations.Set(zone, nsIPs, clampTTL(nsTTL, 0, s.cfg.MaxCacheTTL.Duration))
+	return nsIPs, nil
+}
+
+// fetchFromAuthoritative обходит делегирования до авторитетного сервера и
+// запрашивает у него записи с подписями
+func (s *DNSServer) fetchFromAuthoritative(qname string, qtype uint16) ([]dns.RR, []*dns.RRSIG) {
+	ctx, cancel := s.resolutionContext(context.Background())
+	defer cancel()
+
+	resp, err := s.iterate(withSignatures(ctx), qname, qtype)
+	if err != nil {
+		if ctx.Err() != nil {
+			atomic.AddUint64(&s.resolutionDeadlines, 1)
+			slog.Warn("Превышено время обхода делегирований", "qname", qname)
+		} else if budgetExhausted(ctx) {
+			atomic.AddUint64(&s.queryBudgetExhausted, 1)
+			slog.Warn("Исчерпан лимит запросов при обходе делегирований", "qname", qname)
+		} else {
+			slog.Debug("Обход делегирований не дал ответа", "qname", qname, "error", err)
+		}
+		return nil, nil
+	}
+	return answerWithSignatures(resp, qtype)
+}
+
+// iterate обходит делегирования от ближайшей известной зоны (или от корня)
+// до авторитетного сервера qname и возвращает его ответ: записи, NXDOMAIN
+// или NODATA с SOA в секции authority. Каждое направление должно вести
+// строго ниже текущей зоны, поэтому зоны, ссылающиеся друг на друга, не
+// зацикливают обход: обратное направление отвергается, и обход завершается
+// ошибкой.
+func (s *DNSServer) iterate(ctx context.Context, qname string, qtype uint16) (*dns.Msg, error) {
+	zone, servers, ok := s.delegations.Closest(qname)
+	if !ok {
+		zone, servers = ".", s.rootServers
+	}
+
+	for depth := 0; depth < maxReferrals; depth++ {
+		resp := s.queryZone(ctx, servers, qname, qtype)
+		if resp == nil && zone == "." {
+			resp = s.retryRoots(ctx, servers, qname, qtype)
+		}
+		if resp == nil {
+			return nil, fmt.Errorf("серверы зоны %s не ответили", zone)
+		}
+
+		if resp.Rcode == dns.RcodeNameError || len(resp.Answer) > 0 {
+			return inBailiwick(resp, zone), nil
+		}
+
+		child, childServers, ttl, ok := s.referral(ctx, resp, zone, qname)
+		if !ok {
+			if resp.Authoritative || hasSOA(resp.Ns) {
+				return inBailiwick(resp, zone), nil
+			}
+			return nil, fmt.Errorf("сервер зоны %s не дал ни ответа, ни направления", zone)
+		}
+		slog.Debug("Направление к дочерней зоне", "zone", child, "servers", len(childServers))
+		s.delegations.Set(child, childServers, clampTTL(ttl, 0, s.cfg.MaxCacheTTL.Duration))
+		zone, servers = child, childServers
+	}
+
+	slog.Warn("Превышено количество направлений", "qname", qname, "limit", maxReferrals)
+	return nil, fmt.Errorf("превышено количество направлений (%d)", maxReferrals)
+}
+
+// queryAuthoritative запрашивает записи у конкретного авторитетного сервера;
+// подписи DNSSEC запрашиваются, если их требует ctx
+func (s *DNSServer) queryAuthoritative(ctx context.Context, server, qname string, qtype uint16) (*dns.Msg, error) {
+	c := &dns.Client{Timeout: s.cfg.UpstreamTimeout.Duration}
+	msg := &dns.Msg{}
+	msg.SetQuestion(dns.Fqdn(qname), qtype)
+	msg.SetEdns0(s.cfg.UpstreamUDPSize, wantSignatures(ctx))
+
+	return s.exchange(ctx, c, msg, s.authAddr(server))
+}
+
+// queryUsable запрашивает сервер и возвращает ответ, если он пригоден.
+// REFUSED, SERVFAIL и прочие ошибки означают, что этот сервер не может
+// ответить: такой ответ не принимается, опрашивается следующий сервер.
+func (s *DNSServer) queryUsable(ctx context.Context, server, qname string, qtype uint16) *dns.Msg {
+	r, err := s.queryAuthoritative(ctx, server, qname, qtype)
+	if err != nil {
+		return nil
+	}
+	if r.Rcode != dns.RcodeSuccess && r.Rcode != dns.RcodeNameError {
+		slog.Debug("Сервер не ответил на запрос", "server", server, "qname", qname, "rcode", dns.RcodeToString[r.Rcode])
+		return nil
+	}
+	return r
+}
+
+// answerWithSignatures выделяет из ответа записи запрошенного типа и их подписи
+func answerWithSignatures(resp *dns.Msg, qtype uint16) ([]dns.RR, []*dns.RRSIG) {
+	var rrset []dns.RR
+	var rrsigs []*dns.RRSIG
+	for _, answer := range resp.Answer {
+		if answer.Header().Rrtype == qtype {
+			rrset = append(rrset, answer)
+		} else if rrsig, ok := answer.(*dns.RRSIG); ok && rrsig.TypeCovered == qtype {
+			rrsigs = append(rrsigs, rrsig)
+		}
+	}
+	return rrset, rrsigs
+}
+
+// fetchDNSSECRecordsAsync асинхронно получает DNSSEC записи
+func (s *DNSServer) fetchDNSSECRecordsAsync(qname string) ([]dns.RR, []*dns.DNSKEY, []*dns.DS, error) {
+	var rrs []dns.RR
+	var keys []*dns.DNSKEY
+	var dsRecords []*dns.DS
+	var mu sync.Mutex
+	var wg sync.WaitGroup
+	ctx, cancel := s.resolutionContext(context.Background())
+	defer cancel()
+	
+	// Получение RRSIG
+	wg.Add(1)
+	go func() {
+		defer wg.Done()
+		results := s.lookupRecords(ctx, qname, "RRSIG")
+		mu.Lock()
+		defer mu.Unlock()
+		for _, res := range results {
+			if rr, err := dns.NewRR(res); err == nil {
+				rrs = append(rrs, rr)
+			}
+		}
+	}()
+	
+	// Получение DNSKEY
+	wg.Add(1)
+	go func() {
+		defer wg.Done()
+		results := s.lookupRecords(ctx, qname, "DNSKEY")
+		mu.Lock()
+		defer mu.Unlock()
+		for _, res := range results {
+			if rr, err := dns.NewRR(res); err == nil {
+				if key, ok := rr.(*dns.DNSKEY); ok {
+					keys = append(keys, key)
+				}
+			}
+		}
+	}()
+	
+	// Получение DS
+	wg.Add(1)
+	go func() {
+		defer wg.Done()
+		results := s.lookupRecords(ctx, qname, "DS")
+		mu.Lock()
+		defer mu.Unlock()
+		for _, res := range results {
+			if rr, err := dns.NewRR(res); err == nil {
+				if ds, ok := rr.(*dns.DS); ok {
+					dsRecords = append(dsRecords, ds)
+				}
+			}
+		}
+	}()
+	
+	wg.Wait()
+	
+	if len(rrs) == 0 && len(keys) == 0 && len(dsRecords) == 0 {
+		return rrs, keys, dsRecords, fmt.Errorf("DNSSEC записи не найдены")
+	}
+	
+	return rrs, keys, dsRecords, nil
+}
+
+// sendANYResponse отвечает на запрос ANY синтезированной записью HINFO "RFC8482"
+func (s *DNSServer) sendANYResponse(w dns.ResponseWriter, req *dns.Msg) {
+	reply := new(dns.Msg)
+	reply.SetReply(req)
+	reply.RecursionAvailable = true
+	reply.Answer = append(reply.Answer, &dns.HINFO{
+		Hdr: dns.RR_Header{Name: req.Question[0].Name, Rrtype: dns.TypeHINFO, Class: dns.ClassINET, Ttl: anyHINFOTTL},
+		Cpu: "RFC8482",
+		Os:  "",
+	})
+	if err := w.WriteMsg(reply); err != nil {
+		slog.Error("Ошибка записи ответа", "error", err)
+	}
+}
+
+// sendErrorResponse отправляет ошибочный ответ
+func (s *DNSServer) sendErrorResponse(w dns.ResponseWriter, req *dns.Msg, rcode int, errMsg string) {
+	slog.Debug("Ответ с ошибкой", "rcode", dns.RcodeToString[rcode], "reason", errMsg)
+	reply := new(dns.Msg)
+	reply.SetRcode(req, rcode)
+	reply.RecursionAvailable = true
+	w.WriteMsg(reply)
+}
+
+// Start запускает DNS-сервер на одном или нескольких адресах через запятую
+// (например, "0.0.0.0:53,[::]:53") и блокируется до его остановки. Если
+// один из адресов не удалось открыть, остальные останавливаются. Сервер
+// работает до вызова Shutdown и сам по таймеру не перезапускается: кэш и
+// сокеты живут все время работы процесса. После Shutdown возвращает nil.
+func (s *DNSServer) Start(addr string) error {
+	s.loadCache()
+
+	if s.metricsAddr != "" {
+		go s.startMetricsServer(s.metricsAddr)
+	}
+
+	plan := s.listenerPlan(addr)
+	for _, l := range plan {
+		switch l.Transport {
+		case transportDoT:
+			go s.startDoT(l.Addr)
+		case transportDoH:
+			go s.startDoH(l.Addr)
+		}
+	}
+
+	if s.cfg.HealthListen != "" {
+		go s.startHealthServer(s.cfg.HealthListen)
+	}
+
+	// Запуск очистки кэша в отдельной горутине
+	go s.startCleaner()
+
+	if s.cfg.PrefetchInterval.Duration > 0 {
+		go s.startPrefetcher()
+	}
+	
+	// Регистрация обработчика
+	dns.HandleFunc(".", s.handleRequest)
+	
+	// Создание серверов. Адрес без IP (":53") или "[::]:53" открывает
+	// двухстековый сокет; отдельные адреса IPv4 и IPv6 нужны там, где
+	// двухстековые сокеты отключены (IPV6_V6ONLY). На каждый UDP-адрес
+	// открывается udp_listeners сокетов со своими циклами приема
+	listeners := s.udpListeners()
+	var servers []*dns.Server
+	for _, l := range plan {
+		switch l.Transport {
+		case transportUDP:
+			for i := 0; i < listeners; i++ {
+				servers = append(servers, &dns.Server{
+					Addr:      l.Addr,
+					Net:       "udp",
+					ReusePort: listeners > 1,
+					// Буфер приема должен вмещать запросы клиентов с EDNS, а не только 512 байт
+					UDPSize: int(s.cfg.MaxUDPSize),
+				})
+			}
+		case transportTCP:
+			servers = append(servers, s.withTCPTimeouts(&dns.Server{Addr: l.Addr, Net: "tcp"}))
+		}
+	}
+	// Прогрев кэша идет параллельно с открытием сокетов; о готовности
+	// сервер сообщает, только когда открыты все сокеты и прогрев завершен
+	warmed := s.warmUp()
+	var started int32
+	for _, server := range servers {
+		server.NotifyStartedFunc = func() {
+			if atomic.AddInt32(&started, 1) == int32(len(servers)) {
+				go func() {
+					<-warmed
+					if atomic.LoadUint32(&s.stopping) == 0 {
+						atomic.StoreUint32(&s.serving, 1)
+					}
+				}()
+			}
+		}
+	}
+	s.dnsServerMu.Lock()
+	s.dnsServers = servers
+	s.dnsServerMu.Unlock()
+
+	errc := make(chan error, len(servers))
+	for _, server := range servers {
+		go func(server *dns.Server) {
+			slog.Info("DNS-сервер запущен", "addr", server.Addr, "net", server.Net)
+			errc <- server.ListenAndServe()
+		}(server)
+	}
+
+	err := <-errc
+	atomic.StoreUint32(&s.serving, 0)
+	if atomic.LoadUint32(&s.stopping) == 1 {
+		return nil
+	}
+	for _, server := range servers {
+		server.Shutdown()
+	}
+	return err
+}
+
+// udpListeners возвращает число UDP-сокетов на один адрес прослушивания
+func (s *DNSServer) udpListeners() int {
+	if s.cfg.UDPListeners > 0 {
+		return s.cfg.UDPListeners
+	}
+	return runtime.GOMAXPROCS(0)
+}
+
+// drain ждет завершения обрабатываемых запросов, но не дольше срока ctx
+func (s *DNSServer) drain(ctx context.Context) {
+	ticker := time.NewTicker(drainInterval)
+	defer ticker.Stop()
+	for atomic.LoadInt64(&s.activeRequests) > 0 {
+		select {
+		case <-ticker.C:
+		case <-ctx.Done():
+			slog.Warn("Не все запросы завершились до остановки", "active", atomic.LoadInt64(&s.activeRequests))
+			return
+		}
+	}
+}
+
+// splitListen разбирает список адресов прослушивания через запятую
+func splitListen(list string) []string {
+	var addrs []string
+	for _, addr := range strings.Split(list, ",") {
+		if addr = strings.TrimSpace(addr); addr != "" {
+			addrs = append(addrs, addr)
+		}
+	}
+	return addrs
+}
+
+// Shutdown прекращает прием запросов, дает обрабатываемым запросам
+// завершиться до истечения ctx и сохраняет кэш на диск. Сокеты закрываются
+// только после того, как начатые запросы отправят ответы: на время
+// завершения новые запросы получают REFUSED.
+func (s *DNSServer) Shutdown(ctx context.Context) error {
+	atomic.StoreUint32(&s.stopping, 1)
+	atomic.StoreUint32(&s.serving, 0)
+	s.drain(ctx)
+
+	s.dnsServerMu.Lock()
+	servers := append(append([]*dns.Server(nil), s.dnsServers...), s.dotServers...)
+	dohServers := s.dohServers
+	s.dnsServerMu.Unlock()
+
+	var err error
+	for _, server := range servers {
+		if serr := server.ShutdownContext(ctx); serr != nil && err == nil {
+			err = serr
+		}
+	}
+	for _, server := range dohServers {
+		if serr := server.Shutdown(ctx); serr != nil && err == nil {
+			err = serr
+		}
+	}
+	s.saveCache()
+	if cerr := s.queryLog.Close(); cerr != nil {
+		slog.Warn("Ошибка закрытия журнала запросов", "error", cerr)
+	}
+	slog.Info("DNS-сервер остановлен")
+	return err
+}
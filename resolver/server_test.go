@@ -0,0 +1,643 @@
+package resolver
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"net"
+	"os"
+	"path/filepath"
+	"sync/atomic"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// testResponseWriter сохраняет ответ обработчика вместо отправки по сети
+type testResponseWriter struct {
+	remote net.Addr
+	msg    *dns.Msg
+}
+
+func (w *testResponseWriter) LocalAddr() net.Addr {
+	return &net.UDPAddr{IP: net.IPv4(127, 0, 0, 1), Port: 5454}
+}
+
+func (w *testResponseWriter) RemoteAddr() net.Addr {
+	if w.remote != nil {
+		return w.remote
+	}
+	return &net.UDPAddr{IP: net.IPv4(127, 0, 0, 1), Port: 40000}
+}
+
+func (w *testResponseWriter) Network() string { return "udp" }
+
+func (w *testResponseWriter) WriteMsg(m *dns.Msg) error {
+	w.msg = m
+	return nil
+}
+
+func (w *testResponseWriter) Write(b []byte) (int, error) {
+	m := new(dns.Msg)
+	if err := m.Unpack(b); err != nil {
+		return 0, err
+	}
+	w.msg = m
+	return len(b), nil
+}
+
+func (w *testResponseWriter) Close() error        { return nil }
+func (w *testResponseWriter) TsigStatus() error   { return nil }
+func (w *testResponseWriter) TsigTimersOnly(bool) {}
+func (w *testResponseWriter) Hijack()             {}
+
+// startOutcomeUpstream запускает вышестоящий резолвер с разными исходами по имени
+func startOutcomeUpstream(t *testing.T) string {
+	return startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		reply.RecursionAvailable = true
+		switch r.Question[0].Name {
+		case "ok.example.":
+			rr, _ := dns.NewRR("ok.example. 300 IN A 192.0.2.1")
+			ns, _ := dns.NewRR("example. 3600 IN NS ns.example.")
+			glue, _ := dns.NewRR("ns.example. 3600 IN A 192.0.2.53")
+			reply.Answer = append(reply.Answer, rr)
+			reply.Ns = append(reply.Ns, ns)
+			reply.Extra = append(reply.Extra, glue)
+		case "missing.example.":
+			soa, _ := dns.NewRR("example. 300 IN SOA ns.example. admin.example. 1 3600 600 86400 300")
+			reply.Rcode = dns.RcodeNameError
+			reply.Ns = append(reply.Ns, soa)
+		case "broken.example.":
+			reply.Rcode = dns.RcodeServerFailure
+		}
+		w.WriteMsg(reply)
+	})
+}
+
+func TestRequireRDRefusesNonRecursiveQueries(t *testing.T) {
+	s := NewDNSServer()
+	s.cfg.RequireRD = true
+	s.forwarders = []string{startOutcomeUpstream(t)}
+
+	req := new(dns.Msg)
+	req.SetQuestion("ok.example.", dns.TypeA)
+	req.RecursionDesired = false
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+	if w.msg == nil || w.msg.Rcode != dns.RcodeRefused || len(w.msg.Answer) != 0 {
+		t.Fatalf("Запрос без RD в режиме require_rd должен получать REFUSED, получено %v", w.msg)
+	}
+	if _, ok := s.cache.Get(cacheKey("ok.example.", dns.TypeA)); ok {
+		t.Errorf("Запрос без RD не должен пересылаться")
+	}
+
+	req.RecursionDesired = true
+	w = &testResponseWriter{}
+	s.handleRequest(w, req)
+	if w.msg == nil || w.msg.Rcode != dns.RcodeSuccess || len(w.msg.Answer) != 1 {
+		t.Errorf("Запрос с RD должен получать пересланный ответ, получено %v", w.msg)
+	}
+}
+
+func TestResponseFlags(t *testing.T) {
+	s := NewDNSServer()
+	s.forwarders = []string{startOutcomeUpstream(t)}
+
+	req := new(dns.Msg)
+	req.SetQuestion("ok.example.", dns.TypeA)
+	req.RecursionDesired = true
+	req.CheckingDisabled = true
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil {
+		t.Fatalf("Ответ не получен")
+	}
+	if !w.msg.RecursionDesired {
+		t.Errorf("Флаг RD запроса должен копироваться в ответ")
+	}
+	if !w.msg.CheckingDisabled {
+		t.Errorf("Флаг CD запроса должен копироваться в ответ")
+	}
+	if !w.msg.RecursionAvailable {
+		t.Errorf("В ответе рекурсивного резолвера должен быть установлен RA")
+	}
+	if w.msg.Authoritative {
+		t.Errorf("Рекурсивный ответ не должен быть авторитетным")
+	}
+	if w.msg.Rcode != dns.RcodeSuccess || len(w.msg.Answer) != 1 {
+		t.Errorf("Ожидался NOERROR с одной записью, получено %s %v", dns.RcodeToString[w.msg.Rcode], w.msg.Answer)
+	}
+}
+
+func TestResponseRcodeReflectsOutcome(t *testing.T) {
+	s := NewDNSServer()
+	s.forwarders = []string{startOutcomeUpstream(t)}
+
+	for name, want := range map[string]int{
+		"missing.example.": dns.RcodeNameError,
+		"nodata.example.":  dns.RcodeSuccess,
+		"broken.example.":  dns.RcodeServerFailure,
+	} {
+		req := new(dns.Msg)
+		req.SetQuestion(name, dns.TypeA)
+		w := &testResponseWriter{}
+		s.handleRequest(w, req)
+
+		if w.msg == nil {
+			t.Fatalf("Ответ для %s не получен", name)
+		}
+		if w.msg.Rcode != want {
+			t.Errorf("Для %s ожидался %s, получено %s", name, dns.RcodeToString[want], dns.RcodeToString[w.msg.Rcode])
+		}
+		if !w.msg.RecursionAvailable {
+			t.Errorf("Для %s в ответе должен быть установлен RA", name)
+		}
+	}
+}
+
+func TestMultipleQuestionsFormErr(t *testing.T) {
+	s := NewDNSServer()
+
+	req := new(dns.Msg)
+	req.SetQuestion("example.com.", dns.TypeA)
+	req.Question = append(req.Question, dns.Question{Name: "example.org.", Qtype: dns.TypeAAAA, Qclass: dns.ClassINET})
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil || w.msg.Rcode != dns.RcodeFormatError {
+		t.Fatalf("Ожидался FORMERR для запроса с двумя вопросами, получено %v", w.msg)
+	}
+	if w.msg.Id != req.Id {
+		t.Errorf("ID ответа %d не совпадает с ID запроса %d", w.msg.Id, req.Id)
+	}
+	if len(w.msg.Answer) != 0 {
+		t.Errorf("Ответ FORMERR не должен содержать записей")
+	}
+}
+
+func TestUnsupportedRequestsRcode(t *testing.T) {
+	s := NewDNSServer()
+
+	update := new(dns.Msg)
+	update.SetUpdate("example.com.")
+	hesiod := new(dns.Msg)
+	hesiod.SetQuestion("example.com.", dns.TypeA)
+	hesiod.Question[0].Qclass = dns.ClassHESIOD
+	notify := new(dns.Msg)
+	notify.SetNotify("example.com.")
+	empty := new(dns.Msg)
+
+	tests := []struct {
+		name  string
+		req   *dns.Msg
+		rcode int
+	}{
+		{"UPDATE", update, dns.RcodeNotImplemented},
+		{"NOTIFY", notify, dns.RcodeNotImplemented},
+		{"класс HS", hesiod, dns.RcodeRefused},
+		{"без вопросов", empty, dns.RcodeFormatError},
+	}
+	for _, tt := range tests {
+		w := &testResponseWriter{}
+		s.handleRequest(w, tt.req)
+		if w.msg == nil || w.msg.Rcode != tt.rcode {
+			t.Errorf("%s: ожидался %s, получено %v", tt.name, dns.RcodeToString[tt.rcode], w.msg)
+		}
+	}
+}
+
+func TestEmptyQueryKeepalive(t *testing.T) {
+	s := NewDNSServer()
+	s.cfg.EmptyQueryKeepalive = true
+
+	req := new(dns.Msg)
+	req.Id = dns.Id()
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil || w.msg.Rcode != dns.RcodeSuccess || w.msg.Id != req.Id {
+		t.Fatalf("Ожидался NOERROR на пустой запрос, получено %v", w.msg)
+	}
+	if len(w.msg.Question) != 0 || len(w.msg.Answer) != 0 {
+		t.Errorf("Ответ на пустой запрос не должен содержать вопросов и записей: %v", w.msg)
+	}
+
+	// Неподдерживаемые коды операций отклоняются и в этом режиме
+	update := new(dns.Msg)
+	update.SetUpdate("example.com.")
+	w = &testResponseWriter{}
+	s.handleRequest(w, update)
+	if w.msg == nil || w.msg.Rcode != dns.RcodeNotImplemented {
+		t.Errorf("Ожидался NOTIMP для UPDATE, получено %v", w.msg)
+	}
+}
+
+func TestErrorRcode(t *testing.T) {
+	tests := []struct {
+		err   error
+		rcode int
+	}{
+		{nil, dns.RcodeSuccess},
+		{ErrNXDOMAIN, dns.RcodeNameError},
+		{ErrServFail, dns.RcodeServerFailure},
+		{ErrRefused, dns.RcodeRefused},
+		{fmt.Errorf("обертка: %w", ErrNXDOMAIN), dns.RcodeNameError},
+		{context.Canceled, dns.RcodeServerFailure},
+	}
+	for _, tt := range tests {
+		if got := Rcode(tt.err); got != tt.rcode {
+			t.Errorf("Rcode(%v) = %s, ожидалось %s", tt.err, dns.RcodeToString[got], dns.RcodeToString[tt.rcode])
+		}
+	}
+}
+
+func TestMinimalResponses(t *testing.T) {
+	upstream := startOutcomeUpstream(t)
+	query := func(s *DNSServer, name string) *dns.Msg {
+		req := new(dns.Msg)
+		req.SetQuestion(name, dns.TypeA)
+		w := &testResponseWriter{}
+		s.handleRequest(w, req)
+		if w.msg == nil {
+			t.Fatalf("Ответ на %s не получен", name)
+		}
+		return w.msg
+	}
+
+	s := NewDNSServer()
+	s.forwarders = []string{upstream}
+	resp := query(s, "ok.example.")
+	if len(resp.Answer) != 1 || len(resp.Ns) != 0 || len(resp.Extra) != 0 {
+		t.Errorf("В минимальном режиме ожидалась только секция answer, получено ns=%v extra=%v", resp.Ns, resp.Extra)
+	}
+	resp = query(s, "missing.example.")
+	if len(resp.Ns) != 1 || resp.Ns[0].Header().Rrtype != dns.TypeSOA {
+		t.Errorf("Отрицательный ответ должен содержать SOA, получено %v", resp.Ns)
+	}
+
+	full := NewDNSServer()
+	full.forwarders = []string{upstream}
+	full.cfg.MinimalResponses = false
+	resp = query(full, "ok.example.")
+	if len(resp.Ns) != 1 || len(resp.Extra) != 1 {
+		t.Errorf("Без минимального режима ожидались секции authority и additional, получено ns=%v extra=%v", resp.Ns, resp.Extra)
+	}
+	// Повторный ответ из кэша сохраняет секции
+	resp = query(full, "ok.example.")
+	if len(resp.Ns) != 1 || len(resp.Extra) != 1 {
+		t.Errorf("Ответ из кэша должен сохранять секции authority и additional, получено ns=%v extra=%v", resp.Ns, resp.Extra)
+	}
+}
+
+func TestANYQueryAnsweredWithRFC8482(t *testing.T) {
+	var queries atomic.Int32
+	s := NewDNSServer()
+	s.forwarders = []string{startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		queries.Add(1)
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		w.WriteMsg(reply)
+	})}
+
+	req := new(dns.Msg)
+	req.SetQuestion("any.example.", dns.TypeANY)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil {
+		t.Fatalf("Ответ не получен")
+	}
+	if w.msg.Rcode != dns.RcodeSuccess || len(w.msg.Answer) != 1 {
+		t.Fatalf("Ожидался NOERROR с одной записью, получено %s %v", dns.RcodeToString[w.msg.Rcode], w.msg.Answer)
+	}
+	hinfo, ok := w.msg.Answer[0].(*dns.HINFO)
+	if !ok || hinfo.Cpu != "RFC8482" || hinfo.Hdr.Name != "any.example." {
+		t.Errorf("Ожидалась запись HINFO \"RFC8482\", получено %v", w.msg.Answer[0])
+	}
+	if n := queries.Load(); n != 0 {
+		t.Errorf("Запрос ANY не должен пересылаться, запросов к upstream: %d", n)
+	}
+}
+
+func TestResolverAPI(t *testing.T) {
+	cfg := DefaultConfig()
+	cfg.Forwarders = []string{startOutcomeUpstream(t)}
+	cfg.MetricsListen = ""
+	r, err := New(cfg)
+	if err != nil {
+		t.Fatalf("Ошибка создания резолвера: %v", err)
+	}
+	ctx := context.Background()
+
+	records, err := r.Resolve(ctx, "ok.example", dns.TypeA)
+	if err != nil || len(records) != 1 {
+		t.Fatalf("Ожидалась одна запись, получено %v, %v", records, err)
+	}
+
+	if _, err := r.Resolve(ctx, "missing.example.", dns.TypeA); !errors.Is(err, ErrNXDOMAIN) {
+		t.Errorf("Ожидалась ошибка ErrNXDOMAIN, получено %v", err)
+	}
+	if _, err := r.Resolve(ctx, "broken.example.", dns.TypeA); !errors.Is(err, ErrServFail) {
+		t.Errorf("Ожидалась ошибка ErrServFail, получено %v", err)
+	}
+	if records, err := r.Resolve(ctx, "nodata.example.", dns.TypeA); err != nil || len(records) != 0 {
+		t.Errorf("Для NODATA ожидался пустой ответ без ошибки, получено %v, %v", records, err)
+	}
+
+	cancelled, cancel := context.WithCancel(ctx)
+	cancel()
+	if _, err := r.Resolve(cancelled, "ok.example.", dns.TypeA); !errors.Is(err, context.Canceled) {
+		t.Errorf("Ожидалась ошибка отмены контекста, получено %v", err)
+	}
+}
+
+func TestShutdownStopsServerAndSavesCache(t *testing.T) {
+	addr := freeUDPAddr(t, "udp4", "127.0.0.1:0")
+
+	s := NewDNSServer()
+	s.cacheFile = filepath.Join(t.TempDir(), "cache.json")
+	s.metricsAddr = ""
+	s.cfg.HealthListen = ""
+	rr, _ := dns.NewRR("saved.example. 300 IN A 192.0.2.1")
+	s.cache.Set(cacheKey("saved.example.", dns.TypeA), &CacheEntry{
+		Name:      "saved.example.",
+		Qtype:     dns.TypeA,
+		Records:   []dns.RR{rr},
+		ExpiresAt: time.Now().Add(time.Minute),
+	})
+
+	errc := make(chan error, 1)
+	go func() { errc <- s.Start(addr) }()
+
+	waitServing(t, s)
+
+	ctx, cancel := context.WithTimeout(context.Background(), time.Second)
+	defer cancel()
+	if err := s.Shutdown(ctx); err != nil {
+		t.Fatalf("Ошибка остановки: %v", err)
+	}
+
+	select {
+	case err := <-errc:
+		if err != nil {
+			t.Errorf("После Shutdown Start должен вернуть nil, получено %v", err)
+		}
+	case <-time.After(2 * time.Second):
+		t.Fatalf("Start не вернулся после Shutdown")
+	}
+
+	if _, err := os.Stat(s.cacheFile); err != nil {
+		t.Errorf("Кэш должен быть сохранен при остановке: %v", err)
+	}
+}
+
+// freeUDPAddr возвращает свободный UDP-адрес; тест пропускается, если
+// семейство адресов недоступно
+func freeUDPAddr(t testing.TB, network, addr string) string {
+	t.Helper()
+	pc, err := net.ListenPacket(network, addr)
+	if err != nil {
+		t.Skipf("%s недоступен: %v", network, err)
+	}
+	defer pc.Close()
+	return pc.LocalAddr().String()
+}
+
+// waitServing ждет, пока сервер откроет все адреса прослушивания
+func waitServing(t testing.TB, s *DNSServer) {
+	t.Helper()
+	deadline := time.Now().Add(2 * time.Second)
+	for atomic.LoadUint32(&s.serving) == 0 {
+		if time.Now().After(deadline) {
+			t.Fatalf("Сервер не запустился")
+		}
+		time.Sleep(10 * time.Millisecond)
+	}
+}
+
+func TestStartServesIPv4AndIPv6Listeners(t *testing.T) {
+	v4 := freeUDPAddr(t, "udp4", "127.0.0.1:0")
+	v6 := freeUDPAddr(t, "udp6", "[::1]:0")
+
+	s := NewDNSServer()
+	s.cacheFile = ""
+	s.metricsAddr = ""
+	s.cfg.HealthListen = ""
+	go s.Start(v4 + "," + v6)
+	waitServing(t, s)
+	defer s.Shutdown(context.Background())
+
+	for _, addr := range []string{v4, v6} {
+		// ANY получает ответ без обращения к вышестоящим серверам (RFC 8482)
+		req := new(dns.Msg)
+		req.SetQuestion("example.com.", dns.TypeANY)
+		c := &dns.Client{Timeout: time.Second}
+		resp, _, err := c.Exchange(req, addr)
+		if err != nil {
+			t.Errorf("Клиент %s не получил ответ: %v", addr, err)
+			continue
+		}
+		if len(resp.Answer) == 0 {
+			t.Errorf("Клиент %s получил пустой ответ", addr)
+		}
+	}
+}
+
+func TestShutdownDrainsInFlightQueries(t *testing.T) {
+	upstream := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		time.Sleep(300 * time.Millisecond)
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		rr, _ := dns.NewRR(r.Question[0].Name + " 300 IN A 192.0.2.1")
+		reply.Answer = append(reply.Answer, rr)
+		w.WriteMsg(reply)
+	})
+	addr := freeUDPAddr(t, "udp4", "127.0.0.1:0")
+
+	s := NewDNSServer()
+	s.cacheFile = ""
+	s.metricsAddr = ""
+	s.cfg.HealthListen = ""
+	s.forwarders = []string{upstream}
+	go s.Start(addr)
+	waitServing(t, s)
+
+	type answer struct {
+		resp *dns.Msg
+		err  error
+	}
+	inflight := make(chan answer, 1)
+	go func() {
+		req := new(dns.Msg)
+		req.SetQuestion("slow.example.", dns.TypeA)
+		c := &dns.Client{Timeout: 2 * time.Second}
+		resp, _, err := c.Exchange(req, addr)
+		inflight <- answer{resp, err}
+	}()
+	deadline := time.Now().Add(time.Second)
+	for atomic.LoadInt64(&s.activeRequests) == 0 {
+		if time.Now().After(deadline) {
+			t.Fatalf("Запрос не начал обрабатываться")
+		}
+		time.Sleep(5 * time.Millisecond)
+	}
+
+	shutdown := make(chan error, 1)
+	go func() {
+		ctx, cancel := context.WithTimeout(context.Background(), 2*time.Second)
+		defer cancel()
+		shutdown <- s.Shutdown(ctx)
+	}()
+	for atomic.LoadUint32(&s.stopping) == 0 {
+		time.Sleep(time.Millisecond)
+	}
+
+	// Новый запрос во время завершения не принимается
+	req := new(dns.Msg)
+	req.SetQuestion("new.example.", dns.TypeA)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+	if w.msg == nil || w.msg.Rcode != dns.RcodeRefused {
+		t.Errorf("Во время завершения новый запрос должен получать REFUSED, получено %v", w.msg)
+	}
+
+	got := <-inflight
+	if got.err != nil {
+		t.Fatalf("Начатый запрос не получил ответ: %v", got.err)
+	}
+	if got.resp.Rcode != dns.RcodeSuccess || len(got.resp.Answer) != 1 {
+		t.Errorf("Начатый запрос должен завершиться ответом, получено %v", got.resp)
+	}
+	if err := <-shutdown; err != nil {
+		t.Errorf("Ошибка остановки: %v", err)
+	}
+}
+
+func TestStartOpensSeveralUDPListeners(t *testing.T) {
+	addr := freeUDPAddr(t, "udp4", "127.0.0.1:0")
+
+	s := NewDNSServer()
+	s.cacheFile = ""
+	s.metricsAddr = ""
+	s.cfg.HealthListen = ""
+	s.cfg.UDPListeners = 4
+	go s.Start(addr)
+	waitServing(t, s)
+	defer s.Shutdown(context.Background())
+
+	s.dnsServerMu.Lock()
+	listeners := len(s.dnsServers)
+	s.dnsServerMu.Unlock()
+	if listeners != 4 {
+		t.Fatalf("Ожидалось 4 UDP-сокета на адрес, открыто %d", listeners)
+	}
+
+	// Каждый клиентский сокет ядро закрепляет за одним из сокетов сервера;
+	// ответ должен прийти, какой бы из них ни принял запрос
+	for i := 0; i < 16; i++ {
+		req := new(dns.Msg)
+		req.SetQuestion("example.com.", dns.TypeANY)
+		c := &dns.Client{Timeout: time.Second}
+		if _, _, err := c.Exchange(req, addr); err != nil {
+			t.Fatalf("Запрос %d остался без ответа: %v", i, err)
+		}
+	}
+}
+
+func TestStartAnswersFromReceivingSocket(t *testing.T) {
+	first := freeUDPAddr(t, "udp4", "127.0.0.1:0")
+	second := freeUDPAddr(t, "udp4", "127.0.0.1:0")
+
+	s := NewDNSServer()
+	s.cacheFile = ""
+	s.metricsAddr = ""
+	s.cfg.HealthListen = ""
+	tr := &cannedTransport{}
+	s.transport = tr
+	s.forwarders = []string{"192.0.2.53:53"}
+	go s.Start(first + "," + second)
+	waitServing(t, s)
+	defer s.Shutdown(context.Background())
+
+	// Неподключенный сокет принимает ответ с любого адреса, поэтому можно
+	// проверить, с какого сокета сервера он отправлен
+	conn, err := net.ListenUDP("udp4", &net.UDPAddr{IP: net.IPv4(127, 0, 0, 1)})
+	if err != nil {
+		t.Fatalf("Не удалось открыть клиентский сокет: %v", err)
+	}
+	defer conn.Close()
+
+	for i, addr := range []string{first, second} {
+		req := new(dns.Msg)
+		req.SetQuestion("example.com.", dns.TypeA)
+		req.Id = uint16(i + 1)
+		wire, _ := req.Pack()
+		dst, _ := net.ResolveUDPAddr("udp4", addr)
+		if _, err := conn.WriteToUDP(wire, dst); err != nil {
+			t.Fatalf("Ошибка отправки на %s: %v", addr, err)
+		}
+
+		buf := make([]byte, dns.MaxMsgSize)
+		conn.SetReadDeadline(time.Now().Add(2 * time.Second))
+		n, from, err := conn.ReadFromUDP(buf)
+		if err != nil {
+			t.Fatalf("Нет ответа от %s: %v", addr, err)
+		}
+		if from.String() != dst.String() {
+			t.Errorf("Запрос на %s получил ответ с %s", addr, from)
+		}
+		resp := new(dns.Msg)
+		if err := resp.Unpack(buf[:n]); err != nil || resp.Id != req.Id || len(resp.Answer) != 1 {
+			t.Errorf("Некорректный ответ от %s: %v (ошибка %v)", addr, resp, err)
+		}
+	}
+
+	// Оба сокета используют общий кэш
+	if n := atomic.LoadUint64(&tr.queries); n != 1 {
+		t.Errorf("Второй сокет должен отвечать из общего кэша, запросов к серверу: %d", n)
+	}
+}
+
+func TestStartAnswersSERVFAILAfterHandlerPanic(t *testing.T) {
+	addr := freeUDPAddr(t, "udp4", "127.0.0.1:0")
+
+	s := NewDNSServer()
+	s.cacheFile = ""
+	s.metricsAddr = ""
+	s.cfg.HealthListen = ""
+	s.transport = panicTransport{}
+	s.forwarders = []string{"192.0.2.53:53"}
+	rr, _ := dns.NewRR("cached.example. 300 IN A 192.0.2.1")
+	s.cache.Set(cacheKey("cached.example.", dns.TypeA), &CacheEntry{
+		Name:      "cached.example.",
+		Qtype:     dns.TypeA,
+		Records:   []dns.RR{rr},
+		ExpiresAt: time.Now().Add(time.Hour),
+	})
+	go s.Start(addr)
+	waitServing(t, s)
+	defer s.Shutdown(context.Background())
+
+	c := &dns.Client{Timeout: 2 * time.Second}
+	req := new(dns.Msg)
+	req.SetQuestion("example.com.", dns.TypeA)
+	resp, _, err := c.Exchange(req, addr)
+	if err != nil {
+		t.Fatalf("Клиент не получил ответа после паники обработчика: %v", err)
+	}
+	if resp.Rcode != dns.RcodeServerFailure {
+		t.Errorf("Ожидался SERVFAIL, получено %s", dns.RcodeToString[resp.Rcode])
+	}
+
+	// Паника одного запроса не мешает обслуживанию следующих
+	req = new(dns.Msg)
+	req.SetQuestion("cached.example.", dns.TypeA)
+	resp, _, err = c.Exchange(req, addr)
+	if err != nil || resp.Rcode != dns.RcodeSuccess || len(resp.Answer) != 1 {
+		t.Errorf("Сервер не ответил после паники: %v (ошибка %v)", resp, err)
+	}
+}
@@ -0,0 +1,89 @@
+package resolver
+
+import (
+	"path/filepath"
+	"testing"
+
+	"github.com/miekg/dns"
+)
+
+const (
+	testHTTPSRecord = `svc.example. 300 IN HTTPS 1 . alpn="h3,h2" ipv4hint="192.0.2.10" ipv6hint="2001:db8::10" ech="AEX+DQBBAQAgACAKOV6qEsRBpOt1j1avpYQc"`
+	testSVCBRecord  = `_dns.svc.example. 300 IN SVCB 1 dns.svc.example. alpn="dot" port="853"`
+	testSVCBGlue    = `dns.svc.example. 300 IN A 192.0.2.53`
+)
+
+// startSVCBUpstream отвечает записью HTTPS или SVCB с подсказкой адреса в additional
+func startSVCBUpstream(t *testing.T) string {
+	return startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		switch r.Question[0].Qtype {
+		case dns.TypeHTTPS:
+			rr, _ := dns.NewRR(testHTTPSRecord)
+			reply.Answer = append(reply.Answer, rr)
+		case dns.TypeSVCB:
+			rr, _ := dns.NewRR(testSVCBRecord)
+			glue, _ := dns.NewRR(testSVCBGlue)
+			reply.Answer = append(reply.Answer, rr)
+			reply.Extra = append(reply.Extra, glue)
+		}
+		w.WriteMsg(reply)
+	})
+}
+
+func TestHTTPSAndSVCBCachedUnmodified(t *testing.T) {
+	s := NewDNSServer()
+	s.forwarders = []string{startSVCBUpstream(t)}
+
+	tests := []struct {
+		name   string
+		qtype  uint16
+		record string
+		extra  string
+	}{
+		{"svc.example.", dns.TypeHTTPS, testHTTPSRecord, ""},
+		{"_dns.svc.example.", dns.TypeSVCB, testSVCBRecord, testSVCBGlue},
+	}
+	for _, tt := range tests {
+		want, _ := dns.NewRR(tt.record)
+		key := cacheKey(tt.name, tt.qtype)
+		qtypeStr := dns.TypeToString[tt.qtype]
+
+		for _, cached := range []bool{false, true} {
+			result := s.lookup(key, tt.name, qtypeStr, tt.qtype)
+			if result.cached != cached {
+				t.Errorf("%s: cached = %v, ожидалось %v", qtypeStr, result.cached, cached)
+			}
+			if len(result.answers) != 1 || result.answers[0].String() != want.String() {
+				t.Fatalf("%s: получено %v, ожидалось %s", qtypeStr, result.answers, want)
+			}
+			if tt.extra != "" && (len(result.additional) != 1 || result.additional[0].String() != mustRR(t, tt.extra).String()) {
+				t.Errorf("%s: подсказки адресов в additional должны сохраняться, получено %v", qtypeStr, result.additional)
+			}
+		}
+	}
+
+	// Записи переживают сохранение кэша на диск
+	path := filepath.Join(t.TempDir(), "cache.json")
+	if _, err := s.cache.Save(path); err != nil {
+		t.Fatalf("Ошибка сохранения кэша: %v", err)
+	}
+	restored := NewCache(10)
+	if _, err := restored.Load(path); err != nil {
+		t.Fatalf("Ошибка загрузки кэша: %v", err)
+	}
+	records, ok := restored.Get(cacheKey("svc.example.", dns.TypeHTTPS))
+	if !ok || len(records) != 1 || records[0].String() != mustRR(t, testHTTPSRecord).String() {
+		t.Errorf("Запись HTTPS должна восстанавливаться без изменений, получено %v", records)
+	}
+}
+
+func mustRR(t *testing.T, s string) dns.RR {
+	t.Helper()
+	rr, err := dns.NewRR(s)
+	if err != nil {
+		t.Fatalf("Некорректная запись %q: %v", s, err)
+	}
+	return rr
+}
@@ -0,0 +1,80 @@
+package resolver
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"math/rand/v2"
+	"net"
+	"syscall"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+const (
+	sourcePortMin      = 1024
+	sourcePortMax      = 65535
+	sourcePortAttempts = 8 // Попыток занять случайный порт, если выбранный занят
+
+	upstreamRetries      = 1                     // Повторов UDP-запроса без ответа
+	upstreamRetryBackoff = 50 * time.Millisecond // Пауза перед первым повтором
+)
+
+// queryTransport выполняет один обмен DNS-сообщениями с сервером addr.
+// Клиент c задает протокол и таймаут запроса. Тесты и бенчмарки подменяют
+// транспорт, чтобы разрешение не зависело от сети.
+type queryTransport interface {
+	Exchange(ctx context.Context, c *dns.Client, msg *dns.Msg, addr string) (*dns.Msg, time.Duration, error)
+}
+
+// clientTransport отправляет запросы по сети средствами dns.Client. UDP-запросы
+// уходят с порта, случайно выбранного из [minPort, maxPort], а не с порта,
+// выданного ядром: случайный порт вместе с ID затрудняет подмену ответов.
+type clientTransport struct {
+	minPort int
+	maxPort int
+}
+
+func newConfigTransport(cfg Config) clientTransport {
+	return clientTransport{minPort: cfg.SourcePortMin, maxPort: cfg.SourcePortMax}
+}
+
+func (t clientTransport) Exchange(ctx context.Context, c *dns.Client, msg *dns.Msg, addr string) (*dns.Msg, time.Duration, error) {
+	if c.Net != "" && c.Net != "udp" {
+		return c.ExchangeContext(ctx, msg, addr)
+	}
+	conn, err := t.dialUDP(ctx, addr)
+	if err != nil {
+		return nil, 0, err
+	}
+	defer conn.Close()
+	return c.ExchangeWithConnContext(ctx, msg, &dns.Conn{Conn: conn})
+}
+
+// dialUDP открывает UDP-сокет к addr на случайном порту из диапазона,
+// повторяя попытку, если порт уже занят другим сокетом
+func (t clientTransport) dialUDP(ctx context.Context, addr string) (net.Conn, error) {
+	var err error
+	for i := 0; i < sourcePortAttempts; i++ {
+		port := t.minPort + rand.IntN(t.maxPort-t.minPort+1)
+		d := net.Dialer{LocalAddr: &net.UDPAddr{Port: port}}
+		var conn net.Conn
+		conn, err = d.DialContext(ctx, "udp", addr)
+		if err == nil {
+			return conn, nil
+		}
+		if !errors.Is(err, syscall.EADDRINUSE) {
+			return nil, err
+		}
+	}
+	return nil, fmt.Errorf("не удалось занять исходящий порт из диапазона %d-%d: %w", t.minPort, t.maxPort, err)
+}
+
+// validSourcePorts проверяет диапазон source_port_min - source_port_max
+func validSourcePorts(cfg Config) error {
+	if cfg.SourcePortMin < 1 || cfg.SourcePortMax > 65535 || cfg.SourcePortMin > cfg.SourcePortMax {
+		return fmt.Errorf("source_port_min и source_port_max должны задавать диапазон в пределах 1-65535")
+	}
+	return nil
+}
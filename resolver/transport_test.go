@@ -0,0 +1,173 @@
+package resolver
+
+import (
+	"context"
+	"fmt"
+	"net"
+	"sync"
+	"sync/atomic"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// cannedTransport отвечает на любой запрос одной A-записью, не обращаясь к сети
+type cannedTransport struct {
+	queries uint64
+}
+
+func (t *cannedTransport) Exchange(ctx context.Context, c *dns.Client, msg *dns.Msg, addr string) (*dns.Msg, time.Duration, error) {
+	atomic.AddUint64(&t.queries, 1)
+	reply := new(dns.Msg)
+	reply.SetReply(msg)
+	reply.RecursionAvailable = true
+	q := msg.Question[0]
+	if q.Qtype == dns.TypeA {
+		reply.Answer = append(reply.Answer, &dns.A{
+			Hdr: dns.RR_Header{Name: q.Name, Rrtype: dns.TypeA, Class: dns.ClassINET, Ttl: 300},
+			A:   net.IPv4(192, 0, 2, 1),
+		})
+	}
+	return reply, time.Millisecond, nil
+}
+
+func TestForwardUsesTransport(t *testing.T) {
+	s := NewDNSServer()
+	tr := &cannedTransport{}
+	s.transport = tr
+	// Адрес нигде не прослушивается: ответ может дать только транспорт
+	s.forwarders = []string{"192.0.2.53:53"}
+
+	req := new(dns.Msg)
+	req.SetQuestion("example.com.", dns.TypeA)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil || len(w.msg.Answer) != 1 {
+		t.Fatalf("Ожидалась одна запись от транспорта, получено %v", w.msg)
+	}
+	if n := atomic.LoadUint64(&tr.queries); n != 1 {
+		t.Errorf("Ожидался один запрос через транспорт, выполнено %d", n)
+	}
+}
+
+// mockTransport отвечает на запросы готовыми ответами серверов по адресу
+// и записывает, к каким серверам обращалось разрешение
+type mockTransport struct {
+	servers map[string]func(req *dns.Msg) *dns.Msg
+	queried []string
+}
+
+func (t *mockTransport) Exchange(ctx context.Context, c *dns.Client, msg *dns.Msg, addr string) (*dns.Msg, time.Duration, error) {
+	t.queried = append(t.queried, addr)
+	handler, ok := t.servers[addr]
+	if !ok {
+		return nil, 0, fmt.Errorf("сервер %s недоступен", addr)
+	}
+	reply := handler(msg)
+	reply.SetReply(msg)
+	return reply, time.Millisecond, nil
+}
+
+// referral возвращает обработчик, направляющий запросы к серверу зоны
+func referral(t *testing.T, ns, glue string) func(*dns.Msg) *dns.Msg {
+	return func(*dns.Msg) *dns.Msg {
+		reply := new(dns.Msg)
+		reply.Ns = append(reply.Ns, mustRR(t, ns))
+		reply.Extra = append(reply.Extra, mustRR(t, glue))
+		return reply
+	}
+}
+
+func TestRecursionThroughMockTransport(t *testing.T) {
+	tr := &mockTransport{servers: map[string]func(*dns.Msg) *dns.Msg{
+		"198.41.0.4:53": referral(t, "com. 172800 IN NS a.gtld-servers.net.", "a.gtld-servers.net. 172800 IN A 192.5.6.30"),
+		"192.5.6.30:53": referral(t, "example.com. 172800 IN NS ns1.example.com.", "ns1.example.com. 172800 IN A 192.0.2.53"),
+		"192.0.2.53:53": func(req *dns.Msg) *dns.Msg {
+			reply := new(dns.Msg)
+			reply.Authoritative = true
+			reply.Answer = append(reply.Answer, mustRR(t, req.Question[0].Name+" 300 IN A 192.0.2.80"))
+			return reply
+		},
+	}}
+
+	s := NewDNSServer()
+	s.transport = tr
+	s.rootServers = []string{"198.41.0.4"}
+
+	rrset, _ := s.fetchFromAuthoritative("www.example.com.", dns.TypeA)
+	if len(rrset) != 1 {
+		t.Fatalf("Ожидалась одна запись, получено %v", rrset)
+	}
+	if a, ok := rrset[0].(*dns.A); !ok || a.A.String() != "192.0.2.80" {
+		t.Errorf("Неожиданный ответ %v", rrset[0])
+	}
+
+	want := []string{"198.41.0.4:53", "192.5.6.30:53", "192.0.2.53:53"}
+	if fmt.Sprint(tr.queried) != fmt.Sprint(want) {
+		t.Errorf("Порядок обращения к серверам %v, ожидался %v", tr.queried, want)
+	}
+	if _, ok := s.delegations.Get("example.com."); !ok {
+		t.Error("Делегирование example.com. должно быть закэшировано")
+	}
+}
+
+func TestClientTransportSourcePortRange(t *testing.T) {
+	var ports []int
+	var mu sync.Mutex
+	pc, err := net.ListenPacket("udp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("Не удалось открыть сокет: %v", err)
+	}
+	server := &dns.Server{PacketConn: pc, Handler: dns.HandlerFunc(func(w dns.ResponseWriter, req *dns.Msg) {
+		mu.Lock()
+		ports = append(ports, w.RemoteAddr().(*net.UDPAddr).Port)
+		mu.Unlock()
+		reply := new(dns.Msg)
+		reply.SetReply(req)
+		w.WriteMsg(reply)
+	})}
+	go server.ActivateAndServe()
+	defer server.Shutdown()
+
+	const minPort, maxPort = 40000, 40099
+	cfg := DefaultConfig()
+	cfg.SourcePortMin = minPort
+	cfg.SourcePortMax = maxPort
+	tr := newConfigTransport(cfg)
+	c := &dns.Client{Timeout: 2 * time.Second}
+	for i := 0; i < 5; i++ {
+		req := new(dns.Msg)
+		req.SetQuestion("example.com.", dns.TypeA)
+		if _, _, err := tr.Exchange(context.Background(), c, req, pc.LocalAddr().String()); err != nil {
+			t.Fatalf("Ошибка запроса: %v", err)
+		}
+	}
+
+	mu.Lock()
+	defer mu.Unlock()
+	if len(ports) != 5 {
+		t.Fatalf("Ожидалось 5 запросов, сервер получил %d", len(ports))
+	}
+	for _, port := range ports {
+		if port < minPort || port > maxPort {
+			t.Errorf("Исходящий порт %d вне диапазона %d-%d", port, minPort, maxPort)
+		}
+	}
+}
+
+func TestClientTransportRetriesBusySourcePort(t *testing.T) {
+	// Единственный порт диапазона занят: все попытки завершаются ошибкой
+	busy, err := net.ListenPacket("udp", ":0")
+	if err != nil {
+		t.Fatalf("Не удалось открыть сокет: %v", err)
+	}
+	defer busy.Close()
+	port := busy.LocalAddr().(*net.UDPAddr).Port
+
+	tr := clientTransport{minPort: port, maxPort: port}
+	if _, err := tr.dialUDP(context.Background(), "127.0.0.1:53"); err == nil {
+		t.Error("Ожидалась ошибка при занятом единственном порту диапазона")
+	}
+}
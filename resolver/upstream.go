@@ -0,0 +1,286 @@
+package resolver
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"log/slog"
+	"net"
+	"strings"
+	"sync/atomic"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// exchange отправляет запрос вышестоящему серверу и принимает ответ только
+// если он действительно относится к отправленному запросу. Запрос прерывается
+// по истечении ctx, даже если таймаут клиента еще не наступил.
+func (s *DNSServer) exchange(ctx context.Context, c *dns.Client, msg *dns.Msg, addr string) (*dns.Msg, error) {
+	resp, err := s.exchangeOnce(ctx, c, msg, addr)
+	if err == nil && resp.Rcode == dns.RcodeBadCookie {
+		// Сервер не принял нашу cookie и выдал новую - повторяем запрос с ней
+		resp, err = s.exchangeOnce(ctx, c, msg, addr)
+	}
+	// Сервер без поддержки EDNS отвечает FORMERR или NOTIMP без OPT-записи
+	// (RFC 6891, раздел 7) - запоминаем это и повторяем запрос без EDNS
+	if err == nil && resp.IsEdns0() == nil && msg.IsEdns0() != nil && !s.infra.NoEDNS(addr) &&
+		(resp.Rcode == dns.RcodeFormatError || resp.Rcode == dns.RcodeNotImplemented) {
+		slog.Debug("Сервер не поддерживает EDNS", "server", addr)
+		s.infra.SetNoEDNS(addr)
+		resp, err = s.exchangeOnce(ctx, c, msg, addr)
+	}
+	// Обрезанный UDP-ответ содержит неполный набор записей: он не
+	// используется и не кэшируется, а запрос повторяется по TCP
+	if err == nil && resp.Truncated && c.Net != "tcp" {
+		slog.Debug("Ответ обрезан, повтор по TCP", "server", addr)
+		tcp := &dns.Client{Net: "tcp", Timeout: c.Timeout}
+		return s.exchangeOnce(ctx, tcp, msg, addr)
+	}
+	return resp, err
+}
+
+// exchangeOnce выполняет один обмен с сервером, проверяя ответ и DNS cookie
+func (s *DNSServer) exchangeOnce(ctx context.Context, c *dns.Client, msg *dns.Msg, addr string) (*dns.Msg, error) {
+	if err := ctx.Err(); err != nil {
+		return nil, err
+	}
+
+	// Серверу без поддержки EDNS запрос отправляется без OPT-записи и cookie
+	out := msg
+	edns := !s.infra.NoEDNS(addr)
+	if edns {
+		advertiseUDPSize(msg, s.cfg.UpstreamUDPSize)
+		s.cookies.Apply(msg, addr)
+	} else if msg.IsEdns0() != nil {
+		out = msg.Copy()
+		out.Extra = withoutOPT(out.Extra)
+	}
+
+	resp, rtt, err := s.exchangeRetry(ctx, c, out, addr)
+	if err != nil {
+		if ctx.Err() != nil {
+			// Истек срок всего разрешения, сервер в этом не виноват
+			return nil, ctx.Err()
+		}
+		if errors.Is(err, errQueryBudget) {
+			return nil, err
+		}
+		s.rtt.RecordFailure(addr)
+		s.infra.RecordError(addr, err)
+		return nil, err
+	}
+	s.rtt.Record(addr, rtt)
+
+	if err := validateResponse(out, resp); err != nil {
+		slog.Warn("Отброшен ответ вышестоящего сервера", "server", addr, "error", err)
+		s.infra.RecordError(addr, err)
+		return nil, err
+	}
+
+	if edns {
+		if err := s.cookies.Update(addr, resp); err != nil {
+			slog.Warn("Отброшен ответ с неверной DNS cookie", "server", addr, "error", err)
+			s.infra.RecordError(addr, err)
+			return nil, err
+		}
+		if opt := resp.IsEdns0(); opt != nil {
+			s.infra.RecordUDPSize(addr, opt.UDPSize())
+		}
+	}
+
+	return resp, nil
+}
+
+// advertiseUDPSize объявляет в запросе размер буфера UDP size (RFC 6891):
+// без OPT-записи сервер ограничен 512 байтами и чаще обрезает ответы.
+// Флаг DO и опции уже имеющейся OPT-записи сохраняются.
+func advertiseUDPSize(msg *dns.Msg, size uint16) {
+	if opt := msg.IsEdns0(); opt != nil {
+		opt.SetUDPSize(size)
+		return
+	}
+	msg.SetEdns0(size, false)
+}
+
+// exchangeRetry отправляет запрос и повторяет его до upstream_retries раз,
+// если UDP-ответ не пришел за таймаут: одиночная потеря пакета не должна
+// исключать сервер. Повтор отправляется с тем же ID, поэтому проверка ответа
+// не зависит от номера попытки; перед каждым повтором пауза удваивается.
+func (s *DNSServer) exchangeRetry(ctx context.Context, c *dns.Client, msg *dns.Msg, addr string) (*dns.Msg, time.Duration, error) {
+	backoff := upstreamRetryBackoff
+	for attempt := 0; ; attempt++ {
+		resp, rtt, err := s.exchangeAttempt(ctx, c, msg, addr)
+		if err == nil || !isTimeout(err) || ctx.Err() != nil || c.Net == "tcp" || attempt >= s.cfg.UpstreamRetries {
+			return resp, rtt, err
+		}
+		slog.Debug("Нет ответа от сервера, повтор запроса", "server", addr, "attempt", attempt+1)
+		select {
+		case <-time.After(backoff):
+		case <-ctx.Done():
+			return nil, 0, ctx.Err()
+		}
+		backoff *= 2
+	}
+}
+
+// exchangeAttempt выполняет одну попытку обмена с сервером
+func (s *DNSServer) exchangeAttempt(ctx context.Context, c *dns.Client, msg *dns.Msg, addr string) (*dns.Msg, time.Duration, error) {
+	if err := spendQuery(ctx); err != nil {
+		return nil, 0, err
+	}
+
+	// Срок ctx заменяет таймаут клиента, поэтому таймаут одного запроса
+	// задается отдельно, чтобы медленный сервер не занял все время разрешения
+	qctx := ctx
+	if c.Timeout > 0 {
+		var cancel context.CancelFunc
+		qctx, cancel = context.WithTimeout(ctx, c.Timeout)
+		defer cancel()
+	}
+
+	resp, rtt, err := s.transport.Exchange(qctx, c, msg, addr)
+	if err != nil && isTimeout(err) && ctx.Err() == nil {
+		atomic.AddUint64(&s.upstreamTimeouts, 1)
+	}
+	return resp, rtt, err
+}
+
+// validateResponse проверяет ID транзакции и секцию вопросов ответа
+func validateResponse(req, resp *dns.Msg) error {
+	if resp == nil {
+		return fmt.Errorf("пустой ответ")
+	}
+
+	if resp.Id != req.Id {
+		return fmt.Errorf("ID ответа %d не совпадает с ID запроса %d", resp.Id, req.Id)
+	}
+
+	if !resp.Response {
+		return fmt.Errorf("в ответе не установлен флаг QR")
+	}
+
+	if len(resp.Question) != len(req.Question) {
+		return fmt.Errorf("количество вопросов в ответе %d не совпадает с запросом %d", len(resp.Question), len(req.Question))
+	}
+
+	for i, q := range req.Question {
+		rq := resp.Question[i]
+		if !strings.EqualFold(dns.Fqdn(rq.Name), dns.Fqdn(q.Name)) || rq.Qtype != q.Qtype || rq.Qclass != q.Qclass {
+			return fmt.Errorf("вопрос ответа %s не совпадает с запросом %s", rq.String(), q.String())
+		}
+	}
+
+	return nil
+}
+
+// forward пересылает вопрос вышестоящим резолверам с флагом RD и
+// возвращает ответ первого успешно ответившего. Резолверы опрашиваются от
+// самого быстрого по сглаженному RTT.
+func (s *DNSServer) forward(ctx context.Context, qname string, qtype uint16) (*dns.Msg, error) {
+	return s.forwardTo(ctx, s.forwarders, qname, qtype)
+}
+
+// forwardTo пересылает вопрос резолверам upstreams
+func (s *DNSServer) forwardTo(ctx context.Context, upstreams []string, qname string, qtype uint16) (*dns.Msg, error) {
+	msg := new(dns.Msg)
+	msg.SetQuestion(dns.Fqdn(qname), qtype)
+	msg.RecursionDesired = true
+	// DNSKEY и DS запрашиваются вместе с подписями: их спрашивают клиенты,
+	// сами строящие цепочку доверия, а кэш общий для клиентов с флагом DO и без
+	if qtype == dns.TypeDNSKEY || qtype == dns.TypeDS {
+		msg.SetEdns0(s.cfg.UpstreamUDPSize, true)
+	}
+	if subnet, ok := contextSubnet(ctx); ok {
+		addSubnet(msg, subnet)
+	}
+
+	c := &dns.Client{Timeout: s.cfg.UpstreamTimeout.Duration}
+	lastErr := fmt.Errorf("вышестоящие резолверы не настроены")
+	for _, upstream := range s.candidates(upstreams) {
+		resp, err := s.exchange(ctx, c, msg, upstream)
+		if err != nil {
+			if ctx.Err() != nil || errors.Is(err, errQueryBudget) {
+				return nil, err
+			}
+			lastErr = err
+			continue
+		}
+		if resp.Rcode != dns.RcodeSuccess && resp.Rcode != dns.RcodeNameError {
+			// Резолвер, ответивший SERVFAIL или REFUSED, опускается в
+			// порядке опроса, как и не ответивший: быстрый, но неработающий
+			// резолвер не должен опрашиваться первым
+			s.rtt.RecordFailure(upstream)
+			lastErr = fmt.Errorf("%s вернул %s", upstream, dns.RcodeToString[resp.Rcode])
+			continue
+		}
+		return resp, nil
+	}
+
+	return nil, lastErr
+}
+
+// forwardersFor возвращает вышестоящие резолверы для qname: серверы
+// ближайшей зоны условной пересылки или общие forwarders. Пустой список
+// означает рекурсивное разрешение.
+func (s *DNSServer) forwardersFor(qname string) []string {
+	if len(s.forwardZones) > 0 {
+		name := strings.ToLower(dns.Fqdn(qname))
+		for off, end := 0, false; !end; off, end = dns.NextLabel(name, off) {
+			if upstreams, ok := s.forwardZones[name[off:]]; ok {
+				return upstreams
+			}
+		}
+		if upstreams, ok := s.forwardZones["."]; ok {
+			return upstreams
+		}
+	}
+	return s.forwarders
+}
+
+// normalizeForwardZones приводит имена зон условной пересылки к нижнему
+// регистру с точкой в конце, а адреса серверов - к виду host:port
+func normalizeForwardZones(zones map[string][]string) map[string][]string {
+	if len(zones) == 0 {
+		return nil
+	}
+	normalized := make(map[string][]string, len(zones))
+	for zone, upstreams := range zones {
+		normalized[strings.ToLower(dns.Fqdn(zone))] = normalizeUpstreams(upstreams)
+	}
+	return normalized
+}
+
+// validForwardZones проверяет правила условной пересылки
+func validForwardZones(zones map[string][]string) error {
+	for zone, upstreams := range zones {
+		if _, ok := dns.IsDomainName(zone); !ok {
+			return fmt.Errorf("forward_zones: некорректное имя зоны %q", zone)
+		}
+		if len(normalizeUpstreams(upstreams)) == 0 {
+			return fmt.Errorf("forward_zones: для зоны %s не указаны серверы", zone)
+		}
+	}
+	return nil
+}
+
+// parseUpstreams разбирает список адресов через запятую, добавляя порт 53 по умолчанию
+func parseUpstreams(list string) []string {
+	return normalizeUpstreams(strings.Split(list, ","))
+}
+
+// normalizeUpstreams убирает пустые адреса и добавляет порт 53 по умолчанию
+func normalizeUpstreams(addrs []string) []string {
+	var upstreams []string
+	for _, addr := range addrs {
+		addr = strings.TrimSpace(addr)
+		if addr == "" {
+			continue
+		}
+		if _, _, err := net.SplitHostPort(addr); err != nil {
+			addr = net.JoinHostPort(addr, "53")
+		}
+		upstreams = append(upstreams, addr)
+	}
+	return upstreams
+}
@@ -0,0 +1,514 @@
+package resolver
+
+import (
+	"context"
+	"fmt"
+	"net"
+	"sync/atomic"
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+// startTestUDPServer запускает локальный DNS-сервер с заданным обработчиком
+func startTestUDPServer(t *testing.T, handler dns.HandlerFunc) string {
+	t.Helper()
+	pc, err := net.ListenPacket("udp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("Не удалось прослушать UDP: %v", err)
+	}
+	srv := &dns.Server{PacketConn: pc, Handler: handler}
+	go srv.ActivateAndServe()
+	t.Cleanup(func() { srv.Shutdown() })
+	return pc.LocalAddr().String()
+}
+
+func TestValidateResponseRejectsMismatchedID(t *testing.T) {
+	req := new(dns.Msg)
+	req.SetQuestion("example.com.", dns.TypeA)
+
+	resp := new(dns.Msg)
+	resp.SetReply(req)
+	resp.Id = req.Id + 1
+
+	if err := validateResponse(req, resp); err == nil {
+		t.Errorf("Ожидалась ошибка для ответа с чужим ID")
+	}
+}
+
+func TestValidateResponseRejectsMismatchedQuestion(t *testing.T) {
+	req := new(dns.Msg)
+	req.SetQuestion("example.com.", dns.TypeA)
+
+	other := new(dns.Msg)
+	other.SetQuestion("attacker.example.", dns.TypeA)
+	resp := new(dns.Msg)
+	resp.SetReply(other)
+	resp.Id = req.Id
+
+	if err := validateResponse(req, resp); err == nil {
+		t.Errorf("Ожидалась ошибка для ответа на чужой вопрос")
+	}
+
+	resp.SetReply(req)
+	resp.Question[0].Qtype = dns.TypeAAAA
+	if err := validateResponse(req, resp); err == nil {
+		t.Errorf("Ожидалась ошибка для ответа с другим QTYPE")
+	}
+}
+
+func TestValidateResponseAcceptsMatchingCaseInsensitive(t *testing.T) {
+	req := new(dns.Msg)
+	req.SetQuestion("Example.COM.", dns.TypeA)
+
+	resp := new(dns.Msg)
+	resp.SetReply(req)
+	resp.Question[0].Name = "example.com."
+
+	if err := validateResponse(req, resp); err != nil {
+		t.Errorf("Неожиданная ошибка: %v", err)
+	}
+}
+
+func TestExchangeRejectsSpoofedID(t *testing.T) {
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		reply.Id = r.Id ^ 0xffff
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	c := &dns.Client{Timeout: time.Second}
+	msg := new(dns.Msg)
+	msg.SetQuestion("example.com.", dns.TypeA)
+
+	if resp, err := s.exchange(context.Background(), c, msg, addr); err == nil {
+		t.Errorf("Ожидалась ошибка для ответа с поддельным ID, получено %v", resp)
+	}
+}
+
+func TestForwardModeCachesAnswer(t *testing.T) {
+	var queries atomic.Int32
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		queries.Add(1)
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		if !r.RecursionDesired {
+			reply.Rcode = dns.RcodeRefused
+		} else {
+			rr, _ := dns.NewRR("forward.example. 300 IN A 192.0.2.53")
+			reply.Answer = append(reply.Answer, rr)
+		}
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+	key := "forward.example.:1"
+
+	result := s.lookup(key, "forward.example.", "A", dns.TypeA)
+	if result.cached {
+		t.Errorf("Первый запрос не должен обслуживаться из кэша")
+	}
+	if result.rcode != dns.RcodeSuccess {
+		t.Errorf("Ожидался NOERROR, получено %s", dns.RcodeToString[result.rcode])
+	}
+	answers := result.answers
+	if len(answers) != 1 {
+		t.Fatalf("Ожидался 1 ответ от вышестоящего резолвера, получено %d", len(answers))
+	}
+	if a, ok := answers[0].(*dns.A); !ok || a.A.String() != "192.0.2.53" {
+		t.Errorf("Неверный пересланный ответ: %v", answers[0])
+	}
+
+	if _, ok := s.cache.Get(key); !ok {
+		t.Errorf("Пересланный ответ должен быть закэширован")
+	}
+
+	if !s.lookup(key, "forward.example.", "A", dns.TypeA).cached {
+		t.Errorf("Повторный запрос должен быть отмечен как попадание в кэш")
+	}
+	if n := queries.Load(); n != 1 {
+		t.Errorf("Повторный запрос должен обслуживаться из кэша, запросов к upstream: %d", n)
+	}
+}
+
+func TestParseUpstreams(t *testing.T) {
+	got := parseUpstreams("1.1.1.1, 8.8.8.8:5353,,2606:4700:4700::1111")
+	want := []string{"1.1.1.1:53", "8.8.8.8:5353", "[2606:4700:4700::1111]:53"}
+	if len(got) != len(want) {
+		t.Fatalf("Ожидалось %v, получено %v", want, got)
+	}
+	for i := range want {
+		if got[i] != want[i] {
+			t.Errorf("Ожидалось %s, получено %s", want[i], got[i])
+		}
+	}
+}
+
+func TestServfailIsCachedBriefly(t *testing.T) {
+	var queries atomic.Int32
+	var healthy atomic.Bool
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		queries.Add(1)
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		if healthy.Load() {
+			rr, _ := dns.NewRR("flaky.example. 300 IN A 192.0.2.9")
+			reply.Answer = append(reply.Answer, rr)
+		} else {
+			reply.Rcode = dns.RcodeServerFailure
+		}
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+	s.cfg.ServfailTTL = Duration{100 * time.Millisecond}
+	key := cacheKey("flaky.example.", dns.TypeA)
+
+	for i := 0; i < 2; i++ {
+		if result := s.lookup(key, "flaky.example.", "A", dns.TypeA); result.rcode != dns.RcodeServerFailure {
+			t.Fatalf("Попытка %d: ожидался SERVFAIL, получено %s", i+1, dns.RcodeToString[result.rcode])
+		}
+	}
+	if n := queries.Load(); n != 1 {
+		t.Errorf("Повторный сбой должен обслуживаться из кэша сбоев, запросов к upstream: %d", n)
+	}
+
+	healthy.Store(true)
+	time.Sleep(150 * time.Millisecond)
+	result := s.lookup(key, "flaky.example.", "A", dns.TypeA)
+	if result.rcode != dns.RcodeSuccess || len(result.answers) != 1 {
+		t.Errorf("После истечения servfail_ttl ожидался успешный ответ, получено %s %v", dns.RcodeToString[result.rcode], result.answers)
+	}
+}
+
+func TestResolutionDeadlineAbortsSlowChain(t *testing.T) {
+	var queries atomic.Int32
+	slow := func(w dns.ResponseWriter, r *dns.Msg) {
+		queries.Add(1)
+		time.Sleep(300 * time.Millisecond)
+		reply := new(dns.Msg)
+		reply.SetRcode(r, dns.RcodeServerFailure)
+		w.WriteMsg(reply)
+	}
+
+	s := NewDNSServer()
+	s.cfg.UpstreamTimeout = Duration{time.Second}
+	s.cfg.ResolutionTimeout = Duration{500 * time.Millisecond}
+	s.forwarders = []string{
+		startTestUDPServer(t, slow),
+		startTestUDPServer(t, slow),
+		startTestUDPServer(t, slow),
+	}
+
+	start := time.Now()
+	result := s.lookup(cacheKey("slow.example.", dns.TypeA), "slow.example.", "A", dns.TypeA)
+	elapsed := time.Since(start)
+
+	if result.rcode != dns.RcodeServerFailure {
+		t.Errorf("После истечения срока ожидался SERVFAIL, получен %s", dns.RcodeToString[result.rcode])
+	}
+	if elapsed > 800*time.Millisecond {
+		t.Errorf("Разрешение должно прерваться по сроку 500ms, заняло %v", elapsed)
+	}
+	if n := queries.Load(); n > 2 {
+		t.Errorf("После истечения срока вышестоящие серверы не должны опрашиваться, запросов: %d", n)
+	}
+	if n := atomic.LoadUint64(&s.resolutionDeadlines); n != 1 {
+		t.Errorf("Счетчик прерванных разрешений = %d, ожидалось 1", n)
+	}
+}
+
+// truncatedHandler отвечает по UDP обрезанным ответом с одной записью из трех,
+// а по TCP - полным ответом
+func truncatedHandler(w dns.ResponseWriter, r *dns.Msg) {
+	reply := new(dns.Msg)
+	reply.SetReply(r)
+	count := 3
+	if w.RemoteAddr().Network() == "udp" {
+		reply.Truncated = true
+		count = 1
+	}
+	for i := 1; i <= count; i++ {
+		rr, _ := dns.NewRR(fmt.Sprintf("%s 300 IN A 192.0.2.%d", r.Question[0].Name, i))
+		reply.Answer = append(reply.Answer, rr)
+	}
+	w.WriteMsg(reply)
+}
+
+func TestTruncatedResponseRetriedOverTCP(t *testing.T) {
+	addr := startTestUDPServer(t, truncatedHandler)
+	ln, err := net.Listen("tcp", addr)
+	if err != nil {
+		t.Skipf("TCP-порт %s занят: %v", addr, err)
+	}
+	srv := &dns.Server{Listener: ln, Handler: dns.HandlerFunc(truncatedHandler)}
+	go srv.ActivateAndServe()
+	t.Cleanup(func() { srv.Shutdown() })
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+	key := cacheKey("big.example.", dns.TypeA)
+	result := s.lookup(key, "big.example.", "A", dns.TypeA)
+	if len(result.answers) != 3 {
+		t.Fatalf("Ожидался полный ответ по TCP из 3 записей, получено %d", len(result.answers))
+	}
+	if records, ok := s.cache.Get(key); !ok || len(records) != 3 {
+		t.Errorf("В кэше должен быть полный ответ, получено %v", records)
+	}
+}
+
+func TestTruncatedResponseNotCachedWithoutTCP(t *testing.T) {
+	s := NewDNSServer()
+	s.forwarders = []string{startTestUDPServer(t, truncatedHandler)}
+	key := cacheKey("big.example.", dns.TypeA)
+
+	if result := s.lookup(key, "big.example.", "A", dns.TypeA); result.rcode != dns.RcodeServerFailure {
+		t.Errorf("Без TCP обрезанный ответ не должен использоваться, получено %s с %d записями",
+			dns.RcodeToString[result.rcode], len(result.answers))
+	}
+	if records, ok := s.cache.Get(key); ok {
+		t.Errorf("Неполный ответ не должен кэшироваться: %v", records)
+	}
+}
+
+func TestForwardDNSKEYReturnsAndCachesSignatures(t *testing.T) {
+	const key = "mdsswUyr3DPW132mOi8V9xESWE8jTo0dxCjjnopKl+GqJxpVXckHAeF+KkxLbxILfDLUT0rAK9iUzy1L53eKGQ=="
+	var queries atomic.Int32
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		queries.Add(1)
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		dnskey, _ := dns.NewRR("signed.example. 3600 IN DNSKEY 257 3 13 " + key)
+		reply.Answer = append(reply.Answer, dnskey)
+		// Как и настоящий сервер, подписи отдаются только при флаге DO
+		if opt := r.IsEdns0(); opt != nil && opt.Do() {
+			sig, _ := dns.NewRR("signed.example. 3600 IN RRSIG DNSKEY 13 2 3600 20300101000000 20200101000000 12345 signed.example. " + key)
+			reply.Answer = append(reply.Answer, sig)
+			reply.SetEdns0(1232, true)
+		}
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+	s.dnssecEnabled = false
+
+	for i := 0; i < 2; i++ {
+		req := new(dns.Msg)
+		req.SetQuestion("signed.example.", dns.TypeDNSKEY)
+		req.SetEdns0(1232, true)
+		w := &testResponseWriter{}
+		s.handleRequest(w, req)
+
+		if w.msg == nil || len(w.msg.Answer) != 2 {
+			t.Fatalf("Запрос %d: ожидались DNSKEY и RRSIG, получено %v", i+1, w.msg)
+		}
+		if sig, ok := w.msg.Answer[1].(*dns.RRSIG); !ok || sig.TypeCovered != dns.TypeDNSKEY {
+			t.Errorf("Запрос %d: ожидалась подпись DNSKEY, получено %v", i+1, w.msg.Answer[1])
+		}
+	}
+	if n := queries.Load(); n != 1 {
+		t.Errorf("Повторный запрос должен обслуживаться из кэша, запросов к upstream: %d", n)
+	}
+
+	records, ok := s.cache.Get(cacheKey("signed.example.", dns.TypeDNSKEY))
+	if !ok || len(records) != 2 {
+		t.Fatalf("В кэше ожидались DNSKEY и RRSIG, получено %v", records)
+	}
+}
+
+func TestUpstreamRetryAfterDroppedPacket(t *testing.T) {
+	var queries atomic.Int32
+	var firstID, retryID atomic.Uint32
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		// Первый пакет "теряется": сервер на него не отвечает
+		if queries.Add(1) == 1 {
+			firstID.Store(uint32(r.Id))
+			return
+		}
+		retryID.Store(uint32(r.Id))
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		rr, _ := dns.NewRR(r.Question[0].Name + " 300 IN A 192.0.2.53")
+		reply.Answer = append(reply.Answer, rr)
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.cfg.UpstreamTimeout = Duration{200 * time.Millisecond}
+	s.cfg.UpstreamRetries = 1
+	s.forwarders = []string{addr}
+
+	result := s.lookup(cacheKey("lossy.example.", dns.TypeA), "lossy.example.", "A", dns.TypeA)
+	if result.rcode != dns.RcodeSuccess || len(result.answers) != 1 {
+		t.Fatalf("Ожидался ответ после повтора, получено %s %v", dns.RcodeToString[result.rcode], result.answers)
+	}
+	if n := queries.Load(); n != 2 {
+		t.Errorf("Ожидалось 2 запроса к серверу, получено %d", n)
+	}
+	if firstID.Load() != retryID.Load() {
+		t.Errorf("Повтор должен уходить с тем же ID: %d и %d", firstID.Load(), retryID.Load())
+	}
+	if n := atomic.LoadUint64(&s.upstreamTimeouts); n != 1 {
+		t.Errorf("Ожидался один учтенный таймаут, получено %d", n)
+	}
+}
+
+func TestUpstreamWithoutRetriesGivesUp(t *testing.T) {
+	var queries atomic.Int32
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		queries.Add(1)
+	})
+
+	s := NewDNSServer()
+	s.cfg.UpstreamTimeout = Duration{100 * time.Millisecond}
+	s.cfg.UpstreamRetries = 0
+	s.forwarders = []string{addr}
+
+	result := s.lookup(cacheKey("lossy.example.", dns.TypeA), "lossy.example.", "A", dns.TypeA)
+	if result.rcode != dns.RcodeServerFailure {
+		t.Errorf("Ожидался SERVFAIL, получено %s", dns.RcodeToString[result.rcode])
+	}
+	if n := queries.Load(); n != 1 {
+		t.Errorf("Без повторов ожидался 1 запрос, получено %d", n)
+	}
+}
+
+func TestConditionalForwarding(t *testing.T) {
+	var queries atomic.Int32
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		queries.Add(1)
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		rr, _ := dns.NewRR(r.Question[0].Name + " 300 IN A 10.0.0.5")
+		reply.Answer = append(reply.Answer, rr)
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwardZones = normalizeForwardZones(map[string][]string{"Corp.Example": {addr}})
+
+	result := s.lookup(cacheKey("intranet.corp.example.", dns.TypeA), "intranet.corp.example.", "A", dns.TypeA)
+	if len(result.answers) != 1 {
+		t.Fatalf("Ожидался ответ внутреннего резолвера, получено %v", result.answers)
+	}
+	if a, ok := result.answers[0].(*dns.A); !ok || a.A.String() != "10.0.0.5" {
+		t.Errorf("Неверный пересланный ответ: %v", result.answers[0])
+	}
+	if queries.Load() != 1 {
+		t.Errorf("Ожидался 1 запрос к внутреннему резолверу, выполнено %d", queries.Load())
+	}
+
+	// Имена вне зоны, в том числе с тем же окончанием строки, разрешаются рекурсивно
+	for _, name := range []string{"www.example.com.", "notcorp.example.", "example."} {
+		if upstreams := s.forwardersFor(name); len(upstreams) != 0 {
+			t.Errorf("%s не должен пересылаться, получено %v", name, upstreams)
+		}
+	}
+	if upstreams := s.forwardersFor("CORP.example"); len(upstreams) != 1 || upstreams[0] != addr {
+		t.Errorf("Вершина зоны должна пересылаться на %s, получено %v", addr, upstreams)
+	}
+}
+
+func TestForwardAdvertisesUpstreamUDPSize(t *testing.T) {
+	var size, do atomic.Int32
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		if opt := r.IsEdns0(); opt != nil {
+			size.Store(int32(opt.UDPSize()))
+			if opt.Do() {
+				do.Store(1)
+			}
+		}
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		w.WriteMsg(reply)
+	})
+
+	cfg := DefaultConfig()
+	cfg.EDNSCookies = false
+	cfg.MetricsListen = ""
+	s, err := NewDNSServerWithConfig(cfg)
+	if err != nil {
+		t.Fatalf("Ошибка создания сервера: %v", err)
+	}
+	s.forwarders = []string{addr}
+	if _, err := s.forward(context.Background(), "example.com.", dns.TypeA); err != nil {
+		t.Fatalf("Ошибка пересылки: %v", err)
+	}
+
+	// Даже без cookie и ECS запрос объявляет буфер upstream_udp_size
+	if got := size.Load(); got != upstreamUDPSize {
+		t.Errorf("Объявлен буфер %d, ожидалось %d", got, upstreamUDPSize)
+	}
+	if do.Load() != 0 {
+		t.Errorf("Для запроса A флаг DO не должен устанавливаться")
+	}
+}
+
+// rcodeTransport отвечает на запрос к каждому адресу заданным кодом ответа
+// и записывает, к каким адресам обращалась пересылка
+type rcodeTransport struct {
+	rcodes  map[string]int
+	queried []string
+}
+
+func (t *rcodeTransport) Exchange(ctx context.Context, c *dns.Client, msg *dns.Msg, addr string) (*dns.Msg, time.Duration, error) {
+	t.queried = append(t.queried, addr)
+	reply := new(dns.Msg)
+	reply.SetRcode(msg, t.rcodes[addr])
+	if reply.Rcode == dns.RcodeSuccess {
+		rr, _ := dns.NewRR(msg.Question[0].Name + " 300 IN A 192.0.2.80")
+		reply.Answer = append(reply.Answer, rr)
+	}
+	return reply, time.Millisecond, nil
+}
+
+func TestForwardFailsOverAndDemotesBrokenUpstream(t *testing.T) {
+	tr := &rcodeTransport{rcodes: map[string]int{
+		"192.0.2.1:53": dns.RcodeServerFailure,
+		"192.0.2.2:53": dns.RcodeSuccess,
+	}}
+	s := NewDNSServer()
+	s.transport = tr
+	s.forwarders = []string{"192.0.2.1:53", "192.0.2.2:53"}
+
+	for i := 0; i < 2; i++ {
+		resp, err := s.forward(context.Background(), "example.com.", dns.TypeA)
+		if err != nil || len(resp.Answer) != 1 {
+			t.Fatalf("Запрос %d: ожидался ответ второго резолвера, получено %v, %v", i+1, resp, err)
+		}
+	}
+
+	// Первый запрос переходит ко второму резолверу после SERVFAIL, второй
+	// сразу идет к работающему резолверу
+	want := []string{"192.0.2.1:53", "192.0.2.2:53", "192.0.2.2:53"}
+	if len(tr.queried) != len(want) {
+		t.Fatalf("Опрошены %v, ожидалось %v", tr.queried, want)
+	}
+	for i := range want {
+		if tr.queried[i] != want[i] {
+			t.Errorf("Запрос %d отправлен %s, ожидалось %s", i+1, tr.queried[i], want[i])
+		}
+	}
+}
+
+func TestForwardPrefersFastestUpstream(t *testing.T) {
+	tr := &rcodeTransport{rcodes: map[string]int{}}
+	s := NewDNSServer()
+	s.transport = tr
+	s.forwarders = []string{"192.0.2.1:53", "192.0.2.2:53"}
+	s.rtt.Record("192.0.2.1:53", 200*time.Millisecond)
+	s.rtt.Record("192.0.2.2:53", 5*time.Millisecond)
+
+	if _, err := s.forward(context.Background(), "example.com.", dns.TypeA); err != nil {
+		t.Fatalf("Ошибка пересылки: %v", err)
+	}
+	if len(tr.queried) != 1 || tr.queried[0] != "192.0.2.2:53" {
+		t.Errorf("Ожидался запрос только к самому быстрому резолверу, опрошены %v", tr.queried)
+	}
+}
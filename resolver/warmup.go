@@ -0,0 +1,107 @@
+package resolver
+
+import (
+	"fmt"
+	"log/slog"
+	"sync"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+const (
+	warmupTimeout = 30 * time.Second // Предельное время ожидания прогрева кэша
+	warmupWorkers = 8                // Одновременных разрешений при прогреве
+)
+
+// validWarmupNames проверяет имена для прогрева кэша
+func validWarmupNames(names []string) error {
+	for _, name := range names {
+		if _, ok := dns.IsDomainName(name); !ok || name == "" {
+			return fmt.Errorf("некорректное имя в warmup_names: %q", name)
+		}
+	}
+	return nil
+}
+
+// warmupQuestion - запрос, разрешаемый при прогреве кэша
+type warmupQuestion struct {
+	name  string
+	qtype uint16
+}
+
+// warmupQuestions составляет список запросов для прогрева: NS корня, NS
+// доменов верхнего уровня из names и записи A и AAAA самих имен. Повторы
+// исключаются.
+func warmupQuestions(names []string) []warmupQuestion {
+	seen := make(map[warmupQuestion]bool)
+	var questions []warmupQuestion
+	add := func(name string, qtype uint16) {
+		q := warmupQuestion{name: dns.CanonicalName(name), qtype: qtype}
+		if !seen[q] {
+			seen[q] = true
+			questions = append(questions, q)
+		}
+	}
+
+	add(".", dns.TypeNS)
+	for _, name := range names {
+		if labels := dns.SplitDomainName(name); len(labels) > 1 {
+			add(labels[len(labels)-1], dns.TypeNS)
+		}
+	}
+	for _, name := range names {
+		add(name, dns.TypeA)
+		add(name, dns.TypeAAAA)
+	}
+	return questions
+}
+
+// warmUp разрешает имена из warmup_names, заполняя кэш, и возвращает канал,
+// который закрывается по завершении прогрева или по истечении
+// warmup_timeout. Незавершенные разрешения продолжаются в фоне.
+func (s *DNSServer) warmUp() <-chan struct{} {
+	done := make(chan struct{})
+	if len(s.cfg.WarmupNames) == 0 {
+		close(done)
+		return done
+	}
+
+	questions := warmupQuestions(s.cfg.WarmupNames)
+	finished := make(chan struct{})
+	go func() {
+		defer close(finished)
+		sem := make(chan struct{}, warmupWorkers)
+		var wg sync.WaitGroup
+		for _, q := range questions {
+			sem <- struct{}{}
+			wg.Add(1)
+			go func(q warmupQuestion) {
+				defer func() {
+					<-sem
+					wg.Done()
+				}()
+				s.lookup(cacheKey(q.name, q.qtype), q.name, dns.TypeToString[q.qtype], q.qtype)
+			}(q)
+		}
+		wg.Wait()
+	}()
+
+	go func() {
+		defer close(done)
+		start := time.Now()
+		var timeout <-chan time.Time
+		if d := s.cfg.WarmupTimeout.Duration; d > 0 {
+			timer := time.NewTimer(d)
+			defer timer.Stop()
+			timeout = timer.C
+		}
+		select {
+		case <-finished:
+			slog.Info("Кэш прогрет", "queries", len(questions), "duration", time.Since(start))
+		case <-timeout:
+			slog.Warn("Прогрев кэша не завершился за отведенное время", "queries", len(questions), "timeout", s.cfg.WarmupTimeout.Duration)
+		}
+	}()
+	return done
+}
@@ -0,0 +1,85 @@
+package resolver
+
+import (
+	"testing"
+	"time"
+
+	"github.com/miekg/dns"
+)
+
+func TestWarmupQuestions(t *testing.T) {
+	got := warmupQuestions([]string{"WWW.Example.com", "mail.example.com.", "example.org"})
+	want := []warmupQuestion{
+		{".", dns.TypeNS},
+		{"com.", dns.TypeNS},
+		{"org.", dns.TypeNS},
+		{"www.example.com.", dns.TypeA},
+		{"www.example.com.", dns.TypeAAAA},
+		{"mail.example.com.", dns.TypeA},
+		{"mail.example.com.", dns.TypeAAAA},
+		{"example.org.", dns.TypeA},
+		{"example.org.", dns.TypeAAAA},
+	}
+	if len(got) != len(want) {
+		t.Fatalf("Ожидалось %d запросов, получено %d: %v", len(want), len(got), got)
+	}
+	for i := range want {
+		if got[i] != want[i] {
+			t.Errorf("Запрос %d: %v, ожидалось %v", i, got[i], want[i])
+		}
+	}
+}
+
+func TestWarmUpPopulatesCache(t *testing.T) {
+	addr := startTestUDPServer(t, func(w dns.ResponseWriter, r *dns.Msg) {
+		reply := new(dns.Msg)
+		reply.SetReply(r)
+		q := r.Question[0]
+		var text string
+		switch q.Qtype {
+		case dns.TypeA:
+			text = q.Name + " 300 IN A 192.0.2.1"
+		case dns.TypeAAAA:
+			text = q.Name + " 300 IN AAAA 2001:db8::1"
+		case dns.TypeNS:
+			text = q.Name + " 3600 IN NS ns.example.net."
+		}
+		if rr, err := dns.NewRR(text); err == nil && text != "" {
+			reply.Answer = append(reply.Answer, rr)
+		}
+		w.WriteMsg(reply)
+	})
+
+	s := NewDNSServer()
+	s.forwarders = []string{addr}
+	s.cfg.WarmupNames = []string{"www.example.com"}
+
+	select {
+	case <-s.warmUp():
+	case <-time.After(5 * time.Second):
+		t.Fatal("Прогрев кэша не завершился")
+	}
+
+	for _, q := range []warmupQuestion{
+		{".", dns.TypeNS},
+		{"com.", dns.TypeNS},
+		{"www.example.com.", dns.TypeA},
+		{"www.example.com.", dns.TypeAAAA},
+	} {
+		if _, ok := s.cache.Get(cacheKey(q.name, q.qtype)); !ok {
+			t.Errorf("%s %s должен быть в кэше после прогрева", q.name, dns.TypeToString[q.qtype])
+		}
+	}
+}
+
+func TestWarmUpWithoutNames(t *testing.T) {
+	s := NewDNSServer()
+	select {
+	case <-s.warmUp():
+	default:
+		t.Fatal("Без warmup_names прогрев должен завершаться сразу")
+	}
+	if s.cache.Len() != 0 {
+		t.Errorf("Кэш должен остаться пустым, записей: %d", s.cache.Len())
+	}
+}
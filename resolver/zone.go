@@ -0,0 +1,161 @@
+package resolver
+
+import (
+	"fmt"
+	"io"
+	"os"
+	"strings"
+
+	"github.com/miekg/dns"
+)
+
+// Zone - локальная авторитетная зона, загруженная из мастер-файла (RFC 1035).
+// Делегирования и шаблонные записи внутри зоны не поддерживаются.
+type Zone struct {
+	origin  string
+	soa     *dns.SOA
+	records map[string][]dns.RR // Записи по имени в нижнем регистре
+	names   map[string]bool     // Существующие имена, включая пустые нетерминальные
+}
+
+// LoadZoneFile загружает зону origin из мастер-файла
+func LoadZoneFile(origin, path string) (*Zone, error) {
+	f, err := os.Open(path)
+	if err != nil {
+		return nil, err
+	}
+	defer f.Close()
+
+	return parseZone(f, origin, path)
+}
+
+func parseZone(r io.Reader, origin, file string) (*Zone, error) {
+	origin = strings.ToLower(dns.Fqdn(origin))
+	z := &Zone{origin: origin, records: make(map[string][]dns.RR), names: make(map[string]bool)}
+
+	zp := dns.NewZoneParser(r, origin, file)
+	for rr, ok := zp.Next(); ok; rr, ok = zp.Next() {
+		name := strings.ToLower(rr.Header().Name)
+		if !dns.IsSubDomain(origin, name) {
+			return nil, fmt.Errorf("запись %s вне зоны %s", rr.Header().Name, origin)
+		}
+		if soa, ok := rr.(*dns.SOA); ok {
+			if name != origin {
+				return nil, fmt.Errorf("запись SOA %s не на вершине зоны %s", rr.Header().Name, origin)
+			}
+			z.soa = soa
+		}
+		z.records[name] = append(z.records[name], rr)
+
+		// Имена между записью и вершиной зоны существуют, даже если у них нет записей
+		for n := name; !z.names[n]; {
+			z.names[n] = true
+			if n == origin {
+				break
+			}
+			if next, end := dns.NextLabel(n, 0); end {
+				n = "."
+			} else {
+				n = n[next:]
+			}
+		}
+	}
+	if err := zp.Err(); err != nil {
+		return nil, err
+	}
+	if z.soa == nil {
+		return nil, fmt.Errorf("в зоне %s нет записи SOA", origin)
+	}
+	return z, nil
+}
+
+// Lookup отвечает на вопрос из данных зоны: возвращает секции answer и
+// authority и код ответа. Цепочка CNAME прослеживается, пока цель остается
+// в зоне; отрицательные ответы содержат SOA (RFC 2308).
+func (z *Zone) Lookup(name string, qtype uint16) (answer, ns []dns.RR, rcode int) {
+	qname := strings.ToLower(dns.Fqdn(name))
+	for i := 0; i < maxCNAMEChain; i++ {
+		rrs := z.records[qname]
+		if matched := filterZoneRecords(rrs, qtype); len(matched) > 0 {
+			return append(answer, matched...), nil, dns.RcodeSuccess
+		}
+
+		cnames := filterZoneRecords(rrs, dns.TypeCNAME)
+		if len(cnames) == 0 {
+			if z.names[qname] {
+				return answer, z.negativeSOA(), dns.RcodeSuccess
+			}
+			return answer, z.negativeSOA(), dns.RcodeNameError
+		}
+		answer = append(answer, cnames[0])
+		target := strings.ToLower(cnames[0].(*dns.CNAME).Target)
+		if !dns.IsSubDomain(z.origin, target) {
+			// Цель вне зоны клиент разрешает отдельным запросом
+			return answer, nil, dns.RcodeSuccess
+		}
+		qname = target
+	}
+	return answer, nil, dns.RcodeSuccess
+}
+
+// negativeSOA возвращает SOA для отрицательного ответа с TTL, не превышающим
+// поле MINIMUM (RFC 2308, раздел 3)
+func (z *Zone) negativeSOA() []dns.RR {
+	soa := dns.Copy(z.soa).(*dns.SOA)
+	if soa.Minttl < soa.Hdr.Ttl {
+		soa.Hdr.Ttl = soa.Minttl
+	}
+	return []dns.RR{soa}
+}
+
+// filterZoneRecords возвращает копии записей типа qtype (всех записей для ANY):
+// ответ может изменяться при отправке, а данные зоны общие для всех запросов
+func filterZoneRecords(rrs []dns.RR, qtype uint16) []dns.RR {
+	var matched []dns.RR
+	for _, rr := range rrs {
+		if qtype == dns.TypeANY || rr.Header().Rrtype == qtype {
+			matched = append(matched, dns.Copy(rr))
+		}
+	}
+	return matched
+}
+
+// ZoneStore - набор локальных зон, индексированных по имени вершины
+type ZoneStore struct {
+	zones map[string]*Zone
+}
+
+// LoadZones загружает зоны из файлов; ключ files - имя зоны, значение - путь
+func LoadZones(files map[string]string) (*ZoneStore, error) {
+	zs := &ZoneStore{zones: make(map[string]*Zone, len(files))}
+	for origin, path := range files {
+		zone, err := LoadZoneFile(origin, path)
+		if err != nil {
+			return nil, fmt.Errorf("зона %s: %w", origin, err)
+		}
+		zs.zones[zone.origin] = zone
+	}
+	return zs, nil
+}
+
+// Find возвращает ближайшую зону, содержащую name, или nil
+func (zs *ZoneStore) Find(name string) *Zone {
+	if zs == nil || len(zs.zones) == 0 {
+		return nil
+	}
+	name = strings.ToLower(dns.Fqdn(name))
+	for off, end := 0, false; !end; off, end = dns.NextLabel(name, off) {
+		if zone, ok := zs.zones[name[off:]]; ok {
+			return zone
+		}
+	}
+	return zs.zones["."]
+}
+
+// Len возвращает количество загруженных зон
+func (zs *ZoneStore) Len() int {
+	if zs == nil {
+		return 0
+	}
+	return len(zs.zones)
+}
@@ -0,0 +1,109 @@
+package resolver
+
+import (
+	"os"
+	"path/filepath"
+	"strings"
+	"testing"
+
+	"github.com/miekg/dns"
+)
+
+const sampleZone = `$TTL 3600
+@       IN SOA  ns1 hostmaster 2024010101 7200 3600 1209600 300
+        IN NS   ns1
+ns1     IN A    10.0.0.1
+www     IN A    10.0.0.80
+        IN A    10.0.0.81
+web     IN CNAME www
+ext     IN CNAME example.com.
+a.b     IN TXT  "deep"
+`
+
+func TestZoneLookup(t *testing.T) {
+	zone, err := parseZone(strings.NewReader(sampleZone), "corp.example", "")
+	if err != nil {
+		t.Fatalf("Ошибка разбора зоны: %v", err)
+	}
+
+	answer, _, rcode := zone.Lookup("WWW.corp.example.", dns.TypeA)
+	if rcode != dns.RcodeSuccess || len(answer) != 2 {
+		t.Fatalf("Ожидалось 2 A-записи, получено %v (%s)", answer, dns.RcodeToString[rcode])
+	}
+
+	answer, _, _ = zone.Lookup("web.corp.example.", dns.TypeA)
+	if len(answer) != 3 || answer[0].Header().Rrtype != dns.TypeCNAME {
+		t.Errorf("Ожидалась CNAME и две A-записи цели, получено %v", answer)
+	}
+	answer, ns, _ := zone.Lookup("ext.corp.example.", dns.TypeA)
+	if len(answer) != 1 || len(ns) != 0 {
+		t.Errorf("Для цели вне зоны ожидалась только CNAME, получено %v %v", answer, ns)
+	}
+
+	// Имя без записей запрошенного типа и пустой нетерминальный узел - NODATA
+	for _, name := range []string{"www.corp.example.", "b.corp.example."} {
+		answer, ns, rcode = zone.Lookup(name, dns.TypeMX)
+		if rcode != dns.RcodeSuccess || len(answer) != 0 || len(ns) != 1 {
+			t.Errorf("%s: ожидался NODATA с SOA, получено %v %v (%s)", name, answer, ns, dns.RcodeToString[rcode])
+		}
+	}
+
+	_, ns, rcode = zone.Lookup("missing.corp.example.", dns.TypeA)
+	if rcode != dns.RcodeNameError || len(ns) != 1 {
+		t.Fatalf("Ожидался NXDOMAIN с SOA, получено %v (%s)", ns, dns.RcodeToString[rcode])
+	}
+	if ttl := ns[0].Header().Ttl; ttl != 300 {
+		t.Errorf("TTL SOA в отрицательном ответе должен быть ограничен MINIMUM, получено %d", ttl)
+	}
+}
+
+func TestZoneParseErrors(t *testing.T) {
+	for _, data := range []string{
+		"www IN A 10.0.0.1\n",
+		"@ IN SOA ns1 hostmaster 1 7200 3600 1209600 300\nwww.other.example. IN A 10.0.0.1\n",
+		"@ IN SOA ns1 hostmaster 1 7200 3600 1209600 300\nwww IN A not-an-ip\n",
+	} {
+		if _, err := parseZone(strings.NewReader(data), "corp.example.", ""); err == nil {
+			t.Errorf("Ожидалась ошибка для зоны %q", data)
+		}
+	}
+}
+
+func TestHandleRequestLocalZone(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "corp.zone")
+	if err := os.WriteFile(path, []byte(sampleZone), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	cfg := DefaultConfig()
+	cfg.CacheFile = ""
+	cfg.LocalZones = map[string]string{"corp.example": path}
+	s, err := NewDNSServerWithConfig(cfg)
+	if err != nil {
+		t.Fatalf("Ошибка создания сервера: %v", err)
+	}
+	// Адрес нигде не прослушивается: рекурсия не должна понадобиться
+	s.forwarders = []string{"192.0.2.53:53"}
+
+	req := new(dns.Msg)
+	req.SetQuestion("ns1.corp.example.", dns.TypeA)
+	w := &testResponseWriter{}
+	s.handleRequest(w, req)
+
+	if w.msg == nil || len(w.msg.Answer) != 1 {
+		t.Fatalf("Ожидалась A-запись из зоны, получено %v", w.msg)
+	}
+	if !w.msg.Authoritative {
+		t.Error("Ответ из локальной зоны должен иметь флаг AA")
+	}
+	if a, ok := w.msg.Answer[0].(*dns.A); !ok || a.A.String() != "10.0.0.1" {
+		t.Errorf("Неверный ответ: %v", w.msg.Answer[0])
+	}
+
+	req = new(dns.Msg)
+	req.SetQuestion("missing.corp.example.", dns.TypeA)
+	w = &testResponseWriter{}
+	s.handleRequest(w, req)
+	if w.msg == nil || w.msg.Rcode != dns.RcodeNameError || !w.msg.Authoritative {
+		t.Errorf("Ожидался авторитетный NXDOMAIN, получено %v", w.msg)
+	}
+}